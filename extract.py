@@ -1,11 +1,20 @@
 from collections import ChainMap
 import binaryninja as binaryninja
-import rpyc
 import json
-c = rpyc.connect("0.0.0.0", 18812)
+import os
+import sys
 
-bv: binaryninja.BinaryView = c.root.bv
-bn: binaryninja = c.root.binaryninja
+# `python3 extract.py chall.bndb` (what `teemo --bndb` runs) opens the file
+# with headless binja, without one it asks the binja serving rpyc on 18812
+if len(sys.argv) > 1:
+    bv: binaryninja.BinaryView = binaryninja.load(sys.argv[1])
+    bn: binaryninja = binaryninja
+else:
+    import rpyc
+    c = rpyc.connect("0.0.0.0", 18812)
+
+    bv: binaryninja.BinaryView = c.root.bv
+    bn: binaryninja = c.root.binaryninja
 
 anonymous = 0
 structs = {}
@@ -16,14 +25,20 @@ typedefs = {}
 pointers = {}
 functions = {}
 arrays = {}
+qualifiers = {}
 variables = {}
+subprograms = {}
+lines = {}
+frames = {}
 
 def escape(name: binaryninja.QualifiedName):
     return name.name[0]
 
 def extract_typename(kind: binaryninja.Type, name: str | None = None):
     match kind.type_class.value:
-        case binaryninja.TypeClass.IntegerTypeClass.value:
+        case binaryninja.TypeClass.IntegerTypeClass.value | \
+             binaryninja.TypeClass.FloatTypeClass.value | \
+             binaryninja.TypeClass.BoolTypeClass.value:
             return name or kind.get_string()
         case binaryninja.TypeClass.StructureTypeClass.value:
             if kind.registered_name is None:
@@ -53,9 +68,42 @@ def extract_typename(kind: binaryninja.Type, name: str | None = None):
             print(f"unhandled: {name} {type(kind)}")
             exit(1)
 
+# [offset, name, typename], bitfields as objects with their bit position
+# (binja 4.1 and later)
+def member(field):
+    bit_width = getattr(field, "bit_width", 0)
+    if not bit_width:
+        return (field.offset, field.name, visit(field.type))
+    return {
+        "offset": field.offset,
+        "name": field.name,
+        "typename": visit(field.type),
+        "bit_offset": field.bit_position,
+        "bit_size": bit_width,
+    }
+
+# enumerator values of 16 byte enums, as teemo reads them
+def wide(value):
+    if -2**63 <= value < 2**64:
+        return value
+    return hex(value)
+
 def visit(kind: binaryninja.Type, name: str | None = None):
     global anonymous
 
+    # const and volatile are flags on any binja type, they wrap the bare one
+    if kind.const.value or kind.volatile.value:
+        key = kind.get_string()
+        if key not in qualifiers:
+            bare = kind.mutable_copy()
+            bare.const = False
+            bare.volatile = False
+            qualifiers[key] = {}
+            qualifiers[key]["target"] = visit(bare.immutable_copy(), name)
+            qualifiers[key]["const"] = kind.const.value
+            qualifiers[key]["volatile"] = kind.volatile.value
+        return key
+
     anon = False
     key = extract_typename(kind, name)
     if key is None:
@@ -77,6 +125,12 @@ def visit(kind: binaryninja.Type, name: str | None = None):
             integers[key] = {}
             integers[key]["size"] = len(kind)
             integers[key]["signed"] = kind.signed.value
+            if kind.get_string() in ("char", "signed char", "unsigned char"):
+                integers[key]["encoding"] = "char"
+        case binaryninja.TypeClass.FloatTypeClass.value:
+            integers[key] = {"size": len(kind), "signed": False, "encoding": "float"}
+        case binaryninja.TypeClass.BoolTypeClass.value:
+            integers[key] = {"size": len(kind), "signed": False, "encoding": "bool"}
         case binaryninja.TypeClass.StructureTypeClass.value:
             match kind.type.value:
                 case binaryninja.StructureVariant.StructStructureType:
@@ -88,12 +142,25 @@ def visit(kind: binaryninja.Type, name: str | None = None):
             target[key] = {}
             target[key]["size"] = len(kind)
             target[key]["anon"] = anon
-            target[key]["fields"] = list(map(lambda field: (field.offset, field.name, visit(field.type)), kind.members))
+            target[key]["fields"] = list(map(member, kind.members))
+            # forward declared, teemo writes a declaration instead of an empty struct
+            if len(kind) == 0 and not kind.members:
+                target[key]["declaration"] = True
         case binaryninja.TypeClass.PointerTypeClass.value:
             pointers[key] = {}
             pointers[key]["size"] = len(kind)
             pointers[key]["target"] = visit(kind.target)
+            # C++ references, T& and T&&
+            match kind.ref_type.value:
+                case binaryninja.ReferenceType.ReferenceReferenceType.value:
+                    pointers[key]["reference"] = "lvalue"
+                case binaryninja.ReferenceType.RValueReferenceType.value:
+                    pointers[key]["reference"] = "rvalue"
         case binaryninja.TypeClass.NamedTypeReferenceClass.value:
+            # a reference to a struct that's never defined
+            if kind.target(bv) is None:
+                structs[key] = {"size": 0, "anon": False, "fields": [], "declaration": True}
+                return key
             target = visit(kind.target(bv))
             if key == target:
                 return key
@@ -103,15 +170,30 @@ def visit(kind: binaryninja.Type, name: str | None = None):
             enums[key] = {}
             enums[key]["size"] = len(kind)
             enums[key]["signed"] = kind.signed.value
-            enums[key]["fields"] = list(map(lambda field: (field.name, field.value), kind.members))
+            # json numbers past 64 bits don't survive parsing, those go as strings
+            enums[key]["fields"] = list(map(lambda field: (field.name, wide(field.value)), kind.members))
         case binaryninja.TypeClass.FunctionTypeClass.value:
             functions[key] = {}
             functions[key]["parameters"] = list(map(lambda p: (p.name, visit(p.type)), kind.parameters))
             functions[key]["returntype"] = visit(kind.return_value)
+            if kind.has_variable_arguments.value:
+                functions[key]["variadic"] = True
+            # the conventions teemo has a DW_CC_* for, others are left to the platform
+            convention = kind.calling_convention
+            if convention is not None and convention.name in ("cdecl", "stdcall", "fastcall", "thiscall", "win64", "sysv"):
+                functions[key]["convention"] = convention.name
         case binaryninja.TypeClass.ArrayTypeClass.value:
             arrays[key] = {}
             arrays[key]["count"] = kind.count
-            arrays[key]["target"] = visit(kind.element_type)
+            # int[4][8] is a single array of two dimensions
+            element = kind.element_type
+            dimensions = []
+            while element.type_class.value == binaryninja.TypeClass.ArrayTypeClass.value:
+                dimensions.append(element.count)
+                element = element.element_type
+            if dimensions:
+                arrays[key]["dimensions"] = dimensions
+            arrays[key]["target"] = visit(element)
         case binaryninja.TypeClass.VoidTypeClass.value:
             pass
         case _:
@@ -133,6 +215,63 @@ for name, symbols in bv.symbols.items():
         variables[symbol.address]["size"] = len(variable)
         variables[symbol.address]["typename"] = visit(variable.type)
 
+for function in bv.functions:
+    subprograms[function.start] = {}
+    subprograms[function.start]["name"] = function.name
+    subprograms[function.start]["size"] = max(r.end for r in function.address_ranges) - function.start
+    subprograms[function.start]["typename"] = visit(function.type)
+    if function.symbol.binding == binaryninja.SymbolBinding.LocalBinding:
+        subprograms[function.start]["local"] = True
+    # binja's stack offsets are from the stack pointer on entry, the CFA is
+    # above the return address x86 pushes
+    cfa = bv.arch.address_size if bv.arch.name in ("x86", "x86_64") else 0
+    subprograms[function.start]["locals"] = [
+        {"name": v.name, "typename": visit(v.type), "offset": v.storage - cfa}
+        for v in function.stack_layout
+        if v.source_type == binaryninja.VariableSourceType.StackVariableSourceType and v.storage < 0
+    ]
+    # register variables by their full register, binja has partial ones like
+    # `dil` that DWARF doesn't number
+    subprograms[function.start]["locals"] += [
+        {
+            "name": v.name,
+            "typename": visit(v.type),
+            "location": {"register": bv.arch.regs[bv.arch.get_reg_name(v.storage)].full_width_reg},
+        }
+        for v in function.vars
+        if v.source_type == binaryninja.VariableSourceType.RegisterVariableSourceType
+    ]
+
+    # the CFA for `--frames frames.json` wherever binja knows the stack
+    # pointer, a row each time it changes. saved registers aren't tracked
+    sp = bv.arch.stack_pointer
+    rows = {}
+    last = [sp, cfa]
+    for _, address in function.instructions:
+        value = function.get_reg_value_at(address, sp)
+        if value.type != binaryninja.RegisterValueType.StackFrameOffset:
+            continue
+        rule = [sp, cfa - value.value]
+        if rule != last:
+            rows[address - function.start] = {"cfa": rule}
+            last = rule
+    frames[function.start] = {"size": subprograms[function.start]["size"], "rows": rows}
+
+# the pseudo-C of every function for `--sources sources --lines lines.json`,
+# each line at the first address binja attributes to it
+os.makedirs("sources", exist_ok=True)
+for function in bv.functions:
+    pseudo_c = function.pseudo_c_if_available
+    if pseudo_c is None:
+        continue
+    end = function.start + subprograms[function.start]["size"]
+    text = []
+    for number, line in enumerate(pseudo_c.get_linear_lines(function.hlil.root), 1):
+        text.append(str(line))
+        if function.start <= line.address < end and line.address not in lines:
+            lines[line.address] = {"file": f"{function.name}.c", "line": number}
+    open(f"sources/{function.name}.c", "w+").write("\n".join(text) + "\n")
+
 json.dump(structs, open("structs.json", "w+"))
 json.dump(unions, open("unions.json", "w+"))
 json.dump(enums, open("enums.json", "w+"))
@@ -141,4 +280,8 @@ json.dump(typedefs, open("typedefs.json", "w+"))
 json.dump(pointers, open("pointers.json", "w+"))
 json.dump(functions, open("functions.json", "w+"))
 json.dump(arrays, open("arrays.json", "w+"))
-json.dump(variables, open("variables.json", "w+"))
\ No newline at end of file
+json.dump(qualifiers, open("qualifiers.json", "w+"))
+json.dump(variables, open("variables.json", "w+"))
+json.dump(subprograms, open("subprograms.json", "w+"))
+json.dump(lines, open("lines.json", "w+"))
+json.dump(frames, open("frames.json", "w+"))
\ No newline at end of file
@@ -0,0 +1,159 @@
+# exports the program's data types and symbols as ghidra.json for
+# `teemo --format ghidra`, see src/importer.rs for the layout.
+# run it from the script manager, it asks for the directory to write into.
+#@category Export
+import json
+import os
+
+from ghidra.program.model.data import (
+    AbstractFloatDataType,
+    AbstractIntegerDataType,
+    Array,
+    BitFieldDataType,
+    BooleanDataType,
+    CharDataType,
+    Enum,
+    FunctionDefinition,
+    Pointer,
+    Structure,
+    TypeDef,
+    Undefined,
+    Union,
+    VoidDataType,
+    WideCharDataType,
+)
+
+# the ones teemo has a DW_CC_* for
+CONVENTIONS = ("__cdecl", "__stdcall", "__fastcall", "__thiscall", "__pascal", "__vectorcall")
+
+types = {}
+symbols = []
+
+
+def components(composite):
+    members = []
+    for component in composite.getDefinedComponents():
+        kind = component.getDataType()
+        member = {
+            "offset": component.getOffset(),
+            "name": component.getFieldName() or "field_0x%x" % component.getOffset(),
+        }
+        if isinstance(kind, BitFieldDataType):
+            member["type"] = visit(kind.getBaseDataType())
+            member["bit_offset"] = kind.getBitOffset()
+            member["bit_size"] = kind.getBitSize()
+        else:
+            member["type"] = visit(kind)
+        members.append(member)
+    return members
+
+
+def visit(kind):
+    if kind is None or isinstance(kind, VoidDataType):
+        return "void"
+    name = kind.getName()
+    if name in types:
+        return name
+    # claimed before descending so self-referencing types end
+    types[name] = None
+
+    if isinstance(kind, Structure):
+        entry = {"kind": "struct", "size": kind.getLength(), "components": []}
+        types[name] = entry
+        entry["components"] = components(kind)
+    elif isinstance(kind, Union):
+        entry = {"kind": "union", "size": kind.getLength(), "components": []}
+        types[name] = entry
+        entry["components"] = components(kind)
+    elif isinstance(kind, Pointer):
+        types[name] = {
+            "kind": "pointer",
+            "size": kind.getLength(),
+            "type": visit(kind.getDataType()),
+        }
+    elif isinstance(kind, TypeDef):
+        types[name] = {"kind": "typedef", "type": visit(kind.getDataType())}
+    elif isinstance(kind, Enum):
+        types[name] = {
+            "kind": "enum",
+            "size": kind.getLength(),
+            "values": [[value, kind.getValue(value)] for value in kind.getNames()],
+        }
+    elif isinstance(kind, Array):
+        types[name] = {
+            "kind": "array",
+            "count": kind.getNumElements(),
+            "type": visit(kind.getDataType()),
+        }
+    elif isinstance(kind, FunctionDefinition):
+        types[name] = {
+            "kind": "function",
+            "return": visit(kind.getReturnType()),
+            "parameters": [
+                {"name": parameter.getName(), "type": visit(parameter.getDataType())}
+                for parameter in kind.getArguments()
+            ],
+            "varargs": kind.hasVarArgs(),
+        }
+        # "default" and "unknown" leave it to the platform
+        convention = kind.getCallingConventionName()
+        if convention in CONVENTIONS:
+            types[name]["convention"] = convention
+    elif isinstance(kind, (CharDataType, WideCharDataType)):
+        types[name] = {
+            "kind": "builtin",
+            "size": kind.getLength(),
+            "signed": kind.isSigned(),
+            "class": "char",
+        }
+    elif isinstance(kind, BooleanDataType):
+        types[name] = {"kind": "builtin", "size": kind.getLength(), "class": "bool"}
+    elif isinstance(kind, AbstractFloatDataType):
+        types[name] = {"kind": "builtin", "size": kind.getLength(), "class": "float"}
+    elif isinstance(kind, AbstractIntegerDataType):
+        types[name] = {
+            "kind": "builtin",
+            "size": kind.getLength(),
+            "signed": kind.isSigned(),
+        }
+    else:
+        # undefinedN and whatever else has no structure, a plain unsigned blob
+        types[name] = {"kind": "builtin", "size": max(kind.getLength(), 1)}
+    return name
+
+
+for kind in currentProgram.getDataTypeManager().getAllDataTypes():
+    visit(kind)
+
+listing = currentProgram.getListing()
+for data in listing.getDefinedData(True):
+    symbol = data.getPrimarySymbol()
+    if symbol is None or isinstance(data.getDataType(), Undefined):
+        continue
+    symbols.append(
+        {
+            "name": symbol.getName(),
+            "address": data.getAddress().getOffset(),
+            "size": data.getLength(),
+            "type": visit(data.getDataType()),
+        }
+    )
+
+for function in currentProgram.getFunctionManager().getFunctions(True):
+    if function.isThunk() or function.isExternal():
+        continue
+    body = function.getBody()
+    entry = function.getEntryPoint().getOffset()
+    symbols.append(
+        {
+            "name": function.getName(),
+            "address": entry,
+            "size": body.getMaxAddress().getOffset() + 1 - entry,
+            "type": visit(function.getSignature()),
+            "function": True,
+        }
+    )
+
+directory = askDirectory("where to write ghidra.json", "export").getAbsolutePath()
+with open(os.path.join(directory, "ghidra.json"), "w") as export:
+    json.dump({"types": types, "symbols": symbols}, export)
@@ -0,0 +1,143 @@
+# exports the local types, function prototypes and names as ida.json for
+# `teemo --format ida`, see src/importer.rs for the layout.
+# run it with File > Script file..., ida.json ends up next to the database.
+import json
+import os
+
+import ida_bytes
+import ida_funcs
+import ida_nalt
+import ida_typeinf
+import idautils
+import idc
+
+# the ones teemo has a DW_CC_* for
+CONVENTIONS = {
+    ida_typeinf.CM_CC_CDECL: "cdecl",
+    ida_typeinf.CM_CC_ELLIPSIS: "cdecl",
+    ida_typeinf.CM_CC_STDCALL: "stdcall",
+    ida_typeinf.CM_CC_PASCAL: "pascal",
+    ida_typeinf.CM_CC_FASTCALL: "fastcall",
+    ida_typeinf.CM_CC_THISCALL: "thiscall",
+}
+
+types = {}
+functions = []
+names = []
+
+
+def udt(tif):
+    details = ida_typeinf.udt_type_data_t()
+    tif.get_udt_details(details)
+    members = []
+    for udm in details:
+        member = {"offset": udm.offset, "name": udm.name, "type": visit(udm.type)}
+        if udm.is_bitfield():
+            member["bitfield"] = udm.size
+        members.append(member)
+    return {
+        "kind": "union" if tif.is_union() else "struct",
+        "size": tif.get_size(),
+        "members": members,
+    }
+
+
+def visit(tif):
+    if tif.is_void():
+        return "void"
+    key = tif.dstr()
+    if key in types:
+        return key
+    # claimed before descending so self-referencing types end
+    types[key] = None
+
+    if tif.is_typedef() and not (tif.is_udt() or tif.is_enum()):
+        target = ida_typeinf.tinfo_t()
+        target.get_named_type(None, tif.get_next_type_name())
+        types[key] = {"kind": "typedef", "target": visit(target)}
+    elif tif.is_udt():
+        types[key] = udt(tif)
+    elif tif.is_enum():
+        details = ida_typeinf.enum_type_data_t()
+        tif.get_enum_details(details)
+        types[key] = {
+            "kind": "enum",
+            "size": tif.get_size(),
+            "members": [{"name": edm.name, "value": edm.value} for edm in details],
+        }
+    elif tif.is_ptr():
+        types[key] = {
+            "kind": "pointer",
+            "size": tif.get_size(),
+            "target": visit(tif.get_pointed_object()),
+        }
+    elif tif.is_array():
+        types[key] = {
+            "kind": "array",
+            "count": tif.get_array_nelems(),
+            "target": visit(tif.get_array_element()),
+        }
+    elif tif.is_func():
+        details = ida_typeinf.func_type_data_t()
+        tif.get_func_details(details)
+        types[key] = {
+            "kind": "function",
+            "return": visit(details.rettype),
+            "args": [{"name": arg.name, "type": visit(arg.type)} for arg in details],
+            "varargs": details.is_vararg_cc(),
+        }
+        convention = CONVENTIONS.get(details.cc & ida_typeinf.CM_CC_MASK)
+        if convention:
+            types[key]["convention"] = convention
+    elif tif.is_floating():
+        types[key] = {"kind": "float", "size": tif.get_size()}
+    elif tif.is_bool():
+        types[key] = {"kind": "bool", "size": tif.get_size()}
+    elif tif.is_char():
+        types[key] = {"kind": "char", "size": tif.get_size(), "signed": tif.is_signed()}
+    else:
+        # _BYTE, _DWORD and the like are plain unsigned
+        types[key] = {
+            "kind": "integer",
+            "size": max(tif.get_size(), 1),
+            "signed": tif.is_signed(),
+        }
+    return key
+
+
+limit = getattr(ida_typeinf, "get_ordinal_limit", None) or ida_typeinf.get_ordinal_qty
+for ordinal in range(1, limit(None)):
+    tif = ida_typeinf.tinfo_t()
+    if tif.get_numbered_type(None, ordinal):
+        visit(tif)
+
+for start in idautils.Functions():
+    function = ida_funcs.get_func(start)
+    if function.flags & (ida_funcs.FUNC_THUNK | ida_funcs.FUNC_LIB):
+        continue
+    tif = ida_typeinf.tinfo_t()
+    if not ida_nalt.get_tinfo(tif, start) and not ida_typeinf.guess_tinfo(tif, start):
+        continue
+    functions.append(
+        {
+            "name": ida_funcs.get_func_name(start),
+            "start": start,
+            "end": function.end_ea,
+            "type": visit(tif),
+        }
+    )
+
+for ea, name in idautils.Names():
+    if ida_bytes.is_code(ida_bytes.get_flags(ea)):
+        continue
+    tif = ida_typeinf.tinfo_t()
+    if not ida_nalt.get_tinfo(tif, ea):
+        continue
+    names.append(
+        {"name": name, "ea": ea, "size": ida_bytes.get_item_size(ea), "type": visit(tif)}
+    )
+
+path = os.path.join(os.path.dirname(idc.get_idb_path()), "ida.json")
+with open(path, "w") as export:
+    json.dump({"types": types, "functions": functions, "names": names}, export)
+print("wrote %s" % path)
@@ -0,0 +1,58 @@
+// .debug_aranges: the address ranges of every unit's functions and globals,
+// so debuggers and profilers map an address to its unit without scanning all
+// of .debug_info. gimli doesn't write the section, the layout is DWARF 5's
+// (version 2, unchanged since DWARF 2):
+//
+//   unit_length, version, debug_info_offset, address_size, segment_selector_size
+//   padding to twice the address size
+//   (address, length)... (0, 0)
+
+use gimli::write::{EndianVec, Writer};
+use gimli::{DebugInfoOffset, Encoding, RunTimeEndian};
+
+use crate::{units, DynErr};
+
+// one set per unit with any (start, size) in it
+pub fn write(
+    encoding: Encoding,
+    endian: RunTimeEndian,
+    units: &[(DebugInfoOffset, Vec<(u64, u64)>)],
+) -> Result<Vec<u8>, DynErr> {
+    let address_size = encoding.address_size;
+    let mut section = EndianVec::new(endian);
+    for (offset, stretches) in units {
+        let ranges = units::coalesce(
+            stretches
+                .iter()
+                .filter(|(_, size)| *size != 0)
+                .map(|(start, size)| (*start, start.saturating_add(*size)))
+                .collect(),
+        );
+        if ranges.is_empty() {
+            continue;
+        }
+
+        let start = section.len();
+        let length = section.write_initial_length(encoding.format)?;
+        let length_base = section.len();
+        section.write_u16(2)?;
+        section.write_udata(offset.0 as u64, encoding.format.word_size())?;
+        section.write_u8(address_size)?;
+        section.write_u8(0)?;
+        // the tuples are aligned relative to the start of the set
+        let tuple = 2 * address_size as usize;
+        while !(section.len() - start).is_multiple_of(tuple) {
+            section.write_u8(0)?;
+        }
+        for (begin, end) in ranges {
+            section.write_udata(begin, address_size)?;
+            section.write_udata(end - begin, address_size)?;
+        }
+        section.write_udata(0, address_size)?;
+        section.write_udata(0, address_size)?;
+
+        let size = (section.len() - length_base) as u64;
+        section.write_initial_length_at(length, size, encoding.format)?;
+    }
+    Ok(section.into_vec())
+}
@@ -0,0 +1,38 @@
+// `--bndb chall.bndb` runs the export step itself: extract.py, built into
+// teemo, opens the database (or the binary) with headless binja and writes
+// the export into --types-dir, which the rest reads as usual. it needs a
+// binja license with headless python and its python modules on PYTHONPATH,
+// $TEEMO_PYTHON picks the interpreter. without headless binja, extract.py
+// asks a running binja over rpyc instead.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use log::info;
+
+use crate::Err;
+
+const EXTRACT: &str = include_str!("../extract.py");
+
+pub fn extract(bndb: &Path, dir: &Path) -> Err {
+    let bndb = fs::canonicalize(bndb).map_err(|e| format!("{}: {}", bndb.display(), e))?;
+    fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let script = dir.join(".teemo-extract.py");
+    fs::write(&script, EXTRACT)?;
+    let python = std::env::var("TEEMO_PYTHON").unwrap_or_else(|_| String::from("python3"));
+    let status = Command::new(&python)
+        .arg(".teemo-extract.py")
+        .arg(&bndb)
+        .current_dir(dir)
+        .status();
+    _ = fs::remove_file(&script);
+    match status {
+        Ok(status) if status.success() => {
+            info!("exported {} into {}", bndb.display(), dir.display());
+            Ok(())
+        }
+        Ok(status) => Err(format!("extract.py on {} failed, {}", bndb.display(), status).into()),
+        Err(e) => Err(format!("{}: {}", python, e).into()),
+    }
+}
@@ -0,0 +1,86 @@
+// `--build-id` gives the debug file a .note.gnu.build-id, which gdb and
+// debuginfod find debug files by: copied from the binary (--binary, or the
+// one --debuglink points at the output) so the file can go into
+// /usr/lib/debug/.build-id/ab/cdef....debug, or for a binary without one a
+// 128-bit FNV-1a of the output's sections. `--debuglink` gives such a binary
+// the same note so the two match.
+//
+// `--debuginfod-layout root` copies the output to root/buildid/<id>/debuginfo
+// as well, and the binary to root/buildid/<id>/executable, the paths a
+// debuginfod client asks for. any static file server on root can then be a
+// DEBUGINFOD_URLS entry for gdb.
+
+use std::fs;
+use std::path::Path;
+
+use goblin::elf::note::NT_GNU_BUILD_ID;
+use goblin::elf::Elf;
+use log::info;
+
+use crate::{DynErr, Err};
+
+// the id in the notes of an ELF file
+pub fn read(path: &Path) -> Result<Option<Vec<u8>>, DynErr> {
+    let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let elf = Elf::parse(&data)?;
+    let Some(notes) = elf.iter_note_sections(&data, None) else {
+        return Ok(None);
+    };
+    Ok(notes
+        .flatten()
+        .find(|note| note.n_type == NT_GNU_BUILD_ID && note.name.trim_end_matches('\0') == "GNU")
+        .map(|note| note.desc.to_vec()))
+}
+
+pub fn compute<'a>(sections: impl Iterator<Item = (&'a str, &'a [u8])>) -> Vec<u8> {
+    let mut hash: u128 = 0x6c62272e07bb014262b821756295c58d;
+    for (name, data) in sections {
+        for byte in name.bytes().chain([0]).chain(data.iter().copied()) {
+            hash ^= byte as u128;
+            hash = hash.wrapping_mul(0x0000000001000000000000000000013b);
+        }
+    }
+    hash.to_be_bytes().to_vec()
+}
+
+// the section's contents, an ELF note named GNU
+pub fn note(id: &[u8], little_endian: bool) -> Vec<u8> {
+    let word = |value: u32| match little_endian {
+        true => value.to_le_bytes(),
+        false => value.to_be_bytes(),
+    };
+    let mut note = Vec::new();
+    note.extend_from_slice(&word(4));
+    note.extend_from_slice(&word(id.len() as u32));
+    note.extend_from_slice(&word(NT_GNU_BUILD_ID));
+    note.extend_from_slice(b"GNU\0");
+    note.extend_from_slice(id);
+    note.resize(note.len().next_multiple_of(4), 0);
+    note
+}
+
+pub fn hex(id: &[u8]) -> String {
+    id.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// where gdb looks for the debug file under a debug directory
+pub fn path(id: &[u8]) -> String {
+    let hex = hex(id);
+    let (directory, file) = hex.split_at(hex.len().min(2));
+    format!(".build-id/{}/{}.debug", directory, file)
+}
+
+pub fn publish(root: &Path, output: &Path, binary: Option<&Path>) -> Err {
+    let id = read(output)?.ok_or_else(|| format!("{} has no build-id", output.display()))?;
+    let directory = root.join("buildid").join(hex(&id));
+    fs::create_dir_all(&directory).map_err(|e| format!("{}: {}", directory.display(), e))?;
+    _ = fs::copy(output, directory.join("debuginfo"))?;
+    // only the binary the id came from
+    if let Some(binary) = binary {
+        if read(binary)?.as_deref() == Some(id.as_slice()) {
+            _ = fs::copy(binary, directory.join("executable"))?;
+        }
+    }
+    info!("published as {}", directory.display());
+    Ok(())
+}
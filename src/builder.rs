@@ -0,0 +1,367 @@
+// the library interface, for tools that want debug files without going
+// through the command line:
+//
+//   let types = teemo::TypeDatabase::load("export")?;
+//   teemo::DwarfBuilder::new("x86_64")?.add_types(types).write_to("app.debug")?;
+//
+// the builder's setters are the command line's flags of the same name.
+// `run` is the command line on top of the same pieces.
+
+use std::path::Path;
+
+use crate::{
+    collect_export, compress, finish, generate, presets, target, Compat, DebugSup, DynErr, Err,
+    ExportFormat,
+};
+use crate::{HashMap, Options, TypeSet};
+
+// types, variables and the rest of an export
+#[derive(Clone, Default)]
+pub struct TypeDatabase {
+    set: TypeSet,
+}
+
+impl TypeDatabase {
+    // an export directory, types.json or one file per kind
+    pub fn load(dir: impl AsRef<Path>) -> Result<TypeDatabase, DynErr> {
+        Ok(TypeDatabase {
+            set: collect_export(
+                dir.as_ref(),
+                ExportFormat::Binja,
+                true,
+                false,
+                &HashMap::new(),
+            )?,
+        })
+    }
+
+    // the built-in types of a preset, e.g. glibc-heap-2.35
+    pub fn preset(name: &str) -> Result<TypeDatabase, DynErr> {
+        Ok(TypeDatabase {
+            set: presets::load(name)?,
+        })
+    }
+
+    // entries from `other` win on name collisions
+    pub fn extend(&mut self, other: TypeDatabase) {
+        self.set.extend(other.set);
+    }
+
+    // writes the database out as an export directory
+    pub fn save(&self, dir: impl AsRef<Path>) -> Err {
+        self.set.save(dir.as_ref())
+    }
+}
+
+pub struct DwarfBuilder {
+    options: Options,
+    types: TypeSet,
+}
+
+impl DwarfBuilder {
+    // for one of the arches `--arch` takes
+    pub fn new(arch: &str) -> Result<DwarfBuilder, DynErr> {
+        Ok(DwarfBuilder {
+            options: Options::new(target::lookup(arch)?),
+            types: TypeSet::default(),
+        })
+    }
+
+    // DWARF32 with line tables, as `--compat ghidra`
+    pub fn ghidra(mut self) -> DwarfBuilder {
+        self.options.compat = Compat::Ghidra;
+        self
+    }
+
+    // `--elf-class 32` or `64`, for arches that have both
+    pub fn elf_class(mut self, bits: &str) -> DwarfBuilder {
+        self.options.target = self.options.target.with_class(bits);
+        self
+    }
+
+    pub fn verify(mut self) -> DwarfBuilder {
+        self.options.verify = true;
+        self
+    }
+
+    pub fn verify_binja(mut self) -> DwarfBuilder {
+        self.options.verify_binja = true;
+        self
+    }
+
+    pub fn gdb_index(mut self) -> DwarfBuilder {
+        self.options.gdb_index = true;
+        self
+    }
+
+    pub fn split_dwarf(mut self) -> DwarfBuilder {
+        self.options.split_dwarf = true;
+        self
+    }
+
+    pub fn type_units(mut self) -> DwarfBuilder {
+        self.options.type_units = true;
+        self
+    }
+
+    pub fn coff(mut self) -> DwarfBuilder {
+        self.options.coff = true;
+        self
+    }
+
+    // `zlib` or `zlib-gnu`
+    pub fn compress_debug_sections(mut self, style: &str) -> Result<DwarfBuilder, DynErr> {
+        self.options.compression = Some(match style {
+            "zlib" => compress::Style::Zlib,
+            "zlib-gnu" => compress::Style::ZlibGnu,
+            _ => return Err(format!("unknown compression `{}`", style).into()),
+        });
+        Ok(self)
+    }
+
+    pub fn units(mut self, path: impl AsRef<Path>) -> DwarfBuilder {
+        self.options.units = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    // of the units that don't name theirs, c by default
+    pub fn language(mut self, language: &str) -> DwarfBuilder {
+        self.options.language = language.to_string();
+        self
+    }
+
+    pub fn trampolines(mut self, path: impl AsRef<Path>) -> DwarfBuilder {
+        self.options.trampolines = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn spaces(mut self, path: impl AsRef<Path>) -> DwarfBuilder {
+        self.options.spaces = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn frames(mut self, path: impl AsRef<Path>) -> DwarfBuilder {
+        self.options.frames = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn lines(mut self, path: impl AsRef<Path>) -> DwarfBuilder {
+        self.options.lines = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn sources(mut self, dir: impl AsRef<Path>) -> DwarfBuilder {
+        self.options.sources = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    // the binary the export came from, whose sections the output mirrors
+    pub fn binary(mut self, path: impl AsRef<Path>) -> DwarfBuilder {
+        self.options.binary = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn image_base(mut self, base: u64) -> DwarfBuilder {
+        self.options.image_base = Some(base);
+        self
+    }
+
+    pub fn rebase_delta(mut self, delta: i64) -> DwarfBuilder {
+        self.options.rebase_delta = delta;
+        self
+    }
+
+    // writes the binary with the debug info added instead
+    pub fn inject(mut self, binary: impl AsRef<Path>) -> DwarfBuilder {
+        self.options.inject = Some(binary.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn dynsym(mut self, name: &str) -> DwarfBuilder {
+        self.options.dynsym.push(name.to_string());
+        self
+    }
+
+    pub fn inject_align(mut self, align: u64) -> Result<DwarfBuilder, DynErr> {
+        if !align.is_power_of_two() {
+            return Err(format!("--inject-align {} isn't a power of two", align).into());
+        }
+        self.options.inject_align = Some(align);
+        Ok(self)
+    }
+
+    pub fn inject_load(mut self) -> DwarfBuilder {
+        self.options.inject_load = true;
+        self
+    }
+
+    pub fn debuglink(mut self, binary: impl AsRef<Path>) -> DwarfBuilder {
+        self.options.debuglink = Some(binary.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn build_id(mut self) -> DwarfBuilder {
+        self.options.build_id = true;
+        self
+    }
+
+    // implies build_id, like the flag
+    pub fn debuginfod_layout(mut self, root: impl AsRef<Path>) -> DwarfBuilder {
+        self.options.debuginfod_layout = Some(root.as_ref().to_path_buf());
+        self.options.build_id = true;
+        self
+    }
+
+    pub fn report(mut self, path: impl AsRef<Path>) -> DwarfBuilder {
+        self.options.report = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn gdb_printers(mut self, path: impl AsRef<Path>) -> DwarfBuilder {
+        self.options.gdb_printers = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn gdb_script(mut self, path: impl AsRef<Path>) -> DwarfBuilder {
+        self.options.gdb_scripts.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn lldb_summaries(mut self, path: impl AsRef<Path>) -> DwarfBuilder {
+        self.options.lldb_summaries = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn lenient(mut self) -> DwarfBuilder {
+        self.options.lenient = true;
+        self
+    }
+
+    pub fn strict(mut self) -> DwarfBuilder {
+        self.options.strict = true;
+        self
+    }
+
+    pub fn pad_holes(mut self) -> DwarfBuilder {
+        self.options.pad_holes = true;
+        self
+    }
+
+    pub fn add_types(mut self, database: TypeDatabase) -> DwarfBuilder {
+        self.types.extend(database.set);
+        self
+    }
+
+    pub fn write_to(&self, output: impl AsRef<Path>) -> Err {
+        let mut type_set = self.types.clone();
+        let (types, variables) = type_set.split_types();
+        generate(
+            &self.options,
+            &types,
+            &variables,
+            &type_set.constants,
+            &type_set.subprograms,
+            output.as_ref(),
+            DebugSup::None,
+        )?;
+        finish(&self.options, output.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::readback::Dies;
+
+    const TYPES: &str = r#"{
+        "types": {
+            "int": {"kind": "integer", "size": 4, "signed": true},
+            "unsigned int": {"kind": "integer", "size": 4, "signed": false},
+            "color": {"kind": "enum", "size": 4, "signed": false, "fields": [
+                {"name": "RED", "value": 0},
+                {"name": "BLUE", "value": 2}
+            ]},
+            "node": {"kind": "struct", "size": 24, "anon": false, "fields": [
+                {"offset": 0, "name": "next", "typename": "node*"},
+                {"offset": 8, "name": "key", "typename": "int"},
+                {"offset": 12, "name": "color", "typename": "color"},
+                {"offset": 16, "name": "flags", "typename": "unsigned int",
+                 "bit_offset": 0, "bit_size": 3}
+            ]},
+            "node*": {"kind": "pointer", "size": 8, "target": "node"},
+            "node_t": {"kind": "typedef", "target": "node"},
+            "int (node*)": {"kind": "function", "parameters": [
+                {"name": "list", "typename": "node*"}
+            ], "returntype": "int"}
+        },
+        "variables": {"16384": {"name": "head", "size": 8, "typename": "node*"}},
+        "subprograms": {"4096": {"name": "length", "size": 32, "typename": "int (node*)"}}
+    }"#;
+
+    #[test]
+    fn loaded_export_reads_back() {
+        let dir = std::env::temp_dir().join(format!("teemo-builder-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("types.json"), TYPES).unwrap();
+
+        let builders = [
+            ("plain", DwarfBuilder::new("x86_64").unwrap()),
+            (
+                "type-units",
+                DwarfBuilder::new("x86_64").unwrap().type_units(),
+            ),
+            ("ghidra", DwarfBuilder::new("x86_64").unwrap().ghidra()),
+        ];
+        for (name, builder) in builders {
+            let output = dir.join(format!("{}.debug", name));
+            let types = TypeDatabase::load(&dir).unwrap();
+            builder.verify().add_types(types).write_to(&output).unwrap();
+            let set = Dies::from_elf(&output).unwrap().to_type_set();
+
+            let node = &set.structs["node"];
+            assert_eq!(node.size, 24, "{}", name);
+            let fields: Vec<_> = (node.fields.iter())
+                .map(|field| {
+                    let bits = field.bit_offset.zip(field.bit_size);
+                    (
+                        field.offset,
+                        field.name.as_str(),
+                        field.typename.as_str(),
+                        bits,
+                    )
+                })
+                .collect();
+            assert_eq!(
+                fields,
+                [
+                    (0, "next", "node*", None),
+                    (8, "key", "int", None),
+                    (12, "color", "color", None),
+                    (16, "flags", "unsigned int", Some((0, 3))),
+                ],
+                "{}",
+                name
+            );
+            assert_eq!(set.pointers["node*"].target, "node", "{}", name);
+            assert_eq!(set.typedefs["node_t"].target, "node", "{}", name);
+            let values: Vec<_> = (set.enums["color"].fields.iter())
+                .map(|field| (field.name.as_str(), field.value))
+                .collect();
+            assert_eq!(values, [("RED", 0), ("BLUE", 2)], "{}", name);
+
+            let head = &set.variables[&0x4000];
+            assert_eq!(
+                (head.name.as_str(), head.typename.as_str()),
+                ("head", "node*")
+            );
+            let length = &set.subprograms[&0x1000];
+            assert_eq!((length.name.as_str(), length.size), ("length", 32));
+            let prototype = &set.functions[&length.typename];
+            assert_eq!(prototype.returntype, "int", "{}", name);
+            assert_eq!(prototype.parameters[0].typename, "node*", "{}", name);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,146 @@
+// `--cache DIR` keeps everything a run generates under a hash of what it was
+// made from: the command line, the directory it ran in, where the output goes
+// and the contents of the export, the other input files, --sources and the
+// binary. a run whose inputs all hash the same copies the kept files instead
+// of reading the export and generating it again, so going back and forth
+// between states of an export only pays for the new ones.
+//
+// it caches outputs, it doesn't regenerate incrementally: nothing below the
+// whole output is kept, so a run with anything changed generates every DIE
+// again. that's about half a second for 100k types, the DIEs of one type
+// are too cheap to make for reading them back to pay. files are kept until
+// DIR is cleared.
+//
+// DIR/<key>/ holds the output, the .dwo, --report, --gdb-printers and
+// --lldb-summaries by their index in `files` and <output>.src as `src/`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::info;
+
+use crate::{split, watch, DynErr, Err, Options};
+
+struct Hasher(u128);
+
+impl Hasher {
+    fn add(&mut self, bytes: &[u8]) {
+        // the length first, so neighbouring inputs can't trade bytes
+        for byte in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+            self.0 ^= *byte as u128;
+            self.0 = self.0.wrapping_mul(0x0000000001000000000000000000013b);
+        }
+    }
+}
+
+// the output's path from the root, it may not exist yet
+fn absolute(output: &Path) -> Result<PathBuf, DynErr> {
+    let directory = match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Ok(match (fs::canonicalize(directory), output.file_name()) {
+        (Ok(directory), Some(name)) => directory.join(name),
+        _ => std::path::absolute(output)?,
+    })
+}
+
+pub fn key(options: &Options) -> Result<String, DynErr> {
+    let mut hasher = Hasher(0x6c62272e07bb014262b821756295c58d);
+    hasher.add(env!("CARGO_PKG_VERSION").as_bytes());
+    for arg in std::env::args_os().skip(1) {
+        hasher.add(arg.as_encoded_bytes());
+    }
+    // relative paths on the command line mean something else elsewhere
+    hasher.add(std::env::current_dir()?.as_os_str().as_encoded_bytes());
+    hasher.add(absolute(&options.output)?.as_os_str().as_encoded_bytes());
+    let mut inputs = watch::inputs(options);
+    inputs.extend(options.binary.iter().cloned());
+    inputs.extend(options.inject.iter().cloned());
+    inputs.extend(options.debuglink.iter().cloned());
+    if let Some(dir) = &options.sources {
+        let mut sources: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries.flatten().map(|entry| entry.path()).collect(),
+            Err(_) => Vec::new(),
+        };
+        sources.sort();
+        inputs.extend(sources);
+    }
+    for input in inputs {
+        hasher.add(input.as_os_str().as_encoded_bytes());
+        match fs::read(&input) {
+            Ok(data) => hasher.add(&data),
+            // missing ones count as missing
+            Err(_) => hasher.add(&[]),
+        }
+    }
+    Ok(format!("{:032x}", hasher.0))
+}
+
+// the files one run generates: the output, the .dwo with --split-dwarf and
+// the scripts and report asked for
+fn files(options: &Options, output: &Path) -> Result<Vec<PathBuf>, DynErr> {
+    let mut files = vec![output.to_path_buf()];
+    if options.split_dwarf {
+        files.push(split::dwo_path(output)?);
+    }
+    files.extend(options.report.iter().cloned());
+    files.extend(options.gdb_printers.iter().cloned());
+    files.extend(options.lldb_summaries.iter().cloned());
+    Ok(files)
+}
+
+// where --sources copies the functions' sources to, see lines::sources
+fn sources(options: &Options, output: &Path) -> Option<PathBuf> {
+    options
+        .sources
+        .as_ref()
+        .map(|_| PathBuf::from(format!("{}.src", output.display())))
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Err {
+    fs::create_dir_all(to).map_err(|e| format!("{}: {}", to.display(), e))?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            _ = fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+// copies a kept output into place, false if there's none
+pub fn restore(dir: &Path, key: &str, options: &Options, output: &Path) -> Result<bool, DynErr> {
+    let kept = dir.join(key);
+    let files = files(options, output)?;
+    let sources = sources(options, output);
+    if !(0..files.len()).all(|index| kept.join(index.to_string()).is_file())
+        || (sources.is_some() && !kept.join("src").is_dir())
+    {
+        return Ok(false);
+    }
+    for (index, file) in files.iter().enumerate() {
+        _ = fs::copy(kept.join(index.to_string()), file)?;
+    }
+    if let Some(sources) = sources {
+        copy_dir(&kept.join("src"), &sources)?;
+    }
+    info!(
+        "{} is unchanged, copied from {}",
+        output.display(),
+        dir.display()
+    );
+    Ok(true)
+}
+
+pub fn store(dir: &Path, key: &str, options: &Options, output: &Path) -> Err {
+    let kept = dir.join(key);
+    fs::create_dir_all(&kept).map_err(|e| format!("{}: {}", kept.display(), e))?;
+    for (index, file) in files(options, output)?.iter().enumerate() {
+        _ = fs::copy(file, kept.join(index.to_string()))?;
+    }
+    if let Some(sources) = sources(options, output) {
+        copy_dir(&sources, &kept.join("src"))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,75 @@
+// `--check` goes through a whole run, reading the export and building every
+// DIE, without writing anything: not the output, a .dwo, an --inject binary
+// or the --cache. besides what a run fails on anyway (json that doesn't
+// parse, unknown types, types containing themselves) it reports variables
+// or functions whose bytes overlap and names given to more than one of them,
+// which gdb would only ever find one of, and fails if there are any.
+
+use crate::{GlobalVariable, HashMap, Subprogram};
+
+pub fn issues(
+    global_variables: &HashMap<u64, GlobalVariable>,
+    subprograms: &HashMap<u64, Subprogram>,
+) -> Vec<String> {
+    let mut issues = Vec::new();
+
+    // both kinds are keyed by address, so each only needs comparing with the
+    // one reaching furthest before it
+    let overlaps = |issues: &mut Vec<String>, what: &str, ranges: Vec<(&str, u64, u64)>| {
+        let mut furthest: Option<(&str, u64, u64)> = None;
+        for (name, start, size) in ranges {
+            let end = start.saturating_add(size);
+            if let Some((before, at, reach)) = furthest {
+                if reach > start {
+                    issues.push(format!(
+                        "{} `{}` at {:#x} overlaps `{}` at {:#x}, {} bytes in",
+                        what,
+                        name,
+                        start,
+                        before,
+                        at,
+                        start - at
+                    ));
+                }
+                if reach >= end {
+                    continue;
+                }
+            }
+            furthest = Some((name, start, end));
+        }
+    };
+    overlaps(
+        &mut issues,
+        "variable",
+        (global_variables.iter())
+            .map(|(address, variable)| (variable.name.as_str(), *address, variable.size))
+            .collect(),
+    );
+    overlaps(
+        &mut issues,
+        "function",
+        (subprograms.iter())
+            .map(|(address, subprogram)| (subprogram.name.as_str(), *address, subprogram.size))
+            .collect(),
+    );
+
+    let mut named: HashMap<&str, Vec<u64>> = HashMap::new();
+    for (address, variable) in global_variables.iter() {
+        named.entry(&variable.name).or_default().push(*address);
+    }
+    for (address, subprogram) in subprograms.iter() {
+        named.entry(&subprogram.name).or_default().push(*address);
+    }
+    for (name, addresses) in named.iter().filter(|(_, addresses)| addresses.len() > 1) {
+        let addresses: Vec<String> = (addresses.iter())
+            .map(|address| format!("{:#x}", address))
+            .collect();
+        issues.push(format!(
+            "`{}` names {} things, at {}",
+            name,
+            addresses.len(),
+            addresses.join(", ")
+        ));
+    }
+    issues
+}
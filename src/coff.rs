@@ -0,0 +1,153 @@
+// `--coff` writes the debug sections into a COFF object instead, the DWARF
+// in PE that mingw's gdb reads: `add-symbol-file test.o` on windows. the
+// sections are the ELF's, the empty .text among them, followed by a symbol
+// table and the string table holding the names past eight bytes,
+// which the section headers name as `/<offset>`.
+//
+// symbols are absolute. COFF only has 32 bits for their values, the ones
+// above that (a 64 bit image's default base is 0x140000000) are left to the
+// DWARF, whose addresses are full width.
+
+use std::fs;
+use std::path::Path;
+
+use goblin::elf::header::{EM_386, EM_AARCH64, EM_ARM, EM_RISCV, EM_X86_64};
+use goblin::elf64::sym;
+
+use crate::target::Target;
+use crate::{Endianity, Err, HashMap, RawSymbol, Section};
+
+const HEADER_SIZE: usize = 20;
+const SECTION_SIZE: usize = 40;
+const SYMBOL_SIZE: usize = 18;
+
+const IMAGE_SCN_CNT_CODE: u32 = 0x20;
+const IMAGE_SCN_CNT_INITIALIZED_DATA: u32 = 0x40;
+const IMAGE_SCN_ALIGN_1BYTES: u32 = 0x100000;
+const IMAGE_SCN_ALIGN_16BYTES: u32 = 0x500000;
+const IMAGE_SCN_MEM_DISCARDABLE: u32 = 0x2000000;
+const IMAGE_SCN_MEM_EXECUTE: u32 = 0x20000000;
+const IMAGE_SCN_MEM_READ: u32 = 0x40000000;
+
+const IMAGE_SYM_ABSOLUTE: i16 = -1;
+const IMAGE_SYM_DTYPE_FUNCTION: u16 = 0x20;
+const IMAGE_SYM_CLASS_EXTERNAL: u8 = 2;
+const IMAGE_SYM_CLASS_STATIC: u8 = 3;
+
+fn machine(target: &Target) -> Result<u16, String> {
+    if target.endianity != Endianity::Little {
+        return Err("COFF is little endian only".to_string());
+    }
+    Ok(match (target.machine, target.pointer_size) {
+        (EM_X86_64, _) => 0x8664,
+        (EM_386, _) => 0x14c,
+        (EM_AARCH64, _) => 0xaa64,
+        // thumb-2 windows
+        (EM_ARM, _) => 0x1c4,
+        (EM_RISCV, 8) => 0x5064,
+        (EM_RISCV, _) => 0x5032,
+        (machine, _) => return Err(format!("no COFF machine for ELF machine {}", machine)),
+    })
+}
+
+// the name field of a section header or a symbol, long names go to `strings`
+fn name(name: &str, strings: &mut Vec<u8>, section: bool) -> [u8; 8] {
+    let mut field = [0u8; 8];
+    if name.len() <= 8 {
+        field[..name.len()].copy_from_slice(name.as_bytes());
+        return field;
+    }
+    // the table's offsets count its own size field
+    let offset = strings.len() as u32 + 4;
+    strings.extend_from_slice(name.as_bytes());
+    strings.push(0);
+    if section {
+        let offset = format!("/{}", offset);
+        field[..offset.len()].copy_from_slice(offset.as_bytes());
+    } else {
+        field[4..].copy_from_slice(&offset.to_le_bytes());
+    }
+    field
+}
+
+pub fn write(
+    target: &Target,
+    sections: &HashMap<String, Section>,
+    symbols: &[(String, RawSymbol)],
+    output: &Path,
+) -> Err {
+    let machine = machine(target)?;
+    let mut strings = Vec::new();
+
+    let mut headers = Vec::new();
+    let mut contents = Vec::new();
+    let mut offset = (HEADER_SIZE + sections.len() * SECTION_SIZE) as u32;
+    for (section_name, section) in sections.iter() {
+        let raw = &section.raw;
+        let characteristics = match section_name.as_str() {
+            ".text" => {
+                IMAGE_SCN_CNT_CODE
+                    | IMAGE_SCN_ALIGN_16BYTES
+                    | IMAGE_SCN_MEM_EXECUTE
+                    | IMAGE_SCN_MEM_READ
+            }
+            _ => {
+                IMAGE_SCN_CNT_INITIALIZED_DATA
+                    | IMAGE_SCN_ALIGN_1BYTES
+                    | IMAGE_SCN_MEM_DISCARDABLE
+                    | IMAGE_SCN_MEM_READ
+            }
+        };
+        headers.extend_from_slice(&name(section_name, &mut strings, true));
+        // VirtualSize and VirtualAddress, zero in objects
+        headers.extend_from_slice(&[0; 8]);
+        headers.extend_from_slice(&(raw.len() as u32).to_le_bytes());
+        let pointer = if raw.is_empty() { 0 } else { offset };
+        headers.extend_from_slice(&pointer.to_le_bytes());
+        // no relocations or line numbers
+        headers.extend_from_slice(&[0; 12]);
+        headers.extend_from_slice(&characteristics.to_le_bytes());
+        contents.extend_from_slice(raw);
+        offset += raw.len() as u32;
+    }
+
+    let mut table = Vec::with_capacity(symbols.len() * SYMBOL_SIZE);
+    let mut written = 0u32;
+    for (symbol_name, symbol) in symbols {
+        let Ok(value) = u32::try_from(symbol.st_value) else {
+            continue;
+        };
+        table.extend_from_slice(&name(symbol_name, &mut strings, false));
+        table.extend_from_slice(&value.to_le_bytes());
+        table.extend_from_slice(&IMAGE_SYM_ABSOLUTE.to_le_bytes());
+        let kind = match symbol.st_info & 0xf {
+            sym::STT_FUNC => IMAGE_SYM_DTYPE_FUNCTION,
+            _ => 0,
+        };
+        table.extend_from_slice(&kind.to_le_bytes());
+        table.push(match symbol.st_info >> 4 {
+            sym::STB_LOCAL => IMAGE_SYM_CLASS_STATIC,
+            _ => IMAGE_SYM_CLASS_EXTERNAL,
+        });
+        // no auxiliary records
+        table.push(0);
+        written += 1;
+    }
+
+    let mut file = Vec::with_capacity(offset as usize + table.len() + strings.len() + 4);
+    file.extend_from_slice(&machine.to_le_bytes());
+    file.extend_from_slice(&(sections.len() as u16).to_le_bytes());
+    // no timestamp, so the output stays reproducible
+    file.extend_from_slice(&0u32.to_le_bytes());
+    file.extend_from_slice(&offset.to_le_bytes());
+    file.extend_from_slice(&written.to_le_bytes());
+    // no optional header and no characteristics
+    file.extend_from_slice(&[0; 4]);
+    file.extend_from_slice(&headers);
+    file.extend_from_slice(&contents);
+    file.extend_from_slice(&table);
+    file.extend_from_slice(&(strings.len() as u32 + 4).to_le_bytes());
+    file.extend_from_slice(&strings);
+    fs::write(output, file).map_err(|e| format!("{}: {}", output.display(), e))?;
+    Ok(())
+}
@@ -0,0 +1,297 @@
+// `--compress-debug-sections` zlib-compresses every .debug_* section like
+// `objcopy --compress-debug-sections`: by default as SHF_COMPRESSED with an
+// Elf_Chdr in front, with `=zlib-gnu` the old way as .zdebug_* starting with
+// "ZLIB" and the big endian uncompressed size.
+//
+// there's no zlib around, so this is a small deflate of its own: greedy LZ77
+// over a hash of the next three bytes, coded with the fixed huffman tables.
+// debug info is mostly repeated abbreviations, strings and references, which
+// that already shrinks to a fraction.
+
+use goblin::elf::compression_header::ELFCOMPRESS_ZLIB;
+
+use crate::target::Target;
+use crate::DynErr;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Style {
+    Zlib,
+    ZlibGnu,
+}
+
+const WINDOW: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+// how many earlier positions of a hash are tried
+const CHAIN: usize = 64;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+// deflate packs bits from the least significant one up
+struct Bits {
+    out: Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+
+impl Bits {
+    fn put(&mut self, value: u32, bits: u32) {
+        self.buffer |= (value as u64) << self.count;
+        self.count += bits;
+        while self.count >= 8 {
+            self.out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // huffman codes go most significant bit first
+    fn code(&mut self, code: u32, bits: u32) {
+        self.put(code.reverse_bits() >> (32 - bits), bits);
+    }
+
+    fn literal(&mut self, symbol: u32) {
+        match symbol {
+            0..=143 => self.code(0x30 + symbol, 8),
+            144..=255 => self.code(0x190 + symbol - 144, 9),
+            256..=279 => self.code(symbol - 256, 7),
+            _ => self.code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.buffer as u8);
+        }
+        self.out
+    }
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+fn hash(data: &[u8]) -> usize {
+    ((data[0] as usize) << 10 ^ (data[1] as usize) << 5 ^ data[2] as usize) & 0x7fff
+}
+
+// a zlib stream of `data`, a single fixed huffman block
+pub fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut bits = Bits {
+        // 32K window, no dictionary, fastest level
+        out: vec![0x78, 0x01],
+        buffer: 0,
+        count: 0,
+    };
+    // final block, fixed codes
+    bits.put(0b011, 3);
+
+    let mut head = vec![usize::MAX; 0x8000];
+    let mut previous = vec![usize::MAX; data.len()];
+    let mut position = 0;
+    while position < data.len() {
+        let mut best = (0, 0);
+        if position + MIN_MATCH <= data.len() {
+            let limit = (data.len() - position).min(MAX_MATCH);
+            let mut candidate = head[hash(&data[position..])];
+            for _ in 0..CHAIN {
+                if candidate == usize::MAX || position - candidate > WINDOW {
+                    break;
+                }
+                let length = data[candidate..]
+                    .iter()
+                    .zip(&data[position..position + limit])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if length > best.0 {
+                    best = (length, position - candidate);
+                }
+                if length == limit {
+                    break;
+                }
+                candidate = previous[candidate];
+            }
+        }
+
+        let step = if best.0 >= MIN_MATCH {
+            let (length, distance) = best;
+            let code = LENGTH_BASE
+                .iter()
+                .rposition(|base| *base as usize <= length)
+                .unwrap();
+            bits.literal(257 + code as u32);
+            bits.put(
+                (length - LENGTH_BASE[code] as usize) as u32,
+                LENGTH_EXTRA[code] as u32,
+            );
+            let code = DISTANCE_BASE
+                .iter()
+                .rposition(|base| *base as usize <= distance)
+                .unwrap();
+            bits.code(code as u32, 5);
+            bits.put(
+                (distance - DISTANCE_BASE[code] as usize) as u32,
+                DISTANCE_EXTRA[code] as u32,
+            );
+            length
+        } else {
+            bits.literal(data[position] as u32);
+            1
+        };
+        for at in position..position + step {
+            if at + MIN_MATCH <= data.len() {
+                let hash = hash(&data[at..]);
+                previous[at] = head[hash];
+                head[hash] = at;
+            }
+        }
+        position += step;
+    }
+    bits.literal(256);
+
+    let mut out = bits.finish();
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+// the name and contents of a compressed section, the name only changes for
+// zlib-gnu
+pub fn section(
+    target: &Target,
+    style: Style,
+    name: &str,
+    data: &[u8],
+) -> Result<(String, Vec<u8>), DynErr> {
+    let compressed = zlib(data);
+    let mut out = Vec::new();
+    match style {
+        Style::Zlib => {
+            out.extend(target.compression_header(ELFCOMPRESS_ZLIB, data.len() as u64, 1)?);
+            out.extend(compressed);
+            Ok((name.to_string(), out))
+        }
+        Style::ZlibGnu => {
+            out.extend_from_slice(b"ZLIB");
+            out.extend_from_slice(&(data.len() as u64).to_be_bytes());
+            out.extend(compressed);
+            Ok((name.replacen(".debug_", ".zdebug_", 1), out))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inflate(data: &[u8]) -> Vec<u8> {
+        let inflated = zlib(data);
+        // miniz checks the adler32 at the end too
+        miniz_oxide::inflate::decompress_to_vec_zlib(&inflated).unwrap()
+    }
+
+    // the same bytes every run, without runs of their own
+    fn noise(length: usize, seed: &mut u64) -> Vec<u8> {
+        (0..length)
+            .map(|_| {
+                *seed ^= *seed << 13;
+                *seed ^= *seed >> 7;
+                *seed ^= *seed << 17;
+                *seed as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn adler32_matches_zlib() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+    }
+
+    #[test]
+    fn empty_and_one_byte() {
+        assert_eq!(inflate(b""), b"");
+        assert_eq!(inflate(b"x"), b"x");
+        assert_eq!(inflate(&[0xff]), [0xff]);
+    }
+
+    #[test]
+    fn every_match_length() {
+        let mut seed = 1;
+        let mut data = Vec::new();
+        for length in MIN_MATCH..=MAX_MATCH + 1 {
+            let repeated = noise(length, &mut seed);
+            data.extend(&repeated);
+            data.extend(noise(16, &mut seed));
+            data.extend(&repeated);
+            data.extend(noise(16, &mut seed));
+        }
+        assert_eq!(inflate(&data), data);
+    }
+
+    #[test]
+    fn every_distance_code_boundary() {
+        let mut seed = 2;
+        let mut data = Vec::new();
+        let mut distances: Vec<usize> = (DISTANCE_BASE.iter())
+            .flat_map(|base| [*base as usize - 1, *base as usize])
+            .collect();
+        distances.extend([WINDOW - 1, WINDOW, WINDOW + 1]);
+        for distance in distances.into_iter().filter(|distance| *distance >= 1) {
+            let repeated = noise(MIN_MATCH.max(distance.min(8)), &mut seed);
+            data.extend(&repeated);
+            data.extend(noise(distance.saturating_sub(repeated.len()), &mut seed));
+            data.extend(&repeated);
+        }
+        assert_eq!(inflate(&data), data);
+    }
+
+    #[test]
+    fn long_runs() {
+        for byte in [0, 0xff] {
+            for length in [MAX_MATCH, MAX_MATCH + 1, MAX_MATCH + MIN_MATCH, 10_000] {
+                let data = vec![byte; length];
+                assert_eq!(inflate(&data), data);
+            }
+        }
+        let data = b"ab".repeat(5_000);
+        assert_eq!(inflate(&data), data);
+        let data = vec![0; 100_000];
+        assert!(zlib(&data).len() < 1_000);
+    }
+
+    #[test]
+    fn past_64k() {
+        let mut seed = 3;
+        let mut data = Vec::new();
+        let words: Vec<Vec<u8>> = (0..64).map(|_| noise(12, &mut seed)).collect();
+        while data.len() < 200_000 {
+            let pick = noise(2, &mut seed);
+            data.extend(&words[pick[0] as usize % words.len()][..4 + pick[1] as usize % 8]);
+        }
+        data.extend(noise(70_000, &mut seed));
+        assert_eq!(inflate(&data), data);
+    }
+}
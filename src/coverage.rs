@@ -0,0 +1,271 @@
+// `teemo coverage --binary chall`: how much of the binary the export types,
+// to see where reversing effort is still missing.
+//
+// the data side is every .data* and .bss* section against the exported
+// variables, the code side every function symbol against the exported
+// subprograms and the ones given with `--trampolines`. a stripped binary's
+// functions are the ranges .eh_frame's FDEs cover instead, and without those
+// it's how many bytes of the executable sections are typed. untyped
+// stretches are listed largest first.
+
+use std::fs;
+use std::path::Path;
+
+use gimli::read::{BaseAddresses, CieOrFde, EhFrame, UnwindSection};
+use gimli::RunTimeEndian;
+use goblin::elf::section_header::SHF_EXECINSTR;
+use goblin::elf::sym::STT_FUNC;
+use goblin::elf::Elf;
+
+use crate::layout::size_of;
+use crate::{collect, trampolines, DynErr, Err, HashMap, Options};
+
+// how many gaps and functions get listed
+const LISTED: usize = 10;
+
+struct Gap {
+    start: u64,
+    end: u64,
+    section: String,
+    // the variable just below the gap, for orientation
+    after: Option<String>,
+}
+
+// `whole` is never 0, there's nothing to report then
+fn percent(part: u64, whole: u64) -> f64 {
+    part as f64 * 100.0 / whole as f64
+}
+
+// (start, length) of every function .eh_frame has an FDE for
+fn fdes(elf: &Elf, data: &[u8]) -> Result<Vec<(u64, u64)>, DynErr> {
+    let section = match (elf.section_headers.iter())
+        .find(|section| elf.shdr_strtab.get_at(section.sh_name) == Some(".eh_frame"))
+    {
+        Some(section) => section,
+        None => return Ok(Vec::new()),
+    };
+    let start = section.sh_offset as usize;
+    let bytes = (data.get(start..start + section.sh_size as usize))
+        .ok_or(".eh_frame runs past the end of the file")?;
+    let endian = match elf.little_endian {
+        true => RunTimeEndian::Little,
+        false => RunTimeEndian::Big,
+    };
+    let mut eh_frame = EhFrame::new(bytes, endian);
+    eh_frame.set_address_size(if elf.is_64 { 8 } else { 4 });
+    let bases = BaseAddresses::default().set_eh_frame(section.sh_addr);
+
+    let mut ranges = Vec::new();
+    let mut entries = eh_frame.entries(&bases);
+    while let Some(entry) = entries.next()? {
+        if let CieOrFde::Fde(partial) = entry {
+            let fde = partial.parse(EhFrame::cie_from_offset)?;
+            if fde.len() != 0 {
+                ranges.push((fde.initial_address(), fde.len()));
+            }
+        }
+    }
+    Ok(ranges)
+}
+
+// how many bytes of start..end the (sorted) ranges cover
+fn overlap(ranges: &[(u64, u64)], start: u64, end: u64) -> u64 {
+    let mut cursor = start;
+    let mut bytes = 0;
+    for (range_start, range_end) in ranges.iter() {
+        let from = (*range_start).max(cursor);
+        let to = (*range_end).min(end);
+        if from < to {
+            bytes += to - from;
+            cursor = to;
+        }
+    }
+    bytes
+}
+
+pub fn run(options: &Options, binary: &Path) -> Err {
+    let data = fs::read(binary).map_err(|e| format!("{}: {}", binary.display(), e))?;
+    let elf = Elf::parse(&data)?;
+
+    let mut type_set = collect(options)?;
+    let (types, variables) = type_set.split_types();
+    let trampolines = match &options.trampolines {
+        Some(path) => trampolines::load(path)?,
+        None => HashMap::new(),
+    };
+
+    let mut ranges = Vec::new();
+    for section in elf.section_headers.iter() {
+        let name = elf.shdr_strtab.get_at(section.sh_name).unwrap_or("");
+        if section.sh_size != 0 && (name.starts_with(".data") || name.starts_with(".bss")) {
+            ranges.push((
+                section.sh_addr,
+                section.sh_addr + section.sh_size,
+                name.to_string(),
+            ));
+        }
+    }
+    if ranges.is_empty() {
+        return Err(format!("{}: no .data or .bss sections", binary.display()).into());
+    }
+
+    // typed bytes and the gaps between them, section by section
+    let mut total = 0;
+    let mut typed = 0;
+    let mut gaps = Vec::new();
+    for (start, end, section) in ranges.iter() {
+        total += end - start;
+        let mut cursor = *start;
+        let mut after = None;
+        for (address, variable) in variables.range(..*end) {
+            let size = match variable.size {
+                0 => size_of(&types, &variable.typename).unwrap_or(0),
+                size => size,
+            };
+            let variable_end = (address + size).min(*end);
+            if variable_end <= cursor {
+                continue;
+            }
+            let variable_start = (*address).max(cursor);
+            if variable_start > cursor {
+                gaps.push(Gap {
+                    start: cursor,
+                    end: variable_start,
+                    section: section.clone(),
+                    after: after.clone(),
+                });
+            }
+            typed += variable_end - variable_start;
+            cursor = variable_end;
+            after = Some(variable.name.clone());
+        }
+        if cursor < *end {
+            gaps.push(Gap {
+                start: cursor,
+                end: *end,
+                section: section.clone(),
+                after,
+            });
+        }
+    }
+    gaps.sort_by_key(|gap| std::cmp::Reverse(gap.end - gap.start));
+
+    // functions by address, symtab and dynsym name the same ones
+    let mut functions: HashMap<u64, (String, u64)> = HashMap::new();
+    for (symbols, strtab) in [(&elf.syms, &elf.strtab), (&elf.dynsyms, &elf.dynstrtab)] {
+        for symbol in symbols.iter() {
+            if symbol.st_type() != STT_FUNC || symbol.st_value == 0 {
+                continue;
+            }
+            let name = strtab.get_at(symbol.st_name).unwrap_or("").to_string();
+            _ = functions
+                .entry(symbol.st_value)
+                .or_insert((name, symbol.st_size));
+        }
+    }
+    // no symbols, the FDEs are the functions
+    let symbols = !functions.is_empty();
+    if !symbols {
+        for (address, size) in fdes(&elf, &data)? {
+            _ = functions.entry(address).or_insert((String::new(), size));
+        }
+    }
+    let covered = |address: u64| {
+        trampolines
+            .range(..=address)
+            .next_back()
+            .is_some_and(|(start, trampoline)| address < start + trampoline.size)
+            || type_set
+                .subprograms
+                .range(..=address)
+                .next_back()
+                .is_some_and(|(start, subprogram)| address < start + subprogram.size)
+    };
+    let mut untyped: Vec<(&u64, &(String, u64))> = functions
+        .iter()
+        .filter(|(address, _)| !covered(**address))
+        .collect();
+    untyped.sort_by_key(|(_, (_, size))| std::cmp::Reverse(*size));
+    let typed_functions = functions.len() - untyped.len();
+
+    println!(
+        "data: {:#x} of {:#x} bytes typed ({:.1}%)",
+        typed,
+        total,
+        percent(typed, total)
+    );
+    match (symbols, functions.len()) {
+        (true, count) => println!(
+            "functions: {} of {} typed ({:.1}%)",
+            typed_functions,
+            count,
+            percent(typed_functions as u64, count as u64)
+        ),
+        (false, 0) => {
+            // nor FDEs, by the executable sections' bytes then
+            let mut ranges: Vec<(u64, u64)> = (type_set.subprograms.iter())
+                .map(|(start, subprogram)| (*start, start + subprogram.size))
+                .chain(
+                    (trampolines.iter())
+                        .map(|(start, trampoline)| (*start, start + trampoline.size)),
+                )
+                .collect();
+            ranges.sort();
+            let mut code = 0;
+            let mut typed_code = 0;
+            for section in elf.section_headers.iter() {
+                if section.sh_flags & SHF_EXECINSTR as u64 == 0 || section.sh_addr == 0 {
+                    continue;
+                }
+                let end = section.sh_addr + section.sh_size;
+                code += section.sh_size;
+                typed_code += overlap(&ranges, section.sh_addr, end);
+            }
+            match code {
+                0 => println!("functions: no function symbols found"),
+                _ => println!(
+                    "functions: no function symbols found, {:#x} of {:#x} code bytes typed ({:.1}%)",
+                    typed_code,
+                    code,
+                    percent(typed_code, code)
+                ),
+            }
+        }
+        (false, count) => println!(
+            "functions: no function symbols found, {} of {} .eh_frame ranges typed ({:.1}%)",
+            typed_functions,
+            count,
+            percent(typed_functions as u64, count as u64)
+        ),
+    }
+
+    if !gaps.is_empty() {
+        println!("\nlargest untyped data:");
+    }
+    for gap in gaps.iter().take(LISTED) {
+        let after = match &gap.after {
+            Some(name) => format!(", after {}", name),
+            None => String::new(),
+        };
+        println!(
+            "  {:#x}-{:#x}  {:#8x} bytes  {}{}",
+            gap.start,
+            gap.end,
+            gap.end - gap.start,
+            gap.section,
+            after
+        );
+    }
+
+    if !untyped.is_empty() {
+        println!("\nlargest untyped functions:");
+    }
+    for (address, (name, size)) in untyped.iter().take(LISTED) {
+        match name.as_str() {
+            "" => println!("  {:#x}  {:#8x} bytes", address, size),
+            name => println!("  {:#x}  {:#8x} bytes  {}", address, size, name),
+        }
+    }
+
+    Ok(())
+}
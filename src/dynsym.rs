@@ -0,0 +1,368 @@
+// `--inject ./chall --dynsym win --dynsym check` also exports those functions
+// from the injected binary's .dynsym, so dlsym and LD_PRELOAD hooks find them
+// when it runs, not only gdb. they come from the .symtab of the debug file,
+// at the addresses the export gave them.
+//
+// the dynamic symbols, their names and versions and the hash tables are
+// rebuilt into a new read-only segment after everything else and .dynamic is
+// pointed at them, the old copies stay where they were, unused. the added
+// symbols are versionless, VER_NDX_GLOBAL. .gnu.hash has the symbols it hashes
+// grouped by bucket, so those get reordered and the dynamic relocations
+// renumbered to match. the segment takes the program header of one of the
+// binary's PT_NOTEs, see inject.rs.
+
+use goblin::elf::dynamic::{
+    Dyn, DT_GNU_HASH, DT_HASH, DT_JMPREL, DT_PLTREL, DT_PLTRELSZ, DT_REL, DT_RELA, DT_RELASZ,
+    DT_RELSZ, DT_STRSZ, DT_STRTAB, DT_SYMTAB, DT_VERSYM,
+};
+use goblin::elf::program_header::{ProgramHeader, PT_DYNAMIC, PT_LOAD};
+use goblin::elf::reloc::Reloc;
+use goblin::elf::section_header::{
+    SectionHeader, SHT_DYNSYM, SHT_GNU_HASH, SHT_GNU_VERSYM, SHT_HASH, SHT_NOBITS,
+};
+use goblin::elf::sym::{Sym, STB_GLOBAL, STT_FUNC};
+use goblin::elf::Elf;
+use log::info;
+use scroll::ctx::SizeWith;
+use scroll::{Pread, Pwrite};
+
+use crate::inject::{self, context};
+use crate::DynErr;
+
+// a function to add, as the debug file's .symtab has it
+pub struct Export {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+}
+
+// what the loader hashes names with for each table
+fn gnu_hash(name: &[u8]) -> u32 {
+    (name.iter()).fold(5381u32, |hash, byte| {
+        hash.wrapping_mul(33).wrapping_add(*byte as u32)
+    })
+}
+
+fn sysv_hash(name: &[u8]) -> u32 {
+    name.iter().fold(0u32, |hash, byte| {
+        let hash = (hash << 4).wrapping_add(*byte as u32);
+        let high = hash & 0xf000_0000;
+        (hash ^ (high >> 24)) & !high
+    })
+}
+
+// the file offset of an address in a loaded segment
+fn offset_of(headers: &[ProgramHeader], address: u64) -> Option<usize> {
+    (headers.iter())
+        .find(|header| {
+            header.p_type == PT_LOAD
+                && address >= header.p_vaddr
+                && address - header.p_vaddr < header.p_filesz
+        })
+        .map(|header| (address - header.p_vaddr + header.p_offset) as usize)
+}
+
+fn align(data: &mut Vec<u8>, alignment: usize) {
+    data.resize(data.len().next_multiple_of(alignment), 0);
+}
+
+// `data` with `exports` added to its dynamic symbols
+pub fn export(data: &[u8], exports: Vec<Export>) -> Result<Vec<u8>, DynErr> {
+    let elf = Elf::parse(data)?;
+    let ctx = context(&elf);
+    let segment = (elf.program_headers.iter()).find(|header| header.p_type == PT_DYNAMIC);
+    let (Some(dynamic), Some(dynamic_header)) = (&elf.dynamic, segment) else {
+        return Err("--dynsym needs a dynamically linked binary".into());
+    };
+    let tag = |tag: u64| {
+        (dynamic.dyns.iter())
+            .find(|d| d.d_tag == tag)
+            .map(|d| d.d_val)
+    };
+    let read = |address: u64, size: usize| -> Result<&[u8], DynErr> {
+        offset_of(&elf.program_headers, address)
+            .and_then(|offset| data.get(offset..offset + size))
+            .ok_or_else(|| format!("{:#x} isn't loaded from the binary", address).into())
+    };
+    let word = |bytes: &[u8]| -> u32 { bytes.pread_with(0, ctx.le).unwrap() };
+
+    // goblin already read the symbols through it
+    if tag(DT_SYMTAB).is_none() {
+        return Err("the binary has no DT_SYMTAB".into());
+    }
+    let strtab = tag(DT_STRTAB).ok_or("the binary has no DT_STRTAB")?;
+    let strsz = tag(DT_STRSZ).ok_or("the binary has no DT_STRSZ")?;
+    let gnu = tag(DT_GNU_HASH);
+    let hash = tag(DT_HASH);
+    if gnu.is_none() && hash.is_none() {
+        return Err("the binary has neither .gnu.hash nor .hash".into());
+    }
+    let versym = tag(DT_VERSYM);
+
+    let count = elf.dynsyms.len();
+    let mut symbols: Vec<Sym> = elf.dynsyms.iter().collect();
+    let mut names = read(strtab, strsz as usize)?.to_vec();
+    let mut versions: Option<Vec<u16>> = match versym {
+        Some(address) => {
+            let bytes = read(address, count * 2)?;
+            Some(
+                (0..count)
+                    .map(|i| bytes.pread_with(i * 2, ctx.le).unwrap())
+                    .collect(),
+            )
+        }
+        None => None,
+    };
+    // the first symbol .gnu.hash covers, the ones before are undefined
+    let first = match gnu {
+        Some(address) => word(&read(address, 8)?[4..]) as usize,
+        None => count,
+    };
+
+    for export in exports {
+        if (elf.dynsyms.iter()).any(|symbol| {
+            symbol.st_shndx != 0 && elf.dynstrtab.get_at(symbol.st_name) == Some(&export.name)
+        }) {
+            info!("`{}` is already a dynamic symbol", export.name);
+            continue;
+        }
+        // st_shndx only has to be a defined section for the load address to
+        // apply, SHN_ABS wouldn't get it
+        let section = (elf.section_headers.iter())
+            .position(|header| {
+                header.is_alloc()
+                    && header.sh_type != SHT_NOBITS
+                    && export.address >= header.sh_addr
+                    && export.address - header.sh_addr < header.sh_size
+            })
+            .ok_or_else(|| {
+                format!(
+                    "`{}` at {:#x} isn't in any section of the binary",
+                    export.name, export.address
+                )
+            })?;
+        symbols.push(Sym {
+            st_name: names.len(),
+            st_info: STB_GLOBAL << 4 | STT_FUNC,
+            st_other: 0,
+            st_shndx: section,
+            st_value: export.address,
+            st_size: export.size,
+        });
+        names.extend_from_slice(export.name.as_bytes());
+        names.push(0);
+        if let Some(versions) = &mut versions {
+            versions.push(1);
+        }
+    }
+    if symbols.len() == count {
+        return Ok(data.to_vec());
+    }
+
+    let name = |symbol: &Sym| -> &[u8] {
+        let start = symbol.st_name.min(names.len());
+        let end =
+            (names[start..].iter().position(|byte| *byte == 0)).map_or(names.len(), |n| start + n);
+        &names[start..end]
+    };
+    let buckets = ((symbols.len() - first) / 4).max(1);
+    let mut order: Vec<usize> = (0..symbols.len()).collect();
+    if gnu.is_some() {
+        order[first..].sort_by_key(|i| gnu_hash(name(&symbols[*i])) as usize % buckets);
+    }
+    let mut renumbered = vec![0; count];
+    for (new, old) in order.iter().enumerate() {
+        if *old < count {
+            renumbered[*old] = new;
+        }
+    }
+
+    let mut out = data.to_vec();
+    // the relocations naming a symbol that moved, each once, since .rela.plt
+    // is often inside DT_RELASZ too
+    if renumbered.iter().enumerate().any(|(old, new)| old != *new) {
+        let mut tables = Vec::new();
+        if let (Some(address), Some(size)) = (tag(DT_RELA), tag(DT_RELASZ)) {
+            tables.push((address, size, true));
+        }
+        if let (Some(address), Some(size)) = (tag(DT_REL), tag(DT_RELSZ)) {
+            tables.push((address, size, false));
+        }
+        if let (Some(address), Some(size)) = (tag(DT_JMPREL), tag(DT_PLTRELSZ)) {
+            tables.push((address, size, tag(DT_PLTREL) == Some(DT_RELA)));
+        }
+        let mut done = std::collections::BTreeSet::new();
+        for (address, size, rela) in tables {
+            let entry = Reloc::size_with(&(rela, ctx));
+            let start = offset_of(&elf.program_headers, address)
+                .ok_or_else(|| format!("relocations at {:#x} aren't loaded", address))?;
+            for offset in (start..start + size as usize).step_by(entry) {
+                if !done.insert(offset) {
+                    continue;
+                }
+                let mut reloc: Reloc = data.pread_with(offset, (rela, ctx))?;
+                if reloc.r_sym != 0 && reloc.r_sym < count {
+                    reloc.r_sym = renumbered[reloc.r_sym];
+                    out.pwrite_with(reloc, offset, (rela, ctx))?;
+                }
+            }
+        }
+    }
+
+    align(&mut out, 16);
+    let start = out.len();
+    let base = inject::free_address(&elf, start);
+    let at = |out: &Vec<u8>| base + (out.len() - start) as u64;
+
+    let symbol_size = Sym::size_with(&ctx);
+    let new_symtab = at(&out);
+    for i in order.iter() {
+        let offset = out.len();
+        out.resize(offset + symbol_size, 0);
+        out.pwrite_with(symbols[*i], offset, ctx)?;
+    }
+
+    let new_versym = match &versions {
+        Some(versions) => {
+            let address = at(&out);
+            for i in order.iter() {
+                let offset = out.len();
+                out.resize(offset + 2, 0);
+                out.pwrite_with(versions[*i], offset, ctx.le)?;
+            }
+            Some((address, at(&out)))
+        }
+        None => None,
+    };
+
+    let push = |out: &mut Vec<u8>, value: u32| {
+        let offset = out.len();
+        out.resize(offset + 4, 0);
+        out.pwrite_with(value, offset, ctx.le).unwrap();
+    };
+    let hashed: Vec<&[u8]> = order.iter().map(|i| name(&symbols[*i])).collect();
+
+    let new_gnu = match gnu {
+        Some(_) => {
+            align(&mut out, 8);
+            let address = at(&out);
+            let (bits, shift) = match ctx.container.is_big() {
+                true => (64, 6),
+                false => (32, 5),
+            };
+            let words = ((hashed.len() - first) * 2 / bits)
+                .max(1)
+                .next_power_of_two();
+            let mut bloom = vec![0u64; words];
+            for name in hashed[first..].iter() {
+                let hash = gnu_hash(name) as usize;
+                bloom[hash / bits % words] |= 1 << (hash % bits) | 1 << ((hash >> shift) % bits);
+            }
+            for value in [buckets, first, words, shift] {
+                push(&mut out, value as u32);
+            }
+            for value in bloom {
+                let offset = out.len();
+                out.resize(offset + bits / 8, 0);
+                match bits {
+                    64 => out.pwrite_with(value, offset, ctx.le)?,
+                    _ => out.pwrite_with(value as u32, offset, ctx.le)?,
+                };
+            }
+            let mut heads = vec![0u32; buckets];
+            for (i, name) in hashed.iter().enumerate().skip(first).rev() {
+                heads[gnu_hash(name) as usize % buckets] = i as u32;
+            }
+            for head in heads {
+                push(&mut out, head);
+            }
+            // the low bit ends a bucket's run
+            for (i, name) in hashed.iter().enumerate().skip(first) {
+                let last = hashed.get(i + 1).is_none_or(|next| {
+                    gnu_hash(next) as usize % buckets != gnu_hash(name) as usize % buckets
+                });
+                push(&mut out, gnu_hash(name) & !1 | last as u32);
+            }
+            Some((address, at(&out)))
+        }
+        None => None,
+    };
+
+    let new_hash = match hash {
+        Some(_) => {
+            align(&mut out, 4);
+            let address = at(&out);
+            let buckets = (hashed.len() / 2).max(1);
+            let mut heads = vec![0u32; buckets];
+            let mut chains = vec![0u32; hashed.len()];
+            for (i, name) in hashed.iter().enumerate().skip(1) {
+                let bucket = sysv_hash(name) as usize % buckets;
+                chains[i] = heads[bucket];
+                heads[bucket] = i as u32;
+            }
+            push(&mut out, buckets as u32);
+            push(&mut out, hashed.len() as u32);
+            for value in heads.into_iter().chain(chains) {
+                push(&mut out, value);
+            }
+            Some((address, at(&out)))
+        }
+        None => None,
+    };
+
+    let new_symtab = (
+        new_symtab,
+        new_symtab + (symbols.len() * symbol_size) as u64,
+    );
+    let new_strtab = (at(&out), at(&out) + names.len() as u64);
+    out.extend_from_slice(&names);
+    let size = (out.len() - start) as u64;
+
+    // .dynamic in place
+    let entry = Dyn::size_with(&ctx);
+    for (i, d) in dynamic.dyns.iter().enumerate() {
+        let value = match (d.d_tag, new_gnu, new_hash, new_versym) {
+            (DT_SYMTAB, ..) => new_symtab.0,
+            (DT_STRTAB, ..) => new_strtab.0,
+            (DT_STRSZ, ..) => names.len() as u64,
+            (DT_GNU_HASH, Some(gnu), ..) => gnu.0,
+            (DT_HASH, _, Some(hash), _) => hash.0,
+            (DT_VERSYM, .., Some(versym)) => versym.0,
+            _ => continue,
+        };
+        let d = Dyn {
+            d_tag: d.d_tag,
+            d_val: value,
+        };
+        out.pwrite_with(d, dynamic_header.p_offset as usize + i * entry, ctx)?;
+    }
+
+    // the section headers follow, for the tools reading those
+    let strings = (elf.section_headers.iter())
+        .find(|header| header.sh_type == SHT_DYNSYM)
+        .map(|header| header.sh_link as usize);
+    let shentsize = SectionHeader::size(ctx);
+    for (i, header) in elf.section_headers.iter().enumerate() {
+        let range = match header.sh_type {
+            SHT_DYNSYM => Some(new_symtab),
+            SHT_GNU_VERSYM => new_versym,
+            SHT_GNU_HASH => new_gnu,
+            SHT_HASH => new_hash,
+            _ if Some(i) == strings => Some(new_strtab),
+            _ => continue,
+        };
+        let Some((address, end)) = range else {
+            continue;
+        };
+        let mut header = header.clone();
+        header.sh_addr = address;
+        header.sh_offset = address - base + start as u64;
+        header.sh_size = end - address;
+        out.pwrite_with(header, elf.header.e_shoff as usize + i * shentsize, ctx)?;
+    }
+
+    inject::map(&mut out, &elf, start, size, "--dynsym")?;
+
+    info!("{} dynamic symbols added", symbols.len() - count);
+    Ok(out)
+}
@@ -0,0 +1,156 @@
+// `teemo explain 0x404078`: which variable an address falls into and the
+// field path down to it, the arithmetic otherwise done by hand while reading
+// crash dumps.
+//
+// 0x404078: main_arena+0x58
+//   +0x0     main_arena      malloc_state (0x898 bytes)
+//   +0x58    .bins[9]        mchunkptr (0x8 bytes)
+//
+// unions descend into their first arm covering the offset and name the rest
+// on that arm's line.
+
+use crate::layout::size_of;
+use crate::{collect, BinjaType, Class, Err, GlobalVariable, HashMap, Options};
+
+// `name` behind any typedefs and qualifiers, as long as they don't loop
+fn resolve<'a>(types: &'a HashMap<String, BinjaType>, name: &str) -> Option<&'a BinjaType> {
+    let mut binja_type = types.get(name)?;
+    for _ in 0..64 {
+        match binja_type {
+            BinjaType::Typedef(typedef) => binja_type = types.get(&typedef.target)?,
+            BinjaType::Qualified(qualified) => binja_type = types.get(&qualified.target)?,
+            _ => return Some(binja_type),
+        }
+    }
+    None
+}
+
+fn describe(types: &HashMap<String, BinjaType>, typename: &str) -> String {
+    let name = if typename.is_empty() {
+        "void"
+    } else {
+        typename
+    };
+    match size_of(types, typename) {
+        Some(size) => format!("{} ({:#x} bytes)", name, size),
+        None => name.to_string(),
+    }
+}
+
+pub fn explain(
+    types: &HashMap<String, BinjaType>,
+    variables: &HashMap<u64, GlobalVariable>,
+    address: u64,
+) -> Result<Vec<String>, String> {
+    // the closest variable at or below the address that still covers it
+    let Some((base, variable)) = variables.range(..=address).next_back() else {
+        return Err(format!("no variable at or below {:#x}", address));
+    };
+    let size = match variable.size {
+        0 => size_of(types, &variable.typename).unwrap_or(0),
+        size => size,
+    };
+    if address - base >= size.max(1) {
+        return Err(format!(
+            "{:#x} is past the end of {} ({:#x} bytes at {:#x})",
+            address, variable.name, size, base
+        ));
+    }
+
+    let mut offset = address - base;
+    let mut lines = vec![format!("{:#x}: {}+{:#x}", address, variable.name, offset)];
+    let mut at = 0;
+    let mut step = variable.name.clone();
+    let mut typename = variable.typename.clone();
+    let mut also = String::new();
+
+    // one line per level, until a type without members
+    for _ in 0..64 {
+        lines.push(format!(
+            "  +{:<8}{:<16}{}{}",
+            format!("{:#x}", at),
+            step,
+            describe(types, &typename),
+            also
+        ));
+        also.clear();
+
+        match resolve(types, &typename) {
+            Some(
+                BinjaType::Structure(s)
+                | BinjaType::Union(s)
+                | BinjaType::Class(Class { layout: s, .. }),
+            ) => {
+                let covering: Vec<_> = s
+                    .fields
+                    .iter()
+                    .filter(|field| {
+                        let size = size_of(types, &field.typename).unwrap_or(0);
+                        field.offset <= offset && offset < field.offset + size.max(1)
+                    })
+                    .collect();
+                let Some(field) = covering.first() else {
+                    let before = s.fields.iter().rev().find(|field| field.offset <= offset);
+                    lines.push(match before {
+                        Some(field) => {
+                            format!("  +{:#x} is padding after .{}", at + offset, field.name)
+                        }
+                        None => format!("  +{:#x} is padding", at + offset),
+                    });
+                    return Ok(lines);
+                };
+                if covering.len() > 1 {
+                    let others: Vec<&str> = covering[1..].iter().map(|f| f.name.as_str()).collect();
+                    also = format!(", also .{}", others.join(", ."));
+                }
+                offset -= field.offset;
+                at += field.offset;
+                step = format!(".{}", field.name);
+                typename = field.typename.clone();
+            }
+            Some(BinjaType::Array(array)) => {
+                let element = size_of(types, &array.target).unwrap_or(0);
+                if element == 0 {
+                    break;
+                }
+                let index = offset / element;
+                offset -= index * element;
+                at += index * element;
+                step = array.subscripts(index);
+                typename = array.target.clone();
+            }
+            Some(BinjaType::Vector(vector)) => {
+                let lane = size_of(types, &vector.target).unwrap_or(0);
+                if lane == 0 {
+                    break;
+                }
+                let index = offset / lane;
+                offset -= index * lane;
+                at += index * lane;
+                step = format!("[{}]", index);
+                typename = vector.target.clone();
+            }
+            _ => break,
+        }
+    }
+
+    // an address inside a scalar, like the second byte of a pointer
+    if offset != 0 {
+        let plural = if offset == 1 { "" } else { "s" };
+        lines.push(format!(
+            "  +{:#x} is {} byte{} into it",
+            at + offset,
+            offset,
+            plural
+        ));
+    }
+    Ok(lines)
+}
+
+pub fn run(options: &Options, address: u64) -> Err {
+    let (types, variables) = collect(options)?.split();
+    for line in explain(&types, &variables, address)? {
+        println!("{}", line);
+    }
+    Ok(())
+}
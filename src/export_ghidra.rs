@@ -0,0 +1,502 @@
+// `teemo export-ghidra types.py` writes a ghidra script that builds the
+// export's types (or the DWARF in FILE's) in ghidra's data type manager, so
+// a team with binja and ghidra users shares the one set of types. run it
+// from the script manager to add them to the open program, or headless with
+// a path to have them in a data type archive instead:
+//
+//   analyzeHeadless /tmp proj -import chall -postScript types.py heap.gdt
+//
+// they go under /teemo with the members at the export's offsets, in
+// structures without packing, so nothing hangs on ghidra's idea of the
+// alignment. bitfields are placed in the fewest bytes holding them, the
+// shift counted in the target's byte order. ghidra's union members all start
+// at 0, the ones past it get a structure of their own, named after the union
+// and the member. qualifiers are left off, ghidra doesn't keep them, vectors
+// are arrays and member pointers their bytes. the script works in jython and
+// pyghidra alike, and rerunning it replaces the types it made before.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::gdb_scripts::quote;
+use crate::layout::size_of;
+use crate::{
+    collect, readback, BinjaType, DynErr, Encoding, Endianity, Err, Field, HashMap, Options,
+    Structure,
+};
+
+const PRELUDE: &str = r#"# ghidra data types teemo generated from the export. run it from the script
+# manager to add them to the current program, or with a .gdt path as its
+# argument (or without a program) to write them to that archive
+import json
+import sys
+
+from java.io import File
+from ghidra.program.model.data import (
+    AbstractFloatDataType,
+    AbstractIntegerDataType,
+    ArrayDataType,
+    BooleanDataType,
+    CategoryPath,
+    CharDataType,
+    DataTypeConflictHandler,
+    EnumDataType,
+    FileDataTypeManager,
+    FunctionDefinitionDataType,
+    ParameterDefinitionImpl,
+    PointerDataType,
+    StructureDataType,
+    TypedefDataType,
+    Undefined1DataType,
+    UnionDataType,
+    UnsignedCharDataType,
+    VoidDataType,
+)
+
+CATEGORY = CategoryPath("/teemo")
+REPLACE = DataTypeConflictHandler.REPLACE_HANDLER
+
+
+class Builder:
+    def __init__(self, dtm):
+        self.dtm = dtm
+        self.built, self.filled, self.failed = {}, set(), []
+
+    def integer(self, size, signed, encoding):
+        if encoding == "bool" and size == 1:
+            return BooleanDataType.dataType
+        if encoding == "char" and size == 1:
+            return CharDataType.dataType if signed else UnsignedCharDataType.dataType
+        if encoding == "float":
+            return AbstractFloatDataType.getFloatDataType(size, self.dtm)
+        if signed:
+            return AbstractIntegerDataType.getSignedDataType(size, self.dtm)
+        return AbstractIntegerDataType.getUnsignedDataType(size, self.dtm)
+
+    # empty structures and unions first, for the pointers to them
+    def declare(self):
+        for name, entry in sorted(TYPES.items()):
+            if entry["kind"] == "struct":
+                stub = StructureDataType(CATEGORY, name, 0, self.dtm)
+            elif entry["kind"] == "union":
+                stub = UnionDataType(CATEGORY, name, self.dtm)
+            else:
+                continue
+            self.built[name] = self.dtm.addDataType(stub, REPLACE)
+
+    # the structures and unions `name` holds get their members
+    def complete(self, name):
+        entry = TYPES.get(name)
+        if entry is None:
+            return
+        if entry["kind"] in ("struct", "union"):
+            self.fill(name)
+        elif entry["kind"] in ("typedef", "array"):
+            self.complete(entry["target"])
+
+    def get(self, name):
+        if name == "":
+            return VoidDataType.dataType
+        if name in self.built:
+            return self.built[name]
+        entry = TYPES[name]
+        kind = entry["kind"]
+        if kind == "integer":
+            data_type = self.integer(entry["size"], entry["signed"], entry["encoding"])
+            if " " not in name and name != data_type.getName():
+                data_type = TypedefDataType(CATEGORY, name, data_type, self.dtm)
+        elif kind == "pointer":
+            data_type = PointerDataType(self.get(entry["target"]), entry["size"], self.dtm)
+        elif kind == "typedef":
+            data_type = TypedefDataType(CATEGORY, name, self.get(entry["target"]), self.dtm)
+        elif kind == "function":
+            data_type = FunctionDefinitionDataType(CATEGORY, name, self.dtm)
+            data_type.setReturnType(self.get(entry["returns"]))
+            data_type.setArguments([
+                ParameterDefinitionImpl(parameter or None, self.get(typename), None)
+                for parameter, typename in entry["parameters"]
+            ])
+            data_type.setVarArgs(entry["variadic"])
+        elif kind == "enum":
+            data_type = EnumDataType(CATEGORY, name, entry["size"], self.dtm)
+            for enumerator, value in entry["values"]:
+                data_type.add(enumerator, value)
+        else:
+            self.complete(entry["target"])
+            data_type, size = self.get(entry["target"]), entry["size"]
+            for count in reversed(entry["counts"]):
+                data_type = ArrayDataType(data_type, count, size, self.dtm)
+                size = data_type.getLength()
+        data_type = self.dtm.resolve(data_type, REPLACE)
+        self.built[name] = data_type
+        return data_type
+
+    def member_type(self, member):
+        if member["typename"] is None:
+            return self.integer(member["bits"][0], False, None)
+        self.complete(member["typename"])
+        return self.get(member["typename"])
+
+    def place(self, whole, member):
+        try:
+            data_type = self.member_type(member)
+            name = member["name"] or None
+            if member["bits"]:
+                width, shift, bits = member["bits"]
+                whole.insertBitFieldAt(member["offset"], width, shift, data_type, bits, name, None)
+            elif member["size"] == 0:
+                whole.insertAtOffset(member["offset"], data_type, 0, name, None)
+            else:
+                whole.replaceAtOffset(member["offset"], data_type, member["size"], name, None)
+        except:
+            self.failed.append("%s.%s: %s" % (whole.getName(), member["name"], sys.exc_info()[1]))
+
+    def fill(self, name):
+        if name in self.filled:
+            return
+        self.filled.add(name)
+        entry = TYPES[name]
+        if entry["kind"] == "struct":
+            if entry["declaration"]:
+                return
+            whole = StructureDataType(CATEGORY, name, entry["size"], self.dtm)
+            for member in entry["members"]:
+                self.place(whole, member)
+        else:
+            whole = UnionDataType(CATEGORY, name, self.dtm)
+            for member in entry["members"]:
+                label = member["name"] or None
+                if member["moved"]:
+                    end = member["offset"] + (member["bits"][0] if member["bits"] else member["size"])
+                    moved = StructureDataType(CATEGORY, "%s_%s" % (name, member["name"]), end, self.dtm)
+                    self.place(moved, member)
+                    whole.add(self.dtm.resolve(moved, REPLACE), end, label, None)
+                elif member["bits"]:
+                    whole.addBitField(self.member_type(member), member["bits"][2], label, None)
+                else:
+                    whole.add(self.member_type(member), member["size"], label, None)
+            if whole.getLength() < entry["size"]:
+                whole.add(ArrayDataType(Undefined1DataType.dataType, entry["size"], 1), entry["size"], None, None)
+        self.built[name].replaceWith(whole)
+
+"#;
+
+const MAIN: &str = r#"
+
+def main():
+    args = getScriptArgs()
+    archive = None
+    if len(args) or currentProgram is None:
+        path = File(args[0]) if len(args) else askFile("teemo: write the types to", "Write")
+        if path.exists():
+            archive = FileDataTypeManager.openFileArchive(path, True)
+        else:
+            archive = FileDataTypeManager.createFileArchive(path)
+        dtm = archive
+    else:
+        dtm = currentProgram.getDataTypeManager()
+    builder = Builder(dtm)
+    transaction = dtm.startTransaction("teemo types")
+    try:
+        builder.declare()
+        for name in sorted(TYPES):
+            builder.complete(name)
+            builder.get(name)
+    finally:
+        dtm.endTransaction(transaction, True)
+    if archive is not None:
+        archive.save()
+        archive.close()
+    for failure in builder.failed:
+        print("teemo: couldn't place " + failure)
+    print("teemo: %d types into %s" % (len(builder.built), dtm.getName()))
+
+
+main()
+"#;
+
+// what the script makes of a type, with the qualifiers looked through and
+// the sizes worked out
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum Entry<'a> {
+    Integer {
+        size: u64,
+        signed: bool,
+        encoding: Option<&'static str>,
+    },
+    Pointer {
+        size: u64,
+        target: &'a str,
+    },
+    Typedef {
+        target: &'a str,
+    },
+    Function {
+        returns: &'a str,
+        parameters: Vec<(&'a str, &'a str)>,
+        variadic: bool,
+    },
+    Enum {
+        size: u64,
+        values: Vec<(&'a str, i64)>,
+    },
+    // the element's size, then the dimensions outermost first
+    Array {
+        target: &'a str,
+        size: u64,
+        counts: Vec<u64>,
+    },
+    Struct {
+        size: u64,
+        declaration: bool,
+        members: Vec<Member<'a>>,
+    },
+    Union {
+        size: u64,
+        members: Vec<Member<'a>>,
+    },
+}
+
+#[derive(Serialize)]
+struct Member<'a> {
+    offset: u64,
+    name: String,
+    // None for a bitfield of something that isn't an integer
+    typename: Option<&'a str>,
+    size: u64,
+    // bitfields: the bytes from `offset` holding them, the shift in there
+    // and the width
+    bits: Option<(u64, u64, u64)>,
+    // a union member past the start
+    moved: bool,
+}
+
+struct Lowering<'a> {
+    types: &'a HashMap<String, BinjaType>,
+    pointer_size: u64,
+    endianity: Endianity,
+}
+
+impl<'a> Lowering<'a> {
+    fn pointer(&self, size: u64) -> u64 {
+        match size {
+            0 => self.pointer_size,
+            size => size,
+        }
+    }
+
+    // `name` without the qualifiers on it
+    fn plain(&self, mut name: &'a str) -> &'a str {
+        for _ in 0..64 {
+            match self.types.get(name) {
+                Some(BinjaType::Qualified(qualified)) => name = &qualified.target,
+                _ => break,
+            }
+        }
+        name
+    }
+
+    // whether a bitfield of `name` can be one in ghidra, an integer or enum
+    fn integral(&self, mut name: &'a str) -> bool {
+        for _ in 0..64 {
+            match self.types.get(name) {
+                Some(BinjaType::Typedef(typedef)) => name = &typedef.target,
+                Some(BinjaType::Qualified(qualified)) => name = &qualified.target,
+                Some(BinjaType::Integer(_) | BinjaType::Enum(_)) => return true,
+                _ => break,
+            }
+        }
+        false
+    }
+
+    fn member(&self, field: &'a Field, name: String, union: bool) -> Member<'a> {
+        let typename = self.plain(&field.typename);
+        let size = size_of(self.types, typename).unwrap_or(0);
+        match field.bit_size {
+            Some(bits) => {
+                let start = field.offset * 8 + field.bit_offset.unwrap_or(0);
+                let within = start % 8;
+                let width = (within + bits).div_ceil(8);
+                let shift = match self.endianity {
+                    Endianity::Little => within,
+                    Endianity::Big => width * 8 - within - bits,
+                };
+                Member {
+                    offset: start / 8,
+                    name,
+                    typename: self.integral(typename).then_some(typename),
+                    size,
+                    bits: Some((width, shift, bits)),
+                    moved: union && (start >= 8 || shift != 0 || self.endianity == Endianity::Big),
+                }
+            }
+            None => Member {
+                offset: field.offset,
+                name,
+                typename: Some(typename),
+                size,
+                bits: None,
+                moved: union && field.offset != 0,
+            },
+        }
+    }
+
+    fn members(&self, layout: &'a Structure, union: bool) -> Vec<Member<'a>> {
+        (layout.fields.iter())
+            .map(|field| self.member(field, field.name.clone(), union))
+            .collect()
+    }
+
+    fn entry(&self, binja_type: &'a BinjaType) -> Option<Entry<'a>> {
+        Some(match binja_type {
+            BinjaType::Integer(integer) => Entry::Integer {
+                size: integer.size,
+                signed: integer.signed,
+                encoding: integer.encoding.map(|encoding| match encoding {
+                    Encoding::Char => "char",
+                    Encoding::Float => "float",
+                    Encoding::Bool => "bool",
+                }),
+            },
+            BinjaType::Pointer(pointer) => match pointer.member_of {
+                Some(_) => Entry::Integer {
+                    size: self.pointer(pointer.size),
+                    signed: false,
+                    encoding: None,
+                },
+                None => Entry::Pointer {
+                    size: self.pointer(pointer.size),
+                    target: self.plain(&pointer.target),
+                },
+            },
+            BinjaType::Typedef(typedef) => Entry::Typedef {
+                target: self.plain(&typedef.target),
+            },
+            BinjaType::Qualified(_) => return None,
+            BinjaType::Function(function) => Entry::Function {
+                returns: self.plain(&function.returntype),
+                parameters: (function.parameters.iter())
+                    .map(|parameter| (parameter.name.as_str(), self.plain(&parameter.typename)))
+                    .collect(),
+                variadic: function.variadic,
+            },
+            BinjaType::Enum(enumeration) => match enumeration.size {
+                1 | 2 | 4 | 8 => Entry::Enum {
+                    size: enumeration.size,
+                    values: (enumeration.fields.iter())
+                        .map(|field| {
+                            (
+                                field.name.as_str(),
+                                enumeration.normalize(field.value) as i64,
+                            )
+                        })
+                        .collect(),
+                },
+                size => Entry::Integer {
+                    size,
+                    signed: enumeration.signed,
+                    encoding: None,
+                },
+            },
+            BinjaType::Array(array) => match &array.dynamic {
+                Some(dynamic) if dynamic.from_data => Entry::Pointer {
+                    size: self.pointer_size,
+                    target: self.plain(&array.target),
+                },
+                Some(dynamic) => Entry::Integer {
+                    size: self.pointer(dynamic.size),
+                    signed: false,
+                    encoding: None,
+                },
+                None => {
+                    let target = self.plain(&array.target);
+                    Entry::Array {
+                        target,
+                        size: size_of(self.types, target).unwrap_or(0),
+                        counts: array.counts().collect(),
+                    }
+                }
+            },
+            BinjaType::Vector(vector) => {
+                let target = self.plain(&vector.target);
+                Entry::Array {
+                    target,
+                    size: size_of(self.types, target).unwrap_or(0),
+                    counts: vec![vector.count],
+                }
+            }
+            BinjaType::Structure(structure) => Entry::Struct {
+                size: structure.size,
+                declaration: structure.declaration,
+                members: self.members(structure, false),
+            },
+            BinjaType::Union(union) => Entry::Union {
+                size: union.size,
+                members: self.members(union, true),
+            },
+            // the bases are members like ghidra's own DWARF import has them
+            BinjaType::Class(class) => {
+                let mut members = self.members(&class.layout, false);
+                for base in class.bases.iter() {
+                    let typename = self.plain(&base.typename);
+                    members.push(Member {
+                        offset: base.offset,
+                        name: format!("super_{}", base.typename),
+                        typename: Some(typename),
+                        size: size_of(self.types, typename).unwrap_or(0),
+                        bits: None,
+                        moved: false,
+                    });
+                }
+                Entry::Struct {
+                    size: class.layout.size,
+                    declaration: class.layout.declaration,
+                    members,
+                }
+            }
+        })
+    }
+}
+
+pub fn script(
+    types: &HashMap<String, BinjaType>,
+    pointer_size: u64,
+    endianity: Endianity,
+) -> Result<String, DynErr> {
+    let lowering = Lowering {
+        types,
+        pointer_size,
+        endianity,
+    };
+    let entries: HashMap<&str, Entry> = (types.iter())
+        .filter_map(|(name, binja_type)| Some((name.as_str(), lowering.entry(binja_type)?)))
+        .collect();
+    let mut script = String::from(PRELUDE);
+    script.push_str(&format!(
+        "\nTYPES = json.loads({})\n",
+        quote(&serde_json::to_string(&entries)?)
+    ));
+    script.push_str(MAIN);
+    Ok(script)
+}
+
+pub fn run(options: &Options, path: &Path, file: Option<&Path>) -> Err {
+    let (mut type_set, pointer_size, endianity) = match file {
+        Some(file) => {
+            let dies = readback::Dies::from_elf(file)?;
+            (dies.to_type_set(), dies.address_size, dies.endianity)
+        }
+        None => (
+            collect(options)?,
+            options.target.pointer_size,
+            options.target.endianity,
+        ),
+    };
+    let (types, _) = type_set.split_types();
+    let script = script(&types, pointer_size, endianity)?;
+    fs::write(path, script).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(())
+}
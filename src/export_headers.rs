@@ -0,0 +1,728 @@
+// `teemo export-headers types.h` renders the export (or the DWARF in FILE)
+// back into a C header, so exploit code can use the reversed types as they
+// are instead of retyping them:
+//
+//   typedef struct node node;
+//   struct node {
+//       node *next;
+//       uint32_t key;
+//       char __pad_c[4];
+//   };
+//   _Static_assert(sizeof(node) == 16, "node");
+//
+// every struct, union and class is forward declared first and the rest
+// follows in the order the definitions need each other. members go where
+// the export has them, with the holes spelled out as `__pad_` members, and a
+// struct whose members can't all be naturally aligned there (or whose size
+// isn't a multiple of their alignment) gets `__attribute__((packed))`.
+// alignments are taken as large as the size allows, which at worst packs a
+// struct that didn't need it, and the asserts are there for what's left:
+// bitfields placed the SysV way, and pointers of another size than the
+// compiler's.
+//
+// integers come out as the <stdint.h> ones of their size, the export's names
+// for them as typedefs. enums that don't fit the 4 bytes of a C enum get a
+// typedef of their size for the members to use, their enumerators are still
+// defined. C++ classes are structs with their bases as `__base_` members, a
+// member pointer is just its bytes.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use crate::{
+    collect, readback, BinjaType, DynErr, Encoding, Enum, Err, Field, HashMap, Options, Qualified,
+};
+
+// spelled the same whatever the export's sizes for them, the integers named
+// like this come out as their <stdint.h> ones
+const KEYWORDS: &[&str] = &[
+    "signed", "unsigned", "char", "short", "int", "long", "float", "double", "_Bool", "__int128",
+];
+const PROVIDED: &[&str] = &[
+    "int8_t",
+    "uint8_t",
+    "int16_t",
+    "uint16_t",
+    "int32_t",
+    "uint32_t",
+    "int64_t",
+    "uint64_t",
+    "intptr_t",
+    "uintptr_t",
+    "size_t",
+    "ssize_t",
+    "ptrdiff_t",
+    "wchar_t",
+    "char16_t",
+    "char32_t",
+    "bool",
+];
+
+pub fn builtin(name: &str) -> bool {
+    PROVIDED.contains(&name) || name.split(' ').all(|word| KEYWORDS.contains(&word))
+}
+
+// `name` as a C identifier
+pub fn ident(name: &str) -> String {
+    let mut ident: String = (name.chars())
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        ident.insert(0, '_');
+    }
+    ident
+}
+
+// the largest power of two dividing `size`, up to 16
+pub fn natural(size: u64) -> u64 {
+    match size {
+        0 => 1,
+        size => (1 << size.trailing_zeros()).min(16),
+    }
+}
+
+// the C type for an integer of `size` bytes, when there is one
+fn canonical(size: u64, signed: bool, encoding: Option<Encoding>) -> Option<&'static str> {
+    Some(match (encoding, size, signed) {
+        (Some(Encoding::Bool), 1, _) => "_Bool",
+        (Some(Encoding::Float), 2, _) => "_Float16",
+        (Some(Encoding::Float), 4, _) => "float",
+        (Some(Encoding::Float), 8, _) => "double",
+        (Some(Encoding::Float), 16, _) => "long double",
+        (Some(Encoding::Float), _, _) => return None,
+        (Some(Encoding::Char), 1, true) => "char",
+        (Some(Encoding::Char), 1, false) => "unsigned char",
+        (_, 1, true) => "int8_t",
+        (_, 1, false) => "uint8_t",
+        (_, 2, true) => "int16_t",
+        (_, 2, false) => "uint16_t",
+        (_, 4, true) => "int32_t",
+        (_, 4, false) => "uint32_t",
+        (_, 8, true) => "int64_t",
+        (_, 8, false) => "uint64_t",
+        (_, 16, true) => "__int128",
+        (_, 16, false) => "unsigned __int128",
+        _ => return None,
+    })
+}
+
+fn words(qualified: &Qualified) -> Vec<&'static str> {
+    [
+        (qualified.constant, "const"),
+        (qualified.volatile, "volatile"),
+        (qualified.restrict, "restrict"),
+    ]
+    .into_iter()
+    .filter_map(|(set, word)| set.then_some(word))
+    .collect()
+}
+
+// `char __pad_10[4];`
+fn pad(offset: u64, size: u64) -> String {
+    format!("char __pad_{:x}[{}];", offset, size)
+}
+
+// what a struct or union is written as
+struct Plan {
+    packed: bool,
+    align: u64,
+    lines: Vec<String>,
+}
+
+pub struct Header<'a> {
+    types: &'a HashMap<String, BinjaType>,
+    pointer_size: u64,
+    // (size, alignment) as the header lays them out
+    layouts: HashMap<String, (u64, u64)>,
+    // being laid out, for the types containing themselves
+    laying: BTreeSet<String>,
+    // the definitions written and the ones being written
+    defined: BTreeSet<String>,
+    defining: BTreeSet<String>,
+    // enumerators share a scope in C
+    enumerators: BTreeSet<String>,
+    out: String,
+}
+
+impl<'a> Header<'a> {
+    pub fn new(types: &'a HashMap<String, BinjaType>, pointer_size: u64) -> Header<'a> {
+        Header {
+            types,
+            pointer_size,
+            layouts: HashMap::new(),
+            laying: BTreeSet::new(),
+            defined: BTreeSet::new(),
+            defining: BTreeSet::new(),
+            enumerators: BTreeSet::new(),
+            out: String::new(),
+        }
+    }
+
+    fn get(&self, name: &str) -> Result<&'a BinjaType, DynErr> {
+        (self.types.get(name)).ok_or_else(|| format!("unknown type `{}`", name).into())
+    }
+
+    fn pointer(&self, size: u64) -> u64 {
+        match size {
+            0 => self.pointer_size,
+            size => size,
+        }
+    }
+
+    // `name` for a declaration, the export's name unless it's one C already
+    // has
+    fn spelled(&self, name: &str) -> Result<String, DynErr> {
+        Ok(match self.get(name)? {
+            BinjaType::Integer(integer) => {
+                match (
+                    builtin(name),
+                    canonical(integer.size, integer.signed, integer.encoding),
+                ) {
+                    (true, Some(canonical)) => canonical.to_string(),
+                    (true, None) => format!("{}_bytes", ident(name)),
+                    (false, _) => ident(name),
+                }
+            }
+            _ => ident(name),
+        })
+    }
+
+    // the declaration of `declarator` as a `name`, like `char *argv[4]`
+    pub fn declare(&self, name: &str, declarator: &str) -> Result<String, DynErr> {
+        let spaced = |base: &str| match declarator {
+            "" => base.to_string(),
+            _ => format!("{} {}", base, declarator),
+        };
+        if name.is_empty() {
+            return Ok(spaced("void"));
+        }
+        Ok(match self.get(name)? {
+            BinjaType::Typedef(typedef) if builtin(name) => {
+                self.declare(&typedef.target, declarator)?
+            }
+            BinjaType::Pointer(pointer) => {
+                let size = self.pointer(pointer.size);
+                if pointer.member_of.is_some() || size != self.pointer_size {
+                    return Ok(match canonical(size, false, None) {
+                        Some(integer) => spaced(integer),
+                        None => format!("char {}[{}]", declarator, size),
+                    });
+                }
+                let inner = match self.types.get(&pointer.target) {
+                    Some(BinjaType::Array(_) | BinjaType::Function(_)) => {
+                        format!("(*{})", declarator)
+                    }
+                    _ => format!("*{}", declarator),
+                };
+                self.declare(&pointer.target, &inner)?
+            }
+            BinjaType::Qualified(qualified) => {
+                self.qualify(words(qualified), &qualified.target, declarator)?
+            }
+            BinjaType::Array(array) => match &array.dynamic {
+                Some(dynamic) if dynamic.from_data => {
+                    self.declare(&array.target, &format!("*{}", declarator))?
+                }
+                Some(dynamic) => format!("char {}[{}]", declarator, self.pointer(dynamic.size)),
+                None => {
+                    self.declare(&array.target, &format!("{}{}", declarator, array.bounds()))?
+                }
+            },
+            BinjaType::Function(function) => {
+                let mut parameters = Vec::new();
+                for parameter in function.parameters.iter() {
+                    let name = match parameter.name.as_str() {
+                        "" => String::new(),
+                        name => ident(name),
+                    };
+                    parameters.push(self.declare(&parameter.typename, &name)?);
+                }
+                if function.variadic {
+                    parameters.push(String::from("..."));
+                }
+                if parameters.is_empty() {
+                    parameters.push(String::from("void"));
+                }
+                self.declare(
+                    &function.returntype,
+                    &format!("{}({})", declarator, parameters.join(", ")),
+                )?
+            }
+            _ => spaced(&self.spelled(name)?),
+        })
+    }
+
+    // `declare` with `words` qualifying the `name`
+    fn qualify(
+        &self,
+        mut words: Vec<&'static str>,
+        name: &str,
+        declarator: &str,
+    ) -> Result<String, DynErr> {
+        match self.types.get(name) {
+            // `char *const p`
+            Some(BinjaType::Pointer(_)) => {
+                self.declare(name, &format!("{} {}", words.join(" "), declarator))
+            }
+            // it's the elements that are
+            Some(BinjaType::Array(array)) if array.dynamic.is_none() => self.qualify(
+                words,
+                &array.target,
+                &format!("{}{}", declarator, array.bounds()),
+            ),
+            Some(BinjaType::Qualified(qualified)) => {
+                for word in self::words(qualified) {
+                    if !words.contains(&word) {
+                        words.push(word);
+                    }
+                }
+                self.qualify(words, &qualified.target, declarator)
+            }
+            _ => Ok(format!(
+                "{} {}",
+                words.join(" "),
+                self.declare(name, declarator)?
+            )),
+        }
+    }
+
+    // size and alignment in what the header declares
+    fn layout(&mut self, name: &str) -> Result<(u64, u64), DynErr> {
+        if name.is_empty() {
+            return Ok((0, 1));
+        }
+        if let Some(layout) = self.layouts.get(name) {
+            return Ok(*layout);
+        }
+        if !self.laying.insert(name.to_string()) {
+            return Err(format!("`{}` contains itself", name).into());
+        }
+        let layout = match self.get(name)? {
+            BinjaType::Structure(structure) | BinjaType::Union(structure) => {
+                (structure.size, self.plan(name)?.align)
+            }
+            BinjaType::Class(class) => (class.layout.size, self.plan(name)?.align),
+            BinjaType::Integer(integer) => (integer.size, natural(integer.size)),
+            BinjaType::Enum(enumeration) => (enumeration.size, natural(enumeration.size)),
+            BinjaType::Pointer(pointer) => {
+                let size = self.pointer(pointer.size);
+                (size, natural(size))
+            }
+            BinjaType::Typedef(typedef) => self.layout(&typedef.target)?,
+            BinjaType::Qualified(qualified) => self.layout(&qualified.target)?,
+            BinjaType::Array(array) => match &array.dynamic {
+                Some(dynamic) => {
+                    let size = self.pointer(dynamic.size);
+                    (size, natural(size))
+                }
+                None => {
+                    let (size, align) = self.layout(&array.target)?;
+                    (size * array.elements(), align)
+                }
+            },
+            BinjaType::Vector(vector) => {
+                let size = self.layout(&vector.target)?.0 * vector.count;
+                (size, natural(size))
+            }
+            BinjaType::Function(_) => (0, 1),
+        };
+        _ = self.laying.remove(name);
+        _ = self.layouts.insert(name.to_string(), layout);
+        Ok(layout)
+    }
+
+    // a bitfield's type, which has to be an integer or an enum
+    fn integral(&self, name: &str, size: u64) -> Result<String, DynErr> {
+        let mut resolved = self.get(name)?;
+        for _ in 0..64 {
+            match resolved {
+                BinjaType::Typedef(typedef) => resolved = self.get(&typedef.target)?,
+                BinjaType::Qualified(qualified) => resolved = self.get(&qualified.target)?,
+                BinjaType::Integer(_) | BinjaType::Enum(_) => return self.declare(name, ""),
+                _ => break,
+            }
+        }
+        Ok(canonical(size, false, None)
+            .unwrap_or("unsigned int")
+            .to_string())
+    }
+
+    // unnamed bitfields and bytes from bit `from` up to bit `to`
+    fn pad_bits(&self, lines: &mut Vec<String>, mut from: u64, to: u64) {
+        if to <= from {
+            return;
+        }
+        if !from.is_multiple_of(8) {
+            let bits = (8 - from % 8).min(to - from);
+            lines.push(format!("unsigned char : {};", bits));
+            from += bits;
+        }
+        if to - from >= 8 {
+            let bytes = (to - from) / 8;
+            lines.push(pad(from / 8, bytes));
+            from += bytes * 8;
+        }
+        if to > from {
+            lines.push(format!("unsigned char : {};", to - from));
+        }
+    }
+
+    // `field` after what's placed up to bit `cursor`
+    fn member(&mut self, lines: &mut Vec<String>, cursor: &mut u64, field: &Field) -> Err {
+        let (size, _) = self.layout(&field.typename)?;
+        let name = match field.name.as_str() {
+            "" => format!("__field_{:x}", field.offset),
+            name => ident(name),
+        };
+        let start = field.offset * 8 + field.bit_offset.unwrap_or(0);
+        match field.bit_size {
+            Some(bits) => {
+                self.pad_bits(lines, *cursor, start);
+                let typename = self.integral(&field.typename, size)?;
+                lines.push(format!("{} {} : {};", typename, name, bits));
+                *cursor = start + bits;
+            }
+            None => {
+                let at = cursor.div_ceil(8);
+                if field.offset > at {
+                    lines.push(pad(at, field.offset - at));
+                }
+                lines.push(format!("{};", self.declare(&field.typename, &name)?));
+                *cursor = start + size * 8;
+            }
+        }
+        Ok(())
+    }
+
+    // the members of a struct, union or class laid out where the export
+    // puts them
+    fn plan(&mut self, name: &str) -> Result<Plan, DynErr> {
+        let (layout, mut fields, union) = match self.get(name)? {
+            BinjaType::Structure(structure) => (structure, structure.fields.clone(), false),
+            BinjaType::Union(union) => (union, union.fields.clone(), true),
+            BinjaType::Class(class) => {
+                let mut fields = class.layout.fields.clone();
+                for base in class.bases.iter() {
+                    fields.push(Field {
+                        offset: base.offset,
+                        name: format!("__base_{}", ident(&base.typename)),
+                        typename: base.typename.clone(),
+                        endianity: None,
+                        bit_offset: None,
+                        bit_size: None,
+                        access: None,
+                    });
+                }
+                (&class.layout, fields, false)
+            }
+            _ => return Err(format!("`{}` isn't a struct", name).into()),
+        };
+        fields.sort_by_key(|field| field.offset * 8 + field.bit_offset.unwrap_or(0));
+
+        // what's kept, and whether it all still fits naturally aligned
+        let mut kept = Vec::new();
+        let mut lines = Vec::new();
+        let (mut packed, mut align, mut cursor, mut reach) = (false, 1, 0, 0);
+        for field in fields.iter() {
+            let (size, field_align) = self.layout(&field.typename)?;
+            let start = field.offset * 8 + field.bit_offset.unwrap_or(0);
+            let bits = field.bit_size.unwrap_or(size * 8);
+            if field.bit_size == Some(0) {
+                continue;
+            }
+            if start + bits > layout.size * 8 {
+                lines.push(format!(
+                    "// `{}` at {:#x} runs past the end",
+                    field.name, field.offset
+                ));
+                continue;
+            }
+            if !union && start < cursor {
+                lines.push(format!(
+                    "// `{}` at {:#x} overlaps the member before it",
+                    field.name, field.offset
+                ));
+                continue;
+            }
+            reach = reach.max(start + bits);
+            // union members past the start go in packed structs of their own
+            if union && start != 0 {
+                kept.push(field);
+                continue;
+            }
+            match field.bit_size {
+                // SysV moves a bitfield crossing a unit of its type to the next
+                Some(_) => {
+                    packed |= size == 0 || start / (size * 8) != (start + bits - 1) / (size * 8)
+                }
+                None => packed |= !field.offset.is_multiple_of(field_align),
+            }
+            align = align.max(field_align);
+            if !union {
+                cursor = start + bits;
+            }
+            kept.push(field);
+        }
+        packed |= !layout.size.is_multiple_of(align);
+
+        let mut cursor = 0;
+        for field in kept {
+            if union && field.offset * 8 + field.bit_offset.unwrap_or(0) != 0 {
+                let mut inner = Vec::new();
+                self.member(&mut inner, &mut 0, field)?;
+                lines.push(String::from("struct __attribute__((packed)) {"));
+                // its members share the union's names
+                let prefix = format!("__pad_{}_", ident(&field.name));
+                lines.extend(
+                    (inner.into_iter())
+                        .map(|line| format!("    {}", line.replace("__pad_", &prefix))),
+                );
+                lines.push(String::from("};"));
+                continue;
+            }
+            self.member(&mut lines, &mut cursor, field)?;
+            if union {
+                cursor = 0;
+            }
+        }
+        if !union && layout.size > cursor.div_ceil(8) {
+            let at = cursor.div_ceil(8);
+            lines.push(pad(at, layout.size - at));
+        }
+        if union && layout.size * 8 > reach {
+            lines.push(pad(0, layout.size));
+        }
+        Ok(Plan {
+            packed,
+            align: if packed { 1 } else { align },
+            lines,
+        })
+    }
+
+    // whatever a use of `name` needs written before it, its definition when
+    // it's held by value
+    fn need(&mut self, name: &str, complete: bool) -> Err {
+        if name.is_empty() {
+            return Ok(());
+        }
+        match self.get(name)? {
+            BinjaType::Structure(_) | BinjaType::Union(_) | BinjaType::Class(_) => {
+                if complete {
+                    self.define(name)?;
+                }
+            }
+            BinjaType::Typedef(typedef) => {
+                self.define(name)?;
+                // the ones C has are spelled as their target
+                if complete || builtin(name) {
+                    self.need(&typedef.target, complete)?;
+                }
+            }
+            BinjaType::Integer(_) | BinjaType::Enum(_) | BinjaType::Vector(_) => {
+                self.define(name)?
+            }
+            BinjaType::Pointer(pointer) => self.need(&pointer.target, false)?,
+            BinjaType::Qualified(qualified) => self.need(&qualified.target, complete)?,
+            BinjaType::Array(array) => {
+                let complete = !(array.dynamic.as_ref()).is_some_and(|dynamic| dynamic.from_data);
+                self.need(&array.target, complete)?
+            }
+            BinjaType::Function(function) => {
+                self.need(&function.returntype, false)?;
+                for parameter in function.parameters.iter() {
+                    self.need(&parameter.typename, false)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn enumeration(&mut self, name: &str, enumeration: &Enum) -> String {
+        let mut text = format!("enum {} {{\n", ident(name));
+        for field in enumeration.fields.iter() {
+            let mut enumerator = ident(&field.name);
+            if self.enumerators.contains(&enumerator) {
+                enumerator = format!("{}_{}", ident(name), enumerator);
+            }
+            text.push_str(&format!("    {} = {},\n", enumerator, field.shown()));
+            _ = self.enumerators.insert(enumerator);
+        }
+        text.push('}');
+        text
+    }
+
+    // writes out `name` after what it needs
+    fn define(&mut self, name: &str) -> Err {
+        if self.defined.contains(name) {
+            return Ok(());
+        }
+        if !self.defining.insert(name.to_string()) {
+            return Err(format!("`{}` contains itself", name).into());
+        }
+        let text = match self.get(name)? {
+            BinjaType::Structure(structure) | BinjaType::Union(structure)
+                if structure.declaration =>
+            {
+                None
+            }
+            BinjaType::Class(class) if class.layout.declaration => None,
+            binja_type @ (BinjaType::Structure(_) | BinjaType::Union(_) | BinjaType::Class(_)) => {
+                let (size, fields) = match binja_type {
+                    BinjaType::Class(class) => (
+                        class.layout.size,
+                        (class.layout.fields.iter().map(|field| &field.typename))
+                            .chain(class.bases.iter().map(|base| &base.typename))
+                            .collect::<Vec<&String>>(),
+                    ),
+                    BinjaType::Structure(structure) | BinjaType::Union(structure) => (
+                        structure.size,
+                        (structure.fields.iter())
+                            .map(|field| &field.typename)
+                            .collect(),
+                    ),
+                    _ => unreachable!(),
+                };
+                for typename in fields {
+                    self.need(typename, true)?;
+                }
+                let plan = self.plan(name)?;
+                let keyword = match binja_type {
+                    BinjaType::Union(_) => "union",
+                    _ => "struct",
+                };
+                let packed = match plan.packed {
+                    true => " __attribute__((packed))",
+                    false => "",
+                };
+                let mut text = format!("{}{} {} {{\n", keyword, packed, ident(name));
+                for line in plan.lines {
+                    text.push_str(&format!("    {}\n", line));
+                }
+                text.push_str(&format!(
+                    "}};\n_Static_assert(sizeof({}) == {}, \"{}\");",
+                    ident(name),
+                    size,
+                    ident(name)
+                ));
+                Some(text)
+            }
+            BinjaType::Integer(integer) => match (
+                builtin(name),
+                canonical(integer.size, integer.signed, integer.encoding),
+            ) {
+                (true, Some(_)) => None,
+                (_, Some(canonical)) => Some(format!("typedef {} {};", canonical, ident(name))),
+                (_, None) => Some(format!(
+                    "typedef char {}[{}];",
+                    self.spelled(name)?,
+                    integer.size
+                )),
+            },
+            BinjaType::Enum(enumeration) => {
+                let fits = (enumeration.fields.iter()).all(|field| match enumeration.signed {
+                    true => i32::try_from(field.value).is_ok(),
+                    false => u32::try_from(field.value).is_ok(),
+                });
+                let body = self.enumeration(name, enumeration);
+                Some(
+                    match (enumeration.size, fits, enumeration.fields.is_empty()) {
+                        (4, true, false) => format!("typedef {} {};", body, ident(name)),
+                        (size, _, empty) => {
+                            let typedef = match canonical(size, enumeration.signed, None) {
+                                Some(canonical) => {
+                                    format!("typedef {} {};", canonical, ident(name))
+                                }
+                                None => format!("typedef char {}[{}];", ident(name), size),
+                            };
+                            match empty {
+                                true => typedef,
+                                false => format!("{};\n{}", body, typedef),
+                            }
+                        }
+                    },
+                )
+            }
+            BinjaType::Typedef(typedef) if !builtin(name) => {
+                self.need(&typedef.target, false)?;
+                Some(format!(
+                    "typedef {};",
+                    self.declare(&typedef.target, &ident(name))?
+                ))
+            }
+            BinjaType::Vector(vector) => {
+                self.need(&vector.target, true)?;
+                let size = self.layout(name)?.0;
+                Some(format!(
+                    "typedef {} __attribute__((vector_size({})));",
+                    self.declare(&vector.target, &ident(name))?,
+                    size
+                ))
+            }
+            _ => None,
+        };
+        if let Some(text) = text {
+            self.out.push_str(&text);
+            self.out.push_str("\n\n");
+        }
+        _ = self.defining.remove(name);
+        _ = self.defined.insert(name.to_string());
+        Ok(())
+    }
+
+    // the whole header
+    pub fn render(mut self, from: &str) -> Result<String, DynErr> {
+        let mut header = format!(
+            "// generated by `teemo export-headers` from {}\n#pragma once\n\n#include <stdint.h>\n\n",
+            from
+        );
+        for (name, binja_type) in self.types.iter() {
+            let keyword = match binja_type {
+                BinjaType::Union(_) => "union",
+                BinjaType::Structure(_) | BinjaType::Class(_) => "struct",
+                _ => continue,
+            };
+            header.push_str(&format!(
+                "typedef {} {} {};\n",
+                keyword,
+                ident(name),
+                ident(name)
+            ));
+        }
+        header.push('\n');
+        for (name, binja_type) in self.types.iter() {
+            match binja_type {
+                BinjaType::Pointer(_)
+                | BinjaType::Array(_)
+                | BinjaType::Function(_)
+                | BinjaType::Qualified(_) => self.need(name, false)?,
+                _ => self.define(name)?,
+            }
+        }
+        header.push_str(self.out.trim_end());
+        header.push('\n');
+        Ok(header)
+    }
+}
+
+pub fn run(options: &Options, path: &Path, file: Option<&Path>) -> Err {
+    let (mut type_set, pointer_size, from) = match file {
+        Some(file) => {
+            let dies = readback::Dies::from_elf(file)?;
+            (
+                dies.to_type_set(),
+                dies.address_size,
+                file.display().to_string(),
+            )
+        }
+        None => (
+            collect(options)?,
+            options.target.pointer_size,
+            options.types_dir.display().to_string(),
+        ),
+    };
+    let (types, _) = type_set.split_types();
+    let header = Header::new(&types, pointer_size).render(&from)?;
+    fs::write(path, header).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(())
+}
@@ -0,0 +1,705 @@
+// `teemo export-rust bindings.rs` is export-headers for exploit tooling in
+// rust: `#[repr(C)]` structs and unions with the layouts the debug file gets,
+// `type` aliases for the typedefs and named integers, for `mod bindings;`:
+//
+//   #[repr(C)]
+//   #[derive(Clone, Copy)]
+//   pub struct node {
+//       pub next: *mut node,
+//       pub key: u32,
+//       _pad_c: [u8; 4],
+//   }
+//   const _: () = assert!(core::mem::size_of::<node>() == 16);
+//
+// members sit where the export has them with the holes as `_pad_` arrays,
+// `packed` where they can't all be aligned, the same as for the header. a
+// run of bitfields is one `_bitfield_` byte array with a getter and a `set_`
+// setter for each, reading the bits in the target's byte order. union
+// members past the start become packed structs of their own, named after the
+// union and the member. enums are newtypes with a const for each enumerator,
+// a rust enum can't hold the values the export doesn't name.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use crate::export_headers::{builtin, ident, natural};
+use crate::{
+    collect, readback, BinjaType, DynErr, Encoding, Endianity, Enum, Err, Field, HashMap, Options,
+};
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false",
+    "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where",
+    "while", "abstract", "become", "box", "do", "final", "macro", "override", "priv", "try",
+    "typeof", "unsized", "virtual", "yield",
+];
+
+// `name` as a rust identifier
+fn rust(name: &str) -> String {
+    let ident = ident(name);
+    if matches!(ident.as_str(), "self" | "Self" | "super" | "crate" | "_") {
+        format!("{}_", ident)
+    } else if KEYWORDS.contains(&ident.as_str()) {
+        format!("r#{}", ident)
+    } else {
+        ident
+    }
+}
+
+fn primitive(size: u64, signed: bool, encoding: Option<Encoding>) -> Option<&'static str> {
+    Some(match (encoding, size, signed) {
+        (Some(Encoding::Float), 4, _) => "f32",
+        (Some(Encoding::Float), 8, _) => "f64",
+        (Some(Encoding::Float), _, _) => return None,
+        (_, 1, true) => "i8",
+        (_, 1, false) => "u8",
+        (_, 2, true) => "i16",
+        (_, 2, false) => "u16",
+        (_, 4, true) => "i32",
+        (_, 4, false) => "u32",
+        (_, 8, true) => "i64",
+        (_, 8, false) => "u64",
+        (_, 16, true) => "i128",
+        (_, 16, false) => "u128",
+        _ => return None,
+    })
+}
+
+fn pad(offset: u64, size: u64) -> String {
+    format!("_pad_{:x}: [u8; {}],", offset, size)
+}
+
+fn member(field: &Field) -> String {
+    match field.name.as_str() {
+        "" => format!("_field_{:x}", field.offset),
+        name => ident(name),
+    }
+}
+
+fn start(field: &Field) -> u64 {
+    field.offset * 8 + field.bit_offset.unwrap_or(0)
+}
+
+// where a struct's members go
+enum Slot<'f> {
+    Field(&'f Field),
+    // a run of bitfields from this byte on
+    Bits(u64, Vec<&'f Field>),
+    // a union member past the start, in a struct of its own
+    Moved(&'f Field),
+}
+
+struct Plan {
+    packed: bool,
+    align: u64,
+    lines: Vec<String>,
+    methods: Vec<String>,
+    // the structs of the moved union members
+    helpers: Vec<String>,
+}
+
+struct Bindings<'a> {
+    types: &'a HashMap<String, BinjaType>,
+    pointer_size: u64,
+    endianity: Endianity,
+    // (size, alignment) as the bindings lay them out
+    layouts: HashMap<String, (u64, u64)>,
+    laying: BTreeSet<String>,
+}
+
+impl<'a> Bindings<'a> {
+    fn get(&self, name: &str) -> Result<&'a BinjaType, DynErr> {
+        (self.types.get(name)).ok_or_else(|| format!("unknown type `{}`", name).into())
+    }
+
+    fn pointer(&self, size: u64) -> u64 {
+        match size {
+            0 => self.pointer_size,
+            size => size,
+        }
+    }
+
+    // `name` behind typedefs and qualifiers, with the name it has there
+    fn resolve(&self, mut name: &'a str) -> Option<(&'a str, &'a BinjaType)> {
+        for _ in 0..64 {
+            match self.types.get(name)? {
+                BinjaType::Typedef(typedef) => name = &typedef.target,
+                BinjaType::Qualified(qualified) => name = &qualified.target,
+                binja_type => return Some((name, binja_type)),
+            }
+        }
+        None
+    }
+
+    // the rust type for `name`
+    fn spelled(&self, name: &str) -> Result<String, DynErr> {
+        if name.is_empty() {
+            return Ok(String::from("core::ffi::c_void"));
+        }
+        Ok(match self.get(name)? {
+            BinjaType::Integer(integer) => {
+                match (
+                    builtin(name),
+                    primitive(integer.size, integer.signed, integer.encoding),
+                ) {
+                    (true, Some(primitive)) => primitive.to_string(),
+                    (true, None) => format!("[u8; {}]", integer.size),
+                    (false, _) => rust(name),
+                }
+            }
+            BinjaType::Typedef(typedef) if builtin(name) => self.spelled(&typedef.target)?,
+            BinjaType::Qualified(qualified) => self.spelled(&qualified.target)?,
+            BinjaType::Pointer(pointer) => {
+                let size = self.pointer(pointer.size);
+                if pointer.member_of.is_some() || size != self.pointer_size {
+                    return Ok(primitive(size, false, None)
+                        .map_or_else(|| format!("[u8; {}]", size), String::from));
+                }
+                match self.resolve(&pointer.target) {
+                    Some((_, BinjaType::Function(_))) => {
+                        format!("Option<{}>", self.spelled(&pointer.target)?)
+                    }
+                    _ => {
+                        let mutability = match self.types.get(&pointer.target) {
+                            Some(BinjaType::Qualified(qualified)) if qualified.constant => "const",
+                            _ => "mut",
+                        };
+                        format!("*{} {}", mutability, self.spelled(&pointer.target)?)
+                    }
+                }
+            }
+            BinjaType::Array(array) => match &array.dynamic {
+                Some(dynamic) if dynamic.from_data => {
+                    format!("*mut {}", self.spelled(&array.target)?)
+                }
+                Some(dynamic) => format!("[u8; {}]", self.pointer(dynamic.size)),
+                None => {
+                    let mut spelled = self.spelled(&array.target)?;
+                    for count in array.counts().collect::<Vec<u64>>().into_iter().rev() {
+                        spelled = format!("[{}; {}]", spelled, count);
+                    }
+                    spelled
+                }
+            },
+            BinjaType::Function(function) => {
+                let mut parameters = Vec::new();
+                for parameter in function.parameters.iter() {
+                    parameters.push(self.spelled(&parameter.typename)?);
+                }
+                // rust wants a parameter before the `...`
+                if function.variadic && !parameters.is_empty() {
+                    parameters.push(String::from("..."));
+                }
+                let returns = match function.returntype.as_str() {
+                    "" => String::new(),
+                    returntype => format!(" -> {}", self.spelled(returntype)?),
+                };
+                format!(
+                    "unsafe extern \"C\" fn({}){}",
+                    parameters.join(", "),
+                    returns
+                )
+            }
+            _ => rust(name),
+        })
+    }
+
+    // size and alignment in what the bindings declare
+    fn layout(&mut self, name: &str) -> Result<(u64, u64), DynErr> {
+        if name.is_empty() {
+            return Ok((0, 1));
+        }
+        if let Some(layout) = self.layouts.get(name) {
+            return Ok(*layout);
+        }
+        if !self.laying.insert(name.to_string()) {
+            return Err(format!("`{}` contains itself", name).into());
+        }
+        let layout = match self.get(name)? {
+            BinjaType::Structure(_) | BinjaType::Union(_) | BinjaType::Class(_) => {
+                let (size, fields, union) = self.members(name)?;
+                (size, self.plan(name, size, &fields, union)?.align)
+            }
+            BinjaType::Integer(integer) => (integer.size, natural(integer.size)),
+            BinjaType::Enum(enumeration) => (enumeration.size, natural(enumeration.size)),
+            BinjaType::Pointer(pointer) => {
+                let size = self.pointer(pointer.size);
+                (size, natural(size))
+            }
+            BinjaType::Typedef(typedef) => self.layout(&typedef.target)?,
+            BinjaType::Qualified(qualified) => self.layout(&qualified.target)?,
+            BinjaType::Array(array) => match &array.dynamic {
+                Some(dynamic) => {
+                    let size = self.pointer(dynamic.size);
+                    (size, natural(size))
+                }
+                None => {
+                    let (size, align) = self.layout(&array.target)?;
+                    (size * array.elements(), align)
+                }
+            },
+            BinjaType::Vector(vector) => {
+                let size = self.layout(&vector.target)?.0 * vector.count;
+                (size, natural(size))
+            }
+            BinjaType::Function(_) => (0, 1),
+        };
+        _ = self.laying.remove(name);
+        _ = self.layouts.insert(name.to_string(), layout);
+        Ok(layout)
+    }
+
+    // a struct's, union's or class's size and members, a class's bases among
+    // them
+    fn members(&self, name: &str) -> Result<(u64, Vec<Field>, bool), DynErr> {
+        Ok(match self.get(name)? {
+            BinjaType::Structure(structure) => (structure.size, structure.fields.clone(), false),
+            BinjaType::Union(union) => (union.size, union.fields.clone(), true),
+            BinjaType::Class(class) => {
+                let mut fields = class.layout.fields.clone();
+                for base in class.bases.iter() {
+                    fields.push(Field {
+                        offset: base.offset,
+                        name: format!("_base_{}", ident(&base.typename)),
+                        typename: base.typename.clone(),
+                        endianity: None,
+                        bit_offset: None,
+                        bit_size: None,
+                        access: None,
+                    });
+                }
+                (class.layout.size, fields, false)
+            }
+            _ => return Err(format!("`{}` isn't a struct", name).into()),
+        })
+    }
+
+    // the getter and setter of a bitfield in the `storage` array starting at
+    // byte `first`
+    fn accessors(&self, storage: &str, first: u64, field: &Field) -> Result<String, DynErr> {
+        let bits = field.bit_size.unwrap_or(0);
+        let at = start(field) - first * 8;
+        let (low, high) = (at / 8, (at + bits).div_ceil(8));
+        let count = high - low;
+        if count > 16 {
+            return Ok(format!(
+                "    // `{}` is too wide for an accessor\n",
+                field.name
+            ));
+        }
+        let (range, from, to, shift) = match self.endianity {
+            Endianity::Little => (
+                format!("..{}", count),
+                "from_le_bytes",
+                "to_le_bytes",
+                at - low * 8,
+            ),
+            Endianity::Big => (
+                format!("{}..", 16 - count),
+                "from_be_bytes",
+                "to_be_bytes",
+                high * 8 - at - bits,
+            ),
+        };
+        let mask = match bits {
+            128.. => u128::MAX,
+            bits => (1 << bits) - 1,
+        };
+        // the type it's read as, and the conversions from and to the bits
+        let (typename, get, set) = match self.resolve(&field.typename) {
+            Some((_, BinjaType::Integer(integer))) if integer.encoding == Some(Encoding::Bool) => (
+                String::from("bool"),
+                String::from("bits != 0"),
+                String::from("value as u128"),
+            ),
+            Some((_, BinjaType::Integer(integer))) if integer.encoding != Some(Encoding::Float) => {
+                let primitive = primitive(integer.size, integer.signed, None).unwrap_or("u128");
+                (
+                    primitive.to_string(),
+                    self.extend(primitive, integer.signed, bits),
+                    String::from("value as u128"),
+                )
+            }
+            Some((name, BinjaType::Enum(enumeration))) => {
+                match primitive(enumeration.size, enumeration.signed, None) {
+                    Some(primitive) => (
+                        rust(name),
+                        format!(
+                            "{}({})",
+                            rust(name),
+                            self.extend(primitive, enumeration.signed, bits)
+                        ),
+                        String::from("value.0 as u128"),
+                    ),
+                    None => (
+                        String::from("u128"),
+                        String::from("bits"),
+                        String::from("value"),
+                    ),
+                }
+            }
+            _ => (
+                String::from("u128"),
+                String::from("bits"),
+                String::from("value"),
+            ),
+        };
+        let (shifted, unshifted, mask) = match shift {
+            0 => (
+                String::new(),
+                format!("{:#x}", mask),
+                format!("{:#x}", mask),
+            ),
+            shift => (
+                format!(" << {}", shift),
+                format!("({:#x} << {})", mask, shift),
+                format!("{:#x}", mask),
+            ),
+        };
+        let bits = match shift {
+            0 => format!("u128::{}(bytes) & {}", from, mask),
+            shift => format!("(u128::{}(bytes) >> {}) & {}", from, shift, mask),
+        };
+        let name = member(field);
+        let read = format!(
+            "let mut bytes = [0; 16];\n        bytes[{}].copy_from_slice(&self.{}[{}..{}]);",
+            range, storage, low, high
+        );
+        Ok(format!(
+            concat!(
+                "    pub fn {getter}(&self) -> {typename} {{\n",
+                "        {read}\n",
+                "        let bits = {bits};\n",
+                "        {get}\n",
+                "    }}\n",
+                "\n",
+                "    pub fn set_{name}(&mut self, value: {typename}) {{\n",
+                "        {read}\n",
+                "        let bits = (u128::{from}(bytes) & !{unshifted})\n",
+                "            | (({set}) & {mask}){shifted};\n",
+                "        self.{storage}[{low}..{high}].copy_from_slice(&bits.{to}()[{range}]);\n",
+                "    }}\n",
+            ),
+            getter = rust(&field.name),
+            name = ident(&name),
+            typename = typename,
+            read = read,
+            bits = bits,
+            from = from,
+            to = to,
+            unshifted = unshifted,
+            shifted = shifted,
+            mask = mask,
+            get = get,
+            set = set,
+            storage = storage,
+            low = low,
+            high = high,
+            range = range,
+        ))
+    }
+
+    // `bits` as a `primitive`, sign extended from its top one
+    fn extend(&self, primitive: &str, signed: bool, bits: u64) -> String {
+        match signed {
+            true => format!(
+                "((bits << {}) as i128 >> {}) as {}",
+                128 - bits,
+                128 - bits,
+                primitive
+            ),
+            false => format!("bits as {}", primitive),
+        }
+    }
+
+    // the members of `fields` laid out where the export puts them
+    fn plan(
+        &mut self,
+        name: &str,
+        size: u64,
+        fields: &[Field],
+        union: bool,
+    ) -> Result<Plan, DynErr> {
+        let mut fields: Vec<&Field> = fields.iter().collect();
+        fields.sort_by_key(|field| start(field));
+
+        let mut slots = Vec::new();
+        let mut lines = Vec::new();
+        let (mut packed, mut align, mut cursor, mut reach) = (false, 1, 0, 0);
+        for field in fields {
+            let (field_size, field_align) = self.layout(&field.typename)?;
+            let bits = field.bit_size.unwrap_or(field_size * 8);
+            if field.bit_size == Some(0) {
+                continue;
+            }
+            if start(field) + bits > size * 8 {
+                lines.push(format!(
+                    "// `{}` at {:#x} runs past the end",
+                    field.name, field.offset
+                ));
+                continue;
+            }
+            if union {
+                reach = reach.max(start(field) + bits);
+                if start(field) == 0 && field.bit_size.is_none() {
+                    align = align.max(field_align);
+                    slots.push(Slot::Field(field));
+                } else {
+                    slots.push(Slot::Moved(field));
+                }
+                continue;
+            }
+            if start(field) < cursor {
+                lines.push(format!(
+                    "// `{}` at {:#x} overlaps the member before it",
+                    field.name, field.offset
+                ));
+                continue;
+            }
+            match (field.bit_size, slots.last_mut()) {
+                (Some(_), Some(Slot::Bits(_, run))) => run.push(field),
+                (Some(_), _) => slots.push(Slot::Bits(start(field) / 8, vec![field])),
+                (None, _) => {
+                    packed |= !field.offset.is_multiple_of(field_align);
+                    align = align.max(field_align);
+                    slots.push(Slot::Field(field));
+                }
+            }
+            cursor = start(field) + bits;
+        }
+        packed |= !size.is_multiple_of(align);
+
+        let (mut methods, mut helpers) = (Vec::new(), Vec::new());
+        let mut at = 0;
+        for slot in slots {
+            match slot {
+                Slot::Field(field) => {
+                    if field.offset > at {
+                        lines.push(pad(at, field.offset - at));
+                    }
+                    lines.push(format!(
+                        "pub {}: {},",
+                        rust(&member(field)),
+                        self.spelled(&field.typename)?
+                    ));
+                    if !union {
+                        at = field.offset + self.layout(&field.typename)?.0;
+                    }
+                }
+                Slot::Bits(first, run) => {
+                    if first > at {
+                        lines.push(pad(at, first - at));
+                    }
+                    let end = (run.iter())
+                        .map(|field| start(field) + field.bit_size.unwrap_or(0))
+                        .max()
+                        .unwrap_or(0)
+                        .div_ceil(8);
+                    let storage = format!("_bitfield_{:x}", first);
+                    lines.push(format!("{}: [u8; {}],", storage, end - first));
+                    for field in run {
+                        methods.push(self.accessors(&storage, first, field)?);
+                    }
+                    at = end;
+                }
+                Slot::Moved(field) => {
+                    let helper = format!("{}_{}", ident(name), member(field));
+                    let end = match field.bit_size {
+                        Some(bits) => (start(field) + bits).div_ceil(8),
+                        None => field.offset + self.layout(&field.typename)?.0,
+                    };
+                    helpers.push(self.definition(
+                        &helper,
+                        end,
+                        std::slice::from_ref(field),
+                        false,
+                        true,
+                    )?);
+                    lines.push(format!("pub {}: {},", rust(&member(field)), rust(&helper)));
+                }
+            }
+        }
+        if !union && size > at {
+            lines.push(pad(at, size - at));
+        }
+        if union && size * 8 > reach {
+            lines.push(pad(0, size));
+        }
+        Ok(Plan {
+            packed,
+            align: if packed { 1 } else { align },
+            lines,
+            methods,
+            helpers,
+        })
+    }
+
+    fn definition(
+        &mut self,
+        name: &str,
+        size: u64,
+        fields: &[Field],
+        union: bool,
+        packed: bool,
+    ) -> Result<String, DynErr> {
+        let plan = self.plan(name, size, fields, union)?;
+        let mut text = String::new();
+        for helper in plan.helpers {
+            text.push_str(&helper);
+            text.push_str("\n\n");
+        }
+        let representation = match plan.packed || packed {
+            true => "C, packed",
+            false => "C",
+        };
+        let keyword = match union {
+            true => "union",
+            false => "struct",
+        };
+        text.push_str(&format!(
+            "#[repr({})]\n#[derive(Clone, Copy)]\npub {} {} {{\n",
+            representation,
+            keyword,
+            rust(name)
+        ));
+        for line in plan.lines {
+            text.push_str(&format!("    {}\n", line));
+        }
+        text.push_str(&format!(
+            "}}\nconst _: () = assert!(core::mem::size_of::<{}>() == {});",
+            rust(name),
+            size
+        ));
+        if !plan.methods.is_empty() {
+            text.push_str(&format!("\n\nimpl {} {{\n", rust(name)));
+            text.push_str(&plan.methods.join("\n"));
+            text.push('}');
+        }
+        Ok(text)
+    }
+
+    fn enumeration(&self, name: &str, enumeration: &Enum) -> String {
+        let inner = match primitive(enumeration.size, enumeration.signed, None) {
+            Some(primitive) => primitive.to_string(),
+            None => format!("[u8; {}]", enumeration.size),
+        };
+        let mut text = format!(
+            "#[repr(transparent)]\n#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]\npub struct {}(pub {});",
+            rust(name),
+            inner
+        );
+        if enumeration.fields.is_empty() || inner.starts_with('[') {
+            return text;
+        }
+        text.push_str(&format!("\n\nimpl {} {{\n", rust(name)));
+        let mut seen = BTreeSet::new();
+        for (i, field) in enumeration.fields.iter().enumerate() {
+            let mut constant = rust(&field.name);
+            if !seen.insert(constant.clone()) {
+                constant = format!("{}_{}", ident(&field.name), i);
+            }
+            let value = enumeration.normalize(field.value);
+            let value = match enumeration.signed {
+                true => value.to_string(),
+                false => format!("{:#x}", value),
+            };
+            text.push_str(&format!(
+                "    pub const {}: {} = {}({});\n",
+                constant,
+                rust(name),
+                rust(name),
+                value
+            ));
+        }
+        text.push('}');
+        text
+    }
+
+    // `name` defined, None for what's spelled where it's used
+    fn define(&mut self, name: &str) -> Result<Option<String>, DynErr> {
+        Ok(match self.get(name)? {
+            BinjaType::Structure(structure) | BinjaType::Union(structure)
+                if structure.declaration =>
+            {
+                Some(format!(
+                    "#[repr(C)]\npub struct {} {{\n    _opaque: [u8; 0],\n}}",
+                    rust(name)
+                ))
+            }
+            BinjaType::Class(class) if class.layout.declaration => Some(format!(
+                "#[repr(C)]\npub struct {} {{\n    _opaque: [u8; 0],\n}}",
+                rust(name)
+            )),
+            BinjaType::Structure(_) | BinjaType::Union(_) | BinjaType::Class(_) => {
+                let (size, fields, union) = self.members(name)?;
+                Some(self.definition(name, size, &fields, union, false)?)
+            }
+            BinjaType::Integer(integer) if !builtin(name) => Some(format!(
+                "pub type {} = {};",
+                rust(name),
+                primitive(integer.size, integer.signed, integer.encoding)
+                    .map_or_else(|| format!("[u8; {}]", integer.size), String::from)
+            )),
+            BinjaType::Enum(enumeration) => Some(self.enumeration(name, enumeration)),
+            BinjaType::Typedef(typedef) if !builtin(name) => Some(format!(
+                "pub type {} = {};",
+                rust(name),
+                self.spelled(&typedef.target)?
+            )),
+            BinjaType::Vector(vector) => {
+                let size = self.layout(name)?.0;
+                Some(format!(
+                    "#[repr(C, align({}))]\n#[derive(Clone, Copy)]\npub struct {}(pub [{}; {}]);",
+                    natural(size),
+                    rust(name),
+                    self.spelled(&vector.target)?,
+                    vector.count
+                ))
+            }
+            _ => None,
+        })
+    }
+}
+
+pub fn run(options: &Options, path: &Path, file: Option<&Path>) -> Err {
+    let (mut type_set, pointer_size, endianity, from) = match file {
+        Some(file) => {
+            let dies = readback::Dies::from_elf(file)?;
+            (
+                dies.to_type_set(),
+                dies.address_size,
+                dies.endianity,
+                file.display().to_string(),
+            )
+        }
+        None => (
+            collect(options)?,
+            options.target.pointer_size,
+            options.target.endianity,
+            options.types_dir.display().to_string(),
+        ),
+    };
+    let (types, _) = type_set.split_types();
+    let mut bindings = Bindings {
+        types: &types,
+        pointer_size,
+        endianity,
+        layouts: HashMap::new(),
+        laying: BTreeSet::new(),
+    };
+    let mut out = format!(
+        "// generated by `teemo export-rust` from {}\n#![allow(non_camel_case_types, non_snake_case, non_upper_case_globals, dead_code)]\n",
+        from
+    );
+    for name in types.keys() {
+        if let Some(definition) = bindings.define(name)? {
+            out.push('\n');
+            out.push_str(&definition);
+            out.push('\n');
+        }
+    }
+    fs::write(path, out).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(())
+}
@@ -0,0 +1,47 @@
+// `teemo extract libc.so.6.debug libc` reads the DWARF of a build that has
+// it back into the export's json, one types.json the way --types-dir reads
+// it, so the types of a related debug build can go onto a stripped binary:
+//
+//   teemo extract libc.so.6.debug libc
+//   teemo --types-dir libc --binary chall --inject chall.debug
+//
+// the variables and the functions defined come along with their addresses
+// and prototypes, `--types-only` leaves them out when the binary isn't the
+// same build and they'd point at the wrong code. the types read back the way
+// `dump` reads them, see readback.rs.
+
+use std::fs;
+use std::path::Path;
+
+use log::info;
+
+use crate::readback::Dies;
+use crate::{CombinedExport, Err, HashMap};
+
+pub fn run(file: &Path, dir: &Path, types_only: bool) -> Err {
+    let mut type_set = Dies::from_elf(file)?.to_type_set();
+    if types_only {
+        type_set.variables.clear();
+        type_set.subprograms.clear();
+    }
+    let subprograms = std::mem::take(&mut type_set.subprograms);
+    let (types, variables) = type_set.split_types();
+    let export = CombinedExport {
+        types,
+        variables,
+        constants: HashMap::new(),
+        subprograms,
+    };
+    info!(
+        "{}: {} types, {} variables, {} functions",
+        file.display(),
+        export.types.len(),
+        export.variables.len(),
+        export.subprograms.len()
+    );
+    fs::create_dir_all(dir)?;
+    let path = dir.join("types.json");
+    fs::write(&path, serde_json::to_string(&export)?)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(())
+}
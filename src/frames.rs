@@ -0,0 +1,136 @@
+// `--frames frames.json` describes how to unwind through functions whose
+// binary has no CFI of its own, and writes it as .debug_frame so gdb's
+// backtraces get past them and the locals' DW_OP_call_frame_cfa works.
+//
+// {
+//     "4198448": {"size": 32, "rows": {
+//         "1": {"cfa": ["rsp", 16], "saved": {"rbp": -16}},
+//         "4": {"cfa": ["rbp", 16]},
+//         "30": {"cfa": ["rsp", 8], "saved": {"rbp": null}}
+//     }}
+// }
+//
+// keyed by address like variables.json, the rows by the offset into the
+// function they start at. the CFA (the stack pointer before the call) is a
+// register plus an offset, saved registers are at an offset from it and
+// `null` says one has its own value again. a row only says what changes. at
+// the start of a function the CFA is its stack pointer as the call left it
+// and the return address is where the call put it. the binary's .eh_frame
+// stays its own, gdb reads both.
+
+use std::fs;
+use std::path::Path;
+
+use gimli::write::{
+    Address, CallFrameInstruction, CommonInformationEntry, DebugFrame, EndianVec,
+    FrameDescriptionEntry, FrameTable,
+};
+use gimli::Register;
+use goblin::elf::header::{EM_386, EM_AARCH64, EM_ARM, EM_MIPS, EM_RISCV, EM_X86_64};
+use serde::Deserialize;
+
+use crate::target::Target;
+use crate::{registers, DynErr, HashMap};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Frame {
+    pub size: u64,
+    #[serde(default)]
+    pub rows: HashMap<u64, Row>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Row {
+    #[serde(default)]
+    pub cfa: Option<(String, i32)>,
+    #[serde(default)]
+    pub saved: HashMap<String, Option<i32>>,
+}
+
+pub fn load(path: &Path) -> Result<HashMap<u64, Frame>, DynErr> {
+    let frames: HashMap<u64, Frame> = match fs::read_to_string(path) {
+        Ok(json) => {
+            serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))?
+        }
+        Err(e) => return Err(format!("{}: {}", path.display(), e).into()),
+    };
+    for (address, frame) in frames.iter() {
+        if let Some(offset) = frame.rows.keys().find(|offset| **offset >= frame.size) {
+            return Err(format!(
+                "{}: the frame at {:#x} has a row at {} past its {} bytes",
+                path.display(),
+                address,
+                offset,
+                frame.size
+            )
+            .into());
+        }
+    }
+    Ok(frames)
+}
+
+// the stack pointer, the register the return address is in when it isn't
+// pushed and whether a call pushes it
+fn conventions(target: &Target) -> Result<(&'static str, &'static str, bool), DynErr> {
+    match target.machine {
+        EM_X86_64 => Ok(("rsp", "rip", true)),
+        EM_386 => Ok(("esp", "eip", true)),
+        EM_AARCH64 => Ok(("sp", "x30", false)),
+        EM_ARM => Ok(("sp", "lr", false)),
+        EM_RISCV | EM_MIPS => Ok(("sp", "ra", false)),
+        _ => Err("--frames doesn't know how this --arch calls".into()),
+    }
+}
+
+pub fn write(frames: &HashMap<u64, Frame>, target: &Target) -> Result<Vec<u8>, DynErr> {
+    let (stack_pointer, return_address, pushed) = conventions(target)?;
+    let register = |name: &str| registers::number(target, name).map(Register);
+    let return_address = register(return_address)?;
+    let encoding = gimli::Encoding {
+        format: gimli::Format::Dwarf32,
+        version: 1,
+        address_size: target.address_size,
+    };
+    // offsets are in bytes, a factor of -1 keeps the usual negative ones short
+    let mut cie = CommonInformationEntry::new(encoding, 1, -1, return_address);
+    let size = i32::from(target.address_size);
+    if pushed {
+        cie.add_instruction(CallFrameInstruction::Cfa(register(stack_pointer)?, size));
+        cie.add_instruction(CallFrameInstruction::Offset(return_address, -size));
+    } else {
+        cie.add_instruction(CallFrameInstruction::Cfa(register(stack_pointer)?, 0));
+    }
+
+    let mut table = FrameTable::default();
+    let cie = table.add_cie(cie);
+    for (address, frame) in frames.iter() {
+        let length = u32::try_from(frame.size)
+            .map_err(|_| format!("the frame at {:#x} is too large", address))?;
+        let mut fde = FrameDescriptionEntry::new(Address::Constant(*address), length);
+        for (offset, row) in frame.rows.iter() {
+            let failed = |e: DynErr| format!("the frame at {:#x}+{}: {}", address, offset, e);
+            let offset = *offset as u32;
+            if let Some((base, cfa)) = &row.cfa {
+                let base = register(base).map_err(failed)?;
+                fde.add_instruction(offset, CallFrameInstruction::Cfa(base, *cfa));
+            }
+            for (saved, at) in row.saved.iter() {
+                let saved = register(saved).map_err(failed)?;
+                fde.add_instruction(
+                    offset,
+                    match at {
+                        Some(at) => CallFrameInstruction::Offset(saved, *at),
+                        None => CallFrameInstruction::SameValue(saved),
+                    },
+                );
+            }
+        }
+        table.add_fde(cie, fde);
+    }
+
+    let mut debug_frame = DebugFrame(EndianVec::new(target.dwarf_endian()));
+    table.write_debug_frame(&mut debug_frame)?;
+    Ok(debug_frame.0.into_vec())
+}
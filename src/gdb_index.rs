@@ -0,0 +1,347 @@
+// `--gdb-index` adds the .gdb_index gdb would otherwise build itself on every
+// load (or `gdb-add-index` once), so symbol lookup in a big output is instant.
+// version 8, always little endian:
+//
+//   header: version and the offsets of the five areas below, u32 each
+//   units: (.debug_info offset, length) u64 pairs
+//   type units: (.debug_types offset, type offset, signature) u64 triples
+//   addresses: (low, high, unit) with 64-bit bounds and a u32 index
+//   symbols: open addressed (name, vector) u32 offsets into the pool
+//   pool: vectors of u32 unit indices tagged with the symbol kind, then names
+//
+// the symbols are the named DIEs of every unit, with what's in namespaces,
+// classes, structs and unions under its `a::b::c` name, the types of the type
+// units and the enumerators, tagged like gdb's own index. DWARF 5's
+// .debug_names isn't written, gdb only trusts the ones it produced itself.
+
+use gimli::write::{AttributeValue, Dwarf, Unit, UnitEntryId, UnitId};
+use gimli::DebugInfoOffset;
+
+use crate::type_units::TypeUnit;
+use crate::{units, HashMap};
+
+const VERSION: u32 = 8;
+
+// the symbol kinds in bits 28-30 of a vector entry, bit 31 marks static ones
+const TYPE: u32 = 1 << 28;
+const VARIABLE: u32 = 2 << 28;
+const FUNCTION: u32 = 3 << 28;
+const STATIC: u32 = 1 << 31;
+
+// what gdb hashes names with since version 5
+fn hash(name: &[u8]) -> u32 {
+    name.iter().fold(0u32, |r, c| {
+        r.wrapping_mul(67)
+            .wrapping_add(c.to_ascii_lowercase() as u32)
+            .wrapping_sub(113)
+    })
+}
+
+fn name(dwarf: &Dwarf, value: Option<&AttributeValue>) -> Option<Vec<u8>> {
+    match value? {
+        AttributeValue::StringRef(id) => Some(dwarf.strings.get(*id).to_vec()),
+        AttributeValue::String(name) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+fn put(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+// name -> its vector of tagged unit indices
+type Symbols = HashMap<Vec<u8>, Vec<u32>>;
+
+// adds a DIE of the unit numbered `index`, named `a::b::c` for the
+// namespaces and types it's in like gdb's own index names it, and with a
+// namespace or a named aggregate what's in it
+fn add(
+    symbols: &mut Symbols,
+    dwarf: &Dwarf,
+    unit: &Unit,
+    id: UnitEntryId,
+    scope: &[u8],
+    index: u32,
+) {
+    let mut insert = |name: &[u8], kind: u32| {
+        let name = match scope {
+            [] => name.to_vec(),
+            _ => [scope, b"::", name].concat(),
+        };
+        let entry = index | kind;
+        let vector = symbols.entry(name).or_default();
+        if !vector.contains(&entry) {
+            vector.push(entry);
+        }
+    };
+    let entry = unit.get(id);
+    let flag = |at| matches!(entry.get(at), Some(AttributeValue::Flag(true)));
+    let kind = match entry.tag() {
+        // a class's methods and static members are declarations in it
+        gimli::DW_TAG_subprogram | gimli::DW_TAG_variable if flag(gimli::DW_AT_declaration) => {
+            return
+        }
+        gimli::DW_TAG_subprogram => FUNCTION,
+        gimli::DW_TAG_variable => VARIABLE,
+        gimli::DW_TAG_base_type
+        | gimli::DW_TAG_structure_type
+        | gimli::DW_TAG_class_type
+        | gimli::DW_TAG_union_type
+        | gimli::DW_TAG_enumeration_type
+        | gimli::DW_TAG_typedef
+        | gimli::DW_TAG_namespace => TYPE,
+        _ => return,
+    };
+    // the enumerators of an unscoped enum are in the scope around it
+    if entry.tag() == gimli::DW_TAG_enumeration_type {
+        for id in entry.children() {
+            let enumerator = unit.get(*id);
+            if let Some(name) = name(dwarf, enumerator.get(gimli::DW_AT_name)) {
+                insert(&name, VARIABLE | STATIC);
+            }
+        }
+    }
+    let name = match (entry.tag(), name(dwarf, entry.get(gimli::DW_AT_name))) {
+        (_, Some(name)) => name,
+        (gimli::DW_TAG_namespace, None) => b"(anonymous namespace)".to_vec(),
+        (_, None) => return,
+    };
+    match entry.tag() {
+        // gdb doesn't take them for static
+        gimli::DW_TAG_namespace => insert(&name, kind),
+        _ if flag(gimli::DW_AT_external) => insert(&name, kind),
+        _ => insert(&name, kind | STATIC),
+    }
+    if let gimli::DW_TAG_namespace
+    | gimli::DW_TAG_structure_type
+    | gimli::DW_TAG_class_type
+    | gimli::DW_TAG_union_type = entry.tag()
+    {
+        let scope = match scope {
+            [] => name,
+            _ => [scope, b"::", &name].concat(),
+        };
+        for id in entry.children() {
+            add(symbols, dwarf, unit, *id, &scope, index);
+        }
+    }
+}
+
+// the (start, size) of every unit's code, `length` is that of .debug_info
+pub fn write(
+    dwarf: &Dwarf,
+    offsets: &[(UnitId, DebugInfoOffset)],
+    type_units: &[TypeUnit],
+    code: &[Vec<(u64, u64)>],
+    length: usize,
+) -> Vec<u8> {
+    let mut units: Vec<(usize, &UnitId, usize)> = offsets
+        .iter()
+        .enumerate()
+        .map(|(index, (cu, offset))| (offset.0, cu, index))
+        .collect();
+    units.sort_by_key(|(offset, _, _)| *offset);
+
+    let mut unit_list = Vec::new();
+    let mut addresses = Vec::new();
+    let mut symbols: Symbols = HashMap::new();
+    for (position, (start, cu, index)) in units.iter().enumerate() {
+        let end = units.get(position + 1).map_or(length, |next| next.0);
+        unit_list.extend_from_slice(&(*start as u64).to_le_bytes());
+        unit_list.extend_from_slice(&((end - start) as u64).to_le_bytes());
+
+        let ranges = units::coalesce(
+            code[*index]
+                .iter()
+                .map(|(start, size)| (*start, start.saturating_add(*size)))
+                .collect(),
+        );
+        for (low, high) in ranges {
+            addresses.extend_from_slice(&low.to_le_bytes());
+            addresses.extend_from_slice(&high.to_le_bytes());
+            put(&mut addresses, position as u32);
+        }
+
+        let unit = dwarf.units.get(**cu);
+        for id in unit.get(unit.root()).children() {
+            add(&mut symbols, dwarf, unit, *id, &[], position as u32);
+        }
+    }
+
+    // gdb numbers the type units after the compile units
+    let mut type_list = Vec::new();
+    for (index, type_unit) in type_units.iter().enumerate() {
+        for value in [type_unit.offset, type_unit.type_offset, type_unit.signature] {
+            type_list.extend_from_slice(&value.to_le_bytes());
+        }
+        let unit = dwarf.units.get(type_unit.unit);
+        add(
+            &mut symbols,
+            dwarf,
+            unit,
+            type_unit.entry,
+            &[],
+            (units.len() + index) as u32,
+        );
+    }
+
+    // a power of two, at most three quarters full
+    let mut slots = 1;
+    while slots * 3 < symbols.len() * 4 {
+        slots *= 2;
+    }
+    let mut table = vec![(0u32, 0u32); slots];
+    let mut pool = Vec::new();
+    let mut vectors = Vec::new();
+    for (_, vector) in symbols.iter() {
+        vectors.push(pool.len() as u32);
+        put(&mut pool, vector.len() as u32);
+        for entry in vector {
+            put(&mut pool, *entry);
+        }
+    }
+    for ((name, _), vector) in symbols.iter().zip(vectors) {
+        let offset = pool.len() as u32;
+        pool.extend_from_slice(name);
+        pool.push(0);
+
+        let hash = hash(name);
+        let mask = slots as u32 - 1;
+        let step = (hash.wrapping_mul(17) & mask) | 1;
+        let mut slot = hash & mask;
+        while table[slot as usize] != (0, 0) {
+            slot = (slot + step) & mask;
+        }
+        table[slot as usize] = (offset, vector);
+    }
+    let mut symbol_table = Vec::new();
+    for (name, vector) in table {
+        put(&mut symbol_table, name);
+        put(&mut symbol_table, vector);
+    }
+
+    let header = 6 * 4;
+    let units_at = header;
+    let types_at = units_at + unit_list.len();
+    let addresses_at = types_at + type_list.len();
+    let symbols_at = addresses_at + addresses.len();
+    let pool_at = symbols_at + symbol_table.len();
+    let mut out = Vec::new();
+    for value in [
+        VERSION as usize,
+        units_at,
+        types_at,
+        addresses_at,
+        symbols_at,
+        pool_at,
+    ] {
+        put(&mut out, value as u32);
+    }
+    out.extend(unit_list);
+    out.extend(type_list);
+    out.extend(addresses);
+    out.extend(symbol_table);
+    out.extend(pool);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gimli::write::LineProgram;
+
+    // the vector of `name`, probed for the way gdb does
+    fn lookup(index: &[u8], name: &str) -> Option<Vec<u32>> {
+        let word = |at: usize| u32::from_le_bytes(index[at..at + 4].try_into().unwrap());
+        let (symbols, pool) = (word(16) as usize, word(20) as usize);
+        let slots = (pool - symbols) as u32 / 8;
+        let hash = hash(name.as_bytes());
+        let mask = slots - 1;
+        let step = (hash.wrapping_mul(17) & mask) | 1;
+        let mut slot = hash & mask;
+        loop {
+            let at = symbols + slot as usize * 8;
+            let (offset, vector) = (word(at) as usize, word(at + 4) as usize);
+            if (offset, vector) == (0, 0) {
+                return None;
+            }
+            let found = &index[pool + offset..];
+            if &found[..found.iter().position(|c| *c == 0).unwrap()] == name.as_bytes() {
+                let count = word(pool + vector) as usize;
+                return Some((1..=count).map(|i| word(pool + vector + i * 4)).collect());
+            }
+            slot = (slot + step) & mask;
+        }
+    }
+
+    #[test]
+    fn namespaced_symbols_are_qualified() {
+        let encoding = gimli::Encoding {
+            format: gimli::Format::Dwarf32,
+            version: 4,
+            address_size: 8,
+        };
+        let mut dwarf = Dwarf::new();
+        let cu = dwarf.units.add(Unit::new(encoding, LineProgram::none()));
+        let unit = dwarf.units.get_mut(cu);
+        let root = unit.root();
+        let mut add = |parent, tag, name: &str, flag: Option<gimli::DwAt>| {
+            let id = unit.add(parent, tag);
+            let entry = unit.get_mut(id);
+            entry.set(gimli::DW_AT_name, AttributeValue::String(name.into()));
+            if let Some(flag) = flag {
+                entry.set(flag, AttributeValue::Flag(true));
+            }
+            id
+        };
+        let std = add(root, gimli::DW_TAG_namespace, "std", None);
+        let foo = add(std, gimli::DW_TAG_namespace, "foo", None);
+        _ = add(
+            foo,
+            gimli::DW_TAG_subprogram,
+            "bar",
+            Some(gimli::DW_AT_external),
+        );
+        _ = add(
+            foo,
+            gimli::DW_TAG_variable,
+            "gv",
+            Some(gimli::DW_AT_external),
+        );
+        let class = add(foo, gimli::DW_TAG_class_type, "Bar", None);
+        _ = add(class, gimli::DW_TAG_member, "field", None);
+        _ = add(class, gimli::DW_TAG_typedef, "size_type", None);
+        _ = add(
+            class,
+            gimli::DW_TAG_subprogram,
+            "get",
+            Some(gimli::DW_AT_declaration),
+        );
+        let color = add(foo, gimli::DW_TAG_enumeration_type, "color", None);
+        _ = add(color, gimli::DW_TAG_enumerator, "RED", None);
+
+        let index = write(&dwarf, &[(cu, DebugInfoOffset(0))], &[], &[Vec::new()], 64);
+        assert_eq!(lookup(&index, "std"), Some(vec![TYPE]));
+        assert_eq!(lookup(&index, "std::foo"), Some(vec![TYPE]));
+        assert_eq!(lookup(&index, "std::foo::bar"), Some(vec![FUNCTION]));
+        assert_eq!(lookup(&index, "std::foo::gv"), Some(vec![VARIABLE]));
+        assert_eq!(lookup(&index, "std::foo::Bar"), Some(vec![TYPE | STATIC]));
+        assert_eq!(
+            lookup(&index, "std::foo::Bar::size_type"),
+            Some(vec![TYPE | STATIC])
+        );
+        assert_eq!(lookup(&index, "std::foo::color"), Some(vec![TYPE | STATIC]));
+        assert_eq!(
+            lookup(&index, "std::foo::RED"),
+            Some(vec![VARIABLE | STATIC])
+        );
+        for missing in [
+            "bar",
+            "foo::bar",
+            "std::foo::Bar::field",
+            "std::foo::Bar::get",
+        ] {
+            assert_eq!(lookup(&index, missing), None, "{}", missing);
+        }
+    }
+}
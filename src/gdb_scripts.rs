@@ -0,0 +1,63 @@
+// .debug_gdb_scripts lists scripts gdb runs when it loads the file: a kind
+// byte each, 4 for inline python, then the script's name on the first line
+// and the script itself after it, up to a NUL. gdb only runs them from files
+// under an `add-auto-load-safe-path`, and says which it skipped otherwise.
+//
+// `--gdb-script setup.gdb --gdb-script unpack.py` (repeatable) embeds the
+// team's own setup this way, breakpoints, convenience functions and the
+// like, so it comes along with the debug file or the --inject binary. .py
+// files go in as they are, anything else is gdb commands, which gdb.execute
+// runs for it.
+
+use std::fs;
+use std::path::Path;
+
+use crate::DynErr;
+
+// SECTION_SCRIPT_ID_PYTHON_TEXT
+const PYTHON_TEXT: u8 = 4;
+
+// a python string literal
+pub fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\x{:02x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// (name, python) for one --gdb-script
+pub fn load(path: &Path) -> Result<(String, String), DynErr> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if text.contains('\0') {
+        return Err(format!("{}: a script can't hold a NUL", path.display()).into());
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let script = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("py") => text,
+        _ => format!("import gdb\n\ngdb.execute({})\n", quote(&text)),
+    };
+    Ok((name.into_owned(), script))
+}
+
+// the section for (name, python) pairs
+pub fn section(scripts: &[(String, String)]) -> Vec<u8> {
+    let mut section = Vec::new();
+    for (name, script) in scripts {
+        section.push(PYTHON_TEXT);
+        section.extend_from_slice(name.as_bytes());
+        section.push(b'\n');
+        section.extend_from_slice(script.as_bytes());
+        section.push(0);
+    }
+    section
+}
@@ -0,0 +1,1268 @@
+// `--headers types.h` reads reconstructed C declarations instead of a
+// decompiler export: structs, unions, enums, typedefs, prototypes and global
+// variables, with the members laid out the SysV way for the target (natural
+// alignment, ILP32 or LP64 by pointer size, bitfields packed into units of
+// their type unless `__attribute__((packed))`).
+//
+// there's no preprocessor, `#` lines are skipped. types get the names a binja
+// export would give them: `node` for struct node, `node*`, `char[16]`,
+// `int (int, char*)` and `const char`.
+//
+// `--symbols syms.json` places the declared functions and variables:
+//
+// {"head": 16384, "main": {"address": 4198448, "size": 120}}
+//
+// functions need their size, variables take the size of their type.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::target::Target;
+use crate::{
+    Array, DynErr, Encoding, Enum, EnumField, Field, Function, GlobalVariable, HashMap, Integer,
+    Parameter, Pointer, Qualified, Structure, Subprogram, TypeSet, Typedef,
+};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Placement {
+    Address(u64),
+    Sized { address: u64, size: u64 },
+}
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(u64),
+    Punct(&'static str),
+}
+
+const PUNCTUATION: [&str; 28] = [
+    "...", "<<", ">>", "&&", "||", "==", "!=", "<=", ">=", "->", "{", "}", "(", ")", "[", "]", ";",
+    ",", "*", "=", ":", "+", "-", "/", "%", "&", "|", "^",
+];
+const SINGLE: [&str; 8] = ["~", "!", "<", ">", ".", "?", "#", "\\"];
+
+fn lex(source: &str) -> Result<Vec<(Token, usize)>, String> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut at = 0;
+    let mut line_start = true;
+    while at < bytes.len() {
+        let rest = &source[at..];
+        let byte = bytes[at];
+        if byte == b'\n' {
+            line += 1;
+            line_start = true;
+            at += 1;
+        } else if byte.is_ascii_whitespace() {
+            at += 1;
+        } else if byte == b'#' && line_start {
+            // a directive, with its continuation lines
+            while at < bytes.len() && bytes[at] != b'\n' {
+                if bytes[at] == b'\\' && bytes.get(at + 1) == Some(&b'\n') {
+                    line += 1;
+                    at += 1;
+                }
+                at += 1;
+            }
+        } else if rest.starts_with("//") {
+            at += rest.find('\n').unwrap_or(rest.len());
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            let end = comment
+                .find("*/")
+                .ok_or(format!("{}: unterminated comment", line))?;
+            line += rest[..end + 4].matches('\n').count();
+            at += end + 4;
+        } else if byte.is_ascii_alphabetic() || byte == b'_' {
+            let length = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push((Token::Ident(rest[..length].to_string()), line));
+            at += length;
+            line_start = false;
+        } else if byte.is_ascii_digit() {
+            let length = rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+            let digits = rest[..length].trim_end_matches(['u', 'U', 'l', 'L']);
+            let number = if let Some(hex) = digits.strip_prefix("0x").or(digits.strip_prefix("0X"))
+            {
+                u64::from_str_radix(hex, 16)
+            } else if digits.len() > 1 && digits.starts_with('0') {
+                u64::from_str_radix(&digits[1..], 8)
+            } else {
+                digits.parse()
+            }
+            .map_err(|_| format!("{}: bad number `{}`", line, &rest[..length]))?;
+            tokens.push((Token::Number(number), line));
+            at += length;
+            line_start = false;
+        } else if byte == b'\'' {
+            let (value, length) = match rest.as_bytes() {
+                [b'\'', b'\\', b'n', b'\'', ..] => (b'\n', 4),
+                [b'\'', b'\\', b't', b'\'', ..] => (b'\t', 4),
+                [b'\'', b'\\', b'0', b'\'', ..] => (0, 4),
+                [b'\'', b'\\', escaped, b'\'', ..] => (*escaped, 4),
+                [b'\'', value, b'\'', ..] => (*value, 3),
+                _ => return Err(format!("{}: bad character literal", line)),
+            };
+            tokens.push((Token::Number(value as u64), line));
+            at += length;
+            line_start = false;
+        } else if byte == b'"' {
+            // only ever inside attributes and asm labels, which are skipped
+            let end = rest[1..]
+                .find('"')
+                .ok_or(format!("{}: unterminated string", line))?;
+            tokens.push((Token::Number(0), line));
+            at += end + 2;
+            line_start = false;
+        } else {
+            let punct = PUNCTUATION
+                .iter()
+                .chain(SINGLE.iter())
+                .find(|punct| rest.starts_with(**punct))
+                .ok_or(format!("{}: unexpected `{}`", line, byte as char))?;
+            tokens.push((Token::Punct(punct), line));
+            at += punct.len();
+            line_start = false;
+        }
+    }
+    Ok(tokens)
+}
+
+// what a declarator wraps its base type in, innermost first
+enum Derive {
+    Pointer(bool, bool, bool),
+    Array(u64),
+    Function(Vec<Parameter>, bool),
+}
+
+#[derive(Default)]
+struct Specifiers {
+    typename: String,
+    typedef: bool,
+    is_static: bool,
+    constant: bool,
+    volatile: bool,
+    // a struct, union or enum defined right there
+    tagged: bool,
+}
+
+struct Declaration {
+    typename: String,
+    function: bool,
+    is_static: bool,
+    decl: crate::Declaration,
+}
+
+// integer types any header may use without including anything, unless it
+// typedefs them itself
+const BUILTIN: [(&str, u64, bool); 13] = [
+    ("int8_t", 1, true),
+    ("uint8_t", 1, false),
+    ("int16_t", 2, true),
+    ("uint16_t", 2, false),
+    ("int32_t", 4, true),
+    ("uint32_t", 4, false),
+    ("int64_t", 8, true),
+    ("uint64_t", 8, false),
+    ("wchar_t", 4, true),
+    ("char16_t", 2, false),
+    ("char32_t", 4, false),
+    ("bool", 1, false),
+    ("__int128", 16, true),
+];
+// and the ones as wide as a pointer
+const POINTER_SIZED: [(&str, bool); 5] = [
+    ("intptr_t", true),
+    ("uintptr_t", false),
+    ("size_t", false),
+    ("ssize_t", true),
+    ("ptrdiff_t", true),
+];
+
+const IGNORED: [&str; 9] = [
+    "extern",
+    "inline",
+    "__inline",
+    "__inline__",
+    "register",
+    "auto",
+    "_Noreturn",
+    "__extension__",
+    "__restrict",
+];
+
+const BASE_WORDS: [&str; 11] = [
+    "void",
+    "char",
+    "short",
+    "int",
+    "long",
+    "float",
+    "double",
+    "signed",
+    "unsigned",
+    "_Bool",
+    "__signed__",
+];
+
+struct Parser<'a> {
+    path: &'a Path,
+    tokens: Vec<(Token, usize)>,
+    at: usize,
+    target: &'a Target,
+    set: TypeSet,
+    // size and alignment of every complete type
+    layouts: HashMap<String, (u64, u64)>,
+    enumerators: HashMap<String, i64>,
+    declarations: HashMap<String, Declaration>,
+    // typedef names, including the ones naming their struct already
+    typenames: BTreeSet<String>,
+    anonymous: u64,
+}
+
+impl<'a> Parser<'a> {
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.at)
+            .or(self.tokens.last())
+            .map_or(0, |(_, line)| *line)
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> DynErr {
+        format!("{}:{}: {}", self.path.display(), self.line(), message).into()
+    }
+
+    // the header's line the next token is on
+    fn here(&self) -> crate::Declaration {
+        crate::Declaration {
+            file: self.path.display().to_string(),
+            line: self.line() as u64,
+            column: None,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at).map(|(token, _)| token)
+    }
+
+    fn peek_at(&self, ahead: usize) -> Option<&Token> {
+        self.tokens.get(self.at + ahead).map(|(token, _)| token)
+    }
+
+    fn is(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        let is = self.is(punct);
+        if is {
+            self.at += 1;
+        }
+        is
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), DynErr> {
+        match self.eat(punct) {
+            true => Ok(()),
+            false => Err(self.error(format!("expected `{}`", punct))),
+        }
+    }
+
+    fn ident(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Ident(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn eat_ident(&mut self, word: &str) -> bool {
+        let is = self.ident() == Some(word);
+        if is {
+            self.at += 1;
+        }
+        is
+    }
+
+    // skips a balanced run from an opening bracket on
+    fn skip_group(&mut self, open: &str, close: &str) -> Result<(), DynErr> {
+        self.expect(open)?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.peek() {
+                None => return Err(self.error(format!("unbalanced `{}`", open))),
+                Some(Token::Punct(p)) if *p == open => depth += 1,
+                Some(Token::Punct(p)) if *p == close => depth -= 1,
+                _ => {}
+            }
+            self.at += 1;
+        }
+        Ok(())
+    }
+
+    // __attribute__((...)), __declspec(...) and asm labels, true if packed
+    fn attributes(&mut self) -> Result<bool, DynErr> {
+        let mut packed = false;
+        loop {
+            match self.ident() {
+                Some("__attribute__" | "__attribute" | "__declspec") => {
+                    self.at += 1;
+                    let start = self.at;
+                    self.skip_group("(", ")")?;
+                    packed |= self.tokens[start..self.at].iter().any(|(token, _)| {
+                        matches!(token, Token::Ident(name) if name == "packed" || name == "__packed__")
+                    });
+                }
+                Some("__asm__" | "__asm" | "asm") => {
+                    self.at += 1;
+                    self.skip_group("(", ")")?;
+                }
+                _ => return Ok(packed),
+            }
+        }
+    }
+
+    fn layout(&self, typename: &str) -> Result<(u64, u64), DynErr> {
+        if typename.is_empty() {
+            return Err(self.error("void has no size"));
+        }
+        self.layouts
+            .get(typename)
+            .copied()
+            .ok_or_else(|| self.error(format!("`{}` is incomplete here", typename)))
+    }
+
+    // the size and signedness of a builtin the header hasn't typedef'd
+    fn builtin(&self, name: &str) -> Option<(u64, bool)> {
+        if self.typenames.contains(name) {
+            return None;
+        }
+        match BUILTIN.iter().find(|(builtin, _, _)| *builtin == name) {
+            Some((_, size, signed)) => Some((*size, *signed)),
+            None => (POINTER_SIZED.iter())
+                .find(|(builtin, _)| *builtin == name)
+                .map(|(_, signed)| (self.target.pointer_size, *signed)),
+        }
+    }
+
+    fn is_type_name(&self, name: &str) -> bool {
+        self.typenames.contains(name) || self.builtin(name).is_some()
+    }
+
+    fn starts_type(&self) -> bool {
+        match self.ident() {
+            Some(word) => {
+                BASE_WORDS.contains(&word)
+                    || IGNORED.contains(&word)
+                    || matches!(
+                        word,
+                        "struct" | "union" | "enum" | "const" | "volatile" | "typedef" | "static"
+                    )
+                    || self.is_type_name(word)
+            }
+            None => false,
+        }
+    }
+
+    fn integer(&mut self, name: &str, size: u64, signed: bool, encoding: Option<Encoding>) {
+        if !self.set.integers.contains_key(name) {
+            _ = self.set.integers.insert(
+                name.to_string(),
+                Integer {
+                    size,
+                    signed,
+                    endianity: None,
+                    encoding,
+                    bits: None,
+                },
+            );
+            _ = self.layouts.insert(name.to_string(), (size, size.max(1)));
+        }
+    }
+
+    // the C base type spelled by these keywords
+    fn base(&mut self, words: &[String]) -> Result<String, DynErr> {
+        let count = |word: &str| words.iter().filter(|w| *w == word).count();
+        let unsigned = count("unsigned") > 0;
+        let longs = count("long");
+        let pointer = self.target.pointer_size;
+        let int = if pointer == 2 { 2 } else { 4 };
+        let (name, size, signed, encoding) = if count("void") > 0 {
+            return Ok(String::new());
+        } else if count("_Bool") > 0 {
+            ("_Bool", 1, false, Some(Encoding::Bool))
+        } else if count("char") > 0 {
+            match (count("signed") + count("__signed__") > 0, unsigned) {
+                (true, _) => ("signed char", 1, true, Some(Encoding::Char)),
+                (_, true) => ("unsigned char", 1, false, Some(Encoding::Char)),
+                _ => ("char", 1, true, Some(Encoding::Char)),
+            }
+        } else if count("float") > 0 {
+            ("float", 4, false, Some(Encoding::Float))
+        } else if count("double") > 0 && longs > 0 {
+            ("long double", 16, false, Some(Encoding::Float))
+        } else if count("double") > 0 {
+            ("double", 8, false, Some(Encoding::Float))
+        } else if count("short") > 0 {
+            match unsigned {
+                true => ("unsigned short", 2, false, None),
+                false => ("short", 2, true, None),
+            }
+        } else if longs >= 2 {
+            match unsigned {
+                true => ("unsigned long long", 8, false, None),
+                false => ("long long", 8, true, None),
+            }
+        } else if longs == 1 {
+            let size = if pointer == 8 { 8 } else { 4 };
+            match unsigned {
+                true => ("unsigned long", size, false, None),
+                false => ("long", size, true, None),
+            }
+        } else {
+            match unsigned {
+                true => ("unsigned int", int, false, None),
+                false => ("int", int, true, None),
+            }
+        };
+        self.integer(name, size, signed, encoding);
+        Ok(name.to_string())
+    }
+
+    fn specifiers(&mut self) -> Result<Specifiers, DynErr> {
+        let mut specifiers = Specifiers::default();
+        let mut words = Vec::new();
+        let mut named = None;
+        loop {
+            self.attributes()?;
+            let Some(word) = self.ident().map(str::to_string) else {
+                break;
+            };
+            match word.as_str() {
+                "typedef" => specifiers.typedef = true,
+                "static" => specifiers.is_static = true,
+                "const" | "__const" => specifiers.constant = true,
+                "volatile" | "__volatile__" => specifiers.volatile = true,
+                "restrict" => {}
+                word if IGNORED.contains(&word) => {}
+                word if BASE_WORDS.contains(&word) && named.is_none() => {
+                    words.push(word.to_string())
+                }
+                "struct" | "union" | "enum" if named.is_none() && words.is_empty() => {
+                    self.at += 1;
+                    let (name, defined) = match word.as_str() {
+                        "enum" => self.enumeration()?,
+                        _ => self.aggregate(word == "union")?,
+                    };
+                    specifiers.tagged = defined;
+                    named = Some(name);
+                    continue;
+                }
+                word if named.is_none() && words.is_empty() && self.is_type_name(word) => {
+                    if let Some((size, signed)) = self.builtin(word) {
+                        let encoding = match word {
+                            "bool" => Some(Encoding::Bool),
+                            "wchar_t" | "char16_t" | "char32_t" => Some(Encoding::Char),
+                            _ => None,
+                        };
+                        self.integer(word, size, signed, encoding);
+                    }
+                    named = Some(word.to_string());
+                }
+                _ => break,
+            }
+            self.at += 1;
+        }
+        specifiers.typename = match named {
+            Some(name) => name,
+            None if words.is_empty() => return Err(self.error("expected a type")),
+            None => self.base(&words)?,
+        };
+        Ok(specifiers)
+    }
+
+    fn tag_name(&mut self) -> Option<String> {
+        let name = self.ident()?.to_string();
+        self.at += 1;
+        Some(name)
+    }
+
+    fn anonymous_name(&mut self) -> String {
+        let name = format!("anon.{}", self.anonymous);
+        self.anonymous += 1;
+        name
+    }
+
+    // a struct or union after its keyword, the name and whether it's defined
+    fn aggregate(&mut self, union: bool) -> Result<(String, bool), DynErr> {
+        let mut packed = self.attributes()?;
+        let decl = self.here();
+        let tag = self.tag_name();
+        if !self.is("{") {
+            let name = tag.ok_or_else(|| self.error("expected a struct name or `{`"))?;
+            return Ok((name, false));
+        }
+        self.expect("{")?;
+
+        // (name, type, bitfield width)
+        let mut members: Vec<(String, String, Option<u64>)> = Vec::new();
+        while !self.eat("}") {
+            if self.eat(";") {
+                continue;
+            }
+            let specifiers = self.specifiers()?;
+            if self.is(";") {
+                // an anonymous struct or union member
+                if specifiers.tagged {
+                    members.push((String::new(), specifiers.typename.clone(), None));
+                }
+                self.at += 1;
+                continue;
+            }
+            loop {
+                let (name, derives) = match self.is(":") {
+                    true => (None, Vec::new()),
+                    false => self.declarator()?,
+                };
+                let typename = self.derive(&specifiers, derives);
+                let width = match self.eat(":") {
+                    true => Some(self.expression()? as u64),
+                    false => None,
+                };
+                self.attributes()?;
+                members.push((name.unwrap_or_default(), typename, width));
+                if !self.eat(",") {
+                    break;
+                }
+            }
+            self.expect(";")?;
+        }
+        packed |= self.attributes()?;
+
+        let anon = tag.is_none();
+        let name = match tag {
+            Some(name) => name,
+            None => self.anonymous_name(),
+        };
+        let mut structure = self.lay_out(members, union, packed, anon)?;
+        structure.0.decl = Some(decl);
+        _ = self
+            .layouts
+            .insert(name.clone(), (structure.0.size, structure.1));
+        match union {
+            true => _ = self.set.unions.insert(name.clone(), structure.0),
+            false => _ = self.set.structs.insert(name.clone(), structure.0),
+        }
+        Ok((name, true))
+    }
+
+    // SysV layout, returns the struct and its alignment
+    fn lay_out(
+        &self,
+        members: Vec<(String, String, Option<u64>)>,
+        union: bool,
+        packed: bool,
+        anon: bool,
+    ) -> Result<(Structure, u64), DynErr> {
+        let mut fields = Vec::new();
+        let mut bits = 0u64;
+        let mut end = 0;
+        let mut alignment = 1;
+        for (name, typename, width) in members {
+            let (size, align) = match self.layout(&typename) {
+                Ok(layout) => layout,
+                // a flexible array member
+                Err(_) if self.set.arrays.get(&typename).is_some_and(|a| a.count == 0) => {
+                    let element = &self.set.arrays[&typename].target;
+                    (0, self.layout(element)?.1)
+                }
+                Err(e) => return Err(e),
+            };
+            let align = if packed { 1 } else { align };
+            if union {
+                bits = 0;
+            }
+            match width {
+                Some(width) => {
+                    let unit = size * 8;
+                    if width == 0 {
+                        bits = bits.next_multiple_of(unit.max(8));
+                        continue;
+                    }
+                    if !packed && bits % unit + width > unit {
+                        bits = bits.next_multiple_of(unit);
+                    }
+                    let storage = if packed { bits / 8 } else { bits / unit * size };
+                    fields.push(Field {
+                        offset: storage,
+                        name,
+                        typename,
+                        endianity: None,
+                        bit_offset: Some(bits - storage * 8),
+                        bit_size: Some(width),
+                        access: None,
+                    });
+                    bits += width;
+                }
+                None => {
+                    bits = bits.next_multiple_of(align * 8);
+                    fields.push(Field {
+                        offset: bits / 8,
+                        name,
+                        typename,
+                        endianity: None,
+                        bit_offset: None,
+                        bit_size: None,
+                        access: None,
+                    });
+                    bits += size * 8;
+                }
+            }
+            alignment = alignment.max(align);
+            end = end.max(bits);
+        }
+        Ok((
+            Structure {
+                size: end.div_ceil(8).next_multiple_of(alignment),
+                anon,
+                fields,
+                variant: None,
+                declaration: false,
+                decl: None,
+                printer: None,
+            },
+            alignment,
+        ))
+    }
+
+    fn enumeration(&mut self) -> Result<(String, bool), DynErr> {
+        self.attributes()?;
+        let decl = self.here();
+        let tag = self.tag_name();
+        if !self.eat("{") {
+            let name = tag.ok_or_else(|| self.error("expected an enum name or `{`"))?;
+            return Ok((name, false));
+        }
+        let mut fields = Vec::new();
+        let mut next = 0i64;
+        while !self.eat("}") {
+            let name = self
+                .tag_name()
+                .ok_or_else(|| self.error("expected an enumerator"))?;
+            if self.eat("=") {
+                next = self.expression()?;
+            }
+            _ = self.enumerators.insert(name.clone(), next);
+            fields.push((name, next));
+            next += 1;
+            if !self.eat(",") {
+                self.expect("}")?;
+                break;
+            }
+        }
+        self.attributes()?;
+
+        let name = match tag {
+            Some(name) => name,
+            None => self.anonymous_name(),
+        };
+        let signed = fields.iter().any(|(_, value)| *value < 0);
+        let wide = fields
+            .iter()
+            .any(|(_, value)| *value > u32::MAX as i64 || *value < i32::MIN as i64);
+        let size = if wide { 8 } else { 4 };
+        _ = self.layouts.insert(name.clone(), (size, size));
+        _ = self.set.enums.insert(
+            name.clone(),
+            Enum {
+                size,
+                signed,
+                fields: fields
+                    .into_iter()
+                    .map(|(name, value)| EnumField {
+                        name,
+                        value: value as i128,
+                    })
+                    .collect(),
+                decl: Some(decl),
+            },
+        );
+        Ok((name, true))
+    }
+
+    fn qualifiers(&mut self) -> (bool, bool, bool) {
+        let (mut constant, mut volatile, mut restrict) = (false, false, false);
+        loop {
+            match self.ident() {
+                Some("const" | "__const") => constant = true,
+                Some("volatile" | "__volatile__") => volatile = true,
+                Some("restrict" | "__restrict" | "__restrict__") => restrict = true,
+                _ => return (constant, volatile, restrict),
+            }
+            self.at += 1;
+        }
+    }
+
+    // the name, if any, and what it makes of the base type
+    fn declarator(&mut self) -> Result<(Option<String>, Vec<Derive>), DynErr> {
+        let mut pointers = Vec::new();
+        while self.eat("*") {
+            let (constant, volatile, restrict) = self.qualifiers();
+            self.attributes()?;
+            pointers.push(Derive::Pointer(constant, volatile, restrict));
+        }
+        self.attributes()?;
+
+        let nested = self.is("(") && matches!(self.peek_at(1), Some(Token::Punct("*" | "(" | "[")))
+            || self.is("(")
+                && matches!(self.peek_at(1), Some(Token::Ident(name)) if !self.starts_type_word(name));
+        let (name, inner) = if nested {
+            self.expect("(")?;
+            let inner = self.declarator()?;
+            self.expect(")")?;
+            inner
+        } else {
+            // after the specifiers a builtin's name is the one being declared
+            match self.ident() {
+                Some(name) if !self.starts_type() || self.builtin(name).is_some() => {
+                    let name = name.to_string();
+                    self.at += 1;
+                    (Some(name), Vec::new())
+                }
+                _ => (None, Vec::new()),
+            }
+        };
+
+        let mut suffixes = Vec::new();
+        loop {
+            if self.eat("[") {
+                let count = match self.is("]") {
+                    true => 0,
+                    false => self.expression()? as u64,
+                };
+                self.expect("]")?;
+                suffixes.push(Derive::Array(count));
+            } else if self.is("(") {
+                let (parameters, variadic) = self.parameters()?;
+                suffixes.push(Derive::Function(parameters, variadic));
+            } else {
+                break;
+            }
+        }
+        self.attributes()?;
+
+        let mut derives = pointers;
+        derives.extend(suffixes.into_iter().rev());
+        derives.extend(inner);
+        Ok((name, derives))
+    }
+
+    fn starts_type_word(&self, word: &str) -> bool {
+        BASE_WORDS.contains(&word)
+            || matches!(word, "struct" | "union" | "enum" | "const" | "volatile")
+            || self.is_type_name(word)
+    }
+
+    fn parameters(&mut self) -> Result<(Vec<Parameter>, bool), DynErr> {
+        self.expect("(")?;
+        let mut parameters = Vec::new();
+        let mut variadic = false;
+        if self.ident() == Some("void") && matches!(self.peek_at(1), Some(Token::Punct(")"))) {
+            self.at += 1;
+        }
+        while !self.eat(")") {
+            if self.eat("...") {
+                variadic = true;
+                self.expect(")")?;
+                break;
+            }
+            let specifiers = self.specifiers()?;
+            let (name, mut derives) = self.declarator()?;
+            // arrays and functions are passed as pointers
+            match derives.last() {
+                Some(Derive::Array(_)) => {
+                    _ = derives.pop();
+                    derives.push(Derive::Pointer(false, false, false));
+                }
+                Some(Derive::Function(..)) => derives.push(Derive::Pointer(false, false, false)),
+                _ => {}
+            }
+            let typename = self.derive(&specifiers, derives);
+            parameters.push(Parameter {
+                name: name.unwrap_or_default(),
+                typename,
+            });
+            if !self.eat(",") {
+                self.expect(")")?;
+                break;
+            }
+        }
+        Ok((parameters, variadic))
+    }
+
+    fn qualified(
+        &mut self,
+        target: String,
+        constant: bool,
+        volatile: bool,
+        restrict: bool,
+    ) -> String {
+        if !(constant || volatile || restrict) {
+            return target;
+        }
+        let mut name = Vec::new();
+        for (set, word) in [
+            (constant, "const"),
+            (volatile, "volatile"),
+            (restrict, "restrict"),
+        ] {
+            if set {
+                name.push(word);
+            }
+        }
+        let shown = if target.is_empty() { "void" } else { &target };
+        let name = format!("{} {}", name.join(" "), shown);
+        if let Some(layout) = self.layouts.get(&target).copied() {
+            _ = self.layouts.insert(name.clone(), layout);
+        }
+        _ = self.set.qualifiers.insert(
+            name.clone(),
+            Qualified {
+                target,
+                constant,
+                volatile,
+                restrict,
+            },
+        );
+        name
+    }
+
+    fn derive(&mut self, specifiers: &Specifiers, derives: Vec<Derive>) -> String {
+        let base = specifiers.typename.clone();
+        let mut typename = self.qualified(base, specifiers.constant, specifiers.volatile, false);
+        // the array the last derive made, and whether it's new, an array of
+        // it takes its dimensions instead
+        let mut made: Option<(Array, bool)> = None;
+        for derive in derives {
+            let inner = made.take();
+            let shown = if typename.is_empty() {
+                "void".to_string()
+            } else {
+                typename.clone()
+            };
+            typename = match derive {
+                Derive::Pointer(constant, volatile, restrict) => {
+                    let name = format!("{}*", shown);
+                    let size = self.target.pointer_size;
+                    _ = self.layouts.insert(name.clone(), (size, size));
+                    _ = self.set.pointers.insert(
+                        name.clone(),
+                        Pointer {
+                            size,
+                            target: typename,
+                            reference: None,
+                            member_of: None,
+                        },
+                    );
+                    self.qualified(name, constant, volatile, restrict)
+                }
+                Derive::Array(count) => {
+                    let array = match inner {
+                        // `int m[4][8]` is a single array of two dimensions
+                        Some((inner, new)) => {
+                            if new {
+                                _ = self.set.arrays.remove(&typename);
+                            }
+                            Array {
+                                count,
+                                dimensions: inner.counts().collect(),
+                                target: inner.target,
+                                string: false,
+                                dynamic: None,
+                            }
+                        }
+                        None => Array {
+                            count,
+                            target: typename.clone(),
+                            dimensions: Vec::new(),
+                            string: false,
+                            dynamic: None,
+                        },
+                    };
+                    let element = if array.target.is_empty() {
+                        "void"
+                    } else {
+                        &array.target
+                    };
+                    let name = format!("{}{}", element, array.bounds());
+                    let layout = match self.layouts.get(&typename).copied() {
+                        Some((size, align)) if count > 0 => Some((size * count, align)),
+                        _ => None,
+                    };
+                    if let Some(layout) = layout {
+                        _ = self.layouts.insert(name.clone(), layout);
+                    }
+                    let new = !self.set.arrays.contains_key(&name);
+                    made = Some((array.clone(), new));
+                    if new {
+                        _ = self.set.arrays.insert(name.clone(), array);
+                    }
+                    name
+                }
+                Derive::Function(parameters, variadic) => {
+                    let mut shown_parameters: Vec<&str> = parameters
+                        .iter()
+                        .map(|parameter| match parameter.typename.as_str() {
+                            "" => "void",
+                            typename => typename,
+                        })
+                        .collect();
+                    if variadic {
+                        shown_parameters.push("...");
+                    }
+                    let name = format!("{} ({})", shown, shown_parameters.join(", "));
+                    _ = self.set.functions.insert(
+                        name.clone(),
+                        Function {
+                            parameters,
+                            returntype: typename,
+                            variadic,
+                            convention: None,
+                        },
+                    );
+                    name
+                }
+            };
+        }
+        typename
+    }
+
+    fn primary(&mut self) -> Result<i64, DynErr> {
+        if self.eat("(") {
+            let value = self.expression()?;
+            self.expect(")")?;
+            return Ok(value);
+        }
+        if self.eat("-") {
+            return Ok(self.primary()?.wrapping_neg());
+        }
+        if self.eat("~") {
+            return Ok(!self.primary()?);
+        }
+        if self.eat("!") {
+            return Ok((self.primary()? == 0) as i64);
+        }
+        if self.eat_ident("sizeof") {
+            self.expect("(")?;
+            let specifiers = self.specifiers()?;
+            let (_, derives) = self.declarator()?;
+            let typename = self.derive(&specifiers, derives);
+            self.expect(")")?;
+            return Ok(self.layout(&typename)?.0 as i64);
+        }
+        match self.peek().cloned() {
+            Some(Token::Number(number)) => {
+                self.at += 1;
+                Ok(number as i64)
+            }
+            Some(Token::Ident(name)) => match self.enumerators.get(&name) {
+                Some(value) => {
+                    self.at += 1;
+                    Ok(*value)
+                }
+                None => Err(self.error(format!("`{}` isn't a constant", name))),
+            },
+            _ => Err(self.error("expected a constant")),
+        }
+    }
+
+    // integer constant expressions, by precedence climbing
+    fn binary(&mut self, level: usize) -> Result<i64, DynErr> {
+        const LEVELS: [&[&str]; 6] = [
+            &["|"],
+            &["^"],
+            &["&"],
+            &["<<", ">>"],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+        if level == LEVELS.len() {
+            return self.primary();
+        }
+        let mut value = self.binary(level + 1)?;
+        while let Some(Token::Punct(op)) = self.peek().cloned() {
+            if !LEVELS[level].contains(&op) {
+                break;
+            }
+            self.at += 1;
+            let right = self.binary(level + 1)?;
+            value = match op {
+                "|" => value | right,
+                "^" => value ^ right,
+                "&" => value & right,
+                "<<" => value.wrapping_shl(right as u32),
+                ">>" => value.wrapping_shr(right as u32),
+                "+" => value.wrapping_add(right),
+                "-" => value.wrapping_sub(right),
+                "*" => value.wrapping_mul(right),
+                _ if right == 0 => return Err(self.error("division by zero")),
+                "/" => value / right,
+                _ => value % right,
+            };
+        }
+        Ok(value)
+    }
+
+    fn expression(&mut self) -> Result<i64, DynErr> {
+        self.binary(0)
+    }
+
+    fn skip_initializer(&mut self) -> Result<(), DynErr> {
+        loop {
+            match self.peek() {
+                None => return Err(self.error("expected `;`")),
+                Some(Token::Punct("," | ";")) => return Ok(()),
+                Some(Token::Punct("{")) => self.skip_group("{", "}")?,
+                Some(Token::Punct("(")) => self.skip_group("(", ")")?,
+                _ => self.at += 1,
+            }
+        }
+    }
+
+    fn declaration(&mut self) -> Result<(), DynErr> {
+        if self.eat(";") {
+            return Ok(());
+        }
+        // extern "C" { ... } from headers shared with C++
+        if self.ident() == Some("extern") && matches!(self.peek_at(1), Some(Token::Number(_))) {
+            self.at += 2;
+            _ = self.eat("{");
+            return Ok(());
+        }
+        if self.eat("}") {
+            return Ok(());
+        }
+        let specifiers = self.specifiers()?;
+        if self.eat(";") {
+            return Ok(());
+        }
+        loop {
+            let decl = self.here();
+            let (name, derives) = self.declarator()?;
+            let function = matches!(derives.last(), Some(Derive::Function(..)));
+            let typename = self.derive(&specifiers, derives);
+            let name = name.ok_or_else(|| self.error("expected a name"))?;
+            if specifiers.typedef {
+                // `typedef unsigned long size_t`, fine as long as the uses of
+                // the builtin before it got the same size
+                if let Some(integer) = self.set.integers.get(&name) {
+                    let size = self.layouts.get(&typename).map(|(size, _)| *size);
+                    if size != Some(integer.size) {
+                        return Err(self.error(format!(
+                            "`{}` is used as {} bytes before this typedef",
+                            name, integer.size
+                        )));
+                    }
+                    _ = self.set.integers.remove(&name);
+                }
+                _ = self.typenames.insert(name.clone());
+                // `typedef struct node node` names the struct already
+                if name != typename {
+                    if let Some(layout) = self.layouts.get(&typename).copied() {
+                        _ = self.layouts.insert(name.clone(), layout);
+                    }
+                    _ = self.set.typedefs.insert(
+                        name,
+                        Typedef {
+                            target: typename,
+                            decl: Some(decl),
+                        },
+                    );
+                }
+            } else {
+                _ = self.declarations.insert(
+                    name,
+                    Declaration {
+                        typename,
+                        function,
+                        is_static: specifiers.is_static,
+                        decl,
+                    },
+                );
+            }
+            if self.eat("=") {
+                self.skip_initializer()?;
+            }
+            if function && self.is("{") {
+                return self.skip_group("{", "}");
+            }
+            if !self.eat(",") {
+                return self.expect(";");
+            }
+        }
+    }
+}
+
+pub fn load(path: &Path, symbols: Option<&Path>, target: &Target) -> Result<TypeSet, DynErr> {
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let tokens = lex(&source).map_err(|e| format!("{}:{}", path.display(), e))?;
+    let mut parser = Parser {
+        path,
+        tokens,
+        at: 0,
+        target,
+        set: TypeSet::default(),
+        layouts: HashMap::new(),
+        enumerators: HashMap::new(),
+        declarations: HashMap::new(),
+        typenames: BTreeSet::new(),
+        anonymous: 0,
+    };
+    while parser.peek().is_some() {
+        parser.declaration()?;
+    }
+
+    // structs that are only ever pointed to stay empty
+    let set = &parser.set;
+    let known = |name: &String| {
+        name.is_empty()
+            || set.structs.contains_key(name)
+            || set.unions.contains_key(name)
+            || set.integers.contains_key(name)
+            || set.pointers.contains_key(name)
+            || set.typedefs.contains_key(name)
+            || set.functions.contains_key(name)
+            || set.enums.contains_key(name)
+            || set.arrays.contains_key(name)
+            || set.qualifiers.contains_key(name)
+    };
+    let incomplete: Vec<String> = (set.pointers.values().map(|pointer| &pointer.target))
+        .chain(set.typedefs.values().map(|typedef| &typedef.target))
+        .chain(set.qualifiers.values().map(|qualified| &qualified.target))
+        .filter(|target| !known(target))
+        .cloned()
+        .collect();
+    for name in incomplete {
+        _ = parser.set.structs.insert(
+            name,
+            Structure {
+                size: 0,
+                anon: false,
+                fields: Vec::new(),
+                variant: None,
+                declaration: true,
+                decl: None,
+                printer: None,
+            },
+        );
+    }
+
+    let Some(symbols) = symbols else {
+        return Ok(parser.set);
+    };
+    let placements: HashMap<String, Placement> = match fs::read_to_string(symbols) {
+        Ok(json) => {
+            serde_json::from_str(&json).map_err(|e| format!("{}: {}", symbols.display(), e))?
+        }
+        Err(e) => return Err(format!("{}: {}", symbols.display(), e).into()),
+    };
+    for (name, placement) in placements {
+        let declaration = parser.declarations.get(&name).ok_or_else(|| {
+            format!(
+                "{}: `{}` isn't declared in {}",
+                symbols.display(),
+                name,
+                path.display()
+            )
+        })?;
+        let (address, size) = match placement {
+            Placement::Sized { address, size } => (address, Some(size)),
+            Placement::Address(address) => (address, None),
+        };
+        if declaration.function {
+            let size = size.ok_or_else(|| {
+                format!(
+                    "{}: function `{}` needs {{\"address\": ..., \"size\": ...}}",
+                    symbols.display(),
+                    name
+                )
+            })?;
+            _ = parser.set.subprograms.insert(
+                address,
+                Subprogram {
+                    name,
+                    size,
+                    typename: declaration.typename.clone(),
+                    locals: Vec::new(),
+                    local: declaration.is_static,
+                    decl: Some(declaration.decl.clone()),
+                    space: None,
+                },
+            );
+        } else {
+            let size = match size {
+                Some(size) => size,
+                None => parser.layout(&declaration.typename)?.0,
+            };
+            _ = parser.set.variables.insert(
+                address,
+                GlobalVariable {
+                    name,
+                    size,
+                    typename: declaration.typename.clone(),
+                    decl: Some(declaration.decl.clone()),
+                    space: None,
+                },
+            );
+        }
+    }
+    Ok(parser.set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::target;
+
+    fn parse(name: &str, source: &str) -> Result<TypeSet, DynErr> {
+        let path = std::env::temp_dir().join(format!("teemo-{}-{}.h", name, std::process::id()));
+        fs::write(&path, source)?;
+        let set = load(&path, None, &target::lookup("x86_64")?);
+        _ = fs::remove_file(&path);
+        set
+    }
+
+    #[test]
+    fn typedef_of_a_builtin() {
+        let set = parse(
+            "builtin",
+            "typedef unsigned long size_t;\nstruct s { size_t len; char *data; };\n",
+        )
+        .unwrap();
+        assert_eq!(set.typedefs["size_t"].target, "unsigned long");
+        assert!(!set.integers.contains_key("size_t"));
+        assert_eq!(set.structs["s"].size, 16);
+    }
+
+    #[test]
+    fn typedef_of_a_builtin_after_its_use() {
+        let set = parse(
+            "used",
+            "struct s { size_t len; };\ntypedef unsigned long size_t;\n",
+        )
+        .unwrap();
+        assert_eq!(set.typedefs["size_t"].target, "unsigned long");
+        assert!(parse(
+            "resized",
+            "struct s { size_t len; };\ntypedef int size_t;\n"
+        )
+        .is_err());
+    }
+}
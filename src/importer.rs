@@ -0,0 +1,576 @@
+// front-ends for exports of other decompilers, mapped onto the same type model
+// the binja export deserializes into.
+//
+// `--format ghidra` reads the ghidra.json ghidra_export.py writes from the
+// data type manager and the symbol table:
+//
+// {
+//     "types": {
+//         "node": {"kind": "struct", "size": 16, "components": [
+//             {"offset": 0, "name": "next", "type": "node *"},
+//             {"offset": 8, "name": "flags", "type": "uint", "bit_offset": 0, "bit_size": 3}
+//         ]},
+//         "uint": {"kind": "builtin", "size": 4, "signed": false},
+//         "float": {"kind": "builtin", "size": 4, "class": "float"},
+//         "node *": {"kind": "pointer", "size": 8, "type": "node"},
+//         "HANDLE": {"kind": "typedef", "type": "void *"},
+//         "color": {"kind": "enum", "size": 4, "values": [["RED", 0], ["BLUE", 1]]},
+//         "char[16]": {"kind": "array", "count": 16, "type": "char"},
+//         "main": {"kind": "function", "return": "int", "parameters": [
+//             {"name": "argc", "type": "int"}
+//         ]}
+//     },
+//     "symbols": [
+//         {"name": "counter", "address": 16384, "size": 4, "type": "uint"},
+//         {"name": "main", "address": 4198448, "size": 120, "type": "main", "function": true}
+//     ]
+// }
+//
+// unions are "union" with the layout of a struct, builtins are integers unless
+// their "class" is "float", "bool" or "char".
+//
+// `--format ida` reads the ida.json ida_export.py writes from the local types,
+// the function prototypes and the names:
+//
+// {
+//     "types": {
+//         "node": {"kind": "struct", "size": 16, "members": [
+//             {"offset": 0, "name": "next", "type": "node *"},
+//             {"offset": 64, "name": "flags", "type": "unsigned int", "bitfield": 3}
+//         ]},
+//         "unsigned int": {"kind": "integer", "size": 4, "signed": false},
+//         "double": {"kind": "float", "size": 8},
+//         "node *": {"kind": "pointer", "size": 8, "target": "node"},
+//         "HANDLE": {"kind": "typedef", "target": "void *"},
+//         "color": {"kind": "enum", "size": 4, "members": [{"name": "RED", "value": 0}]},
+//         "char[16]": {"kind": "array", "count": 16, "target": "char"},
+//         "int __cdecl(int argc)": {"kind": "function", "return": "int", "args": [
+//             {"name": "argc", "type": "int"}
+//         ]}
+//     },
+//     "functions": [
+//         {"name": "main", "start": 4198448, "end": 4198568, "type": "int __cdecl(int argc)"}
+//     ],
+//     "names": [{"name": "counter", "ea": 16384, "size": 4, "type": "unsigned int"}]
+// }
+//
+// member offsets are in bits like IDA's, bitfields give their width. the
+// other kinds are "union", "bool" and "char", a "char" is signed unless it
+// says otherwise. functions with "static": true are bound locally.
+//
+// "void" is void in both.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{
+    read_export, Array, BinjaType, Convention, DynErr, Encoding, Enum, EnumField, Field, Function,
+    GlobalVariable, HashMap, Integer, Parameter, Pointer, Structure, Subprogram, TypeSet, Typedef,
+};
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct GhidraExport {
+    types: HashMap<String, GhidraType>,
+    #[serde(default)]
+    symbols: Vec<GhidraSymbol>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Component {
+    offset: u64,
+    name: String,
+    #[serde(rename = "type")]
+    typename: String,
+    #[serde(default)]
+    bit_offset: Option<u64>,
+    #[serde(default)]
+    bit_size: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NamedType {
+    name: String,
+    #[serde(rename = "type")]
+    typename: String,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Class {
+    Integer,
+    Float,
+    Bool,
+    Char,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+enum GhidraType {
+    #[serde(rename = "struct")]
+    Structure {
+        size: u64,
+        components: Vec<Component>,
+    },
+    Union {
+        size: u64,
+        components: Vec<Component>,
+    },
+    Builtin {
+        size: u64,
+        #[serde(default)]
+        signed: bool,
+        #[serde(default = "integer")]
+        class: Class,
+    },
+    Pointer {
+        size: u64,
+        #[serde(rename = "type")]
+        target: String,
+    },
+    Typedef {
+        #[serde(rename = "type")]
+        target: String,
+    },
+    Enum {
+        size: u64,
+        // in ghidra's order, negative ones for signed enums
+        values: Vec<(String, i64)>,
+    },
+    Array {
+        count: u64,
+        #[serde(rename = "type")]
+        target: String,
+    },
+    Function {
+        #[serde(rename = "return")]
+        returntype: String,
+        parameters: Vec<NamedType>,
+        #[serde(default)]
+        varargs: bool,
+        // the `__stdcall` style names
+        #[serde(default)]
+        convention: Option<Convention>,
+    },
+}
+
+fn integer() -> Class {
+    Class::Integer
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GhidraSymbol {
+    name: String,
+    address: u64,
+    size: u64,
+    #[serde(rename = "type")]
+    typename: String,
+    #[serde(default)]
+    function: bool,
+    #[serde(default)]
+    local: bool,
+}
+
+// both spell void out
+fn void(name: String) -> String {
+    match name.as_str() {
+        "void" => String::new(),
+        _ => name,
+    }
+}
+
+fn structure(size: u64, components: Vec<Component>) -> Structure {
+    // a struct that's only been declared
+    let declaration = size == 0 && components.is_empty();
+    Structure {
+        size,
+        anon: false,
+        fields: components
+            .into_iter()
+            .map(|component| Field {
+                offset: component.offset,
+                name: component.name,
+                typename: void(component.typename),
+                endianity: None,
+                bit_offset: component.bit_offset,
+                bit_size: component.bit_size,
+                access: None,
+            })
+            .collect(),
+        variant: None,
+        declaration,
+        decl: None,
+        printer: None,
+    }
+}
+
+impl GhidraType {
+    fn convert(self) -> BinjaType {
+        match self {
+            GhidraType::Structure { size, components } => {
+                BinjaType::Structure(structure(size, components))
+            }
+            GhidraType::Union { size, components } => BinjaType::Union(structure(size, components)),
+            GhidraType::Builtin {
+                size,
+                signed,
+                class,
+            } => BinjaType::Integer(Integer {
+                size,
+                signed,
+                endianity: None,
+                encoding: match class {
+                    Class::Integer => None,
+                    Class::Float => Some(Encoding::Float),
+                    Class::Bool => Some(Encoding::Bool),
+                    Class::Char => Some(Encoding::Char),
+                },
+                bits: None,
+            }),
+            GhidraType::Pointer { size, target } => BinjaType::Pointer(Pointer {
+                size,
+                target: void(target),
+                reference: None,
+                member_of: None,
+            }),
+            GhidraType::Typedef { target } => BinjaType::Typedef(Typedef {
+                target: void(target),
+                decl: None,
+            }),
+            GhidraType::Enum { size, values } => enumeration(
+                size,
+                values
+                    .into_iter()
+                    .map(|(name, value)| (name, value as i128))
+                    .collect(),
+            ),
+            GhidraType::Array { count, target } => BinjaType::Array(Array {
+                count,
+                target: void(target),
+                dimensions: Vec::new(),
+                string: false,
+                dynamic: None,
+            }),
+            GhidraType::Function {
+                returntype,
+                parameters,
+                varargs,
+                convention,
+            } => function(
+                returntype,
+                parameters
+                    .into_iter()
+                    .map(|parameter| (parameter.name, parameter.typename)),
+                varargs,
+                convention,
+            ),
+        }
+    }
+}
+
+fn enumeration(size: u64, values: Vec<(String, i128)>) -> BinjaType {
+    BinjaType::Enum(Enum {
+        size,
+        signed: values.iter().any(|(_, value)| *value < 0),
+        fields: values
+            .into_iter()
+            .map(|(name, value)| EnumField { name, value })
+            .collect(),
+        decl: None,
+    })
+}
+
+fn function(
+    returntype: String,
+    parameters: impl Iterator<Item = (String, String)>,
+    variadic: bool,
+    convention: Option<Convention>,
+) -> BinjaType {
+    BinjaType::Function(Function {
+        parameters: parameters
+            .map(|(name, typename)| Parameter {
+                name,
+                typename: void(typename),
+            })
+            .collect(),
+        returntype: void(returntype),
+        variadic,
+        convention,
+    })
+}
+
+fn subprogram(name: String, size: u64, prototype: String, local: bool) -> Subprogram {
+    Subprogram {
+        name,
+        size,
+        typename: prototype,
+        locals: Vec::new(),
+        local,
+        decl: None,
+        space: None,
+    }
+}
+
+fn variable(name: String, size: u64, typename: String) -> GlobalVariable {
+    GlobalVariable {
+        name,
+        size,
+        typename: void(typename),
+        decl: None,
+        space: None,
+    }
+}
+
+pub fn ghidra(path: &Path, required: bool) -> Result<TypeSet, DynErr> {
+    let export: GhidraExport =
+        read_export(path, required).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let types = export
+        .types
+        .into_iter()
+        .filter(|(name, _)| name != "void")
+        .map(|(name, ghidra_type)| (name, ghidra_type.convert()))
+        .collect();
+    let mut type_set = TypeSet::join(types, HashMap::new());
+    for symbol in export.symbols {
+        if symbol.function {
+            _ = type_set.subprograms.insert(
+                symbol.address,
+                subprogram(symbol.name, symbol.size, symbol.typename, symbol.local),
+            );
+        } else {
+            _ = type_set.variables.insert(
+                symbol.address,
+                variable(symbol.name, symbol.size, symbol.typename),
+            );
+        }
+    }
+    Ok(type_set)
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct IdaExport {
+    types: HashMap<String, IdaType>,
+    #[serde(default)]
+    functions: Vec<IdaFunction>,
+    #[serde(default)]
+    names: Vec<IdaName>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Member {
+    // in bits
+    offset: u64,
+    name: String,
+    #[serde(rename = "type")]
+    typename: String,
+    // the width of a bitfield
+    #[serde(default)]
+    bitfield: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IdaEnumMember {
+    name: String,
+    // ida's are unsigned 64 bit
+    value: i128,
+}
+
+fn signed() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+enum IdaType {
+    #[serde(rename = "struct")]
+    Structure {
+        size: u64,
+        members: Vec<Member>,
+    },
+    Union {
+        size: u64,
+        members: Vec<Member>,
+    },
+    Integer {
+        size: u64,
+        signed: bool,
+    },
+    Float {
+        size: u64,
+    },
+    Bool {
+        size: u64,
+    },
+    Char {
+        size: u64,
+        #[serde(default = "signed")]
+        signed: bool,
+    },
+    Pointer {
+        size: u64,
+        target: String,
+    },
+    Typedef {
+        target: String,
+    },
+    Enum {
+        size: u64,
+        members: Vec<IdaEnumMember>,
+    },
+    Array {
+        count: u64,
+        target: String,
+    },
+    Function {
+        #[serde(rename = "return")]
+        returntype: String,
+        args: Vec<NamedType>,
+        #[serde(default)]
+        varargs: bool,
+        #[serde(default)]
+        convention: Option<Convention>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IdaFunction {
+    name: String,
+    start: u64,
+    end: u64,
+    #[serde(rename = "type")]
+    typename: String,
+    // static functions are bound locally
+    #[serde(default, rename = "static")]
+    local: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IdaName {
+    name: String,
+    ea: u64,
+    size: u64,
+    #[serde(rename = "type")]
+    typename: String,
+}
+
+fn members(size: u64, members: Vec<Member>) -> Structure {
+    let declaration = size == 0 && members.is_empty();
+    Structure {
+        size,
+        anon: false,
+        fields: members
+            .into_iter()
+            .map(|member| Field {
+                offset: member.offset / 8,
+                name: member.name,
+                typename: void(member.typename),
+                endianity: None,
+                bit_offset: member.bitfield.map(|_| member.offset % 8),
+                bit_size: member.bitfield,
+                access: None,
+            })
+            .collect(),
+        variant: None,
+        declaration,
+        decl: None,
+        printer: None,
+    }
+}
+
+fn integer_of(size: u64, signed: bool, encoding: Option<Encoding>) -> BinjaType {
+    BinjaType::Integer(Integer {
+        size,
+        signed,
+        endianity: None,
+        encoding,
+        bits: None,
+    })
+}
+
+impl IdaType {
+    fn convert(self) -> BinjaType {
+        match self {
+            IdaType::Structure { size, members: m } => BinjaType::Structure(members(size, m)),
+            IdaType::Union { size, members: m } => BinjaType::Union(members(size, m)),
+            IdaType::Integer { size, signed } => integer_of(size, signed, None),
+            IdaType::Float { size } => integer_of(size, false, Some(Encoding::Float)),
+            IdaType::Bool { size } => integer_of(size, false, Some(Encoding::Bool)),
+            IdaType::Char { size, signed } => integer_of(size, signed, Some(Encoding::Char)),
+            IdaType::Pointer { size, target } => BinjaType::Pointer(Pointer {
+                size,
+                target: void(target),
+                reference: None,
+                member_of: None,
+            }),
+            IdaType::Typedef { target } => BinjaType::Typedef(Typedef {
+                target: void(target),
+                decl: None,
+            }),
+            IdaType::Enum { size, members } => enumeration(
+                size,
+                members
+                    .into_iter()
+                    .map(|member| (member.name, member.value))
+                    .collect(),
+            ),
+            IdaType::Array { count, target } => BinjaType::Array(Array {
+                count,
+                target: void(target),
+                dimensions: Vec::new(),
+                string: false,
+                dynamic: None,
+            }),
+            IdaType::Function {
+                returntype,
+                args,
+                varargs,
+                convention,
+            } => function(
+                returntype,
+                args.into_iter().map(|arg| (arg.name, arg.typename)),
+                varargs,
+                convention,
+            ),
+        }
+    }
+}
+
+pub fn ida(path: &Path, required: bool) -> Result<TypeSet, DynErr> {
+    let export: IdaExport =
+        read_export(path, required).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let types = export
+        .types
+        .into_iter()
+        .filter(|(name, _)| name != "void")
+        .map(|(name, ida_type)| (name, ida_type.convert()))
+        .collect();
+    let mut type_set = TypeSet::join(types, HashMap::new());
+    for function in export.functions {
+        _ = type_set.subprograms.insert(
+            function.start,
+            subprogram(
+                function.name,
+                function.end.saturating_sub(function.start),
+                function.typename,
+                function.local,
+            ),
+        );
+    }
+    for name in export.names {
+        _ = type_set
+            .variables
+            .insert(name.ea, variable(name.name, name.size, name.typename));
+    }
+    Ok(type_set)
+}
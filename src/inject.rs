@@ -0,0 +1,396 @@
+// `teemo --inject ./challenge -o challenge.dbg`: writes the challenge itself
+// with the generated sections added instead of a standalone debug file, so
+// there's nothing to objcopy or add-symbol-file afterwards.
+//
+// the original contents stay where they are. the debug sections and a grown
+// copy of .shstrtab go after them and the section headers move to the end.
+// a binary that still has a .symtab keeps it instead of getting ours. with
+// --dynsym, see dynsym.rs, some of the functions go into its .dynsym as well.
+// the program headers stay the binary's, and with --inject-load the debug
+// sections get a read-only PT_LOAD of their own, for symbolizers reading
+// them out of the running process.
+//
+// there's no room for another program header, so a new segment takes the
+// last PT_NOTE's: the notes stay in the file and their sections, the loader
+// just isn't pointed at them anymore.
+//
+// `--debuglink ./challenge -o challenge.debug` goes the distro way instead:
+// the output stays a separate debug file and the binary gets a
+// .gnu_debuglink naming it, so gdb loads it from next to the binary (or
+// from a .debug directory there) on its own.
+
+use std::fs;
+use std::path::Path;
+
+use goblin::container::{Container, Ctx};
+use goblin::elf::program_header::{ProgramHeader, PF_R, PT_LOAD, PT_NOTE};
+use goblin::elf::section_header::{
+    SectionHeader, SHT_NOBITS, SHT_NOTE, SHT_PROGBITS, SHT_STRTAB, SHT_SYMTAB,
+};
+use goblin::elf::sym::STT_FUNC;
+use goblin::elf::Elf;
+use log::info;
+use scroll::{Endian, Pwrite};
+
+use crate::dynsym::{self, Export};
+use crate::{build_id, DynErr, Err, HashMap};
+
+// a section to add, `link` is the index of another addition
+pub struct Addition {
+    pub name: String,
+    pub header: SectionHeader,
+    pub data: Vec<u8>,
+    pub link: Option<usize>,
+}
+
+fn pad(data: &mut Vec<u8>, alignment: u64) {
+    let alignment = alignment.max(1) as usize;
+    data.resize(data.len().div_ceil(alignment) * alignment, 0);
+}
+
+// how `elf`'s structures are laid out
+pub fn context(elf: &Elf) -> Ctx {
+    Ctx::new(
+        if elf.is_64 {
+            Container::Big
+        } else {
+            Container::Little
+        },
+        if elf.little_endian {
+            Endian::Little
+        } else {
+            Endian::Big
+        },
+    )
+}
+
+// where a segment starting `offset` into the file can be mapped: past all
+// the binary's others, with the same offset into a page as in the file
+pub fn free_address(elf: &Elf, offset: usize) -> u64 {
+    let end = (elf.program_headers.iter())
+        .filter(|header| header.p_type == PT_LOAD)
+        .map(|header| header.p_vaddr + header.p_memsz)
+        .max()
+        .unwrap_or(0);
+    let page = page(elf);
+    end.next_multiple_of(page) + offset as u64 % page
+}
+
+fn page(elf: &Elf) -> u64 {
+    (elf.program_headers.iter())
+        .filter(|header| header.p_type == PT_LOAD)
+        .map(|header| header.p_align)
+        .max()
+        .unwrap_or(0x1000)
+        .max(0x1000)
+}
+
+// maps `size` bytes `offset` into `data` read-only at their free_address, in
+// the last PT_NOTE's slot and kept after the other PT_LOADs
+pub fn map(data: &mut [u8], elf: &Elf, offset: usize, size: u64, what: &str) -> Err {
+    let mut headers = elf.program_headers.clone();
+    let note = (headers.iter())
+        .rposition(|header| header.p_type == PT_NOTE)
+        .ok_or_else(|| format!("{} needs a PT_NOTE to turn into the new segment", what))?;
+    _ = headers.remove(note);
+    let after = (headers.iter())
+        .rposition(|header| header.p_type == PT_LOAD)
+        .map_or(0, |i| i + 1);
+    let address = free_address(elf, offset);
+    headers.insert(
+        after,
+        ProgramHeader {
+            p_type: PT_LOAD,
+            p_flags: PF_R,
+            p_offset: offset as u64,
+            p_vaddr: address,
+            p_paddr: address,
+            p_filesz: size,
+            p_memsz: size,
+            p_align: page(elf),
+        },
+    );
+    let ctx = context(elf);
+    let phentsize = ProgramHeader::size(ctx);
+    for (i, header) in headers.into_iter().enumerate() {
+        data.pwrite_with(header, elf.header.e_phoff as usize + i * phentsize, ctx)?;
+    }
+    Ok(())
+}
+
+// `data` with the additions appended as non-allocated sections
+pub fn append(data: &[u8], additions: Vec<Addition>) -> Result<Vec<u8>, DynErr> {
+    let elf = Elf::parse(data)?;
+    let ctx = context(&elf);
+
+    let mut out = data.to_vec();
+    let mut headers = elf.section_headers.clone();
+    // a fully stripped binary has no section headers at all
+    if headers.is_empty() {
+        headers.push(SectionHeader::default());
+    }
+    let mut names = match elf.section_headers.get(elf.header.e_shstrndx as usize) {
+        Some(header) if elf.header.e_shstrndx != 0 => header
+            .file_range()
+            .and_then(|range| data.get(range))
+            .ok_or("the section name table lies outside the file")?
+            .to_vec(),
+        _ => vec![0],
+    };
+    let shstrndx = match elf.header.e_shstrndx as usize {
+        0 => {
+            headers.push(SectionHeader {
+                sh_name: names.len(),
+                sh_type: SHT_STRTAB,
+                sh_addralign: 1,
+                ..Default::default()
+            });
+            names.extend_from_slice(b".shstrtab\0");
+            headers.len() - 1
+        }
+        index => index,
+    };
+
+    let first = headers.len();
+    for addition in additions {
+        let mut header = addition.header;
+        header.sh_name = names.len();
+        names.extend_from_slice(addition.name.as_bytes());
+        names.push(0);
+        pad(&mut out, header.sh_addralign);
+        header.sh_offset = out.len() as u64;
+        header.sh_size = addition.data.len() as u64;
+        if let Some(link) = addition.link {
+            header.sh_link = (first + link) as u32;
+        }
+        out.extend_from_slice(&addition.data);
+        headers.push(header);
+    }
+
+    headers[shstrndx].sh_offset = out.len() as u64;
+    headers[shstrndx].sh_size = names.len() as u64;
+    out.extend_from_slice(&names);
+
+    pad(&mut out, 8);
+    let shoff = out.len();
+    let shentsize = SectionHeader::size(ctx);
+    out.resize(shoff + headers.len() * shentsize, 0);
+    for (i, header) in headers.iter().enumerate() {
+        out.pwrite_with(header.clone(), shoff + i * shentsize, ctx)?;
+    }
+
+    let mut header = elf.header;
+    header.e_shoff = shoff as u64;
+    header.e_shnum = headers.len() as u16;
+    header.e_shentsize = shentsize as u16;
+    header.e_shstrndx = shstrndx as u16;
+    out.pwrite_with(header, 0, ctx.le)?;
+    Ok(out)
+}
+
+// replaces the debug file at `output` with `binary` carrying its sections,
+// and the functions named in `exports` in its .dynsym. the sections keep the
+// alignment the debug file gave them, or `align` if that's more, and with
+// `load` they're mapped as well
+pub fn inject(binary: &Path, output: &Path, exports: &[String], align: u64, load: bool) -> Err {
+    let mut data = fs::read(binary).map_err(|e| format!("{}: {}", binary.display(), e))?;
+    let debug_data = fs::read(output)?;
+    let debug = Elf::parse(&debug_data)?;
+    if !exports.is_empty() {
+        let mut functions = Vec::new();
+        for name in exports {
+            let symbol = (debug.syms.iter())
+                .find(|symbol| {
+                    symbol.st_type() == STT_FUNC
+                        && debug.strtab.get_at(symbol.st_name) == Some(name)
+                })
+                .ok_or_else(|| format!("--dynsym: no function `{}` in the export", name))?;
+            functions.push(Export {
+                name: name.clone(),
+                address: symbol.st_value,
+                size: symbol.st_size,
+            });
+        }
+        data = dynsym::export(&data, functions)?;
+    }
+    let elf = Elf::parse(&data)?;
+
+    if elf.is_64 != debug.is_64 || elf.little_endian != debug.little_endian {
+        return Err(format!(
+            "{} is {}-bit {} endian, pass the matching --arch",
+            binary.display(),
+            if elf.is_64 { 64 } else { 32 },
+            if elf.little_endian { "little" } else { "big" }
+        )
+        .into());
+    }
+    let name_of = |elf: &Elf, header: &SectionHeader| {
+        elf.shdr_strtab
+            .get_at(header.sh_name)
+            .unwrap_or("")
+            .to_string()
+    };
+    if elf
+        .section_headers
+        .iter()
+        .any(|header| name_of(&elf, header) == ".debug_info")
+    {
+        return Err(format!("{} already has debug info", binary.display()).into());
+    }
+    let keep_symbols = !elf
+        .section_headers
+        .iter()
+        .any(|header| header.sh_type == SHT_SYMTAB);
+    if !keep_symbols {
+        info!("{} has a .symtab, keeping it", binary.display());
+    }
+
+    // the symbol names are the unnamed table .symtab links to
+    let strtab = debug
+        .section_headers
+        .iter()
+        .find(|header| header.sh_type == SHT_SYMTAB)
+        .map(|header| header.sh_link as usize);
+    let mut positions = HashMap::new();
+    let mut additions = Vec::new();
+    for (index, header) in debug.section_headers.iter().enumerate() {
+        let mut name = name_of(&debug, header);
+        let symbols = header.sh_type == SHT_SYMTAB || Some(index) == strtab;
+        // empty ones would only shadow the binary's own, like .eh_frame, and
+        // the binary's headers mirrored into the debug file are its own
+        if index == 0
+            || index == debug.header.e_shstrndx as usize
+            || header.sh_size == 0
+            || header.sh_type == SHT_NOBITS
+            || (symbols && !keep_symbols)
+        {
+            continue;
+        }
+        if Some(index) == strtab {
+            name = String::from(".strtab");
+        }
+        let data = header
+            .file_range()
+            .and_then(|range| debug_data.get(range))
+            .unwrap_or(&[])
+            .to_vec();
+        _ = positions.insert(index, additions.len());
+        let mut header = header.clone();
+        header.sh_addralign = header.sh_addralign.max(align);
+        additions.push(Addition {
+            name,
+            header,
+            data,
+            link: None,
+        });
+    }
+    for addition in additions.iter_mut() {
+        if addition.header.sh_type == SHT_SYMTAB {
+            addition.link = strtab.and_then(|index| positions.get(&index).copied());
+        }
+    }
+
+    let mut out = append(&data, additions)?;
+    if load {
+        // everything added, up to the grown .shstrtab after it
+        let injected = Elf::parse(&out)?;
+        let shstrtab = injected.header.e_shstrndx as usize;
+        let end = injected.section_headers[shstrtab].sh_offset;
+        let start = (injected.section_headers.iter().enumerate())
+            .filter(|(i, header)| *i != shstrtab && header.sh_offset >= data.len() as u64)
+            .map(|(_, header)| header.sh_offset)
+            .min()
+            .unwrap_or(end);
+        let mut mapped = out.clone();
+        map(
+            &mut mapped,
+            &injected,
+            start as usize,
+            end - start,
+            "--inject-load",
+        )?;
+        out = mapped;
+    }
+    fs::write(output, out)?;
+    // still runnable
+    fs::set_permissions(output, fs::metadata(binary)?.permissions())?;
+    info!("injected into {}", output.display());
+    Ok(())
+}
+
+// the CRC-32 .gnu_debuglink carries, zlib's
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// points `binary` at the debug file in `output`, in place like objcopy's
+// --add-gnu-debuglink
+pub fn debuglink(binary: &Path, output: &Path) -> Err {
+    let data = fs::read(binary).map_err(|e| format!("{}: {}", binary.display(), e))?;
+    let elf = Elf::parse(&data)?;
+    if elf
+        .section_headers
+        .iter()
+        .any(|header| elf.shdr_strtab.get_at(header.sh_name) == Some(".gnu_debuglink"))
+    {
+        return Err(format!("{} already has a .gnu_debuglink", binary.display()).into());
+    }
+
+    // only the file name, gdb searches the directories itself
+    let name = output
+        .file_name()
+        .ok_or_else(|| format!("{} is no file name", output.display()))?;
+    let mut link = name.as_encoded_bytes().to_vec();
+    link.push(0);
+    link.resize(link.len().div_ceil(4) * 4, 0);
+    let crc = crc32(&fs::read(output)?);
+    link.extend_from_slice(&if elf.little_endian {
+        crc.to_le_bytes()
+    } else {
+        crc.to_be_bytes()
+    });
+
+    let mut additions = vec![Addition {
+        name: String::from(".gnu_debuglink"),
+        header: SectionHeader {
+            sh_type: SHT_PROGBITS,
+            sh_addralign: 4,
+            ..Default::default()
+        },
+        data: link,
+        link: None,
+    }];
+    // the build-id --build-id made up for a binary without one
+    if let (None, Some(id)) = (build_id::read(binary)?, build_id::read(output)?) {
+        additions.push(Addition {
+            name: String::from(".note.gnu.build-id"),
+            header: SectionHeader {
+                sh_type: SHT_NOTE,
+                sh_addralign: 4,
+                ..Default::default()
+            },
+            data: build_id::note(&id, elf.little_endian),
+            link: None,
+        });
+    }
+    let annotated = append(&data, additions)?;
+    fs::write(binary, annotated)?;
+    info!(
+        "{} links to {} (crc {:#010x})",
+        binary.display(),
+        name.to_string_lossy(),
+        crc
+    );
+    Ok(())
+}
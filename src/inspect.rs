@@ -0,0 +1,441 @@
+// `teemo inspect --core core --var request_queue`: prints a variable out of a
+// core file (or a live process with `--core /proc/<pid>/mem`) using the
+// exported layout, a typed memory viewer without starting gdb.
+//
+// exported addresses are the unrebased ones binja shows. they are moved to
+// where the executable is mapped in the dump unless they already lie there
+// (non-PIE binaries), `--base 0x5555...` overrides the detected load address.
+
+use std::fs::{self, File};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+use goblin::elf::note::NT_FILE;
+use goblin::elf::program_header::PT_LOAD;
+use goblin::elf::Elf;
+
+use crate::layout::size_of;
+use crate::{
+    collect, BinjaType, Class, DynErr, Encoding, Endianity, Enum, Err, Field, HashMap, Integer,
+    Options,
+};
+
+// elements of an array beyond this are elided
+const ELEMENTS: u64 = 64;
+
+enum Memory {
+    // (vaddr, file offset, file size) of every PT_LOAD
+    Core(Vec<u8>, Vec<(u64, u64, u64)>),
+    Process(File),
+}
+
+impl Memory {
+    fn read(&self, address: u64, size: u64) -> Result<Vec<u8>, DynErr> {
+        let unreadable = || format!("{:#x} ({:#x} bytes) isn't in the dump", address, size);
+        match self {
+            Memory::Core(data, segments) => {
+                let &(vaddr, offset, _) = segments
+                    .iter()
+                    .find(|(vaddr, _, filesz)| {
+                        *vaddr <= address && address + size <= vaddr + filesz
+                    })
+                    .ok_or_else(unreadable)?;
+                let start = (offset + address - vaddr) as usize;
+                Ok(data
+                    .get(start..start + size as usize)
+                    .ok_or_else(unreadable)?
+                    .to_vec())
+            }
+            Memory::Process(file) => {
+                let mut buffer = vec![0; size as usize];
+                file.read_exact_at(&mut buffer, address)
+                    .map_err(|_| unreadable())?;
+                Ok(buffer)
+            }
+        }
+    }
+}
+
+// (start, file offset, path) of every mapped file
+type Mappings = Vec<(u64, u64, String)>;
+
+// from the core's NT_FILE note
+fn file_mappings(elf: &Elf, data: &[u8]) -> Mappings {
+    let mut mappings = Vec::new();
+    let Some(notes) = elf.iter_note_headers(data) else {
+        return mappings;
+    };
+    let width = if elf.is_64 { 8 } else { 4 };
+    let word = |desc: &[u8], index: usize| -> Option<u64> {
+        let bytes = desc.get(index * width..(index + 1) * width)?;
+        Some(match width {
+            8 => u64::from_le_bytes(bytes.try_into().ok()?),
+            _ => u32::from_le_bytes(bytes.try_into().ok()?) as u64,
+        })
+    };
+
+    for note in notes.flatten() {
+        if note.n_type != NT_FILE {
+            continue;
+        }
+        let Some(count) = word(note.desc, 0) else {
+            continue;
+        };
+        let count = count as usize;
+        // count, page size, then start, end and page offset per mapping
+        let names = note.desc.get((2 + count * 3) * width..).unwrap_or(&[]);
+        for (i, name) in names.split(|b| *b == 0).take(count).enumerate() {
+            let (Some(start), Some(page)) =
+                (word(note.desc, 2 + i * 3), word(note.desc, 4 + i * 3))
+            else {
+                break;
+            };
+            let page_size = word(note.desc, 1).unwrap_or(0x1000);
+            mappings.push((
+                start,
+                page * page_size,
+                String::from_utf8_lossy(name).into_owned(),
+            ));
+        }
+    }
+    mappings
+}
+
+// from /proc/<pid>/maps
+fn process_mappings(maps: &str) -> Mappings {
+    maps.lines()
+        .filter_map(|line| {
+            let mut columns = line.split_whitespace();
+            let (start, _) = columns.next()?.split_once('-')?;
+            let offset = columns.nth(1)?;
+            let path = columns.nth(2).unwrap_or("");
+            Some((
+                u64::from_str_radix(start, 16).ok()?,
+                u64::from_str_radix(offset, 16).ok()?,
+                path.to_string(),
+            ))
+        })
+        .collect()
+}
+
+// how far exported addresses move: onto where the start of the executable
+// got mapped, unless `address` already lies at or above it (non-PIE)
+fn bias(mappings: &Mappings, exe: Option<&Path>, address: u64) -> Option<u64> {
+    let start = mappings
+        .iter()
+        .find(|(_, offset, path)| {
+            *offset == 0 && exe.is_some_and(|exe| Path::new(path).file_name() == exe.file_name())
+        })
+        .map(|(start, _, _)| *start)?;
+    Some(if address < start { start } else { 0 })
+}
+
+// the same for a running process
+pub fn process_bias(pid: u32, binary: Option<&Path>, address: u64) -> Result<u64, DynErr> {
+    let dir = PathBuf::from(format!("/proc/{}", pid));
+    let maps = fs::read_to_string(dir.join("maps"))
+        .map_err(|e| format!("{}: {}", dir.join("maps").display(), e))?;
+    let exe = fs::read_link(dir.join("exe")).ok();
+    bias(&process_mappings(&maps), binary.or(exe.as_deref()), address)
+        .ok_or_else(|| format!("can't tell where the executable of {} is mapped", pid).into())
+}
+
+// the memory, its mappings and the executable's path if the dump knows it
+fn open(core: &Path) -> Result<(Memory, Mappings, Option<PathBuf>), DynErr> {
+    let error = |e: std::io::Error| format!("{}: {}", core.display(), e);
+
+    // /proc/<pid>/mem reads the live process
+    if core.starts_with("/proc") && core.file_name().is_some_and(|name| name == "mem") {
+        let dir = core.parent().unwrap_or(Path::new("/proc"));
+        let maps = fs::read_to_string(dir.join("maps")).map_err(error)?;
+        let exe = fs::read_link(dir.join("exe")).ok();
+        let file = File::open(core).map_err(error)?;
+        return Ok((Memory::Process(file), process_mappings(&maps), exe));
+    }
+
+    let data = fs::read(core).map_err(error)?;
+    let elf = Elf::parse(&data)?;
+    let segments = elf
+        .program_headers
+        .iter()
+        .filter(|header| header.p_type == PT_LOAD)
+        .map(|header| (header.p_vaddr, header.p_offset, header.p_filesz))
+        .collect();
+    let mappings = file_mappings(&elf, &data);
+    // the executable is the first file mapped
+    let exe = mappings.first().map(|(_, _, path)| PathBuf::from(path));
+    Ok((Memory::Core(data, segments), mappings, exe))
+}
+
+struct Printer<'a> {
+    types: &'a HashMap<String, BinjaType>,
+    memory: &'a Memory,
+    // for pointers exported with size 0
+    pointer_size: u64,
+    // for values without their own byte order
+    endianity: Endianity,
+}
+
+// up to 16 bytes, for __int128
+fn unsigned(bytes: &[u8], endianity: Option<Endianity>) -> u128 {
+    let mut buffer = [0u8; 16];
+    let size = bytes.len().min(16);
+    match endianity {
+        Some(Endianity::Big) => {
+            for (i, byte) in bytes[..size].iter().rev().enumerate() {
+                buffer[i] = *byte;
+            }
+        }
+        _ => buffer[..size].copy_from_slice(&bytes[..size]),
+    }
+    u128::from_le_bytes(buffer)
+}
+
+fn integer(bytes: &[u8], signed: bool, endianity: Option<Endianity>) -> String {
+    let value = unsigned(bytes, endianity);
+    let size = bytes.len().min(16);
+    if signed && size > 0 && size < 16 {
+        // sign extend from the top bit of the integer's own width
+        let shift = 128 - size as u32 * 8;
+        (((value << shift) as i128) >> shift).to_string()
+    } else if signed {
+        (value as i128).to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+impl Printer<'_> {
+    fn resolve(&self, name: &str) -> Option<&BinjaType> {
+        let mut binja_type = self.types.get(name)?;
+        for _ in 0..64 {
+            match binja_type {
+                BinjaType::Typedef(typedef) => binja_type = self.types.get(&typedef.target)?,
+                BinjaType::Qualified(qualified) => {
+                    binja_type = self.types.get(&qualified.target)?
+                }
+                _ => return Some(binja_type),
+            }
+        }
+        None
+    }
+
+    // counted from the least significant bit in little endian, the most
+    // significant one in big endian, like DW_AT_data_bit_offset
+    fn bitfield(&self, field: &Field, address: u64, bits: u64) -> Result<String, DynErr> {
+        let start = field.bit_offset.unwrap_or(0);
+        let bytes = self
+            .memory
+            .read(address + field.offset, (start + bits).div_ceil(8).min(8))?;
+        let endianity = field.endianity.unwrap_or(self.endianity);
+        let raw = unsigned(&bytes, Some(endianity));
+        let shift = match endianity {
+            Endianity::Big => (bytes.len() as u64 * 8).saturating_sub(start + bits),
+            Endianity::Little => start,
+        };
+        let mask = if bits >= 64 { !0 } else { (1 << bits) - 1 };
+        let value = (raw >> shift) & mask;
+        let signed = match self.resolve(&field.typename) {
+            Some(BinjaType::Integer(integer)) => integer.signed,
+            Some(BinjaType::Enum(e)) => {
+                if let Some(field) = (e.fields.iter())
+                    .find(|field| e.normalize(field.value) == e.normalize(value as i128))
+                {
+                    return Ok(field.name.clone());
+                }
+                e.signed
+            }
+            _ => false,
+        };
+        Ok(
+            if signed && bits > 0 && bits < 64 && value >> (bits - 1) != 0 {
+                ((value | !mask) as i64).to_string()
+            } else {
+                value.to_string()
+            },
+        )
+    }
+
+    fn value(
+        &self,
+        typename: &str,
+        address: u64,
+        endianity: Option<Endianity>,
+        indent: usize,
+    ) -> Result<String, DynErr> {
+        let pad = "  ".repeat(indent + 1);
+        let Some(binja_type) = self.resolve(typename) else {
+            return Ok(format!("<unknown type `{}`>", typename));
+        };
+        let size = size_of(self.types, typename).unwrap_or(0);
+
+        Ok(match binja_type {
+            BinjaType::Structure(s)
+            | BinjaType::Union(s)
+            | BinjaType::Class(Class { layout: s, .. }) => {
+                let mut text = String::from("{\n");
+                for field in s.fields.iter() {
+                    let value = match field.bit_size {
+                        Some(bits) => self.bitfield(field, address, bits)?,
+                        None => self.value(
+                            &field.typename,
+                            address + field.offset,
+                            field.endianity,
+                            indent + 1,
+                        )?,
+                    };
+                    text.push_str(&format!("{}.{} = {}\n", pad, field.name, value));
+                }
+                text.push_str(&"  ".repeat(indent));
+                text.push('}');
+                text
+            }
+            BinjaType::Array(array) => {
+                let element = size_of(self.types, &array.target).unwrap_or(0);
+                let count = array.elements().min(ELEMENTS);
+                let elided = if array.elements() > count {
+                    ", ..."
+                } else {
+                    ""
+                };
+                // char arrays read as strings up to the terminator
+                if element == 1
+                    && array.dimensions.is_empty()
+                    && matches!(self.resolve(&array.target), Some(BinjaType::Integer(_)))
+                {
+                    let bytes = self.memory.read(address, array.count)?;
+                    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+                    return Ok(format!("{:?}", String::from_utf8_lossy(&bytes[..end])));
+                }
+                // the elements of more dimensions are listed with their subscripts
+                let listed = !array.dimensions.is_empty()
+                    || matches!(
+                        self.resolve(&array.target),
+                        Some(
+                            BinjaType::Structure(_)
+                                | BinjaType::Union(_)
+                                | BinjaType::Class(Class { layout: _, .. })
+                                | BinjaType::Array(_)
+                        )
+                    );
+                match listed {
+                    true => {
+                        let mut text = String::from("{\n");
+                        for i in 0..count {
+                            let value =
+                                self.value(&array.target, address + i * element, None, indent + 1)?;
+                            text.push_str(&format!("{}{} = {}\n", pad, array.subscripts(i), value));
+                        }
+                        if !elided.is_empty() {
+                            text.push_str(&format!("{}...\n", pad));
+                        }
+                        text.push_str(&"  ".repeat(indent));
+                        text.push('}');
+                        text
+                    }
+                    false => {
+                        let mut values = Vec::new();
+                        for i in 0..count {
+                            values.push(self.value(
+                                &array.target,
+                                address + i * element,
+                                None,
+                                indent,
+                            )?);
+                        }
+                        format!("{{{}{}}}", values.join(", "), elided)
+                    }
+                }
+            }
+            // lanes are scalars, listed like gdb does
+            BinjaType::Vector(vector) => {
+                let lane = size_of(self.types, &vector.target).unwrap_or(0);
+                let mut values = Vec::new();
+                for i in 0..vector.count.min(ELEMENTS) {
+                    values.push(self.value(&vector.target, address + i * lane, None, indent)?);
+                }
+                format!("{{{}}}", values.join(", "))
+            }
+            BinjaType::Integer(Integer {
+                signed,
+                endianity: integer_endianity,
+                encoding,
+                ..
+            }) => {
+                let bytes = self.memory.read(address, size)?;
+                let endianity = endianity.or(*integer_endianity).or(Some(self.endianity));
+                match encoding {
+                    Some(Encoding::Float) if size == 4 => {
+                        f32::from_bits(unsigned(&bytes, endianity) as u32).to_string()
+                    }
+                    Some(Encoding::Float) if size == 8 => {
+                        f64::from_bits(unsigned(&bytes, endianity) as u64).to_string()
+                    }
+                    Some(Encoding::Bool) => (unsigned(&bytes, endianity) != 0).to_string(),
+                    Some(Encoding::Char) if size == 1 => {
+                        format!(
+                            "{} '{}'",
+                            integer(&bytes, *signed, endianity),
+                            bytes[0].escape_ascii()
+                        )
+                    }
+                    // long double and wide chars print as their bits
+                    _ => integer(&bytes, *signed, endianity),
+                }
+            }
+            BinjaType::Enum(e @ Enum { signed, fields, .. }) => {
+                let bytes = self.memory.read(address, size)?;
+                let endianity = endianity.or(Some(self.endianity));
+                let raw = unsigned(&bytes, endianity);
+                let raw = e.normalize(raw as i128);
+                match fields.iter().find(|field| e.normalize(field.value) == raw) {
+                    Some(field) => field.name.clone(),
+                    None => integer(&bytes, *signed, endianity),
+                }
+            }
+            BinjaType::Pointer(pointer) => {
+                let size = match pointer.size {
+                    0 => self.pointer_size,
+                    size => size,
+                };
+                let bytes = self.memory.read(address, size)?;
+                format!(
+                    "{:#x}",
+                    unsigned(&bytes, endianity.or(Some(self.endianity)))
+                )
+            }
+            BinjaType::Function(_) => format!("<function at {:#x}>", address),
+            BinjaType::Typedef(_) | BinjaType::Qualified(_) => unreachable!(),
+        })
+    }
+}
+
+pub fn run(options: &Options, core: &Path, name: &str) -> Err {
+    let (types, variables) = collect(options)?.split();
+    let (address, variable) = variables
+        .iter()
+        .find(|(_, variable)| variable.name == name)
+        .ok_or_else(|| format!("no variable named `{}`", name))?;
+
+    let (memory, mappings, exe) = open(core)?;
+    let exe = options.binary.as_deref().or(exe.as_deref());
+    let base = match options.base {
+        Some(base) => base,
+        None => bias(&mappings, exe, *address)
+            .ok_or("can't tell where the executable is mapped, pass --base")?,
+    };
+
+    let printer = Printer {
+        types: &types,
+        memory: &memory,
+        pointer_size: options.target.pointer_size,
+        endianity: options.target.endianity,
+    };
+    let rebased = base + address;
+    let value = printer.value(&variable.typename, rebased, None, 0)?;
+    println!(
+        "{} @ {:#x} ({}) = {}",
+        variable.name, rebased, variable.typename, value
+    );
+    Ok(())
+}
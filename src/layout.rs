@@ -0,0 +1,67 @@
+// sizes and descriptions of types in the exported graph
+
+use crate::{Array, BinjaType, Class, Encoding, HashMap, Integer};
+
+pub fn kind(binja_type: &BinjaType) -> &'static str {
+    match binja_type {
+        BinjaType::Structure(_) => "struct",
+        BinjaType::Union(_) => "union",
+        BinjaType::Class(_) => "class",
+        BinjaType::Integer(_) => "integer",
+        BinjaType::Pointer(_) => "pointer",
+        BinjaType::Typedef(_) => "typedef",
+        BinjaType::Function(_) => "function",
+        BinjaType::Enum(_) => "enum",
+        BinjaType::Array(_) => "array",
+        BinjaType::Vector(_) => "vector",
+        BinjaType::Qualified(_) => "qualified",
+    }
+}
+
+pub fn encoding(integer: &Integer) -> &'static str {
+    match (integer.encoding, integer.signed) {
+        (Some(Encoding::Float), _) => "float",
+        (Some(Encoding::Bool), _) => "bool",
+        (Some(Encoding::Char), true) => "signed char",
+        (Some(Encoding::Char), false) => "unsigned char",
+        (None, true) => "signed",
+        (None, false) => "unsigned",
+    }
+}
+
+// None for dangling names and typedef cycles
+pub fn size_of(types: &HashMap<String, BinjaType>, name: &str) -> Option<u64> {
+    size_of_bounded(types, name, 0)
+}
+
+fn size_of_bounded(types: &HashMap<String, BinjaType>, name: &str, depth: usize) -> Option<u64> {
+    if name.is_empty() {
+        return Some(0);
+    }
+    if depth > 64 {
+        return None;
+    }
+
+    match types.get(name)? {
+        BinjaType::Structure(s)
+        | BinjaType::Union(s)
+        | BinjaType::Class(Class { layout: s, .. }) => Some(s.size),
+        BinjaType::Integer(i) => Some(i.size),
+        BinjaType::Pointer(p) => Some(p.size),
+        BinjaType::Enum(e) => Some(e.size),
+        BinjaType::Function(_) => Some(0),
+        BinjaType::Typedef(t) => size_of_bounded(types, &t.target, depth + 1),
+        BinjaType::Qualified(q) => size_of_bounded(types, &q.target, depth + 1),
+        // the descriptor's, with the same 0 for the pointer size pointers have
+        BinjaType::Array(Array {
+            dynamic: Some(dynamic),
+            ..
+        }) => Some(dynamic.size),
+        BinjaType::Array(a) => {
+            size_of_bounded(types, &a.target, depth + 1).map(|size| size * a.elements())
+        }
+        BinjaType::Vector(v) => {
+            size_of_bounded(types, &v.target, depth + 1).map(|size| size * v.count)
+        }
+    }
+}
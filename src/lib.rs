@@ -0,0 +1,2783 @@
+use gimli::write::{
+    AttributeValue, DebugInfoOffsets, Dwarf, EndianVec, Expression, LineProgram, Reference,
+    Sections, Unit, UnitEntryId, UnitId,
+};
+use gimli::DebugInfoOffset;
+use goblin::elf64::{
+    header::*, program_header as segment, section_header as section, sym as symbol,
+};
+use log::{error, info};
+use std::collections::BTreeMap as HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread::{Scope, ScopedJoinHandle};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+mod aranges;
+mod bndb;
+mod build_id;
+mod builder;
+mod cache;
+mod check;
+mod coff;
+mod compress;
+mod coverage;
+mod dynsym;
+mod explain;
+mod export_ghidra;
+mod export_headers;
+mod export_rust;
+mod extract;
+mod frames;
+mod gdb_index;
+mod gdb_scripts;
+mod headers;
+mod importer;
+mod inject;
+mod inspect;
+mod layout;
+mod lines;
+mod lldb;
+mod logger;
+mod merge;
+mod overlay;
+mod padding;
+mod pdb;
+mod prepare;
+mod presets;
+mod printers;
+mod project;
+mod readback;
+mod rebase;
+mod registers;
+mod reimport;
+mod report;
+mod resolve;
+mod roots;
+mod rust;
+mod sections;
+mod shell;
+mod spaces;
+mod split;
+mod subprograms;
+pub mod target;
+mod templates;
+mod trampolines;
+mod tui;
+mod type_dies;
+mod type_units;
+mod units;
+mod validate;
+mod variables;
+mod variants;
+mod verify;
+mod watch;
+mod writer;
+
+pub use builder::{DwarfBuilder, TypeDatabase};
+pub use writer::ElfWriter;
+
+type RawSection = section::SectionHeader;
+type RawSegment = segment::ProgramHeader;
+type RawSymbol = symbol::Sym;
+const SIZEOF_SHDR: usize = section::SIZEOF_SHDR;
+const SIZEOF_PHDR: usize = segment::SIZEOF_PHDR;
+const SIZEOF_SYM: usize = symbol::SIZEOF_SYM;
+
+struct Section {
+    hdr: RawSection,
+    raw: Vec<u8>,
+    #[allow(dead_code)]
+    off: u64,
+}
+
+struct Segment {
+    hdr: RawSegment,
+    #[allow(dead_code)]
+    raw: Vec<u8>,
+    #[allow(dead_code)]
+    off: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianity {
+    Little,
+    Big,
+}
+
+impl Endianity {
+    fn dwarf(self) -> gimli::DwEnd {
+        match self {
+            Endianity::Little => gimli::DW_END_little,
+            Endianity::Big => gimli::DW_END_big,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Field {
+    offset: u64,
+    name: String,
+    typename: String,
+    // an integer member stored in another byte order than the target's,
+    // like network order fields
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    endianity: Option<Endianity>,
+    // bitfields: the width in bits, and where they start in bits past `offset`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bit_offset: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bit_size: Option<u64>,
+    // for class members, which are public when it's left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    access: Option<Access>,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Access {
+    Public,
+    Protected,
+    Private,
+}
+
+impl Access {
+    fn dwarf(access: Option<Access>) -> gimli::DwAccess {
+        match access {
+            None | Some(Access::Public) => gimli::DW_ACCESS_public,
+            Some(Access::Protected) => gimli::DW_ACCESS_protected,
+            Some(Access::Private) => gimli::DW_ACCESS_private,
+        }
+    }
+
+    fn from_dwarf(access: gimli::DwAccess) -> Option<Access> {
+        match access {
+            gimli::DW_ACCESS_protected => Some(Access::Protected),
+            gimli::DW_ACCESS_private => Some(Access::Private),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Structure {
+    size: u64,
+    anon: bool,
+    fields: Vec<Field>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    variant: Option<VariantPart>,
+    // only forward declared, emitted as a DW_AT_declaration stub without a
+    // size so debuggers look for the definition elsewhere
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    declaration: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    decl: Option<Declaration>,
+    // how --gdb-printers shows it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    printer: Option<Printer>,
+}
+
+// `{"list": {"next": "next", "value": "data"}}` shows the nodes reached
+// through `next` (or their `value` members) as an array, `{"string": {"data":
+// "buf", "length": "len"}}` the `len` bytes `buf` holds as a string and
+// `{"array": {"data": "items", "length": "count"}}` its first `count` items
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Printer {
+    List {
+        next: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        value: Option<String>,
+    },
+    String {
+        data: String,
+        length: String,
+    },
+    Array {
+        data: String,
+        length: String,
+    },
+}
+
+// a tagged union: the union member's arms are selected by the discriminant
+// member, emitted as DW_TAG_variant_part so debuggers only show the active arm
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct VariantPart {
+    discriminant: String,
+    union: String,
+    // discriminant value and the union arm it selects
+    arms: Vec<(u64, String)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default: Option<String>,
+}
+
+type Union = Structure;
+
+// a C++ class: a struct with base classes and member functions, emitted as
+// DW_TAG_class_type so debuggers show the hierarchy instead of a flat struct
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Class {
+    #[serde(flatten)]
+    layout: Structure,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    bases: Vec<Base>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    methods: Vec<Method>,
+    // the class whose vtable pointer it uses, itself or a base
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    containing_type: Option<String>,
+    // the member holding the vtable pointer, artificial like compilers make it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    vtable_pointer: Option<String>,
+    // the address of its vtable, which gets the `vtable for` symbol gdb
+    // finds the dynamic type of an object by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    vtable: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Base {
+    offset: u64,
+    typename: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    access: Option<Access>,
+}
+
+// a member function's declaration, its prototype's parameter named `this`
+// is the object pointer
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Method {
+    name: String,
+    typename: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    access: Option<Access>,
+    // virtual ones, their slot in the vtable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    vtable_index: Option<u64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pure: bool,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Pointer {
+    // 0 or left out takes the target's pointer size, twice that for a
+    // pointer to a member function
+    #[serde(default)]
+    size: u64,
+    target: String,
+    // a C++ reference to the target instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reference: Option<ReferenceKind>,
+    // a pointer to a member of this class, `int Foo::*`, or to a method of it
+    // when the target is a function type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    member_of: Option<String>,
+}
+
+impl Pointer {
+    // the name it gets where nothing names it: `void*`, `int&`, `int Foo::*`
+    fn spelled(&self) -> String {
+        let shown = if self.target.is_empty() {
+            "void"
+        } else {
+            &self.target
+        };
+        match (self.reference, &self.member_of) {
+            (Some(ReferenceKind::Lvalue), _) => format!("{}&", shown),
+            (Some(ReferenceKind::Rvalue), _) => format!("{}&&", shown),
+            (None, Some(class)) => format!("{} {}::*", shown, class),
+            (None, None) => format!("{}*", shown),
+        }
+    }
+}
+
+// `"lvalue"` for a T&, `"rvalue"` for a T&&
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ReferenceKind {
+    Lvalue,
+    Rvalue,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Typedef {
+    target: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    decl: Option<Declaration>,
+}
+
+// const, volatile and restrict on top of another type, like "const char"
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Qualified {
+    target: String,
+    #[serde(default, rename = "const")]
+    constant: bool,
+    #[serde(default)]
+    volatile: bool,
+    #[serde(default)]
+    restrict: bool,
+}
+
+impl Qualified {
+    // outermost first
+    fn tags(&self) -> Vec<gimli::DwTag> {
+        [
+            (self.constant, gimli::DW_TAG_const_type),
+            (self.volatile, gimli::DW_TAG_volatile_type),
+            (self.restrict, gimli::DW_TAG_restrict_type),
+        ]
+        .into_iter()
+        .filter_map(|(set, tag)| set.then_some(tag))
+        .collect()
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Parameter {
+    name: String,
+    typename: String,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Function {
+    parameters: Vec<Parameter>,
+    returntype: String,
+    // takes more arguments than `parameters`, like printf
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    variadic: bool,
+    // left out for the platform's default one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    convention: Option<Convention>,
+}
+
+// calling conventions, with the names binja, ghidra and ida give them. the
+// non-standard ones get the vendor DW_CC_* values clang writes for them
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Convention {
+    #[serde(alias = "__cdecl")]
+    Cdecl,
+    #[serde(alias = "__stdcall")]
+    Stdcall,
+    #[serde(alias = "__fastcall")]
+    Fastcall,
+    #[serde(alias = "__thiscall")]
+    Thiscall,
+    #[serde(alias = "__pascal")]
+    Pascal,
+    #[serde(alias = "__vectorcall")]
+    Vectorcall,
+    #[serde(alias = "__regcall")]
+    Regcall,
+    Win64,
+    Sysv,
+    Aapcs,
+    #[serde(alias = "aapcs-vfp")]
+    AapcsVfp,
+}
+
+impl Convention {
+    fn dwarf(self) -> gimli::DwCc {
+        gimli::DwCc(match self {
+            Convention::Cdecl => return gimli::DW_CC_normal,
+            Convention::Stdcall => 0xb1,
+            Convention::Fastcall => 0xb3,
+            Convention::Thiscall => 0xb5,
+            Convention::Pascal => 0xb2,
+            Convention::Vectorcall => 0xc0,
+            Convention::Win64 => 0xc1,
+            Convention::Sysv => 0xc2,
+            Convention::Aapcs => 0xc3,
+            Convention::AapcsVfp => 0xc4,
+            Convention::Regcall => 0xcb,
+        })
+    }
+
+    fn from_dwarf(cc: gimli::DwCc) -> Option<Convention> {
+        [
+            Convention::Cdecl,
+            Convention::Stdcall,
+            Convention::Fastcall,
+            Convention::Thiscall,
+            Convention::Pascal,
+            Convention::Vectorcall,
+            Convention::Win64,
+            Convention::Sysv,
+            Convention::Aapcs,
+            Convention::AapcsVfp,
+            Convention::Regcall,
+        ]
+        .into_iter()
+        .find(|convention| convention.dwarf() == cc)
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Array {
+    count: u64,
+    target: String,
+    // the dimensions past the first, `int[4][8]` is a count of 4 with
+    // `"dimensions": [8]`, a single DW_TAG_array_type with a subrange each
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    dimensions: Vec<u64>,
+    // a DW_TAG_string_type of count characters instead, like fortran's
+    // CHARACTER(len=10), or of however many `dynamic` says
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    string: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dynamic: Option<Dynamic>,
+}
+
+// an array (or string) whose elements are elsewhere, found through the object
+// at run time: `{"size": 16, "data": 0, "count": 8}` for a descriptor holding
+// a pointer to the elements and then their count, `{"count": -8,
+// "from_data": true}` for a delphi dynamic array, a pointer to the elements
+// with the count just before them. the count is a word unless `count_size`
+// says otherwise
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Dynamic {
+    // the object's, for the layout of what it's a member of. 0 or left out
+    // takes the target's pointer size like pointers
+    #[serde(default)]
+    size: u64,
+    // where in it the pointer to the elements is
+    #[serde(default)]
+    data: u64,
+    // where the count is, from the start of the object or the elements
+    count: i64,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    from_data: bool,
+    #[serde(default)]
+    count_size: u8,
+}
+
+// a SIMD vector of count lanes, `{"__m128i": {"count": 2, "target": "int64_t"}}`,
+// a DW_TAG_array_type with DW_AT_GNU_vector that gdb prints as `{1, 2}`
+// lanes and lets registers like xmm0 be cast to
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Vector {
+    count: u64,
+    target: String,
+}
+
+impl Dynamic {
+    fn count_size(&self, target: &target::Target) -> u8 {
+        match self.count_size {
+            0 => target.pointer_size as u8,
+            size => size,
+        }
+    }
+
+    // DW_AT_data_location, the elements' address
+    fn data_location(&self) -> Expression {
+        let mut expression = Expression::new();
+        expression.op(gimli::DW_OP_push_object_address);
+        if self.data != 0 {
+            expression.op_plus_uconst(self.data);
+        }
+        expression.op_deref();
+        expression
+    }
+
+    // the count's address, what DW_AT_string_length is
+    fn count_location(&self) -> Expression {
+        let mut expression = match self.from_data {
+            true => self.data_location(),
+            false => {
+                let mut expression = Expression::new();
+                expression.op(gimli::DW_OP_push_object_address);
+                expression
+            }
+        };
+        expression.op_consts(self.count);
+        expression.op(gimli::DW_OP_plus);
+        expression
+    }
+}
+
+impl Array {
+    // outermost first
+    fn counts(&self) -> impl Iterator<Item = u64> + '_ {
+        std::iter::once(self.count).chain(self.dimensions.iter().copied())
+    }
+
+    fn elements(&self) -> u64 {
+        self.counts().product()
+    }
+
+    // `[4][8]`
+    fn bounds(&self) -> String {
+        self.counts().map(|count| format!("[{}]", count)).collect()
+    }
+
+    // the subscripts of the `index`th element in memory order
+    fn subscripts(&self, mut index: u64) -> String {
+        let mut subscripts: Vec<String> = Vec::new();
+        for count in self.counts().collect::<Vec<u64>>().into_iter().rev() {
+            let count = count.max(1);
+            subscripts.push(format!("[{}]", index % count));
+            index /= count;
+        }
+        subscripts.reverse();
+        subscripts.concat()
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct EnumField {
+    name: String,
+    // negative, or as the unsigned bits of a signed enum like older exports
+    // write -1, or past 64 bits for 16 byte enums, which json numbers can't
+    // hold, as a string like "0x10000000000000000"
+    #[serde(serialize_with = "write_wide", deserialize_with = "read_wide")]
+    value: i128,
+}
+
+// a decimal or 0x number of up to 128 bits, negative ones included
+fn parse_wide(text: &str) -> Option<i128> {
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => u128::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<u128>().ok()?,
+    } as i128;
+    Some(if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    })
+}
+
+fn read_wide<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<i128, D::Error> {
+    struct Wide;
+    impl serde::de::Visitor<'_> for Wide {
+        type Value = i128;
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("an integer, or a string of one past 64 bits")
+        }
+        fn visit_i64<E>(self, value: i64) -> Result<i128, E> {
+            Ok(value as i128)
+        }
+        fn visit_u64<E>(self, value: u64) -> Result<i128, E> {
+            Ok(value as i128)
+        }
+        fn visit_i128<E>(self, value: i128) -> Result<i128, E> {
+            Ok(value)
+        }
+        fn visit_u128<E>(self, value: u128) -> Result<i128, E> {
+            Ok(value as i128)
+        }
+        fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<i128, E> {
+            parse_wide(value)
+                .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(value), &self))
+        }
+    }
+    deserializer.deserialize_any(Wide)
+}
+
+// numbers while they fit, so older readers of a dump still parse it
+fn write_wide<S: serde::Serializer>(value: &i128, serializer: S) -> Result<S::Ok, S::Error> {
+    match (i64::try_from(*value), u64::try_from(*value)) {
+        (Ok(value), _) => serializer.serialize_i64(value),
+        (_, Ok(value)) => serializer.serialize_u64(value),
+        _ if *value < 0 => serializer.serialize_str(&format!("-{:#x}", value.unsigned_abs())),
+        _ => serializer.serialize_str(&format!("{:#x}", value)),
+    }
+}
+
+// the low `size` bytes of a value in target memory order, what
+// DW_AT_const_value holds for enumerators and constants past 64 bits
+fn wide_bytes(value: i128, size: u64, endianity: Endianity) -> Vec<u8> {
+    let width = size.min(16) as usize;
+    match endianity {
+        Endianity::Little => value.to_le_bytes()[..width].to_vec(),
+        Endianity::Big => value.to_be_bytes()[16 - width..].to_vec(),
+    }
+}
+
+impl EnumField {
+    fn shown(&self) -> String {
+        match self.value {
+            value if value < 0 => value.to_string(),
+            value => format!("{:#x}", value),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Enum {
+    size: u64,
+    signed: bool,
+    fields: Vec<EnumField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    decl: Option<Declaration>,
+}
+
+impl Enum {
+    // `value` the way the enum's bytes hold it, sign extended if it's signed
+    fn normalize(&self, value: i128) -> i128 {
+        let bits = self.size * 8;
+        if bits == 0 || bits >= 128 {
+            return value;
+        }
+        let value = value & ((1 << bits) - 1);
+        match self.signed && value >> (bits - 1) != 0 {
+            true => value - (1 << bits),
+            false => value,
+        }
+    }
+}
+
+// base types that aren't read as plain integers, char's signedness still
+// comes from `signed`
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Encoding {
+    Float,
+    Bool,
+    Char,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Integer {
+    size: u64,
+    signed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    endianity: Option<Endianity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encoding: Option<Encoding>,
+    // how many of its bits hold the value, for a `_BitInt(37)` in 8 bytes.
+    // any width fits as long as `size` covers it, `__int128` is just size 16
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bits: Option<u64>,
+}
+
+impl Integer {
+    fn dwarf_encoding(&self) -> gimli::DwAte {
+        match (self.encoding, self.signed) {
+            (Some(Encoding::Float), _) => gimli::DW_ATE_float,
+            (Some(Encoding::Bool), _) => gimli::DW_ATE_boolean,
+            (Some(Encoding::Char), true) => gimli::DW_ATE_signed_char,
+            (Some(Encoding::Char), false) => gimli::DW_ATE_unsigned_char,
+            (None, true) => gimli::DW_ATE_signed,
+            (None, false) => gimli::DW_ATE_unsigned,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct GlobalVariable {
+    name: String,
+    size: u64,
+    typename: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    decl: Option<Declaration>,
+    // the --spaces address space it's in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    space: Option<String>,
+}
+
+// where the source declares a type, variable or subprogram, `{"file":
+// "main.c", "line": 12, "column": 5}` with the column optional
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Declaration {
+    file: String,
+    line: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    column: Option<u64>,
+}
+
+// a function's code, `typename` is its prototype among the function types
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Subprogram {
+    name: String,
+    size: u64,
+    typename: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    locals: Vec<Local>,
+    // a static function, bound locally in .symtab and not DW_AT_external
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    local: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    decl: Option<Declaration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    space: Option<String>,
+}
+
+// a stack variable, `offset` is from the canonical frame address (the stack
+// pointer before the call), unless `location` puts it elsewhere. with
+// `ranges` it moves around and is only available within them
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Local {
+    name: String,
+    typename: String,
+    #[serde(default)]
+    offset: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ranges: Vec<Ranged>,
+}
+
+// where a local is from `start` up to `end`, the same way as a local says it
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Ranged {
+    start: u64,
+    end: u64,
+    #[serde(default)]
+    offset: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+}
+
+// `{"register": "rdi"}`, `{"relative": {"register": "rbp", "offset": -8}}` in
+// memory past a register, or `{"pieces": [{"size": 4, "location": ...}]}`
+// spread over several, lowest bytes first. a piece without a location was
+// optimized out
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Location {
+    Register(String),
+    Relative { register: String, offset: i64 },
+    Pieces(Vec<Piece>),
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Piece {
+    size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+}
+
+// a local's location, on the stack without one
+fn place(
+    location: &Option<Location>,
+    offset: i64,
+    target: &target::Target,
+) -> Result<Expression, DynErr> {
+    let mut expression = Expression::new();
+    match location {
+        Some(location) => location.expression(target, &mut expression)?,
+        None => expression.op_fbreg(offset),
+    }
+    Ok(expression)
+}
+
+impl Location {
+    // appends the DWARF expression for it
+    fn expression(&self, target: &target::Target, expression: &mut Expression) -> Err {
+        match self {
+            Location::Register(register) => {
+                expression.op_reg(gimli::Register(registers::number(target, register)?))
+            }
+            Location::Relative { register, offset } => expression.op_breg(
+                gimli::Register(registers::number(target, register)?),
+                *offset,
+            ),
+            Location::Pieces(pieces) => {
+                for piece in pieces {
+                    match &piece.location {
+                        Some(Location::Pieces(_)) => return Err("pieces don't nest".into()),
+                        Some(location) => location.expression(target, expression)?,
+                        None => {}
+                    }
+                    expression.op_piece(piece.size);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// a global with a known value but no address, like a recovered config value
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct Constant {
+    typename: String,
+    value: ConstantValue,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ConstantValue {
+    Unsigned(u64),
+    Signed(i64),
+    // the bytes of a char array, without the terminator
+    String(String),
+}
+
+// tagged with its kind in the combined types.json, e.g. {"kind": "struct", ...}
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum BinjaType {
+    #[serde(rename = "struct")]
+    Structure(Structure),
+    Union(Union),
+    Integer(Integer),
+    Pointer(Pointer),
+    Typedef(Typedef),
+    Function(Function),
+    Enum(Enum),
+    Array(Array),
+    Vector(Vector),
+    Qualified(Qualified),
+    Class(Class),
+}
+
+impl BinjaType {
+    // every type name this type refers to
+    fn references(&self) -> Vec<&String> {
+        match self {
+            BinjaType::Structure(s) | BinjaType::Union(s) => {
+                s.fields.iter().map(|f| &f.typename).collect()
+            }
+            BinjaType::Pointer(p) => std::iter::once(&p.target)
+                .chain(p.member_of.iter())
+                .collect(),
+            BinjaType::Typedef(t) => vec![&t.target],
+            BinjaType::Qualified(q) => vec![&q.target],
+            BinjaType::Array(a) => vec![&a.target],
+            BinjaType::Vector(v) => vec![&v.target],
+            BinjaType::Function(f) => std::iter::once(&f.returntype)
+                .chain(f.parameters.iter().map(|p| &p.typename))
+                .collect(),
+            BinjaType::Class(c) => (c.bases.iter().map(|b| &b.typename))
+                .chain(c.layout.fields.iter().map(|f| &f.typename))
+                .chain(c.methods.iter().map(|m| &m.typename))
+                .chain(c.containing_type.iter())
+                .collect(),
+            BinjaType::Integer(_) | BinjaType::Enum(_) => Vec::new(),
+        }
+    }
+
+    fn references_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            BinjaType::Structure(s) | BinjaType::Union(s) => {
+                s.fields.iter_mut().map(|f| &mut f.typename).collect()
+            }
+            BinjaType::Pointer(p) => std::iter::once(&mut p.target)
+                .chain(p.member_of.iter_mut())
+                .collect(),
+            BinjaType::Typedef(t) => vec![&mut t.target],
+            BinjaType::Qualified(q) => vec![&mut q.target],
+            BinjaType::Array(a) => vec![&mut a.target],
+            BinjaType::Vector(v) => vec![&mut v.target],
+            BinjaType::Function(f) => std::iter::once(&mut f.returntype)
+                .chain(f.parameters.iter_mut().map(|p| &mut p.typename))
+                .collect(),
+            BinjaType::Class(c) => (c.bases.iter_mut().map(|b| &mut b.typename))
+                .chain(c.layout.fields.iter_mut().map(|f| &mut f.typename))
+                .chain(c.methods.iter_mut().map(|m| &mut m.typename))
+                .chain(c.containing_type.iter_mut())
+                .collect(),
+            BinjaType::Integer(_) | BinjaType::Enum(_) => Vec::new(),
+        }
+    }
+
+    // the named kinds whose `::` scope is a namespace or a class
+    fn scoped(&self) -> bool {
+        match self {
+            BinjaType::Structure(s) | BinjaType::Union(s) => !s.anon,
+            BinjaType::Class(c) => !c.layout.anon,
+            BinjaType::Enum(_) | BinjaType::Typedef(_) => true,
+            _ => false,
+        }
+    }
+
+    fn decl(&self) -> Option<&Declaration> {
+        match self {
+            BinjaType::Structure(s) | BinjaType::Union(s) => s.decl.as_ref(),
+            BinjaType::Class(c) => c.layout.decl.as_ref(),
+            BinjaType::Enum(e) => e.decl.as_ref(),
+            BinjaType::Typedef(t) => t.decl.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn decl_mut(&mut self) -> Option<&mut Option<Declaration>> {
+        match self {
+            BinjaType::Structure(s) | BinjaType::Union(s) => Some(&mut s.decl),
+            BinjaType::Class(c) => Some(&mut c.layout.decl),
+            BinjaType::Enum(e) => Some(&mut e.decl),
+            BinjaType::Typedef(t) => Some(&mut t.decl),
+            _ => None,
+        }
+    }
+
+    // the entry it gets, the outermost one for qualifiers
+    fn tag(&self) -> gimli::DwTag {
+        match self {
+            BinjaType::Structure(_) => gimli::DW_TAG_structure_type,
+            BinjaType::Union(_) => gimli::DW_TAG_union_type,
+            BinjaType::Class(_) => gimli::DW_TAG_class_type,
+            BinjaType::Integer(_) => gimli::DW_TAG_base_type,
+            BinjaType::Pointer(p) => match (p.reference, &p.member_of) {
+                (Some(ReferenceKind::Lvalue), _) => gimli::DW_TAG_reference_type,
+                (Some(ReferenceKind::Rvalue), _) => gimli::DW_TAG_rvalue_reference_type,
+                (None, Some(_)) => gimli::DW_TAG_ptr_to_member_type,
+                (None, None) => gimli::DW_TAG_pointer_type,
+            },
+            BinjaType::Typedef(_) => gimli::DW_TAG_typedef,
+            BinjaType::Function(_) => gimli::DW_TAG_subroutine_type,
+            BinjaType::Enum(_) => gimli::DW_TAG_enumeration_type,
+            BinjaType::Array(a) if a.string => gimli::DW_TAG_string_type,
+            BinjaType::Array(_) | BinjaType::Vector(_) => gimli::DW_TAG_array_type,
+            // the inner qualifiers get their own entries when it's filled in
+            BinjaType::Qualified(q) => q
+                .tags()
+                .first()
+                .copied()
+                .unwrap_or(gimli::DW_TAG_const_type),
+        }
+    }
+}
+
+pub type DynErr = Box<dyn std::error::Error>;
+pub type Err = Result<(), DynErr>;
+
+// the decompiler the export comes from
+#[derive(Clone, Copy, PartialEq)]
+enum ExportFormat {
+    Binja,
+    Ida,
+    Ghidra,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Compat {
+    Gdb,
+    // ghidra's importer chokes on DWARF64 and takes a unit's file from its
+    // line table, so every unit with code gets one. it only gets the forms
+    // ghidra's parser reads: addr, block, data1-8, udata, sdata, flag,
+    // string, strp, ref4, ref_addr, exprloc and sec_offset. no ref_sig8 into
+    // .debug_types, no ref_sup4 or strp_sup into a supplementary file and no
+    // .dwo, ghidra doesn't read those
+    Ghidra,
+}
+
+enum Command {
+    Generate,
+    Shell,
+    // browse the export, or the DWARF in an existing file
+    Tui(Option<PathBuf>),
+    // write the export, or the DWARF in an existing file, out as json
+    Dump(PathBuf, Option<PathBuf>),
+    // the same as a C header, as rust, and as a ghidra script making them
+    ExportHeaders(PathBuf, Option<PathBuf>),
+    ExportRust(PathBuf, Option<PathBuf>),
+    ExportGhidra(PathBuf, Option<PathBuf>),
+    // the DWARF in a file as a types.json, in the directory or --types-dir,
+    // and whether to leave the addresses out
+    Extract(PathBuf, Option<PathBuf>, bool),
+    Explain(u64),
+    Coverage,
+    // the core (or /proc/<pid>/mem) and the variable to print
+    Inspect(PathBuf, String),
+    Watch,
+}
+
+struct Options {
+    command: Command,
+    // where the export is read from and the debug file written to
+    types_dir: PathBuf,
+    output: PathBuf,
+    // ignore types.json and read the file per kind layout
+    legacy_export: bool,
+    format: ExportFormat,
+    compat: Compat,
+    target: target::Target,
+    verify: bool,
+    verify_binja: bool,
+    gdb_index: bool,
+    split_dwarf: bool,
+    type_units: bool,
+    coff: bool,
+    // drop what --binary's DWARF has
+    merge: bool,
+    compression: Option<compress::Style>,
+    preset: Option<String>,
+    headers: Option<PathBuf>,
+    symbols: Option<PathBuf>,
+    // exported into types_dir with headless binja first
+    bndb: Option<PathBuf>,
+    pdb: Option<PathBuf>,
+    // where the PE --pdb describes is loaded, or what the export's addresses
+    // are offsets from
+    image_base: Option<u64>,
+    // added to every address, see rebase.rs
+    rebase_delta: i64,
+    overlays: Vec<PathBuf>,
+    defines: HashMap<String, String>,
+    units: Option<PathBuf>,
+    // of the units that don't name theirs
+    language: String,
+    trampolines: Option<PathBuf>,
+    // the address spaces variables and subprograms can be in
+    spaces: Option<PathBuf>,
+    frames: Option<PathBuf>,
+    lines: Option<PathBuf>,
+    sources: Option<PathBuf>,
+    // the binary the export came from
+    binary: Option<PathBuf>,
+    // load address for `inspect` and `watch`
+    base: Option<u64>,
+    // the gdb `watch` reloads the debug file in
+    gdb_mi: Option<PathBuf>,
+    project: Option<PathBuf>,
+    debug_sup: Option<PathBuf>,
+    // a binary to write out with the debug info added, as --output
+    inject: Option<PathBuf>,
+    dynsym: Vec<String>,
+    // the least alignment of the sections --inject adds
+    inject_align: Option<u64>,
+    // a PT_LOAD for them
+    inject_load: bool,
+    // a binary to point at --output with a .gnu_debuglink
+    debuglink: Option<PathBuf>,
+    // a .note.gnu.build-id, the binary's or a hash of the output
+    build_id: bool,
+    // a tree debuginfod clients fetch from, to copy --output into by it
+    debuginfod_layout: Option<PathBuf>,
+    // where generated outputs are kept by a hash of their inputs
+    cache: Option<PathBuf>,
+    // a json summary of what went into the output
+    report: Option<PathBuf>,
+    // pretty-printers for the structs with a "printer", also embedded
+    gdb_printers: Option<PathBuf>,
+    // embedded in .debug_gdb_scripts
+    gdb_scripts: Vec<PathBuf>,
+    // the --gdb-printers for lldb
+    lldb_summaries: Option<PathBuf>,
+    // go through the run but write nothing
+    check: bool,
+    // stand in for unknown types instead of failing
+    lenient: bool,
+    // fail on the layout issues instead of warning about them
+    strict: bool,
+    // spell the holes in structs out as `__pad_` members
+    pad_holes: bool,
+}
+
+impl Options {
+    // what a plain `teemo` run uses, for the library interface
+    fn new(target: target::Target) -> Options {
+        Options {
+            command: Command::Generate,
+            types_dir: PathBuf::from("."),
+            output: PathBuf::from("test.o"),
+            legacy_export: false,
+            format: ExportFormat::Binja,
+            compat: Compat::Gdb,
+            target,
+            verify: false,
+            verify_binja: false,
+            gdb_index: false,
+            split_dwarf: false,
+            type_units: false,
+            coff: false,
+            merge: false,
+            compression: None,
+            preset: None,
+            headers: None,
+            symbols: None,
+            bndb: None,
+            pdb: None,
+            image_base: None,
+            rebase_delta: 0,
+            overlays: Vec::new(),
+            defines: HashMap::new(),
+            units: None,
+            language: String::from("c"),
+            trampolines: None,
+            spaces: None,
+            frames: None,
+            lines: None,
+            sources: None,
+            binary: None,
+            base: None,
+            gdb_mi: None,
+            project: None,
+            debug_sup: None,
+            inject: None,
+            dynsym: Vec::new(),
+            inject_align: None,
+            inject_load: false,
+            debuglink: None,
+            build_id: false,
+            debuginfod_layout: None,
+            cache: None,
+            report: None,
+            gdb_printers: None,
+            gdb_scripts: Vec::new(),
+            lldb_summaries: None,
+            check: false,
+            lenient: false,
+            strict: false,
+            pad_holes: false,
+        }
+    }
+}
+
+const LAYOUT: &str = "\
+the types directory holds either a combined types.json
+
+  {\"types\": {\"node\": {\"kind\": \"struct\", \"size\": 16, \"anon\": false, \"fields\": [...]}},
+   \"variables\": {\"16400\": {\"name\": \"thing\", \"size\": 16, \"typename\": \"Thing\"}}}
+
+with kinds struct, union, integer, pointer, typedef, function, enum, array,
+vector, qualified and class, or the export extract.py writes, one json file per kind:
+
+  structs.json    {\"node\": {\"size\": 16, \"anon\": false, \"fields\": [[0, \"next\", \"node*\"]]}}
+  unions.json     same as structs.json
+  integers.json   {\"uint32_t\": {\"size\": 4, \"signed\": false}}, floats, bools and chars
+                  carry \"encoding\": \"float\", \"bool\" or \"char\"
+  pointers.json   {\"node*\": {\"size\": 8, \"target\": \"node\"}}
+  typedefs.json   {\"meow\": {\"target\": \"int32_t\"}}
+  functions.json  {\"int32_t (char)\": {\"parameters\": [[\"c\", \"char\"]], \"returntype\": \"int32_t\"}}
+  enums.json      {\"color\": {\"size\": 4, \"signed\": false, \"fields\": [[\"red\", 0]]}}
+  arrays.json     {\"char[16]\": {\"count\": 16, \"target\": \"char\"}}
+  vectors.json    {\"__m128i\": {\"count\": 2, \"target\": \"int64_t\"}}
+  qualifiers.json {\"const char\": {\"target\": \"char\", \"const\": true}}
+  classes.json    structs with \"bases\": [{\"offset\": 0, \"typename\": \"Base\"}], \"methods\":
+                  [{\"name\": \"run\", \"typename\": \"void (Thing*)\"}] and \"containing_type\"
+  variables.json  {\"16400\": {\"name\": \"thing\", \"size\": 16, \"typename\": \"Thing\"}}
+  subprograms.json {\"4198448\": {\"name\": \"main\", \"size\": 120, \"typename\": \"int32_t (char)\"}}
+
+with --format ida or ghidra it's the ida.json or ghidra.json that
+ida_export.py and ghidra_export.py write, see src/importer.rs. types refer to each other by name, \"\" is void. qualifiers.json
+(with const, volatile and restrict flags), vectors.json, classes.json, subprograms.json, constants.json, variants.json and templates.json are optional, the rest is
+required unless --preset supplies types. see the README for the optional files and side inputs.";
+
+fn cli() -> clap::Command {
+    use clap::{Arg, ArgAction};
+
+    let path = |name: &'static str, help: &'static str| {
+        Arg::new(name)
+            .value_parser(clap::value_parser!(PathBuf))
+            .help(help)
+    };
+    let flag = |name: &'static str, value: &'static str, help: &'static str| {
+        Arg::new(name)
+            .long(name)
+            .value_name(value)
+            .global(true)
+            .help(help)
+    };
+
+    clap::Command::new("teemo")
+        .about("generates DWARF debug info from a binary ninja type export")
+        .after_help(LAYOUT)
+        .arg(
+            flag("types-dir", "DIR", "directory holding the export")
+                .value_parser(clap::value_parser!(PathBuf))
+                .default_value("."),
+        )
+        .arg(
+            flag("output", "FILE", "debug file to write")
+                .short('o')
+                .value_parser(clap::value_parser!(PathBuf))
+                .default_value("test.o"),
+        )
+        .arg(
+            Arg::new("legacy-export")
+                .long("legacy-export")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("read one json file per kind even if types.json exists"),
+        )
+        .arg(
+            flag("format", "NAME", "decompiler the export comes from")
+                .value_parser(["binja", "ida", "ghidra"])
+                .default_value("binja"),
+        )
+        .arg(
+            flag("compat", "MODE", "debugger to cater to")
+                .value_parser(["gdb", "ghidra"])
+                .default_value("gdb"),
+        )
+        .arg(flag("arch", "NAME", "target architecture").default_value("x86_64"))
+        .arg(
+            flag("elf-class", "BITS", "ELF class, instead of the arch's")
+                .value_parser(["32", "64"]),
+        )
+        .arg(
+            Arg::new("verify")
+                .long("verify")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("parse the output back and check its references and names"),
+        )
+        .arg(
+            Arg::new("check")
+                .long("check")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("reads the export and builds the DWARF but writes nothing"),
+        )
+        .arg(
+            Arg::new("verify-binja")
+                .long("verify-binja")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("check the output re-imports into binja"),
+        )
+        .arg(
+            Arg::new("gdb-index")
+                .long("gdb-index")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("adds a .gdb_index for instant symbol lookup"),
+        )
+        .arg(
+            Arg::new("split-dwarf")
+                .long("split-dwarf")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("moves the DIEs into <output>.dwo behind skeleton units"),
+        )
+        .arg(
+            Arg::new("type-units")
+                .long("type-units")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("shares structs, unions and enums through .debug_types"),
+        )
+        .arg(
+            Arg::new("coff")
+                .long("coff")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["inject", "debuglink"])
+                .help("writes a PE/COFF object instead of an ELF"),
+        )
+        .arg(
+            flag(
+                "compress-debug-sections",
+                "STYLE",
+                "zlib-compresses the .debug_* sections",
+            )
+            .value_parser(["zlib", "zlib-gnu"])
+            .num_args(0..=1)
+            .require_equals(true)
+            .default_missing_value("zlib"),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .global(true)
+                .action(ArgAction::Count)
+                .help("also lists every section, type, variable and function"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .conflicts_with("verbose")
+                .help("only prints errors"),
+        )
+        .arg(
+            Arg::new("lenient")
+                .long("lenient")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("stand in empty structs for unknown types"),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("fail on overlapping members, members past the size and holes"),
+        )
+        .arg(
+            Arg::new("pad-holes")
+                .long("pad-holes")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("fill the bytes no member covers with `char __pad_N[len]` members"),
+        )
+        .arg(flag("preset", "NAME", "built-in types to start from"))
+        .arg(
+            flag("headers", "FILE", "C declarations to read the types from")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            flag(
+                "symbols",
+                "FILE",
+                "addresses of the declarations in --headers",
+            )
+            .value_parser(clap::value_parser!(PathBuf))
+            .requires("headers"),
+        )
+        .arg(
+            flag(
+                "bndb",
+                "FILE",
+                "binja database to export into --types-dir with headless binja",
+            )
+            .value_parser(clap::value_parser!(PathBuf))
+            .conflicts_with_all(["headers", "pdb"]),
+        )
+        .arg(
+            flag(
+                "pdb",
+                "FILE",
+                "windows program database to read the types from",
+            )
+            .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(flag(
+            "image-base",
+            "ADDRESS",
+            "adds ADDRESS to the export's addresses, or where the PE of --pdb is linked \
+                 (0x140000000 by default)",
+        ))
+        .arg(
+            flag(
+                "rebase-delta",
+                "DELTA",
+                "moves every address by DELTA, which can be negative",
+            )
+            .allow_hyphen_values(true),
+        )
+        .arg(
+            flag(
+                "overlay",
+                "FILE",
+                "local fixes applied over the export, repeatable",
+            )
+            .value_parser(clap::value_parser!(PathBuf))
+            .action(ArgAction::Append),
+        )
+        .arg(
+            flag(
+                "define",
+                "KEY[=VALUE]",
+                "selects variants.json entries, repeatable",
+            )
+            .action(ArgAction::Append),
+        )
+        .arg(
+            flag("units", "FILE", "splits the output into compilation units")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            flag(
+                "language",
+                "NAME",
+                "language of the units that don't say, rust for rustc's enums and names",
+            )
+            .default_value("c"),
+        )
+        .arg(
+            flag("trampolines", "FILE", "PLT stubs and thunks to mark")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            flag("spaces", "FILE", "address spaces of a firmware image")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            flag("frames", "FILE", "call frame rules to unwind with")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            flag("lines", "FILE", "source lines of the subprograms")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            flag(
+                "sources",
+                "DIR",
+                "decompiled <function>.c files to debug against",
+            )
+            .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            flag("project", "MANIFEST", "generates every binary of a project")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            flag(
+                "debug-sup",
+                "FILE",
+                "factors project types into a supplementary file",
+            )
+            .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            flag("binary", "FILE", "the binary the export came from")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("merge")
+                .long("merge")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .requires("binary")
+                .help("leaves out what --binary's own DWARF describes"),
+        )
+        .arg(flag("base", "ADDRESS", "load address of the executable"))
+        .arg(
+            flag(
+                "inject",
+                "BINARY",
+                "writes BINARY with the debug info added as --output",
+            )
+            .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            flag(
+                "dynsym",
+                "FUNCTION",
+                "also exports FUNCTION from --inject's .dynsym, repeatable",
+            )
+            .requires("inject")
+            .action(ArgAction::Append),
+        )
+        .arg(
+            flag(
+                "inject-align",
+                "BYTES",
+                "aligns each section --inject adds to at least BYTES",
+            )
+            .requires("inject"),
+        )
+        .arg(
+            Arg::new("inject-load")
+                .long("inject-load")
+                .requires("inject")
+                .action(ArgAction::SetTrue)
+                .help("maps the sections --inject adds with a PT_LOAD of their own"),
+        )
+        .arg(
+            flag(
+                "debuglink",
+                "BINARY",
+                "adds a .gnu_debuglink to --output into BINARY",
+            )
+            .value_parser(clap::value_parser!(PathBuf))
+            .conflicts_with("inject"),
+        )
+        .arg(
+            Arg::new("build-id")
+                .long("build-id")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["inject", "coff"])
+                .help("adds the binary's build-id to --output, or a new one"),
+        )
+        .arg(
+            flag(
+                "report",
+                "FILE",
+                "saves counts of what went into --output as json",
+            )
+            .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            flag(
+                "gdb-printers",
+                "FILE",
+                "writes gdb pretty-printers for the structs with a \"printer\" and embeds them",
+            )
+            .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            flag(
+                "gdb-script",
+                "FILE",
+                "embeds FILE for gdb to run on load, python or gdb commands, repeatable",
+            )
+            .value_parser(clap::value_parser!(PathBuf))
+            .action(ArgAction::Append),
+        )
+        .arg(
+            flag(
+                "lldb-summaries",
+                "FILE",
+                "writes lldb formatters for the structs with a \"printer\"",
+            )
+            .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            flag(
+                "cache",
+                "DIR",
+                "keeps whole outputs to copy again when no input has changed",
+            )
+            .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            flag(
+                "debuginfod-layout",
+                "ROOT",
+                "also writes --output as ROOT/buildid/<build-id>/debuginfo",
+            )
+            .value_parser(clap::value_parser!(PathBuf))
+            .conflicts_with_all(["inject", "coff"]),
+        )
+        .subcommand(clap::Command::new("shell").about("edit the loaded types interactively"))
+        .subcommand(
+            clap::Command::new("tui")
+                .about("browse the export, or the DWARF in FILE")
+                .arg(path("file", "debug file to read back")),
+        )
+        .subcommand(
+            clap::Command::new("dump")
+                .about("write the export, or the DWARF in FILE, out as json")
+                .arg(path("dir", "directory to write").required(true))
+                .arg(path("file", "debug file to read back")),
+        )
+        .subcommand(
+            clap::Command::new("export-headers")
+                .about("write the export, or the DWARF in FILE, out as a C header")
+                .arg(path("header", "header to write").required(true))
+                .arg(path("file", "debug file to read back")),
+        )
+        .subcommand(
+            clap::Command::new("export-rust")
+                .about("write the export, or the DWARF in FILE, out as rust bindings")
+                .arg(path("bindings", "rust file to write").required(true))
+                .arg(path("file", "debug file to read back")),
+        )
+        .subcommand(
+            clap::Command::new("export-ghidra")
+                .about("write a ghidra script making the export's types, or FILE's")
+                .arg(path("script", "script to write").required(true))
+                .arg(path("file", "debug file to read back")),
+        )
+        .subcommand(
+            clap::Command::new("extract")
+                .about("read the DWARF in FILE into a types.json")
+                .arg(path("file", "debug file to read back").required(true))
+                .arg(path("dir", "directory to write, --types-dir by default"))
+                .arg(
+                    Arg::new("types-only")
+                        .long("types-only")
+                        .action(ArgAction::SetTrue)
+                        .help("leaves out the variables and functions, for another build"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("explain")
+                .about("the variable and field path at an address")
+                .arg(Arg::new("address").required(true)),
+        )
+        .subcommand(clap::Command::new("coverage").about("how much of --binary the export types"))
+        .subcommand(
+            clap::Command::new("inspect")
+                .about("print a variable from a core dump")
+                .arg(
+                    Arg::new("core")
+                        .long("core")
+                        .value_name("FILE")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .required(true)
+                        .help("core file or /proc/<pid>/mem"),
+                )
+                .arg(
+                    Arg::new("var")
+                        .long("var")
+                        .value_name("NAME")
+                        .required(true)
+                        .help("variable to print"),
+                ),
+        )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .action(ArgAction::SetTrue)
+                .help("the same as `teemo watch`"),
+        )
+        .subcommand(
+            clap::Command::new("watch")
+                .about("regenerate whenever an input changes")
+                .arg(
+                    Arg::new("gdb-mi")
+                        .long("gdb-mi")
+                        .value_name("SOCKET")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("gdb MI socket or tty to reload the debug file in"),
+                ),
+        )
+}
+
+fn parse_options() -> Result<Options, DynErr> {
+    let matches = cli().get_matches();
+    logger::init(matches.get_count("verbose"), matches.get_flag("quiet"));
+    let path = |name: &str| matches.get_one::<PathBuf>(name).cloned();
+
+    let mut options = Options {
+        command: Command::Generate,
+        types_dir: path("types-dir").unwrap(),
+        output: path("output").unwrap(),
+        legacy_export: matches.get_flag("legacy-export"),
+        format: match matches.get_one::<String>("format").map(String::as_str) {
+            Some("ida") => ExportFormat::Ida,
+            Some("ghidra") => ExportFormat::Ghidra,
+            _ => ExportFormat::Binja,
+        },
+        compat: match matches.get_one::<String>("compat").map(String::as_str) {
+            Some("ghidra") => Compat::Ghidra,
+            _ => Compat::Gdb,
+        },
+        target: match matches.get_one::<String>("elf-class") {
+            Some(bits) => {
+                target::lookup(matches.get_one::<String>("arch").unwrap())?.with_class(bits)
+            }
+            None => target::lookup(matches.get_one::<String>("arch").unwrap())?,
+        },
+        verify: matches.get_flag("verify"),
+        verify_binja: matches.get_flag("verify-binja"),
+        gdb_index: matches.get_flag("gdb-index"),
+        split_dwarf: matches.get_flag("split-dwarf"),
+        type_units: matches.get_flag("type-units"),
+        coff: matches.get_flag("coff"),
+        merge: matches.get_flag("merge"),
+        compression: match matches
+            .get_one::<String>("compress-debug-sections")
+            .map(String::as_str)
+        {
+            Some("zlib-gnu") => Some(compress::Style::ZlibGnu),
+            Some(_) => Some(compress::Style::Zlib),
+            None => None,
+        },
+        preset: matches.get_one::<String>("preset").cloned(),
+        headers: path("headers"),
+        symbols: path("symbols"),
+        bndb: path("bndb"),
+        pdb: path("pdb"),
+        image_base: match matches.get_one::<String>("image-base") {
+            Some(base) => Some(shell::parse_number(base)?),
+            None => None,
+        },
+        rebase_delta: match matches.get_one::<String>("rebase-delta") {
+            Some(delta) => match delta.strip_prefix('-') {
+                Some(magnitude) => (shell::parse_number(magnitude)? as i64).wrapping_neg(),
+                None => shell::parse_number(delta)? as i64,
+            },
+            None => 0,
+        },
+        overlays: matches
+            .get_many::<PathBuf>("overlay")
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect(),
+        defines: matches
+            .get_many::<String>("define")
+            .into_iter()
+            .flatten()
+            .map(|define| variants::parse_define(define))
+            .collect(),
+        units: path("units"),
+        language: matches.get_one::<String>("language").unwrap().clone(),
+        trampolines: path("trampolines"),
+        spaces: path("spaces"),
+        frames: path("frames"),
+        lines: path("lines"),
+        sources: path("sources"),
+        binary: path("binary"),
+        base: match matches.get_one::<String>("base") {
+            Some(base) => Some(shell::parse_number(base)?),
+            None => None,
+        },
+        gdb_mi: None,
+        project: path("project"),
+        debug_sup: path("debug-sup"),
+        inject: path("inject"),
+        dynsym: matches
+            .get_many::<String>("dynsym")
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect(),
+        inject_align: match matches.get_one::<String>("inject-align") {
+            Some(align) => match shell::parse_number(align)? {
+                align if align.is_power_of_two() => Some(align),
+                align => {
+                    return Err(format!("--inject-align {} isn't a power of two", align).into())
+                }
+            },
+            None => None,
+        },
+        inject_load: matches.get_flag("inject-load"),
+        debuglink: path("debuglink"),
+        build_id: matches.get_flag("build-id") || matches.contains_id("debuginfod-layout"),
+        debuginfod_layout: path("debuginfod-layout"),
+        cache: path("cache"),
+        report: path("report"),
+        gdb_printers: path("gdb-printers"),
+        lldb_summaries: path("lldb-summaries"),
+        gdb_scripts: matches
+            .get_many::<PathBuf>("gdb-script")
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect(),
+        check: matches.get_flag("check"),
+        lenient: matches.get_flag("lenient"),
+        strict: matches.get_flag("strict"),
+        pad_holes: matches.get_flag("pad-holes"),
+    };
+
+    options.command = match matches.subcommand() {
+        Some(("shell", _)) => Command::Shell,
+        Some(("tui", sub)) => Command::Tui(sub.get_one::<PathBuf>("file").cloned()),
+        Some(("dump", sub)) => Command::Dump(
+            sub.get_one::<PathBuf>("dir").cloned().unwrap(),
+            sub.get_one::<PathBuf>("file").cloned(),
+        ),
+        Some(("export-headers", sub)) => Command::ExportHeaders(
+            sub.get_one::<PathBuf>("header").cloned().unwrap(),
+            sub.get_one::<PathBuf>("file").cloned(),
+        ),
+        Some(("export-rust", sub)) => Command::ExportRust(
+            sub.get_one::<PathBuf>("bindings").cloned().unwrap(),
+            sub.get_one::<PathBuf>("file").cloned(),
+        ),
+        Some(("export-ghidra", sub)) => Command::ExportGhidra(
+            sub.get_one::<PathBuf>("script").cloned().unwrap(),
+            sub.get_one::<PathBuf>("file").cloned(),
+        ),
+        Some(("extract", sub)) => Command::Extract(
+            sub.get_one::<PathBuf>("file").cloned().unwrap(),
+            sub.get_one::<PathBuf>("dir").cloned(),
+            sub.get_flag("types-only"),
+        ),
+        Some(("explain", sub)) => Command::Explain(shell::parse_number(
+            sub.get_one::<String>("address").unwrap(),
+        )?),
+        Some(("coverage", _)) => Command::Coverage,
+        Some(("inspect", sub)) => Command::Inspect(
+            sub.get_one::<PathBuf>("core").cloned().unwrap(),
+            sub.get_one::<String>("var").cloned().unwrap(),
+        ),
+        Some(("watch", sub)) => {
+            options.gdb_mi = sub.get_one::<PathBuf>("gdb-mi").cloned();
+            Command::Watch
+        }
+        _ if matches.get_flag("watch") => Command::Watch,
+        _ => Command::Generate,
+    };
+
+    Ok(options)
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct TypeSet {
+    structs: HashMap<String, Structure>,
+    unions: HashMap<String, Union>,
+    integers: HashMap<String, Integer>,
+    pointers: HashMap<String, Pointer>,
+    typedefs: HashMap<String, Typedef>,
+    functions: HashMap<String, Function>,
+    enums: HashMap<String, Enum>,
+    arrays: HashMap<String, Array>,
+    vectors: HashMap<String, Vector>,
+    qualifiers: HashMap<String, Qualified>,
+    classes: HashMap<String, Class>,
+    variables: HashMap<u64, GlobalVariable>,
+    constants: HashMap<String, Constant>,
+    subprograms: HashMap<u64, Subprogram>,
+}
+
+impl TypeSet {
+    // entries from `other` win on name collisions
+    fn extend(&mut self, other: TypeSet) {
+        self.structs.extend(other.structs);
+        self.unions.extend(other.unions);
+        self.integers.extend(other.integers);
+        self.pointers.extend(other.pointers);
+        self.typedefs.extend(other.typedefs);
+        self.functions.extend(other.functions);
+        self.enums.extend(other.enums);
+        self.arrays.extend(other.arrays);
+        self.vectors.extend(other.vectors);
+        self.qualifiers.extend(other.qualifiers);
+        self.classes.extend(other.classes);
+        self.variables.extend(other.variables);
+        self.constants.extend(other.constants);
+        self.subprograms.extend(other.subprograms);
+    }
+
+    fn split(self) -> (HashMap<String, BinjaType>, HashMap<u64, GlobalVariable>) {
+        let mut types = HashMap::new();
+
+        self.structs.into_iter().for_each(|(k, v)| {
+            _ = types.insert(k, BinjaType::Structure(v));
+        });
+        self.unions.into_iter().for_each(|(k, v)| {
+            _ = types.insert(k, BinjaType::Union(v));
+        });
+        self.integers.into_iter().for_each(|(k, v)| {
+            _ = types.insert(k, BinjaType::Integer(v));
+        });
+        self.pointers.into_iter().for_each(|(k, v)| {
+            _ = types.insert(k, BinjaType::Pointer(v));
+        });
+        self.typedefs.into_iter().for_each(|(k, v)| {
+            _ = types.insert(k, BinjaType::Typedef(v));
+        });
+        self.functions.into_iter().for_each(|(k, v)| {
+            _ = types.insert(k, BinjaType::Function(v));
+        });
+        self.enums.into_iter().for_each(|(k, v)| {
+            _ = types.insert(k, BinjaType::Enum(v));
+        });
+        self.arrays.into_iter().for_each(|(k, v)| {
+            _ = types.insert(k, BinjaType::Array(v));
+        });
+        self.vectors.into_iter().for_each(|(k, v)| {
+            _ = types.insert(k, BinjaType::Vector(v));
+        });
+        self.qualifiers.into_iter().for_each(|(k, v)| {
+            _ = types.insert(k, BinjaType::Qualified(v));
+        });
+        self.classes.into_iter().for_each(|(k, v)| {
+            _ = types.insert(k, BinjaType::Class(v));
+        });
+
+        (types, self.variables)
+    }
+
+    // inverse of `split`
+    fn join(types: HashMap<String, BinjaType>, variables: HashMap<u64, GlobalVariable>) -> TypeSet {
+        let mut type_set = TypeSet {
+            variables,
+            ..Default::default()
+        };
+        for (name, binja_type) in types {
+            match binja_type {
+                BinjaType::Structure(v) => _ = type_set.structs.insert(name, v),
+                BinjaType::Union(v) => _ = type_set.unions.insert(name, v),
+                BinjaType::Integer(v) => _ = type_set.integers.insert(name, v),
+                BinjaType::Pointer(v) => _ = type_set.pointers.insert(name, v),
+                BinjaType::Typedef(v) => _ = type_set.typedefs.insert(name, v),
+                BinjaType::Function(v) => _ = type_set.functions.insert(name, v),
+                BinjaType::Enum(v) => _ = type_set.enums.insert(name, v),
+                BinjaType::Array(v) => _ = type_set.arrays.insert(name, v),
+                BinjaType::Vector(v) => _ = type_set.vectors.insert(name, v),
+                BinjaType::Qualified(v) => _ = type_set.qualifiers.insert(name, v),
+                BinjaType::Class(v) => _ = type_set.classes.insert(name, v),
+            }
+        }
+        type_set
+    }
+
+    // `split` that leaves what isn't a type or variable behind in the set
+    fn split_types(&mut self) -> (HashMap<String, BinjaType>, HashMap<u64, GlobalVariable>) {
+        let rest = TypeSet {
+            constants: std::mem::take(&mut self.constants),
+            subprograms: std::mem::take(&mut self.subprograms),
+            ..Default::default()
+        };
+        std::mem::replace(self, rest).split()
+    }
+
+    // inverse of `split_types`
+    fn join_types(
+        &mut self,
+        types: HashMap<String, BinjaType>,
+        variables: HashMap<u64, GlobalVariable>,
+    ) {
+        let mut type_set = TypeSet::join(types, variables);
+        type_set.constants = std::mem::take(&mut self.constants);
+        type_set.subprograms = std::mem::take(&mut self.subprograms);
+        *self = type_set;
+    }
+
+    // writes the set back out in the layout `collect_export` reads
+    fn save(&self, dir: &Path) -> Err {
+        fs::create_dir_all(dir)?;
+        fs::write(
+            dir.join("structs.json"),
+            serde_json::to_string(&self.structs)?,
+        )?;
+        fs::write(
+            dir.join("unions.json"),
+            serde_json::to_string(&self.unions)?,
+        )?;
+        fs::write(
+            dir.join("integers.json"),
+            serde_json::to_string(&self.integers)?,
+        )?;
+        fs::write(
+            dir.join("pointers.json"),
+            serde_json::to_string(&self.pointers)?,
+        )?;
+        fs::write(
+            dir.join("typedefs.json"),
+            serde_json::to_string(&self.typedefs)?,
+        )?;
+        fs::write(
+            dir.join("functions.json"),
+            serde_json::to_string(&self.functions)?,
+        )?;
+        fs::write(dir.join("enums.json"), serde_json::to_string(&self.enums)?)?;
+        fs::write(
+            dir.join("arrays.json"),
+            serde_json::to_string(&self.arrays)?,
+        )?;
+        fs::write(
+            dir.join("variables.json"),
+            serde_json::to_string(&self.variables)?,
+        )?;
+        if !self.vectors.is_empty() {
+            fs::write(
+                dir.join("vectors.json"),
+                serde_json::to_string(&self.vectors)?,
+            )?;
+        }
+        if !self.qualifiers.is_empty() {
+            fs::write(
+                dir.join("qualifiers.json"),
+                serde_json::to_string(&self.qualifiers)?,
+            )?;
+        }
+        if !self.classes.is_empty() {
+            fs::write(
+                dir.join("classes.json"),
+                serde_json::to_string(&self.classes)?,
+            )?;
+        }
+        if !self.constants.is_empty() {
+            fs::write(
+                dir.join("constants.json"),
+                serde_json::to_string(&self.constants)?,
+            )?;
+        }
+        if !self.subprograms.is_empty() {
+            fs::write(
+                dir.join("subprograms.json"),
+                serde_json::to_string(&self.subprograms)?,
+            )?;
+        }
+        Ok(())
+    }
+
+    // the same as one types.json
+    fn save_combined(&self, dir: &Path) -> Err {
+        fs::create_dir_all(dir)?;
+        let (types, variables) = self.clone().split();
+        let export = CombinedExport {
+            types,
+            variables,
+            constants: self.constants.clone(),
+            subprograms: self.subprograms.clone(),
+        };
+        fs::write(dir.join("types.json"), serde_json::to_string(&export)?)?;
+        Ok(())
+    }
+}
+
+fn read_export<T: DeserializeOwned + Default>(path: &Path, required: bool) -> Result<T, DynErr> {
+    match fs::read_to_string(path) {
+        Ok(json) => Ok(serde_json::from_str(&json)?),
+        Err(e) if !required && e.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(format!("{}: {}", path.display(), e).into()),
+    }
+}
+
+// missing files are only tolerated when something else (a preset) supplies types
+// the whole export in one document:
+// {"types": {"node": {"kind": "struct", "size": 16, ...}}, "variables": {...}, "constants": {...}}
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct CombinedExport {
+    types: HashMap<String, BinjaType>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    variables: HashMap<u64, GlobalVariable>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    constants: HashMap<String, Constant>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    subprograms: HashMap<u64, Subprogram>,
+}
+
+// the export as it is on disk, before its variants and templates, and
+// whether it's the one types.json
+fn read_base(
+    dir: &Path,
+    format: ExportFormat,
+    required: bool,
+    legacy: bool,
+) -> Result<(TypeSet, bool), DynErr> {
+    let combined = dir.join("types.json");
+    Ok(match format {
+        ExportFormat::Ida => (importer::ida(&dir.join("ida.json"), required)?, false),
+        ExportFormat::Ghidra => (importer::ghidra(&dir.join("ghidra.json"), required)?, false),
+        ExportFormat::Binja if !legacy && combined.exists() => {
+            let export: CombinedExport = read_export(&combined, true)
+                .map_err(|e| format!("{}: {}", combined.display(), e))?;
+            let mut type_set = TypeSet::join(export.types, export.variables);
+            type_set.constants = export.constants;
+            type_set.subprograms = export.subprograms;
+            (type_set, true)
+        }
+        ExportFormat::Binja => (legacy_export(dir, required)?, false),
+    })
+}
+
+// read_base for `options`, what the shell saves edits back into
+fn export_base(options: &Options) -> Result<(TypeSet, bool), DynErr> {
+    read_base(
+        &options.types_dir,
+        options.format,
+        export_required(options),
+        options.legacy_export,
+    )
+}
+
+// the export may only be missing when something else supplies types
+fn export_required(options: &Options) -> bool {
+    options.preset.is_none() && options.headers.is_none() && options.pdb.is_none()
+}
+
+fn collect_export(
+    dir: &Path,
+    format: ExportFormat,
+    required: bool,
+    legacy: bool,
+    defines: &HashMap<String, String>,
+) -> Result<TypeSet, DynErr> {
+    let (type_set, _) = read_base(dir, format, required, legacy)?;
+    let variants: variants::Variants = read_export(&dir.join("variants.json"), false)?;
+    let type_set = variants.select(type_set, defines)?;
+    let templates: templates::Templates = read_export(&dir.join("templates.json"), false)?;
+    templates.expand(type_set)
+}
+
+// read_export on a thread of its own, DynErr doesn't cross threads so its
+// errors come back as text
+fn spawn_export<'scope, T: DeserializeOwned + Default + Send + 'scope>(
+    scope: &'scope Scope<'scope, '_>,
+    path: PathBuf,
+    required: bool,
+) -> ScopedJoinHandle<'scope, Result<T, String>> {
+    scope.spawn(move || read_export(&path, required).map_err(|e| e.to_string()))
+}
+
+fn join_export<T>(handle: ScopedJoinHandle<Result<T, String>>) -> Result<T, DynErr> {
+    Ok(handle.join().unwrap()?)
+}
+
+// one file per kind, as extract.py writes it. they parse in parallel, a big
+// export is mostly structs and pointers
+fn legacy_export(dir: &Path, required: bool) -> Result<TypeSet, DynErr> {
+    std::thread::scope(|scope| {
+        let structs = spawn_export(scope, dir.join("structs.json"), required);
+        let unions = spawn_export(scope, dir.join("unions.json"), required);
+        let integers = spawn_export(scope, dir.join("integers.json"), required);
+        let pointers = spawn_export(scope, dir.join("pointers.json"), required);
+        let typedefs = spawn_export(scope, dir.join("typedefs.json"), required);
+        let functions = spawn_export(scope, dir.join("functions.json"), required);
+        let enums = spawn_export(scope, dir.join("enums.json"), required);
+        let arrays = spawn_export(scope, dir.join("arrays.json"), required);
+        let vectors = spawn_export(scope, dir.join("vectors.json"), false);
+        let qualifiers = spawn_export(scope, dir.join("qualifiers.json"), false);
+        let classes = spawn_export(scope, dir.join("classes.json"), false);
+        let variables = spawn_export(scope, dir.join("variables.json"), required);
+        // older exports have none
+        let constants = spawn_export(scope, dir.join("constants.json"), false);
+        let subprograms = spawn_export(scope, dir.join("subprograms.json"), false);
+        Ok(TypeSet {
+            structs: join_export(structs)?,
+            unions: join_export(unions)?,
+            integers: join_export(integers)?,
+            pointers: join_export(pointers)?,
+            typedefs: join_export(typedefs)?,
+            functions: join_export(functions)?,
+            enums: join_export(enums)?,
+            arrays: join_export(arrays)?,
+            vectors: join_export(vectors)?,
+            qualifiers: join_export(qualifiers)?,
+            classes: join_export(classes)?,
+            variables: join_export(variables)?,
+            constants: join_export(constants)?,
+            subprograms: join_export(subprograms)?,
+        })
+    })
+}
+
+// every emitted type, with the unit it lives in
+type TypeIds = HashMap<String, (UnitId, UnitEntryId)>;
+// gimli's ids only hash
+type Namespaces = std::collections::HashMap<(UnitId, String), UnitEntryId>;
+
+// the scope and the unqualified name of `std::foo::bar`, a `::` inside
+// template arguments or a parameter list doesn't count
+fn scope(name: &str) -> Option<(&str, &str)> {
+    let bytes = name.as_bytes();
+    let mut depth = 0;
+    let mut split = None;
+    for (i, c) in bytes.iter().enumerate() {
+        match c {
+            b'<' | b'(' | b'[' => depth += 1,
+            b'>' | b')' | b']' => depth -= 1,
+            b':' if depth == 0 && bytes.get(i + 1) == Some(&b':') && split != Some(i - 1) => {
+                split = Some(i)
+            }
+            _ => {}
+        }
+    }
+    split
+        .filter(|i| *i > 0 && *i + 2 < name.len())
+        .map(|i| (&name[..i], &name[i + 2..]))
+}
+
+// the Itanium C++ ABI name of `std::foo::Bar` without its prefix, `N3std3foo3BarE`,
+// for scopes that are plain identifiers
+fn mangle(name: &str) -> Option<String> {
+    let mut parts = vec![unqualified(name)];
+    let mut rest = name;
+    while let Some((outer, _)) = scope(rest) {
+        parts.push(unqualified(outer));
+        rest = outer;
+    }
+    parts.reverse();
+    let identifier = |part: &&str| {
+        part.starts_with(|c: char| c == '_' || c.is_ascii_alphabetic())
+            && part.chars().all(|c| c == '_' || c.is_ascii_alphanumeric())
+    };
+    if !parts.iter().all(identifier) {
+        return None;
+    }
+    let encoded: String = parts
+        .iter()
+        .map(|part| format!("{}{}", part.len(), part))
+        .collect();
+    Some(match parts.len() {
+        1 => encoded,
+        _ => format!("N{}E", encoded),
+    })
+}
+
+fn unqualified(name: &str) -> &str {
+    scope(name).map_or(name, |(_, name)| name)
+}
+
+// the DW_TAG_namespace entries of `path` in `cu`, made on first use
+fn namespace(
+    dwarf: &mut Dwarf,
+    namespaces: &mut Namespaces,
+    cu: UnitId,
+    path: &str,
+) -> UnitEntryId {
+    if let Some(id) = namespaces.get(&(cu, path.to_string())) {
+        return *id;
+    }
+    let (parent, name) = match scope(path) {
+        Some((outer, name)) => (namespace(dwarf, namespaces, cu, outer), name),
+        None => (dwarf.units.get(cu).root(), path),
+    };
+    let unit = dwarf.units.get_mut(cu);
+    let id = unit.add(parent, gimli::DW_TAG_namespace);
+    // gdb's name for it, it has no DW_AT_name
+    if name != "(anonymous namespace)" {
+        unit.get_mut(id).set(
+            gimli::DW_AT_name,
+            AttributeValue::StringRef(dwarf.strings.add(name)),
+        );
+    }
+    _ = namespaces.insert((cu, path.to_string()), id);
+    id
+}
+
+// where a variable or function named `std::foo::bar` goes, with the name it
+// gets there. ones scoped by a class stay where they are under the full name
+fn global_scope<'a>(
+    dwarf: &mut Dwarf,
+    mappings: &HashMap<String, BinjaType>,
+    namespaces: &mut Namespaces,
+    cu: UnitId,
+    name: &'a str,
+) -> (UnitEntryId, &'a str) {
+    match scope(name) {
+        Some((outer, unqualified)) if mappings.get(outer).and_then(aggregate).is_none() => {
+            (namespace(dwarf, namespaces, cu, outer), unqualified)
+        }
+        _ => (dwarf.units.get(cu).root(), name),
+    }
+}
+
+fn aggregate(binja_type: &BinjaType) -> Option<&Structure> {
+    match binja_type {
+        BinjaType::Structure(s)
+        | BinjaType::Union(s)
+        | BinjaType::Class(Class { layout: s, .. }) => Some(s),
+        _ => None,
+    }
+}
+
+// anonymous structs and unions only the fields of a single struct or union
+// use, with that struct or union. they're entered inside it like compilers
+// do, which is where gdb looks for the members of anonymous members. so are
+// the types scoped by one, `Outer::Inner`
+fn nesting(mappings: &HashMap<String, BinjaType>) -> HashMap<&String, &String> {
+    let mut users: HashMap<&String, Vec<&String>> = HashMap::new();
+    for (name, binja_type) in mappings.iter() {
+        for reference in binja_type.references() {
+            // a type's references come together, so a repeat is the last one
+            let list = users.entry(reference).or_default();
+            if list.last() != Some(&name) {
+                list.push(name);
+            }
+        }
+    }
+    let aggregate = |name: &String| mappings.get(name).and_then(aggregate);
+    let mut nested: HashMap<&String, &String> = users
+        .into_iter()
+        .filter(|(name, _)| aggregate(name).is_some_and(|s| s.anon && !s.declaration))
+        .filter_map(|(name, users)| match users[..] {
+            [outer] if aggregate(outer).is_some() => Some((name, outer)),
+            _ => None,
+        })
+        .collect();
+    for (name, binja_type) in mappings.iter() {
+        let Some((outer, _)) = scope(name).filter(|_| binja_type.scoped()) else {
+            continue;
+        };
+        if let Some((outer, _)) = mappings
+            .get_key_value(outer)
+            .filter(|(outer, _)| aggregate(outer).is_some())
+        {
+            _ = nested.insert(name, outer);
+        }
+    }
+    nested
+}
+
+// adds an entry for every type and everything it refers to, depth first with
+// an explicit stack since exports can chain types deep enough to overflow the
+// call stack. the namespaces scoped types went into are returned for the
+// variables and functions
+fn visit(
+    dwarf: &mut Dwarf,
+    mappings: &HashMap<String, BinjaType>,
+    placement: &HashMap<&str, UnitId>,
+    nested: &HashMap<&String, &String>,
+    fallback: UnitId,
+    dwarf_types: &mut TypeIds,
+) -> Namespaces {
+    let mut namespaces = Namespaces::new();
+    for name in mappings.keys() {
+        let mut pending = vec![name];
+        while let Some(name) = pending.pop() {
+            if dwarf_types.contains_key(name) || name.is_empty() {
+                continue;
+            }
+            let binja_type = &mappings[name];
+            let (cu, parent) = match nested.get(name) {
+                Some(outer) => match dwarf_types.get(*outer) {
+                    Some(entry) => *entry,
+                    // the struct holding it comes first
+                    None => {
+                        pending.push(name);
+                        pending.push(*outer);
+                        continue;
+                    }
+                },
+                None => {
+                    let cu = *placement.get(name.as_str()).unwrap_or(&fallback);
+                    match scope(name).filter(|_| binja_type.scoped()) {
+                        Some((outer, _)) => (cu, namespace(dwarf, &mut namespaces, cu, outer)),
+                        None => (cu, dwarf.units.get(cu).root()),
+                    }
+                }
+            };
+            let unit = dwarf.units.get_mut(cu);
+            dwarf_types.insert(name.clone(), (cu, unit.add(parent, binja_type.tag())));
+            // reversed, so they're entered in order
+            pending.extend(binja_type.references().into_iter().rev());
+        }
+    }
+    namespaces
+}
+
+// a DW_AT_type for `name` as seen from unit `from`
+fn type_ref(dwarf_types: &TypeIds, from: UnitId, name: &str) -> AttributeValue {
+    let (cu, id) = dwarf_types[name];
+    if cu == from {
+        AttributeValue::UnitRef(id)
+    } else {
+        AttributeValue::DebugInfoRef(Reference::Entry(cu, id))
+    }
+}
+
+// the same for things outside the types, whose types may be in the
+// supplementary file
+fn global_type(
+    sup: &DebugSup,
+    dwarf_types: &TypeIds,
+    from: UnitId,
+    name: &str,
+) -> Option<AttributeValue> {
+    match sup {
+        DebugSup::Reference(supplementary) => supplementary
+            .types
+            .get(name)
+            .map(|offset| AttributeValue::DebugInfoRefSup(*offset)),
+        _ => dwarf_types
+            .contains_key(name)
+            .then(|| type_ref(dwarf_types, from, name)),
+    }
+}
+
+fn collect(options: &Options) -> Result<TypeSet, DynErr> {
+    let mut type_set = TypeSet::default();
+    if let Some(preset) = &options.preset {
+        type_set.extend(presets::load(preset)?);
+    }
+    if let Some(headers) = &options.headers {
+        type_set.extend(headers::load(
+            headers,
+            options.symbols.as_deref(),
+            &options.target,
+        )?);
+    }
+    if let Some(pdb) = &options.pdb {
+        // MSVC's default bases
+        let base = match options.target.pointer_size {
+            8 => 0x140000000,
+            _ => 0x400000,
+        };
+        type_set.extend(pdb::load(pdb, options.image_base.unwrap_or(base))?);
+    }
+    type_set.extend(collect_export(
+        &options.types_dir,
+        options.format,
+        export_required(options),
+        options.legacy_export,
+        &options.defines,
+    )?);
+    for overlay in options.overlays.iter() {
+        type_set = overlay::Overlay::load(overlay)?.apply(type_set)?;
+    }
+    if let (true, Some(binary)) = (options.merge, &options.binary) {
+        merge::merge(&mut type_set, binary)?;
+    }
+    // a single unit's, --units could have C ones the types are used from
+    if options.language == "rust" && options.units.is_none() {
+        rust::primitives(&mut type_set);
+    }
+    Ok(type_set)
+}
+
+pub fn run() -> Err {
+    let options = parse_options()?;
+    if let Some(bndb) = &options.bndb {
+        bndb::extract(bndb, &options.types_dir)?;
+    }
+
+    match &options.command {
+        Command::Shell => return shell::run(&options),
+        Command::Tui(file) => return tui::run(&options, file.as_deref()),
+        Command::Dump(dir, file) => {
+            let type_set = match file {
+                Some(file) => readback::Dies::from_elf(file)?.to_type_set(),
+                None => collect(&options)?,
+            };
+            return type_set.save(dir);
+        }
+        Command::ExportHeaders(header, file) => {
+            return export_headers::run(&options, header, file.as_deref())
+        }
+        Command::ExportRust(bindings, file) => {
+            return export_rust::run(&options, bindings, file.as_deref())
+        }
+        Command::ExportGhidra(script, file) => {
+            return export_ghidra::run(&options, script, file.as_deref())
+        }
+        Command::Extract(file, dir, types_only) => {
+            let dir = dir.as_ref().unwrap_or(&options.types_dir);
+            return extract::run(file, dir, *types_only);
+        }
+        Command::Explain(address) => return explain::run(&options, *address),
+        Command::Coverage => {
+            let binary = options.binary.as_ref().ok_or("coverage needs --binary")?;
+            return coverage::run(&options, binary);
+        }
+        Command::Inspect(core, var) => return inspect::run(&options, core, var),
+        Command::Watch => return watch::run(&options, &options.output),
+        Command::Generate => {}
+    }
+    if let Some(manifest) = &options.project {
+        if options.inject.is_some() || options.debuglink.is_some() {
+            return Err("--inject and --debuglink don't apply to --project".into());
+        }
+        if options.debuginfod_layout.is_some() {
+            return Err("--debuginfod-layout doesn't apply to --project".into());
+        }
+        if options.cache.is_some() || options.report.is_some() {
+            return Err("--cache and --report don't apply to --project".into());
+        }
+        return project::run(&options, manifest);
+    }
+    if options.debug_sup.is_some() {
+        return Err("--debug-sup only applies to --project".into());
+    }
+
+    let key = match (&options.cache, options.check) {
+        (Some(_), false) => Some(cache::key(&options)?),
+        _ => None,
+    };
+    let restored = match (&options.cache, &key) {
+        (Some(dir), Some(key)) => cache::restore(dir, key, &options, &options.output)?,
+        _ => false,
+    };
+    if !restored {
+        let mut type_set = collect(&options)?;
+        let (type_mapping, global_variables) = type_set.split_types();
+        generate(
+            &options,
+            &type_mapping,
+            &global_variables,
+            &type_set.constants,
+            &type_set.subprograms,
+            &options.output,
+            DebugSup::None,
+        )?;
+        if options.check {
+            return Ok(());
+        }
+        if let (Some(dir), Some(key)) = (&options.cache, &key) {
+            cache::store(dir, key, &options, &options.output)?;
+        }
+    }
+    finish(&options, &options.output)
+}
+
+// what's done with a generated debug file, cached or not
+fn finish(options: &Options, output: &Path) -> Err {
+    if let Some(binary) = &options.inject {
+        inject::inject(
+            binary,
+            output,
+            &options.dynsym,
+            options.inject_align.unwrap_or(1),
+            options.inject_load,
+        )?;
+    }
+    if let Some(binary) = &options.debuglink {
+        inject::debuglink(binary, output)?;
+    }
+    if let Some(root) = &options.debuginfod_layout {
+        let binary = options.binary.as_ref().or(options.debuglink.as_ref());
+        build_id::publish(root, output, binary.map(|binary| binary.as_path()))?;
+    }
+    Ok(())
+}
+
+struct Supplementary {
+    // path recorded in each referencing file's .debug_sup
+    filename: String,
+    types: HashMap<String, DebugInfoOffset>,
+}
+
+enum DebugSup<'a> {
+    // a self-contained debug file
+    None,
+    // the supplementary file the shared types get factored into
+    Supplementary,
+    // a debug file whose types live in a supplementary file
+    Reference(&'a Supplementary),
+}
+
+// DWARF 5 section 7.3.6
+// writes the units directly, which hands back the offset of every DIE
+fn write_sections(
+    dwarf: &mut Dwarf,
+    endian: gimli::RunTimeEndian,
+) -> Result<(Sections<EndianVec<gimli::RunTimeEndian>>, DebugInfoOffsets), DynErr> {
+    let mut sections = Sections::new(EndianVec::new(endian));
+    let line_strings = dwarf.line_strings.write(&mut sections.debug_line_str)?;
+    let strings = dwarf.strings.write(&mut sections.debug_str)?;
+    let offsets = dwarf.units.write(&mut sections, &line_strings, &strings)?;
+    Ok((sections, offsets))
+}
+
+fn debug_sup(is_supplementary: bool, filename: &str) -> Vec<u8> {
+    let mut raw = Vec::new();
+    raw.extend_from_slice(&5u16.to_le_bytes());
+    raw.push(is_supplementary as u8);
+    raw.extend_from_slice(filename.as_bytes());
+    raw.push(0);
+    // no checksum
+    raw.push(0);
+    raw
+}
+
+// what generate's stages share once the types' DIEs are in, and the DWARF
+// and ELF they add to. each stage is a method in its own module:
+// variables.rs, subprograms.rs, roots.rs and sections.rs
+struct Generator<'a> {
+    options: &'a Options,
+    mappings: &'a HashMap<String, BinjaType>,
+    sup: &'a DebugSup<'a>,
+    encoding: gimli::Encoding,
+    specs: &'a [units::UnitSpec],
+    unit_ids: Vec<UnitId>,
+    placement: units::Placement<'a>,
+    spaces: &'a HashMap<String, spaces::Space>,
+    dwarf_types: TypeIds,
+    dwarf: Dwarf,
+    namespaces: Namespaces,
+    // what has a source file, which it names once it has an index
+    declared: Vec<Vec<(UnitEntryId, Declaration)>>,
+    writer: ElfWriter,
+}
+
+impl Generator<'_> {
+    fn index(&self, cu: UnitId) -> usize {
+        self.unit_ids.iter().position(|id| *id == cu).unwrap()
+    }
+}
+
+// returns the .debug_info offset of every emitted type
+fn generate(
+    options: &Options,
+    type_mapping: &HashMap<String, BinjaType>,
+    global_variables: &HashMap<u64, GlobalVariable>,
+    constants: &HashMap<String, Constant>,
+    subprograms: &HashMap<u64, Subprogram>,
+    output: &Path,
+    sup: DebugSup,
+) -> Result<HashMap<String, DebugInfoOffset>, DynErr> {
+    // everything after only sees the moved addresses
+    let delta = rebase::delta(options);
+    let rebased;
+    let (type_mapping, global_variables, subprograms) = match delta {
+        0 => (type_mapping, global_variables, subprograms),
+        _ => {
+            rebased = (
+                rebase::types(type_mapping, delta)?,
+                rebase::variables(global_variables, delta)?,
+                rebase::subprograms(subprograms, delta)?,
+            );
+            (&rebased.0, &rebased.1, &rebased.2)
+        }
+    };
+    let spaces = match &options.spaces {
+        Some(path) => spaces::load(path)?,
+        None => HashMap::new(),
+    };
+    let segmented;
+    let (global_variables, subprograms, moves) =
+        match spaces::segment(&spaces, global_variables, subprograms)? {
+            Some(moved) => {
+                segmented = moved;
+                (
+                    &segmented.variables,
+                    &segmented.subprograms,
+                    segmented.moves.as_slice(),
+                )
+            }
+            None => (global_variables, subprograms, &[][..]),
+        };
+    // before anything else can fail, so they're all listed
+    let issues = match options.check {
+        true => check::issues(global_variables, subprograms),
+        false => Vec::new(),
+    };
+    for issue in issues.iter() {
+        error!("check: {}", issue);
+    }
+    // prototypes are looked up even when the types themselves aren't emitted
+    let prototypes = type_mapping;
+    // types of a referencing file are all in the supplementary one
+    let empty = HashMap::new();
+    let type_mapping = match sup {
+        DebugSup::Reference(_) => &empty,
+        _ => type_mapping,
+    };
+
+    let (substituted, unresolved) = prepare::resolve(
+        options,
+        type_mapping,
+        global_variables,
+        constants,
+        subprograms,
+        &sup,
+    )?;
+    let type_mapping = substituted.as_ref().unwrap_or(type_mapping);
+    // the nesting is for later
+    let nested = prepare::layouts(options, type_mapping)?;
+    let padded = prepare::pad(options, type_mapping)?;
+    let type_mapping = padded.as_ref().unwrap_or(type_mapping);
+
+    let mut writer = ElfWriter::new(options.target);
+    // a project's binaries would all need their own
+    let binary = options
+        .binary
+        .as_ref()
+        .or(options.inject.as_ref())
+        .or(options.debuglink.as_ref());
+    if let (Some(binary), None, false) = (binary, &options.project, options.coff) {
+        writer.mirror(binary)?;
+    }
+    if options.coff {
+        // SHF_COMPRESSED is ELF's, gdb reads .zdebug_* from COFF as well
+        if options.compression == Some(compress::Style::Zlib) {
+            return Err("--coff takes --compress-debug-sections=zlib-gnu only".into());
+        }
+        writer.coff();
+    }
+    if let Some(style) = options.compression {
+        writer.compress(style);
+    }
+
+    // Choose the encoding parameters.
+    let encoding = gimli::Encoding {
+        format: match (options.compat, options.target.class) {
+            (Compat::Gdb, ELFCLASS64) => gimli::Format::Dwarf64,
+            _ => gimli::Format::Dwarf32,
+        },
+        // references into a supplementary file need DWARF 5 forms
+        version: match sup {
+            DebugSup::None => 4,
+            _ => 5,
+        },
+        address_size: options.target.address_size,
+    };
+    let specs = match &options.units {
+        Some(path) => units::load(path, &options.language)?,
+        None => vec![units::default(&options.language)?],
+    };
+    // gdb only looks up the units of a .dwo by their id, references between
+    // them and into a supplementary file don't resolve there
+    if options.split_dwarf && (specs.len() > 1 || !matches!(sup, DebugSup::None)) {
+        return Err("--split-dwarf takes a single unit and no --debug-sup".into());
+    }
+    // type units are DWARF 4's, and a .dwo would need its own .debug_types.dwo
+    if options.type_units && (options.split_dwarf || !matches!(sup, DebugSup::None)) {
+        return Err("--type-units takes no --split-dwarf or --debug-sup".into());
+    }
+    // what's left are the forms ghidra's DWARF 4 parser reads
+    if options.compat == Compat::Ghidra
+        && (options.type_units || options.split_dwarf || !matches!(sup, DebugSup::None))
+    {
+        return Err("--compat ghidra takes no --type-units, --split-dwarf or --debug-sup".into());
+    }
+    let trampolines = match &options.trampolines {
+        Some(path) => rebase::trampolines(trampolines::load(path)?, delta)?,
+        None => HashMap::new(),
+    };
+    let frames = match &options.frames {
+        Some(path) => spaces::follow(rebase::keyed(frames::load(path)?, delta)?, moves),
+        None => HashMap::new(),
+    };
+    let mut lines = match &options.lines {
+        Some(path) => spaces::follow(rebase::keyed(lines::load(path)?, delta)?, moves),
+        None => HashMap::new(),
+    };
+    let (source_dir, sources) = match &options.sources {
+        Some(dir) => {
+            let (source_dir, sources) = lines::sources(dir, output, subprograms, &mut lines)?;
+            (Some(source_dir), sources)
+        }
+        None => (None, HashMap::new()),
+    };
+    // Create a container for the compilation units.
+    let mut dwarf = Dwarf::new();
+    let unit_ids: Vec<UnitId> = specs
+        .iter()
+        .map(|_| dwarf.units.add(Unit::new(encoding, LineProgram::none())))
+        .collect();
+    let rust: Vec<UnitId> = (specs.iter().zip(unit_ids.iter()))
+        .filter(|(spec, _)| spec.language == "rust")
+        .map(|(_, cu)| *cu)
+        .collect();
+    let placement = units::place(&specs, &unit_ids, type_mapping)?;
+    let mut dwarf_types: TypeIds = HashMap::new();
+    let namespaces = visit(
+        &mut dwarf,
+        type_mapping,
+        &placement.types,
+        &nested,
+        unit_ids[0],
+        &mut dwarf_types,
+    );
+
+    type_dies::emit(
+        &mut dwarf,
+        &unit_ids,
+        type_mapping,
+        &mut dwarf_types,
+        &rust,
+        options,
+        encoding,
+    )?;
+
+    let mut declared = vec![Vec::new(); unit_ids.len()];
+    for (name, binja_type) in type_mapping.iter() {
+        if let (Some(decl), Some((cu, id))) = (binja_type.decl(), dwarf_types.get(name)) {
+            let index = unit_ids.iter().position(|unit| unit == cu).unwrap();
+            declared[index].push((*id, decl.clone()));
+        }
+    }
+
+    let mut generator = Generator {
+        options,
+        mappings: type_mapping,
+        sup: &sup,
+        encoding,
+        specs: &specs,
+        unit_ids,
+        placement,
+        spaces: &spaces,
+        dwarf_types,
+        dwarf,
+        namespaces,
+        declared,
+        writer,
+    };
+    let data = generator.variables(global_variables)?;
+    generator.vtables()?;
+    generator.constants(constants)?;
+    let mut functions = generator.subprograms(prototypes, subprograms, &lines, &sources)?;
+    generator.trampolines(trampolines, &mut functions)?;
+    if let Some((address, line)) = lines::stray(&lines, &functions.concat()) {
+        return Err(format!(
+            "line {}:{} at {:#x} isn't inside any subprogram",
+            line.file, line.line, address
+        )
+        .into());
+    }
+    generator.roots(&functions, source_dir.as_ref(), &lines)?;
+    let type_offsets = generator.sections(output, functions, data, global_variables, &frames)?;
+    generator.scripts()?;
+    generator.notes(binary)?;
+
+    if options.check {
+        if !issues.is_empty() {
+            return Err(format!("{} issue(s) found by --check", issues.len()).into());
+        }
+        info!("checked, {} would have been written", output.display());
+        return Ok(type_offsets);
+    }
+    let report = report::Report::new(
+        output,
+        type_mapping,
+        (global_variables.len(), subprograms.len(), unresolved),
+        generator.writer.contents(),
+    );
+    generator.writer.write_to(output)?;
+    report.log();
+    if let Some(path) = &options.report {
+        report.save(path)?;
+    }
+
+    Ok(type_offsets)
+}
@@ -0,0 +1,201 @@
+// `--lines lines.json` maps code addresses to source lines, like the lines of
+// the decompiler's pseudo-C, so `list`, `break main.c:12` and `next` work
+// against the exported functions.
+//
+// {
+//     "4198448": {"file": "main.c", "line": 12, "column": 5},
+//     "4198456": {"file": "main.c", "line": 13, "is_statement": false}
+// }
+//
+// keyed by address like variables.json. every address has to lie inside one
+// of the subprograms, each of which becomes a sequence in the line program of
+// the unit it's placed in.
+//
+// `--sources sources/` adds the decompilation itself: `sources/<function>.c`
+// are copied next to the output into `<output>.src`, which becomes the comp_dir
+// of every unit, and the functions get a DW_AT_decl_file naming theirs. lines
+// then refer to those files, `{"file": "main.c", ...}`, and a function
+// without any starts at line 1 of its own. the comp_dir is only the
+// directory's name, gdb looks for it below the directory it runs in, so
+// running it next to the output (or `directory` in gdb) finds the sources
+// and generating elsewhere doesn't change the bytes.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use gimli::write::{Address, DirectoryId, FileId, LineProgram, LineString};
+use gimli::{Encoding, LineEncoding};
+use serde::Deserialize;
+
+use crate::{DynErr, HashMap, Subprogram};
+
+fn default_statement() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Line {
+    pub file: String,
+    pub line: u64,
+    // 0 is the start of the line
+    #[serde(default)]
+    pub column: u64,
+    #[serde(default = "default_statement")]
+    pub is_statement: bool,
+}
+
+pub fn load(path: &Path) -> Result<HashMap<u64, Line>, DynErr> {
+    let lines: HashMap<u64, Line> = match fs::read_to_string(path) {
+        Ok(json) => {
+            serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))?
+        }
+        Err(e) => return Err(format!("{}: {}", path.display(), e).into()),
+    };
+    for (address, line) in lines.iter() {
+        if line.line == 0 || line.file.is_empty() {
+            return Err(format!(
+                "{}: the line at {:#x} needs a file and a line number from 1",
+                path.display(),
+                address
+            )
+            .into());
+        }
+    }
+    Ok(lines)
+}
+
+// (file, first line) of each function with a source, by its address
+pub type Sources = HashMap<u64, (String, u64)>;
+
+// copies the sources of the subprograms that have one, returns the directory
+// they're in along with them
+pub fn sources(
+    dir: &Path,
+    output: &Path,
+    subprograms: &HashMap<u64, Subprogram>,
+    lines: &mut HashMap<u64, Line>,
+) -> Result<(String, Sources), DynErr> {
+    let target = PathBuf::from(format!("{}.src", output.display()));
+    fs::create_dir_all(&target).map_err(|e| format!("{}: {}", target.display(), e))?;
+
+    let mut sources = HashMap::new();
+    for (address, subprogram) in subprograms.iter() {
+        let file = format!("{}.c", subprogram.name);
+        let path = dir.join(&file);
+        let text = match fs::read(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("{}: {}", path.display(), e).into()),
+        };
+        fs::write(target.join(&file), text)?;
+
+        let range = *address..address + subprogram.size;
+        if lines.range(range.clone()).next().is_none() {
+            _ = lines.insert(
+                *address,
+                Line {
+                    file: file.clone(),
+                    line: 1,
+                    column: 0,
+                    is_statement: true,
+                },
+            );
+        }
+        let first = lines
+            .range(range)
+            .find(|(_, line)| line.file == file)
+            .map_or(1, |(_, line)| line.line);
+        _ = sources.insert(*address, (file, first));
+    }
+    if sources.is_empty() {
+        return Err(format!("{}: no <function>.c for any subprogram", dir.display()).into());
+    }
+    let comp_dir =
+        (target.file_name()).map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    Ok((comp_dir, sources))
+}
+
+// the first line outside all of the (start, size) ranges
+pub fn stray<'a>(
+    lines: &'a HashMap<u64, Line>,
+    functions: &[(u64, u64)],
+) -> Option<(u64, &'a Line)> {
+    lines
+        .iter()
+        .find(|(address, _)| {
+            !functions
+                .iter()
+                .any(|(start, size)| *start <= **address && **address - start < *size)
+        })
+        .map(|(address, line)| (*address, line))
+}
+
+// the line program for a unit holding the given functions, None if none of
+// them has lines
+pub fn program(
+    encoding: Encoding,
+    comp_dir: &str,
+    comp_file: &str,
+    lines: &HashMap<u64, Line>,
+    functions: &[(u64, u64)],
+) -> Option<LineProgram> {
+    let mut functions: Vec<(u64, u64)> = functions
+        .iter()
+        .filter(|(start, size)| lines.range(*start..start + size).next().is_some())
+        .copied()
+        .collect();
+    if functions.is_empty() {
+        return None;
+    }
+    functions.sort();
+
+    // DWARF 4 line headers only allow inline strings
+    let mut program = LineProgram::new(
+        encoding,
+        LineEncoding::default(),
+        LineString::String(comp_dir.as_bytes().to_vec()),
+        LineString::String(comp_file.as_bytes().to_vec()),
+        None,
+    );
+    let mut directories: HashMap<String, DirectoryId> = HashMap::new();
+    let mut files: HashMap<&str, FileId> = HashMap::new();
+    for (start, size) in functions {
+        program.begin_sequence(Some(Address::Constant(start)));
+        for (address, line) in lines.range(start..start + size) {
+            let file = match files.get(line.file.as_str()) {
+                Some(file) => *file,
+                None => {
+                    // relative directories are below the unit's comp_dir
+                    let path = Path::new(&line.file);
+                    let directory = match path.parent().and_then(|parent| parent.to_str()) {
+                        None | Some("") => program.default_directory(),
+                        Some(parent) => {
+                            *directories.entry(parent.to_string()).or_insert_with(|| {
+                                program
+                                    .add_directory(LineString::String(parent.as_bytes().to_vec()))
+                            })
+                        }
+                    };
+                    let name = path
+                        .file_name()
+                        .map(|name| name.as_encoded_bytes().to_vec())
+                        .unwrap_or_default();
+                    let file = program.add_file(LineString::String(name), directory, None);
+                    _ = files.insert(&line.file, file);
+                    file
+                }
+            };
+            let row = program.row();
+            row.address_offset = address - start;
+            row.file = file;
+            row.line = line.line;
+            row.column = line.column;
+            row.is_statement = line.is_statement;
+            program.generate_row();
+        }
+        program.end_sequence(size);
+    }
+    Some(program)
+}
@@ -0,0 +1,168 @@
+// `--lldb-summaries summaries.py` is --gdb-printers for lldb: the structs
+// with a "printer" get a synthetic child provider (lists and arrays) or a
+// summary (strings) showing the same as the gdb ones, for
+// `command script import summaries.py`. see printers.rs for the hints.
+
+use crate::gdb_scripts::quote;
+use crate::printers::hinted;
+use crate::{BinjaType, DynErr, HashMap, Printer};
+
+const PRELUDE: &str = r#"# lldb formatters teemo generated from the export's "printer" hints,
+# `command script import` this file
+import lldb
+
+# how many list nodes, array items or string bytes get shown
+LIMIT = 200
+
+
+class ListProvider:
+    next = value = None
+
+    def __init__(self, valobj, internal_dict):
+        self.valobj = valobj
+        self.nodes = []
+
+    def update(self):
+        self.nodes = []
+        node, seen = self.valobj.AddressOf(), set()
+        while node.GetValueAsUnsigned() and node.GetValueAsUnsigned() not in seen and len(seen) < LIMIT:
+            seen.add(node.GetValueAsUnsigned())
+            item = node.Dereference()
+            self.nodes.append(item.GetChildMemberWithName(self.value) if self.value else item)
+            node = item.GetChildMemberWithName(self.next)
+        return False
+
+    def num_children(self):
+        return len(self.nodes)
+
+    def get_child_index(self, name):
+        try:
+            return int(name.strip("[]"))
+        except ValueError:
+            return -1
+
+    def get_child_at_index(self, index):
+        item = self.nodes[index]
+        return item.CreateValueFromData("[%d]" % index, item.GetData(), item.GetType())
+
+    def has_children(self):
+        return True
+
+
+# where `data` keeps its items, pointed to or in itself
+def items(data):
+    if data.GetType().IsPointerType():
+        return data.GetValueAsUnsigned(), data.GetType().GetPointeeType()
+    return data.GetLoadAddress(), data.GetType().GetArrayElementType()
+
+
+class ArrayProvider:
+    data = length = None
+
+    def __init__(self, valobj, internal_dict):
+        self.valobj = valobj
+        self.count = 0
+
+    def update(self):
+        self.address, self.item = items(self.valobj.GetChildMemberWithName(self.data))
+        self.count = min(self.valobj.GetChildMemberWithName(self.length).GetValueAsUnsigned(), LIMIT)
+        return False
+
+    def num_children(self):
+        return self.count
+
+    def get_child_index(self, name):
+        try:
+            return int(name.strip("[]"))
+        except ValueError:
+            return -1
+
+    def get_child_at_index(self, index):
+        address = self.address + index * self.item.GetByteSize()
+        return self.valobj.CreateValueFromAddress("[%d]" % index, address, self.item)
+
+    def has_children(self):
+        return True
+
+
+def string(valobj, data, length):
+    address, _ = items(valobj.GetChildMemberWithName(data))
+    count = min(valobj.GetChildMemberWithName(length).GetValueAsUnsigned(), LIMIT)
+    if count == 0:
+        return '""'
+    error = lldb.SBError()
+    text = valobj.GetProcess().ReadMemory(address, count, error)
+    if error.Fail():
+        return "<%s>" % error.GetCString()
+    return repr(text.decode("utf-8", "replace"))
+"#;
+
+pub fn script(types: &HashMap<String, BinjaType>) -> Result<String, DynErr> {
+    let mut script = String::from(PRELUDE);
+    let mut commands = Vec::new();
+    for (index, (name, printer)) in hinted(types)?.into_iter().enumerate() {
+        // in lldb's single quotes, and past python's %
+        let pattern = format!("'^{}$'", regex(name)).replace('%', "%%");
+        match printer {
+            Printer::List { next, value } => {
+                script.push_str(&format!(
+                    "\n\nclass Formatter{}(ListProvider):\n    next, value = {}, {}\n",
+                    index,
+                    quote(next),
+                    value.as_deref().map_or(String::from("None"), quote)
+                ));
+                commands.push(format!(
+                    "type synthetic add -l %s.Formatter{} -x {}",
+                    index, pattern
+                ));
+            }
+            Printer::Array { data, length } => {
+                script.push_str(&format!(
+                    "\n\nclass Formatter{}(ArrayProvider):\n    data, length = {}, {}\n",
+                    index,
+                    quote(data),
+                    quote(length)
+                ));
+                commands.push(format!(
+                    "type synthetic add -l %s.Formatter{} -x {}",
+                    index, pattern
+                ));
+            }
+            Printer::String { data, length } => {
+                script.push_str(&format!(
+                    "\n\ndef formatter{}(valobj, internal_dict):\n    return string(valobj, {}, {})\n",
+                    index,
+                    quote(data),
+                    quote(length)
+                ));
+                commands.push(format!(
+                    "type summary add -F %s.formatter{} -x {}",
+                    index, pattern
+                ));
+            }
+        }
+    }
+    script.push_str("\n\ndef __lldb_init_module(debugger, internal_dict):\n");
+    for command in commands.iter() {
+        script.push_str(&format!(
+            "    debugger.HandleCommand({} % __name__)\n",
+            quote(command)
+        ));
+    }
+    if commands.is_empty() {
+        script.push_str("    pass\n");
+    }
+    Ok(script)
+}
+
+// `name` matched literally
+fn regex(name: &str) -> String {
+    let mut escaped = String::new();
+    for c in name.chars() {
+        if "\\.^$|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
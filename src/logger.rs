@@ -0,0 +1,41 @@
+// what a run says while it works, through the log crate: what it wrote and
+// anything odd it worked around by default, every section and type too with
+// `-v`, and only the errors with `-q`. warnings and errors go to stderr and
+// the rest to stdout. the output of `explain`, `coverage` and the shell is
+// theirs and isn't affected.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+struct Logger;
+
+impl Log for Logger {
+    // goblin logs through the same crate
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level() && metadata.target().starts_with("teemo")
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            Level::Error | Level::Warn => eprintln!("{}", record.args()),
+            _ => println!("{}", record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Logger = Logger;
+
+pub fn init(verbose: u8, quiet: bool) {
+    // set once per process, a later run only changes the level
+    _ = log::set_logger(&LOGGER);
+    log::set_max_level(match (quiet, verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Info,
+        (false, 1) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    });
+}
@@ -0,0 +1,90 @@
+// `--merge` with `--binary` leaves out what the binary's own DWARF describes
+// already, for binaries that come with some (the statically linked libc's,
+// say): variables and functions at addresses it has DIEs for, and the types
+// it defines the same way, unless something that's still written refers to
+// them. the binary's DWARF is read back like `dump` reads it, so types are
+// the same when they read back the same under the same name, wherever they
+// say they're declared.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use log::info;
+
+use crate::readback::Dies;
+use crate::{BinjaType, Err, TypeSet};
+
+pub fn merge(type_set: &mut TypeSet, binary: &Path) -> Err {
+    let dies = Dies::from_elf(binary)?;
+    let functions: BTreeSet<u64> = (dies.roots.iter())
+        .map(|offset| &dies.dies[offset])
+        .filter(|die| die.tag == gimli::DW_TAG_subprogram)
+        .filter_map(|die| die.address)
+        .collect();
+    let existing = dies.to_type_set();
+
+    let (variables, subprograms) = (type_set.variables.len(), type_set.subprograms.len());
+    type_set
+        .variables
+        .retain(|address, _| !existing.variables.contains_key(address));
+    type_set
+        .subprograms
+        .retain(|address, _| !functions.contains(address));
+    let variables = variables - type_set.variables.len();
+    let subprograms = subprograms - type_set.subprograms.len();
+
+    let (mut types, kept) = type_set.split_types();
+    let (theirs, _) = existing.split();
+    let known: BTreeSet<String> = (types.iter())
+        .filter(|(name, ours)| {
+            theirs.get(*name).is_some_and(|theirs| {
+                let undeclared = |binja_type: &BinjaType| {
+                    let mut binja_type = binja_type.clone();
+                    if let Some(decl) = binja_type.decl_mut() {
+                        *decl = None;
+                    }
+                    serde_json::to_value(binja_type).ok()
+                };
+                undeclared(theirs) == undeclared(ours)
+            })
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    // everything new, and what it uses
+    let mut pending: Vec<&String> = (types.keys())
+        .filter(|name| !known.contains(*name))
+        .chain(kept.values().map(|variable| &variable.typename))
+        .chain(
+            type_set
+                .constants
+                .values()
+                .map(|constant| &constant.typename),
+        )
+        .chain(type_set.subprograms.values().flat_map(|subprogram| {
+            std::iter::once(&subprogram.typename)
+                .chain(subprogram.locals.iter().map(|local| &local.typename))
+        }))
+        .collect();
+    let mut needed = BTreeSet::new();
+    while let Some(name) = pending.pop() {
+        if !needed.insert(name.clone()) {
+            continue;
+        }
+        if let Some(binja_type) = types.get(name) {
+            pending.extend(binja_type.references());
+        }
+    }
+
+    let before = types.len();
+    types.retain(|name, _| !known.contains(name) || needed.contains(name));
+    info!(
+        "merge: {} types, {} variables and {} functions already in {}",
+        before - types.len(),
+        variables,
+        subprograms,
+        binary.display()
+    );
+    type_set.join_types(types, kept);
+    Ok(())
+}
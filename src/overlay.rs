@@ -0,0 +1,225 @@
+// `--overlay fixes.json`: local corrections applied on top of the export, so
+// they survive the next re-export instead of living in generated files.
+//
+// {
+//     "replace": {"structs": {"list": {"size": 16, "anon": false, "fields": [...]}}},
+//     "patch": {
+//         "node": {"size": 24, "fields": {"value": {"offset": 16, "typename": "int64_t"}}},
+//         "Thing": {"add": [[12, "flags", "uint32_t"]], "fields": {"d": {"remove": true}}}
+//     },
+//     "variables": {"16448": {"typename": "node", "name": "list_head"}}
+// }
+//
+// `replace` holds whole definitions in the export's own format and adds or
+// replaces types wholesale. `patch` only touches the attributes it names, and
+// naming a type, field or variable that doesn't exist is an error, so a fix
+// that no longer matches the export stands out rather than quietly dropping.
+// overlays apply in the order given. in project mode new variables have no
+// binary to go to, so `replace` can only hold types there.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::layout::size_of;
+use crate::{BinjaType, Class, DynErr, Endianity, Err, Field, GlobalVariable, HashMap, TypeSet};
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FieldPatch {
+    offset: Option<u64>,
+    name: Option<String>,
+    typename: Option<String>,
+    endianity: Option<Endianity>,
+    remove: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct Patch {
+    size: Option<u64>,
+    signed: Option<bool>,
+    target: Option<String>,
+    count: Option<u64>,
+    fields: HashMap<String, FieldPatch>,
+    add: Vec<Field>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct VariablePatch {
+    name: Option<String>,
+    typename: Option<String>,
+    size: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Overlay {
+    replace: TypeSet,
+    patch: HashMap<String, Patch>,
+    variables: HashMap<u64, VariablePatch>,
+}
+
+fn unsupported(name: &str, attribute: &str) -> DynErr {
+    format!("overlay: `{}` has no {} to patch", name, attribute).into()
+}
+
+impl Overlay {
+    pub fn load(path: &Path) -> Result<Overlay, DynErr> {
+        match fs::read_to_string(path) {
+            Ok(json) => {
+                serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e).into())
+            }
+            Err(e) => Err(format!("{}: {}", path.display(), e).into()),
+        }
+    }
+
+    pub fn apply_types(&self, types: &mut HashMap<String, BinjaType>) -> Err {
+        let (replacements, _) = self.replace.clone().split();
+        types.extend(replacements);
+
+        for (name, patch) in self.patch.iter() {
+            let binja_type = types
+                .get_mut(name)
+                .ok_or_else(|| format!("overlay: no type named `{}`", name))?;
+
+            if let Some(size) = patch.size {
+                match binja_type {
+                    BinjaType::Structure(s)
+                    | BinjaType::Union(s)
+                    | BinjaType::Class(Class { layout: s, .. }) => s.size = size,
+                    BinjaType::Integer(i) => i.size = size,
+                    BinjaType::Enum(e) => e.size = size,
+                    BinjaType::Pointer(p) => p.size = size,
+                    _ => return Err(unsupported(name, "size")),
+                }
+            }
+            if let Some(signed) = patch.signed {
+                match binja_type {
+                    BinjaType::Integer(i) => i.signed = signed,
+                    BinjaType::Enum(e) => e.signed = signed,
+                    _ => return Err(unsupported(name, "signedness")),
+                }
+            }
+            if let Some(target) = &patch.target {
+                match binja_type {
+                    BinjaType::Pointer(p) => p.target = target.clone(),
+                    BinjaType::Typedef(t) => t.target = target.clone(),
+                    BinjaType::Qualified(q) => q.target = target.clone(),
+                    BinjaType::Array(a) => a.target = target.clone(),
+                    BinjaType::Vector(v) => v.target = target.clone(),
+                    _ => return Err(unsupported(name, "target")),
+                }
+            }
+            if let Some(count) = patch.count {
+                match binja_type {
+                    BinjaType::Array(a) => a.count = count,
+                    BinjaType::Vector(v) => v.count = count,
+                    _ => return Err(unsupported(name, "count")),
+                }
+            }
+
+            if patch.fields.is_empty() && patch.add.is_empty() {
+                continue;
+            }
+            let (BinjaType::Structure(s)
+            | BinjaType::Union(s)
+            | BinjaType::Class(Class { layout: s, .. })) = binja_type
+            else {
+                return Err(unsupported(name, "fields"));
+            };
+            for (field, fix) in patch.fields.iter() {
+                let index = s
+                    .fields
+                    .iter()
+                    .position(|f| f.name == *field)
+                    .ok_or_else(|| format!("overlay: `{}` has no field `{}`", name, field))?;
+                if fix.remove {
+                    s.fields.remove(index);
+                    continue;
+                }
+                let f = &mut s.fields[index];
+                if let Some(offset) = fix.offset {
+                    f.offset = offset;
+                }
+                if let Some(new) = &fix.name {
+                    f.name = new.clone();
+                }
+                if let Some(typename) = &fix.typename {
+                    f.typename = typename.clone();
+                }
+                if let Some(endianity) = fix.endianity {
+                    f.endianity = Some(endianity);
+                }
+            }
+            s.fields.extend(patch.add.iter().cloned());
+            // moved and added fields keep members in layout order
+            s.fields.sort_by_key(|f| f.offset);
+        }
+
+        Ok(())
+    }
+
+    // returns the addresses that were patched, in project mode each binary
+    // only has some of the variables
+    pub fn apply_variables(
+        &self,
+        types: &HashMap<String, BinjaType>,
+        variables: &mut HashMap<u64, GlobalVariable>,
+    ) -> Vec<u64> {
+        let mut patched = Vec::new();
+        for (address, patch) in self.variables.iter() {
+            let Some(variable) = variables.get_mut(address) else {
+                continue;
+            };
+            if let Some(name) = &patch.name {
+                variable.name = name.clone();
+            }
+            if let Some(typename) = &patch.typename {
+                variable.typename = typename.clone();
+                // retyping without a size takes the new type's
+                if patch.size.is_none() {
+                    variable.size = size_of(types, typename).unwrap_or(variable.size);
+                }
+            }
+            if let Some(size) = patch.size {
+                variable.size = size;
+            }
+            patched.push(*address);
+        }
+        patched
+    }
+
+    pub fn adds_variables(&self) -> bool {
+        !self.replace.variables.is_empty()
+            || !self.replace.constants.is_empty()
+            || !self.replace.subprograms.is_empty()
+    }
+
+    pub fn check_variables(&self, patched: &[u64]) -> Err {
+        match self
+            .variables
+            .keys()
+            .find(|address| !patched.contains(address))
+        {
+            Some(address) => Err(format!("overlay: no variable at {:#x}", address).into()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn apply(&self, mut type_set: TypeSet) -> Result<TypeSet, DynErr> {
+        let (mut types, mut variables) = type_set.split_types();
+        self.apply_types(&mut types)?;
+        variables.extend(self.replace.variables.clone());
+        let patched = self.apply_variables(&types, &mut variables);
+        self.check_variables(&patched)?;
+        type_set.join_types(types, variables);
+        type_set.constants.extend(self.replace.constants.clone());
+        type_set
+            .subprograms
+            .extend(self.replace.subprograms.clone());
+        Ok(type_set)
+    }
+}
@@ -0,0 +1,127 @@
+// `--pad-holes` spells out the bytes no member of a struct covers as `char
+// __pad_N[len]` members, N the offset in hex the way export-headers names
+// them, so debuggers that work a struct's size out from its members (some IDE
+// ones do) agree with the export's instead of relying on DWARF's holes:
+//
+//   struct node { node *next; uint32_t key; char __pad_c[4]; }
+//
+// the bytes a bitfield touches count as covered, and so do a class's bases.
+// a union whose members are all smaller than it gets one covering the whole
+// of it. the layout warnings are about the export as given, before this.
+
+use log::debug;
+
+use crate::validate::Validator;
+use crate::{Array, BinjaType, DynErr, Encoding, Field, HashMap, Integer, Structure};
+
+fn pad(layout: &mut Structure, offset: u64, size: u64, pads: &mut Vec<(u64, u64)>) {
+    let mut name = format!("__pad_{:x}", offset);
+    while layout.fields.iter().any(|field| field.name == name) {
+        name.push('_');
+    }
+    layout.fields.push(Field {
+        offset,
+        name,
+        typename: format!("char[{}]", size),
+        endianity: None,
+        bit_offset: None,
+        bit_size: None,
+        access: None,
+    });
+    pads.push((offset, size));
+}
+
+// the types with the holes padded, and how many there were
+pub fn pad_holes(
+    types: &HashMap<String, BinjaType>,
+    pointer_size: u64,
+) -> Result<(HashMap<String, BinjaType>, usize), DynErr> {
+    let validator = Validator::new(types, pointer_size);
+    match types.get("char") {
+        Some(_) if validator.size("char") != Some(1) => {
+            return Err("`char` isn't one byte, --pad-holes pads with it".into())
+        }
+        _ => {}
+    }
+
+    let mut padded = types.clone();
+    let mut pads = Vec::new();
+    for (name, binja_type) in padded.iter_mut() {
+        let (layout, bases, union) = match binja_type {
+            BinjaType::Structure(layout) => (layout, Vec::new(), false),
+            BinjaType::Union(layout) => (layout, Vec::new(), true),
+            BinjaType::Class(class) => {
+                let bases = (class.bases.iter())
+                    .map(|base| (base.offset, validator.size(&base.typename).unwrap_or(0)))
+                    .collect();
+                (&mut class.layout, bases, false)
+            }
+            _ => continue,
+        };
+        if layout.declaration {
+            continue;
+        }
+        // the bytes each member touches, from where to where
+        let mut covered: Vec<(u64, u64)> = (layout.fields.iter())
+            .filter_map(|field| {
+                let start = field.offset * 8 + field.bit_offset.unwrap_or(0);
+                let bits = match field.bit_size {
+                    Some(bits) => bits,
+                    None => validator.size(&field.typename)? * 8,
+                };
+                Some((start / 8, (start + bits).div_ceil(8)))
+            })
+            .chain((bases.into_iter()).map(|(offset, size)| (offset, offset + size)))
+            .collect();
+        covered.sort();
+
+        let before = pads.len();
+        if union {
+            let reach = covered.iter().map(|(_, end)| *end).max().unwrap_or(0);
+            if reach < layout.size {
+                pad(layout, 0, layout.size, &mut pads);
+            }
+        } else {
+            let mut cursor = 0;
+            for (start, end) in covered {
+                if start > cursor {
+                    pad(layout, cursor, start - cursor, &mut pads);
+                }
+                cursor = cursor.max(end);
+            }
+            if layout.size > cursor {
+                pad(layout, cursor, layout.size - cursor, &mut pads);
+            }
+        }
+        if pads.len() > before {
+            layout
+                .fields
+                .sort_by_key(|field| field.offset * 8 + field.bit_offset.unwrap_or(0));
+            debug!("padded {} hole(s) in `{}`", pads.len() - before, name);
+        }
+    }
+
+    if !pads.is_empty() {
+        _ = padded
+            .entry(String::from("char"))
+            .or_insert(BinjaType::Integer(Integer {
+                size: 1,
+                signed: true,
+                endianity: None,
+                encoding: Some(Encoding::Char),
+                bits: None,
+            }));
+    }
+    for (_, size) in pads.iter() {
+        _ = padded
+            .entry(format!("char[{}]", size))
+            .or_insert(BinjaType::Array(Array {
+                count: *size,
+                target: String::from("char"),
+                dimensions: Vec::new(),
+                string: false,
+                dynamic: None,
+            }));
+    }
+    Ok((padded, pads.len()))
+}
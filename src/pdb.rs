@@ -0,0 +1,1155 @@
+// `--pdb app.pdb` reads the types and symbols of a windows program database
+// instead of a decompiler export.
+//
+// a pdb is an MSF container: fixed size blocks, a superblock at the start
+// pointing at the stream directory, and every stream a list of blocks. read
+// from it are TPI (stream 2), the CodeView type records from 0x1000 up, DBI
+// (stream 3), the module list and where the rest is, and IPI (stream 4), the
+// ids S_GPROC32_ID names its prototype through. functions come from the
+// S_GPROC32/S_LPROC32 of the module streams, variables and typedefs from the
+// S_GDATA32/S_LDATA32 and S_UDT of the global symbols, and the section
+// headers turn their section:offset into addresses.
+//
+// addresses are --image-base plus the RVA, by default the base MSVC links
+// at. types are named the way a binja export of the PE names them: `_node`,
+// `_node*`, `char[16]`, `int32_t (int32_t, char**)`. simple types below
+// 0x1000 are the CodeView builtins, `long` is four bytes.
+
+use std::fs;
+use std::path::Path;
+
+use crate::{
+    Access, Array, Base, Class, Convention, DynErr, Encoding, Enum, EnumField, Err, Field,
+    Function, GlobalVariable, HashMap, Integer, Parameter, Pointer, Qualified, ReferenceKind,
+    Structure, Subprogram, TypeSet, Typedef,
+};
+
+const MAGIC: &[u8] = b"Microsoft C/C++ MSF 7.00\r\n\x1aDS\0\0\0";
+
+const TPI: usize = 2;
+const DBI: usize = 3;
+const IPI: usize = 4;
+
+const LF_MODIFIER: u16 = 0x1001;
+const LF_POINTER: u16 = 0x1002;
+const LF_PROCEDURE: u16 = 0x1008;
+const LF_MFUNCTION: u16 = 0x1009;
+const LF_ARGLIST: u16 = 0x1201;
+const LF_FIELDLIST: u16 = 0x1203;
+const LF_BITFIELD: u16 = 0x1205;
+const LF_BCLASS: u16 = 0x1400;
+const LF_VBCLASS: u16 = 0x1401;
+const LF_IVBCLASS: u16 = 0x1402;
+const LF_INDEX: u16 = 0x1404;
+const LF_VFUNCTAB: u16 = 0x1409;
+const LF_ENUMERATE: u16 = 0x1502;
+const LF_ARRAY: u16 = 0x1503;
+const LF_CLASS: u16 = 0x1504;
+const LF_STRUCTURE: u16 = 0x1505;
+const LF_UNION: u16 = 0x1506;
+const LF_ENUM: u16 = 0x1507;
+const LF_MEMBER: u16 = 0x150d;
+const LF_STMEMBER: u16 = 0x150e;
+const LF_METHOD: u16 = 0x150f;
+const LF_NESTTYPE: u16 = 0x1510;
+const LF_ONEMETHOD: u16 = 0x1511;
+const LF_FUNC_ID: u16 = 0x1601;
+const LF_MFUNC_ID: u16 = 0x1602;
+
+const S_UDT: u16 = 0x1108;
+const S_LDATA32: u16 = 0x110c;
+const S_GDATA32: u16 = 0x110d;
+const S_LPROC32: u16 = 0x110f;
+const S_GPROC32: u16 = 0x1110;
+const S_LPROC32_ID: u16 = 0x1146;
+const S_GPROC32_ID: u16 = 0x1147;
+
+// struct property bits
+const FORWARD_REF: u16 = 0x80;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, at: 0 }
+    }
+
+    fn done(&self) -> bool {
+        self.at >= self.bytes.len()
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], DynErr> {
+        let bytes = (self.bytes.get(self.at..))
+            .and_then(|rest| rest.get(..length))
+            .ok_or("truncated record")?;
+        self.at += length;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, DynErr> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, DynErr> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, DynErr> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, DynErr> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn string(&mut self) -> Result<String, DynErr> {
+        let rest = &self.bytes[self.at.min(self.bytes.len())..];
+        let length = rest
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or("unterminated name")?;
+        self.at += length + 1;
+        Ok(String::from_utf8_lossy(&rest[..length]).into_owned())
+    }
+
+    // a numeric leaf, the value itself below 0x8000
+    fn numeric(&mut self) -> Result<i64, DynErr> {
+        let leaf = self.u16()?;
+        Ok(match leaf {
+            0..=0x7fff => leaf as i64,
+            0x8000 => self.u8()? as i8 as i64,
+            0x8001 => self.u16()? as i16 as i64,
+            0x8002 => self.u16()? as i64,
+            0x8003 => self.u32()? as i32 as i64,
+            0x8004 => self.u32()? as i64,
+            0x8009 | 0x800a => self.u64()? as i64,
+            _ => return Err(format!("unknown numeric leaf {:#x}", leaf).into()),
+        })
+    }
+
+    // field list members are padded with 0xf1, 0xf2.. up to four bytes
+    fn align(&mut self) {
+        while self.bytes.get(self.at).is_some_and(|b| *b >= 0xf0) {
+            self.at += 1;
+        }
+    }
+}
+
+struct Msf {
+    data: Vec<u8>,
+    block_size: usize,
+    streams: Vec<(usize, Vec<u32>)>,
+}
+
+impl Msf {
+    fn parse(data: Vec<u8>) -> Result<Msf, DynErr> {
+        if !data.starts_with(MAGIC) {
+            return Err("not an MSF 7.00 pdb".into());
+        }
+        let mut header = Reader::new(&data[MAGIC.len()..]);
+        let block_size = header.u32()? as usize;
+        let _free_block_map = header.u32()?;
+        let _blocks = header.u32()?;
+        let directory_size = header.u32()? as usize;
+        let _ = header.u32()?;
+        let map = header.u32()? as usize;
+        if !matches!(block_size, 512 | 1024 | 2048 | 4096) {
+            return Err(format!("odd block size {}", block_size).into());
+        }
+
+        let mut msf = Msf {
+            data,
+            block_size,
+            streams: Vec::new(),
+        };
+        let count = directory_size.div_ceil(block_size);
+        let map = msf.block(map as u32)?;
+        let blocks = (0..count)
+            .map(|i| Reader::new(map.get(i * 4..).unwrap_or_default()).u32())
+            .collect::<Result<Vec<u32>, DynErr>>()?;
+        let directory = msf.read(directory_size, &blocks)?;
+
+        let mut reader = Reader::new(&directory);
+        let sizes = (0..reader.u32()?)
+            .map(|_| {
+                reader.u32().map(|size| match size {
+                    u32::MAX => 0,
+                    size => size as usize,
+                })
+            })
+            .collect::<Result<Vec<usize>, DynErr>>()?;
+        for size in sizes {
+            let blocks = (0..size.div_ceil(block_size))
+                .map(|_| reader.u32())
+                .collect::<Result<Vec<u32>, DynErr>>()?;
+            msf.streams.push((size, blocks));
+        }
+        Ok(msf)
+    }
+
+    fn block(&self, index: u32) -> Result<&[u8], DynErr> {
+        let start = index as usize * self.block_size;
+        self.data
+            .get(start..start + self.block_size)
+            .ok_or(format!("block {} is past the end", index).into())
+    }
+
+    fn read(&self, size: usize, blocks: &[u32]) -> Result<Vec<u8>, DynErr> {
+        let mut bytes = Vec::with_capacity(size);
+        for block in blocks {
+            bytes.extend_from_slice(self.block(*block)?);
+        }
+        bytes.truncate(size);
+        Ok(bytes)
+    }
+
+    fn stream(&self, index: usize) -> Result<Vec<u8>, DynErr> {
+        match self.streams.get(index) {
+            Some((size, blocks)) => self.read(*size, blocks),
+            None => Err(format!("no stream {}", index).into()),
+        }
+    }
+}
+
+// the records of TPI or IPI by type index
+struct Records {
+    first: u32,
+    records: Vec<(u16, Vec<u8>)>,
+}
+
+impl Records {
+    fn parse(stream: &[u8]) -> Result<Records, DynErr> {
+        let mut header = Reader::new(stream);
+        let _version = header.u32()?;
+        let header_size = header.u32()? as usize;
+        let first = header.u32()?;
+        let _end = header.u32()?;
+        let length = header.u32()? as usize;
+
+        let mut reader = Reader::new(
+            stream
+                .get(header_size..header_size + length)
+                .ok_or("truncated type stream")?,
+        );
+        let mut records = Vec::new();
+        while !reader.done() {
+            let length = reader.u16()? as usize;
+            let mut record = Reader::new(reader.take(length)?);
+            let kind = record.u16()?;
+            records.push((kind, record.bytes[2..].to_vec()));
+        }
+        if first.checked_add(records.len() as u32).is_none() {
+            return Err(format!("type records from {:#x} run past the last index", first).into());
+        }
+        Ok(Records { first, records })
+    }
+
+    fn get(&self, index: u32) -> Result<(u16, Reader<'_>), DynErr> {
+        index
+            .checked_sub(self.first)
+            .and_then(|i| self.records.get(i as usize))
+            .map(|(kind, record)| (*kind, Reader::new(record)))
+            .ok_or(format!("no type record {:#x}", index).into())
+    }
+}
+
+// a CV_call_e, the near and far variants alike. C's is the default
+fn convention(call: u8) -> Option<Convention> {
+    match call {
+        0x02 | 0x03 => Some(Convention::Pascal),
+        0x04 | 0x05 => Some(Convention::Fastcall),
+        0x07 | 0x08 => Some(Convention::Stdcall),
+        0x0b => Some(Convention::Thiscall),
+        0x18 => Some(Convention::Vectorcall),
+        _ => None,
+    }
+}
+
+// (name, size, signed, encoding) of a CodeView builtin
+fn simple(kind: u32) -> Option<(&'static str, u64, bool, Option<Encoding>)> {
+    Some(match kind {
+        0x03 => ("", 0, false, None),
+        0x08 => ("HRESULT", 4, true, None),
+        0x10 => ("signed char", 1, true, Some(Encoding::Char)),
+        0x20 => ("unsigned char", 1, false, Some(Encoding::Char)),
+        0x70 => ("char", 1, true, Some(Encoding::Char)),
+        0x71 => ("wchar_t", 2, false, Some(Encoding::Char)),
+        0x7a => ("char16_t", 2, false, Some(Encoding::Char)),
+        0x7b => ("char32_t", 4, false, Some(Encoding::Char)),
+        0x7c => ("char8_t", 1, false, Some(Encoding::Char)),
+        0x68 => ("int8_t", 1, true, None),
+        0x69 => ("uint8_t", 1, false, None),
+        0x11 | 0x72 => ("int16_t", 2, true, None),
+        0x21 | 0x73 => ("uint16_t", 2, false, None),
+        0x12 | 0x74 => ("int32_t", 4, true, None),
+        0x22 | 0x75 => ("uint32_t", 4, false, None),
+        0x13 | 0x76 => ("int64_t", 8, true, None),
+        0x23 | 0x77 => ("uint64_t", 8, false, None),
+        0x14 | 0x78 => ("int128_t", 16, true, None),
+        0x24 | 0x79 => ("uint128_t", 16, false, None),
+        0x46 => ("float16", 2, false, Some(Encoding::Float)),
+        0x40 => ("float", 4, false, Some(Encoding::Float)),
+        0x41 => ("double", 8, false, Some(Encoding::Float)),
+        0x42 => ("long double", 10, false, Some(Encoding::Float)),
+        0x30 => ("bool", 1, false, Some(Encoding::Bool)),
+        0x31 => ("bool16", 2, false, Some(Encoding::Bool)),
+        0x32 => ("bool32", 4, false, Some(Encoding::Bool)),
+        0x33 => ("bool64", 8, false, Some(Encoding::Bool)),
+        _ => return None,
+    })
+}
+
+struct Converter<'a> {
+    types: &'a Records,
+    set: TypeSet,
+    names: HashMap<u32, String>,
+    // forward references resolve to the definition of the same name
+    definitions: HashMap<String, u32>,
+    converting: Vec<u32>,
+    anonymous: u64,
+}
+
+impl Converter<'_> {
+    fn pointer(&mut self, pointer: Pointer) -> String {
+        let name = pointer.spelled();
+        _ = self.set.pointers.insert(name.clone(), pointer);
+        name
+    }
+
+    fn plain(target: String, size: u64) -> Pointer {
+        Pointer {
+            size,
+            target,
+            reference: None,
+            member_of: None,
+        }
+    }
+
+    fn qualified(&mut self, target: String, constant: bool, volatile: bool) -> String {
+        if !(constant || volatile) {
+            return target;
+        }
+        let shown = if target.is_empty() { "void" } else { &target };
+        let name = match (constant, volatile) {
+            (true, true) => format!("const volatile {}", shown),
+            (true, false) => format!("const {}", shown),
+            _ => format!("volatile {}", shown),
+        };
+        _ = self.set.qualifiers.insert(
+            name.clone(),
+            Qualified {
+                target,
+                constant,
+                volatile,
+                restrict: false,
+            },
+        );
+        name
+    }
+
+    fn definition(&self, index: u32) -> u32 {
+        let Ok((kind, mut record)) = self.types.get(index) else {
+            return index;
+        };
+        if !matches!(kind, LF_CLASS | LF_STRUCTURE | LF_UNION | LF_ENUM) {
+            return index;
+        }
+        let _count = record.u16();
+        let property = record.u16().unwrap_or(0);
+        if property & FORWARD_REF == 0 {
+            return index;
+        }
+        match aggregate_name(kind, &mut record) {
+            Ok(name) => self.definitions.get(&name).copied().unwrap_or(index),
+            Err(_) => index,
+        }
+    }
+
+    fn size(&self, mut index: u32) -> Result<u64, DynErr> {
+        // modifiers and enums are the size of what they're of
+        for _ in 0..64 {
+            if index < self.types.first {
+                return Ok(match index >> 8 & 0xf {
+                    0 => simple(index & 0xff).map_or(0, |(_, size, _, _)| size),
+                    6 => 8,
+                    _ => 4,
+                });
+            }
+            let (kind, mut record) = self.types.get(self.definition(index))?;
+            index = match kind {
+                LF_MODIFIER => record.u32()?,
+                LF_ENUM => {
+                    record.take(4)?;
+                    record.u32()?
+                }
+                _ => return Self::sized(kind, record),
+            };
+        }
+        Err(format!("type {:#x} is of itself", index).into())
+    }
+
+    fn sized(kind: u16, mut record: Reader) -> Result<u64, DynErr> {
+        Ok(match kind {
+            LF_POINTER => {
+                let _target = record.u32()?;
+                (record.u32()? >> 13 & 0x3f) as u64
+            }
+            LF_ARRAY => {
+                let _element = record.u32()?;
+                let _index = record.u32()?;
+                record.numeric()? as u64
+            }
+            LF_CLASS | LF_STRUCTURE => {
+                record.take(16)?;
+                record.numeric()? as u64
+            }
+            LF_UNION => {
+                record.take(8)?;
+                record.numeric()? as u64
+            }
+            _ => 0,
+        })
+    }
+
+    // the type's name, converting it on first sight
+    fn name(&mut self, index: u32) -> Result<String, DynErr> {
+        let index = self.definition(index);
+        if let Some(name) = self.names.get(&index) {
+            return Ok(name.clone());
+        }
+        // only aggregates are named before their members are, anything
+        // else reaching itself again is corrupt
+        if self.converting.contains(&index) {
+            return Err(format!("type {:#x} refers to itself", index).into());
+        }
+        self.converting.push(index);
+        let name = self.convert(index);
+        _ = self.converting.pop();
+        let name = name?;
+        _ = self.names.insert(index, name.clone());
+        Ok(name)
+    }
+
+    fn convert(&mut self, index: u32) -> Result<String, DynErr> {
+        if index < self.types.first {
+            let kind = index & 0xff;
+            let (name, size, signed, encoding) =
+                simple(kind).ok_or(format!("unknown builtin type {:#x}", index))?;
+            if !name.is_empty() {
+                _ = self.set.integers.insert(
+                    name.to_string(),
+                    Integer {
+                        size,
+                        signed,
+                        endianity: None,
+                        encoding,
+                        bits: None,
+                    },
+                );
+            }
+            // modes 1 to 6 are the 16, 32 and 64 bit pointers to it
+            return Ok(match index >> 8 & 0xf {
+                0 => name.to_string(),
+                6 => self.pointer(Self::plain(name.to_string(), 8)),
+                _ => self.pointer(Self::plain(name.to_string(), 4)),
+            });
+        }
+
+        let (kind, mut record) = self.types.get(index)?;
+        match kind {
+            LF_MODIFIER => {
+                let target = record.u32()?;
+                let attributes = record.u16()?;
+                let target = self.name(target)?;
+                Ok(self.qualified(target, attributes & 1 != 0, attributes & 2 != 0))
+            }
+            LF_POINTER => {
+                let target = record.u32()?;
+                let attributes = record.u32()?;
+                let size = self.size(index)?;
+                let target = self.name(target)?;
+                let mut pointer = Self::plain(target, size);
+                match attributes >> 5 & 7 {
+                    1 => pointer.reference = Some(ReferenceKind::Lvalue),
+                    4 => pointer.reference = Some(ReferenceKind::Rvalue),
+                    // to a data member or a method, the class follows
+                    2 | 3 => pointer.member_of = Some(self.name(record.u32()?)?),
+                    _ => {}
+                }
+                let pointer = self.pointer(pointer);
+                Ok(self.qualified(pointer, attributes & 1 << 10 != 0, attributes & 1 << 9 != 0))
+            }
+            LF_ARRAY => {
+                let element = record.u32()?;
+                let _index = record.u32()?;
+                let size = record.numeric()? as u64;
+                let count = match self.size(element)? {
+                    0 => 0,
+                    element => size / element,
+                };
+                let target = self.name(element)?;
+                let name = format!("{}[{}]", target, count);
+                _ = self.set.arrays.insert(
+                    name.clone(),
+                    Array {
+                        count,
+                        target,
+                        dimensions: Vec::new(),
+                        string: false,
+                        dynamic: None,
+                    },
+                );
+                Ok(name)
+            }
+            LF_PROCEDURE => {
+                let returntype = record.u32()?;
+                let convention = convention(record.u8()?);
+                record.take(3)?;
+                let arguments = record.u32()?;
+                self.function(returntype, None, arguments, convention)
+            }
+            LF_MFUNCTION => {
+                let returntype = record.u32()?;
+                let _class = record.u32()?;
+                let this = record.u32()?;
+                let convention = convention(record.u8()?);
+                record.take(3)?;
+                let arguments = record.u32()?;
+                self.function(
+                    returntype,
+                    (this != 0).then_some(this),
+                    arguments,
+                    convention,
+                )
+            }
+            LF_CLASS | LF_STRUCTURE | LF_UNION => self.structure(index, kind, record),
+            LF_ENUM => {
+                let _count = record.u16()?;
+                let _property = record.u16()?;
+                let underlying = record.u32()?;
+                let fields = record.u32()?;
+                let name = self.tag(record.string()?);
+                let (_, size, signed, _) = simple(underlying & 0xff).unwrap_or(("", 4, true, None));
+                _ = self.names.insert(index, name.clone());
+                let mut values = Vec::new();
+                self.fields(fields, &mut |_, kind, record| {
+                    if kind == LF_ENUMERATE {
+                        let _attributes = record.u16()?;
+                        let value = record.numeric()?;
+                        values.push(EnumField {
+                            name: record.string()?,
+                            value: value as i128,
+                        });
+                    }
+                    Ok(())
+                })?;
+                _ = self.set.enums.insert(
+                    name.clone(),
+                    Enum {
+                        size,
+                        signed,
+                        fields: values,
+                        decl: None,
+                    },
+                );
+                Ok(name)
+            }
+            _ => Err(format!("type {:#x} has unsupported kind {:#x}", index, kind).into()),
+        }
+    }
+
+    fn tag(&mut self, name: String) -> String {
+        match name.as_str() {
+            "<unnamed-tag>" | "<anonymous-tag>" | "__unnamed" => {
+                self.anonymous += 1;
+                format!("anon.{}", self.anonymous - 1)
+            }
+            _ => name,
+        }
+    }
+
+    fn function(
+        &mut self,
+        returntype: u32,
+        this: Option<u32>,
+        arguments: u32,
+        convention: Option<Convention>,
+    ) -> Result<String, DynErr> {
+        let returntype = self.name(returntype)?;
+        let mut parameters = Vec::new();
+        if let Some(this) = this {
+            parameters.push(Parameter {
+                name: "this".to_string(),
+                typename: self.name(this)?,
+            });
+        }
+        let (kind, mut record) = self.types.get(arguments)?;
+        if kind != LF_ARGLIST {
+            return Err(format!("argument list {:#x} isn't an LF_ARGLIST", arguments).into());
+        }
+        let mut variadic = false;
+        for _ in 0..record.u32()? {
+            // a trailing T_NOTYPE marks varargs
+            match record.u32()? {
+                0 => variadic = true,
+                argument => parameters.push(Parameter {
+                    name: String::new(),
+                    typename: self.name(argument)?,
+                }),
+            }
+        }
+        let mut shown: Vec<&str> = (parameters.iter())
+            .map(|parameter| match parameter.typename.as_str() {
+                "" => "void",
+                typename => typename,
+            })
+            .collect();
+        if variadic {
+            shown.push("...");
+        }
+        let name = format!(
+            "{} ({})",
+            if returntype.is_empty() {
+                "void"
+            } else {
+                &returntype
+            },
+            shown.join(", ")
+        );
+        _ = self.set.functions.insert(
+            name.clone(),
+            Function {
+                parameters,
+                returntype,
+                variadic,
+                convention,
+            },
+        );
+        Ok(name)
+    }
+
+    fn structure(&mut self, index: u32, kind: u16, mut record: Reader) -> Result<String, DynErr> {
+        let _count = record.u16()?;
+        let property = record.u16()?;
+        let fields = record.u32()?;
+        if kind != LF_UNION {
+            record.take(8)?;
+        }
+        let size = record.numeric()? as u64;
+        let name = record.string()?;
+        let anon = matches!(
+            name.as_str(),
+            "<unnamed-tag>" | "<anonymous-tag>" | "__unnamed"
+        );
+        let name = self.tag(name);
+        // claimed before the members so self references end
+        _ = self.names.insert(index, name.clone());
+
+        let mut members = Vec::new();
+        let mut bases = Vec::new();
+        let mut vtable_pointer = false;
+        if property & FORWARD_REF == 0 {
+            self.fields(fields, &mut |this, kind, record| {
+                match kind {
+                    LF_MEMBER => {
+                        let attributes = record.u16()?;
+                        let typeindex = record.u32()?;
+                        let offset = record.numeric()? as u64;
+                        let name = record.string()?;
+                        let (typename, bits) = match this.types.get(typeindex) {
+                            Ok((LF_BITFIELD, mut bitfield)) => {
+                                let base = bitfield.u32()?;
+                                let width = bitfield.u8()? as u64;
+                                let position = bitfield.u8()? as u64;
+                                (this.name(base)?, Some((position, width)))
+                            }
+                            _ => (this.name(typeindex)?, None),
+                        };
+                        members.push(Field {
+                            offset,
+                            name,
+                            typename,
+                            endianity: None,
+                            bit_offset: bits.map(|(position, _)| position),
+                            bit_size: bits.map(|(_, width)| width),
+                            access: access(attributes),
+                        });
+                    }
+                    LF_BCLASS => {
+                        let attributes = record.u16()?;
+                        let typeindex = record.u32()?;
+                        let offset = record.numeric()? as u64;
+                        bases.push(Base {
+                            offset,
+                            typename: this.name(typeindex)?,
+                            access: access(attributes),
+                        });
+                    }
+                    LF_VFUNCTAB => {
+                        let _padding = record.u16()?;
+                        let typeindex = record.u32()?;
+                        vtable_pointer = true;
+                        members.push(Field {
+                            offset: 0,
+                            name: "__vfptr".to_string(),
+                            typename: this.name(typeindex)?,
+                            endianity: None,
+                            bit_offset: None,
+                            bit_size: None,
+                            access: None,
+                        });
+                    }
+                    _ => {}
+                }
+                Ok(())
+            })?;
+        }
+
+        let structure = Structure {
+            size,
+            anon,
+            fields: members,
+            variant: None,
+            declaration: property & FORWARD_REF != 0,
+            decl: None,
+            printer: None,
+        };
+        match kind {
+            LF_UNION => _ = self.set.unions.insert(name.clone(), structure),
+            // structs can derive and have virtual methods too
+            _ if kind == LF_CLASS || !bases.is_empty() || vtable_pointer => {
+                let class = Class {
+                    layout: structure,
+                    bases,
+                    methods: Vec::new(),
+                    containing_type: None,
+                    vtable_pointer: vtable_pointer.then(|| "__vfptr".to_string()),
+                    vtable: None,
+                };
+                _ = self.set.classes.insert(name.clone(), class)
+            }
+            _ => _ = self.set.structs.insert(name.clone(), structure),
+        }
+        Ok(name)
+    }
+
+    // calls `visit` with every member of a field list, following LF_INDEX
+    fn fields(
+        &mut self,
+        list: u32,
+        visit: &mut dyn FnMut(&mut Self, u16, &mut Reader) -> Result<(), DynErr>,
+    ) -> Result<(), DynErr> {
+        let (kind, record) = self.types.get(list)?;
+        if kind != LF_FIELDLIST {
+            return Err(format!("field list {:#x} isn't an LF_FIELDLIST", list).into());
+        }
+        let bytes = record.bytes.to_vec();
+        let mut record = Reader::new(&bytes);
+        while !record.done() {
+            let kind = record.u16()?;
+            match kind {
+                LF_INDEX => {
+                    let _padding = record.u16()?;
+                    let next = record.u32()?;
+                    if next == list || self.converting.contains(&next) {
+                        return Err(format!("field list {:#x} continues into itself", list).into());
+                    }
+                    self.converting.push(list);
+                    let continued = self.fields(next, visit);
+                    _ = self.converting.pop();
+                    return continued;
+                }
+                // the rest only need skipping
+                LF_VBCLASS | LF_IVBCLASS => {
+                    record.take(10)?;
+                    record.numeric()?;
+                    record.numeric()?;
+                }
+                LF_STMEMBER | LF_NESTTYPE => {
+                    record.take(6)?;
+                    record.string()?;
+                }
+                LF_METHOD => {
+                    record.take(6)?;
+                    record.string()?;
+                }
+                LF_ONEMETHOD => {
+                    let attributes = record.u16()?;
+                    record.take(4)?;
+                    // introducing virtuals carry their vtable offset
+                    if matches!(attributes >> 2 & 7, 4 | 6) {
+                        record.take(4)?;
+                    }
+                    record.string()?;
+                }
+                LF_MEMBER | LF_BCLASS | LF_VFUNCTAB | LF_ENUMERATE => {
+                    visit(self, kind, &mut record)?
+                }
+                _ => return Err(format!("unknown field list member {:#x}", kind).into()),
+            }
+            record.align();
+        }
+        Ok(())
+    }
+}
+
+// the CV_fldattr access bits, 3 is public
+fn access(attributes: u16) -> Option<Access> {
+    match attributes & 3 {
+        1 => Some(Access::Private),
+        2 => Some(Access::Protected),
+        _ => None,
+    }
+}
+
+// walks a stream of symbol records
+fn symbols(stream: &[u8], visit: &mut dyn FnMut(u16, &mut Reader) -> Err) -> Err {
+    let mut reader = Reader::new(stream);
+    while reader.bytes.len() - reader.at >= 4 {
+        let length = reader.u16()? as usize;
+        let mut record = Reader::new(reader.take(length)?);
+        let kind = record.u16()?;
+        visit(kind, &mut record)?;
+    }
+    Ok(())
+}
+
+fn aggregate_name(kind: u16, record: &mut Reader) -> Result<String, DynErr> {
+    match kind {
+        LF_ENUM => {
+            record.take(8)?;
+        }
+        LF_UNION => {
+            record.take(4)?;
+            record.numeric()?;
+        }
+        _ => {
+            record.take(12)?;
+            record.numeric()?;
+        }
+    }
+    record.string()
+}
+
+// where the DBI stream says the rest is
+struct Streams {
+    // with the size of their symbols
+    modules: Vec<(u16, usize)>,
+    globals: usize,
+    sections: usize,
+}
+
+fn streams(dbi: &[u8]) -> Result<Streams, DynErr> {
+    let mut header = Reader::new(dbi);
+    header.take(12)?;
+    let _globals = header.u16()?;
+    let _build = header.u16()?;
+    let _publics = header.u16()?;
+    let _version = header.u16()?;
+    let symbols = header.u16()?;
+    let _rebuild = header.u16()?;
+    let sizes = (0..5)
+        .map(|_| header.u32().map(|size| size as usize))
+        .collect::<Result<Vec<usize>, DynErr>>()?;
+    let _mfc = header.u32()?;
+    let optional = header.u32()? as usize;
+    let ec = header.u32()? as usize;
+
+    let mut modules = Vec::new();
+    let mut info = Reader::new(dbi.get(64..64 + sizes[0]).ok_or("truncated DBI stream")?);
+    while !info.done() {
+        info.take(4 + 28 + 2)?;
+        let stream = info.u16()?;
+        let size = info.u32()? as usize;
+        info.take(4 * 2 + 2 * 2 + 4 * 3)?;
+        info.string()?;
+        info.string()?;
+        info.at = info.at.next_multiple_of(4);
+        modules.push((stream, size));
+    }
+    // the optional debug headers follow the EC substream
+    let headers = 64 + sizes[..5].iter().sum::<usize>() + ec;
+    let mut optional = Reader::new(
+        dbi.get(headers..headers + optional)
+            .ok_or("truncated DBI stream")?,
+    );
+    optional.take(10)?;
+    Ok(Streams {
+        modules,
+        globals: symbols as usize,
+        sections: optional.u16()? as usize,
+    })
+}
+
+fn sections(stream: &[u8]) -> Result<Vec<u64>, DynErr> {
+    stream
+        .chunks(40)
+        .map(|header| {
+            let mut header = Reader::new(header);
+            header.take(12)?;
+            Ok(header.u32()? as u64)
+        })
+        .collect()
+}
+
+pub fn load(path: &Path, image_base: u64) -> Result<TypeSet, DynErr> {
+    let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    read(data, image_base).map_err(|e| format!("{}: {}", path.display(), e).into())
+}
+
+fn read(data: Vec<u8>, image_base: u64) -> Result<TypeSet, DynErr> {
+    let msf = Msf::parse(data)?;
+    let types = Records::parse(&msf.stream(TPI)?)?;
+    let ids = match msf.stream(IPI) {
+        Ok(stream) if !stream.is_empty() => Some(Records::parse(&stream)?),
+        _ => None,
+    };
+    let dbi = msf.stream(DBI)?;
+    let streams = streams(&dbi)?;
+    let sections = sections(&msf.stream(streams.sections)?)?;
+    let address = |segment: u16, offset: u32| -> Result<u64, DynErr> {
+        let section = (segment as usize)
+            .checked_sub(1)
+            .and_then(|i| sections.get(i))
+            .ok_or(format!("no section {}", segment))?;
+        (image_base.checked_add(section + offset as u64))
+            .ok_or(format!("{:#x}:{:#x} is past the address space", segment, offset).into())
+    };
+
+    let mut definitions = HashMap::new();
+    for (i, (kind, record)) in types.records.iter().enumerate() {
+        if matches!(*kind, LF_CLASS | LF_STRUCTURE | LF_UNION | LF_ENUM) {
+            let mut record = Reader::new(record);
+            let _count = record.u16()?;
+            if record.u16()? & FORWARD_REF == 0 {
+                let name = aggregate_name(*kind, &mut record)?;
+                _ = definitions.insert(name, types.first + i as u32);
+            }
+        }
+    }
+    let mut converter = Converter {
+        types: &types,
+        set: TypeSet::default(),
+        names: HashMap::new(),
+        definitions,
+        converting: Vec::new(),
+        anonymous: 0,
+    };
+    for i in 0..types.records.len() as u32 {
+        if matches!(
+            types.records[i as usize].0,
+            LF_CLASS | LF_STRUCTURE | LF_UNION | LF_ENUM
+        ) {
+            converter.name(types.first + i)?;
+        }
+    }
+
+    // variables and typedefs from the global symbols
+    let stream = msf.stream(streams.globals)?;
+    symbols(&stream, &mut |kind, record| {
+        match kind {
+            S_GDATA32 | S_LDATA32 => {
+                let typeindex = record.u32()?;
+                let offset = record.u32()?;
+                let segment = record.u16()?;
+                let name = record.string()?;
+                let size = converter.size(typeindex)?;
+                let typename = converter.name(typeindex)?;
+                _ = converter.set.variables.insert(
+                    address(segment, offset)?,
+                    GlobalVariable {
+                        name,
+                        size,
+                        typename,
+                        decl: None,
+                        space: None,
+                    },
+                );
+            }
+            S_UDT => {
+                let typeindex = record.u32()?;
+                let name = record.string()?;
+                let target = converter.name(typeindex)?;
+                if name != target {
+                    _ = converter
+                        .set
+                        .typedefs
+                        .insert(name, Typedef { target, decl: None });
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    })?;
+
+    // the functions from the modules
+    for (stream, size) in streams.modules {
+        if stream == u16::MAX {
+            continue;
+        }
+        let stream = msf.stream(stream as usize)?;
+        // after a signature, and followed by the line info
+        let records = stream.get(4..size).ok_or("truncated module stream")?;
+        symbols(records, &mut |kind, record| {
+            if !matches!(kind, S_GPROC32 | S_LPROC32 | S_GPROC32_ID | S_LPROC32_ID) {
+                return Ok(());
+            }
+            record.take(12)?;
+            let size = record.u32()? as u64;
+            record.take(8)?;
+            let mut typeindex = record.u32()?;
+            let offset = record.u32()?;
+            let segment = record.u16()?;
+            let _flags = record.u8()?;
+            let name = record.string()?;
+            if matches!(kind, S_GPROC32_ID | S_LPROC32_ID) {
+                let ids = ids.as_ref().ok_or("S_GPROC32_ID without an IPI stream")?;
+                let (kind, mut id) = ids.get(typeindex)?;
+                if !matches!(kind, LF_FUNC_ID | LF_MFUNC_ID) {
+                    return Err(format!("id {:#x} isn't a function id", typeindex).into());
+                }
+                _ = id.u32()?;
+                typeindex = id.u32()?;
+            }
+            let typename = converter.name(typeindex)?;
+            _ = converter.set.subprograms.insert(
+                address(segment, offset)?,
+                Subprogram {
+                    name,
+                    size,
+                    typename,
+                    locals: Vec::new(),
+                    local: matches!(kind, S_LPROC32 | S_LPROC32_ID),
+                    decl: None,
+                    space: None,
+                },
+            );
+            Ok(())
+        })?;
+    }
+
+    Ok(converter.set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // written by tests/pdb.py
+    const PDB: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/app.pdb");
+    const BASE: u64 = 0x140000000;
+
+    #[test]
+    fn reads_the_types_and_symbols() {
+        let set = load(Path::new(PDB), BASE).unwrap();
+
+        // the forward reference resolves to the definition
+        let node = &set.structs["_node"];
+        assert_eq!(node.size, 40);
+        assert!(!node.declaration);
+        let fields: Vec<_> = (node.fields.iter())
+            .map(|field| {
+                let bits = field.bit_offset.zip(field.bit_size);
+                (
+                    field.offset,
+                    field.name.as_str(),
+                    field.typename.as_str(),
+                    bits,
+                )
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                (0, "next", "_node*", None),
+                (8, "flags", "uint32_t", Some((0, 3))),
+                (8, "kind", "uint32_t", Some((3, 5))),
+                (12, "name", "char[16]", None),
+                (32, "big", "uint64_t", None),
+            ]
+        );
+        let pointer = &set.pointers["_node*"];
+        assert_eq!((pointer.size, pointer.target.as_str()), (8, "_node"));
+        let array = &set.arrays["char[16]"];
+        assert_eq!((array.count, array.target.as_str()), (16, "char"));
+
+        let color = &set.enums["color"];
+        assert_eq!((color.size, color.signed), (4, true));
+        let values: Vec<_> = (color.fields.iter())
+            .map(|field| (field.name.as_str(), field.value))
+            .collect();
+        assert_eq!(values, [("RED", 0), ("BLUE", 1), ("NEG", -1)]);
+
+        let main = &set.functions["int32_t (int32_t, char**)"];
+        assert_eq!(main.returntype, "int32_t");
+        let parameters: Vec<_> = (main.parameters.iter())
+            .map(|parameter| parameter.typename.as_str())
+            .collect();
+        assert_eq!(parameters, ["int32_t", "char**"]);
+        assert_eq!(set.pointers["char**"].target, "char*");
+
+        // S_UDT naming a type by its own name isn't a typedef
+        assert_eq!(set.typedefs["node"].target, "_node");
+        assert!(!set.typedefs.contains_key("_node"));
+
+        let head = &set.variables[&(BASE + 0x3010)];
+        assert_eq!(
+            (head.name.as_str(), head.typename.as_str()),
+            ("head", "_node*")
+        );
+        assert_eq!(head.size, 8);
+        let palette = &set.variables[&(BASE + 0x3018)];
+        assert_eq!((palette.name.as_str(), palette.size), ("palette", 4));
+
+        // through the LF_FUNC_ID of S_GPROC32_ID
+        let main = &set.subprograms[&(BASE + 0x1030)];
+        assert_eq!(
+            (main.name.as_str(), main.size, main.local),
+            ("main", 120, false)
+        );
+        assert_eq!(main.typename, "int32_t (int32_t, char**)");
+        let helper = &set.subprograms[&(BASE + 0x1100)];
+        assert_eq!(
+            (helper.name.as_str(), helper.size, helper.local),
+            ("helper", 16, true)
+        );
+    }
+
+    #[test]
+    fn truncated_pdbs_are_errors() {
+        let data = fs::read(PDB).unwrap();
+        for length in 0..data.len() {
+            assert!(read(data[..length].to_vec(), BASE).is_err(), "{}", length);
+        }
+    }
+
+    #[test]
+    fn corrupt_pdbs_dont_panic() {
+        let data = fs::read(PDB).unwrap();
+        for at in 0..data.len() {
+            for flip in [0x01, 0x80, 0xff] {
+                let mut corrupt = data.clone();
+                corrupt[at] ^= flip;
+                _ = read(corrupt, BASE);
+            }
+        }
+    }
+
+    #[test]
+    fn self_referencing_types_are_errors() {
+        let mut data = fs::read(PDB).unwrap();
+        // point `_node*` at itself
+        let pointer = [0x02, 0x10, 0x00, 0x10, 0x00, 0x00];
+        let at = (data.windows(pointer.len()))
+            .position(|bytes| bytes == pointer)
+            .unwrap();
+        data[at + 2] = 0x01;
+        match read(data, BASE) {
+            Err(e) => assert!(e.to_string().contains("refers to itself"), "{}", e),
+            Ok(_) => panic!("read a pointer to itself"),
+        }
+    }
+}
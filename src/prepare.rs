@@ -0,0 +1,96 @@
+// what the types go through before any of them is emitted, in order:
+// references to types nothing defines, which --lenient stands empty structs
+// in for, types containing themselves by value, the layout warnings of
+// validate.rs and then --pad-holes.
+
+use log::{error, info, warn};
+
+use crate::{
+    nesting, padding, resolve, validate, BinjaType, Constant, DebugSup, DynErr, GlobalVariable,
+    HashMap, Options, Subprogram,
+};
+
+// under --lenient the types with the stand-ins, None when nothing is
+// missing, and how many references were
+pub fn resolve(
+    options: &Options,
+    mappings: &HashMap<String, BinjaType>,
+    global_variables: &HashMap<u64, GlobalVariable>,
+    constants: &HashMap<String, Constant>,
+    subprograms: &HashMap<u64, Subprogram>,
+    sup: &DebugSup,
+) -> Result<(Option<HashMap<String, BinjaType>>, usize), DynErr> {
+    let missing =
+        resolve::unresolved(
+            mappings,
+            global_variables,
+            constants,
+            subprograms,
+            |name| match sup {
+                DebugSup::Reference(supplementary) => supplementary.types.contains_key(name),
+                _ => mappings.contains_key(name),
+            },
+        );
+    if missing.is_empty() {
+        return Ok((None, 0));
+    }
+    if options.lenient && !matches!(sup, DebugSup::Reference(_)) {
+        for (referrer, name) in missing.iter() {
+            warn!("lenient: {} refers to unknown type `{}`", referrer, name);
+        }
+        return Ok((Some(resolve::substitute(mappings, &missing)), missing.len()));
+    }
+    for (referrer, name) in missing.iter() {
+        error!("unresolved: {} refers to unknown type `{}`", referrer, name);
+    }
+    Err(format!(
+        "{} reference(s) to unknown types, --lenient stands in empty structs",
+        missing.len()
+    )
+    .into())
+}
+
+// the types nested in others, for `visit`
+pub fn layouts<'a>(
+    options: &Options,
+    mappings: &'a HashMap<String, BinjaType>,
+) -> Result<HashMap<&'a String, &'a String>, DynErr> {
+    // both only read the types
+    let (cycles, nested) = std::thread::scope(|scope| {
+        let nested = scope.spawn(|| nesting(mappings));
+        let cycles = resolve::cycles(mappings);
+        (cycles, nested.join().unwrap())
+    });
+    for cycle in cycles.iter() {
+        error!("cycle: `{}` contains itself by value", cycle.join("` -> `"));
+    }
+    if !cycles.is_empty() {
+        return Err(format!("{} type(s) containing themselves by value", cycles.len()).into());
+    }
+    let layouts = validate::issues(mappings, options.target.pointer_size);
+    for issue in layouts.iter() {
+        match options.strict {
+            true => error!("layout: {}", issue),
+            false => warn!("layout: {}", issue),
+        }
+    }
+    if options.strict && !layouts.is_empty() {
+        return Err(format!("{} layout issue(s) with --strict", layouts.len()).into());
+    }
+    Ok(nested)
+}
+
+// the padded types with --pad-holes
+pub fn pad(
+    options: &Options,
+    mappings: &HashMap<String, BinjaType>,
+) -> Result<Option<HashMap<String, BinjaType>>, DynErr> {
+    if !options.pad_holes {
+        return Ok(None);
+    }
+    let (padded, holes) = padding::pad_holes(mappings, options.target.pointer_size)?;
+    if holes > 0 {
+        info!("padded {} hole(s)", holes);
+    }
+    Ok(Some(padded))
+}
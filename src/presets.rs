@@ -0,0 +1,46 @@
+// builtin type sets selectable with `--preset`, for structures that show up in
+// every challenge of a kind and aren't worth exporting each time.
+//
+// variable addresses in a preset are offsets from the library's load base,
+// so load the output with `add-symbol-file out.o -o <libc base>`.
+
+use log::info;
+
+use crate::{DynErr, TypeSet};
+
+// (name, build the symbol offsets were taken from, type set)
+const PRESETS: &[(&str, &str, &str)] = &[
+    (
+        "glibc-heap-2.27",
+        "libc6 2.27-3ubuntu1 (amd64)",
+        include_str!("../presets/glibc-heap-2.27.json"),
+    ),
+    (
+        "glibc-heap-2.31",
+        "libc6 2.31-0ubuntu9.9 (amd64)",
+        include_str!("../presets/glibc-heap-2.31.json"),
+    ),
+    (
+        "glibc-heap-2.35",
+        "libc6 2.35-0ubuntu3 (amd64)",
+        include_str!("../presets/glibc-heap-2.35.json"),
+    ),
+];
+
+pub fn load(name: &str) -> Result<TypeSet, DynErr> {
+    match PRESETS.iter().find(|(preset, _, _)| *preset == name) {
+        Some((_, build, json)) => {
+            info!("preset {}: symbol offsets match {}", name, build);
+            Ok(serde_json::from_str(json)?)
+        }
+        None => {
+            let available: Vec<&str> = PRESETS.iter().map(|(preset, _, _)| *preset).collect();
+            Err(format!(
+                "unknown preset `{}` (available: {})",
+                name,
+                available.join(", ")
+            )
+            .into())
+        }
+    }
+}
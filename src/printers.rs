@@ -0,0 +1,146 @@
+// `--gdb-printers printers.py` writes gdb pretty-printers for the structs
+// whose json has a "printer", and embeds them in .debug_gdb_scripts so gdb
+// loads them along with the debug file (once `add-auto-load-safe-path` lets
+// it, otherwise `source printers.py`):
+//
+//   "node": {"size": 16, "fields": [...], "printer": {"list": {"next": "next"}}}
+//
+// shows a heap list as `{[0] = ..., [1] = ...}` instead of the first node
+// with a pointer to the rest. lists stop at a node seen before and at LIMIT
+// entries, arrays at LIMIT as well. the members named have to exist, their
+// types are gdb's to make sense of.
+
+use crate::gdb_scripts::quote;
+use crate::{BinjaType, Class, DynErr, HashMap, Printer};
+
+const PRELUDE: &str = r#"# pretty-printers teemo generated from the export's "printer" hints
+import re
+
+import gdb
+import gdb.printing
+
+# how many list nodes or array items get shown
+LIMIT = 200
+
+
+class ListPrinter:
+    def __init__(self, val, next, value):
+        self.val, self.next, self.value = val, next, value
+
+    def to_string(self):
+        return str(self.val.type)
+
+    def children(self):
+        node, seen = self.val.address, set()
+        while node and int(node) not in seen and len(seen) < LIMIT:
+            item = node.dereference()
+            name = "[%d]" % len(seen)
+            seen.add(int(node))
+            if self.value:
+                yield name, item[self.value]
+            else:
+                yield name, item.format_string(raw=True)
+            node = item[self.next]
+
+    def display_hint(self):
+        return "array"
+
+
+class StringPrinter:
+    def __init__(self, val, data, length):
+        self.val, self.data, self.length = val, data, length
+
+    def to_string(self):
+        data = self.val[self.data]
+        if data.type.strip_typedefs().code == gdb.TYPE_CODE_ARRAY:
+            data = data[0].address
+        return data.lazy_string(length=int(self.val[self.length]))
+
+    def display_hint(self):
+        return "string"
+
+
+class ArrayPrinter:
+    def __init__(self, val, data, length):
+        self.val, self.data, self.length = val, data, length
+
+    def to_string(self):
+        return "%s of %d" % (self.val.type, int(self.val[self.length]))
+
+    def children(self):
+        data = self.val[self.data]
+        for i in range(min(int(self.val[self.length]), LIMIT)):
+            yield "[%d]" % i, data[i]
+
+    def display_hint(self):
+        return "array"
+
+
+def build():
+    printers = gdb.printing.RegexpCollectionPrettyPrinter("teemo")
+"#;
+
+const REGISTER: &str = r#"    return printers
+
+
+gdb.printing.register_pretty_printer(gdb.current_objfile(), build(), replace=True)
+"#;
+
+// the structs with a printer, an error when one names members it doesn't
+// have
+pub fn hinted(types: &HashMap<String, BinjaType>) -> Result<Vec<(&str, &Printer)>, DynErr> {
+    let mut hinted = Vec::new();
+    for (name, binja_type) in types.iter() {
+        let (BinjaType::Structure(layout)
+        | BinjaType::Union(layout)
+        | BinjaType::Class(Class { layout, .. })) = binja_type
+        else {
+            continue;
+        };
+        let Some(printer) = &layout.printer else {
+            continue;
+        };
+        let members = match printer {
+            Printer::List { next, value } => vec![Some(next), value.as_ref()],
+            Printer::String { data, length } | Printer::Array { data, length } => {
+                vec![Some(data), Some(length)]
+            }
+        };
+        for member in members.into_iter().flatten() {
+            if !layout.fields.iter().any(|field| field.name == *member) {
+                return Err(
+                    format!("printer of `{}` names a member `{}` it lacks", name, member).into(),
+                );
+            }
+        }
+        hinted.push((name.as_str(), printer));
+    }
+    Ok(hinted)
+}
+
+pub fn script(types: &HashMap<String, BinjaType>) -> Result<String, DynErr> {
+    let mut script = String::from(PRELUDE);
+    for (name, printer) in hinted(types)? {
+        let printer = match printer {
+            Printer::List { next, value } => format!(
+                "ListPrinter(val, {}, {})",
+                quote(next),
+                value.as_deref().map_or(String::from("None"), quote)
+            ),
+            Printer::String { data, length } => {
+                format!("StringPrinter(val, {}, {})", quote(data), quote(length))
+            }
+            Printer::Array { data, length } => {
+                format!("ArrayPrinter(val, {}, {})", quote(data), quote(length))
+            }
+        };
+        script.push_str(&format!(
+            "    printers.add_printer({}, \"^%s$\" % re.escape({}), lambda val: {})\n",
+            quote(name),
+            quote(name),
+            printer
+        ));
+    }
+    script.push_str(REGISTER);
+    Ok(script)
+}
@@ -0,0 +1,221 @@
+// project mode: a manifest lists every binary of a challenge (the main program
+// plus the shared libraries shipped with it). types from all of their exports
+// are pooled into one database, so every debug file describes a given type
+// identically, while each binary keeps its own variables.
+//
+// {
+//     "types": ["common"],
+//     "binaries": [
+//         {"name": "chall", "export": "chall", "output": "chall.debug"},
+//         {"name": "libfoo.so", "export": "libfoo", "output": "libfoo.debug"}
+//     ]
+// }
+//
+// paths are relative to the manifest.
+//
+// with `--debug-sup shared.sup` the pooled types are written once into a
+// DWARF 5 supplementary file instead, and each binary's debug file only holds
+// its variables plus DW_FORM_ref_sup references into it, the way dwz factors
+// out common debug info. the supplementary path is recorded in each
+// .debug_sup as given, so keep it relative to where the debug files live.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::info;
+use serde::Deserialize;
+
+use crate::overlay::Overlay;
+use crate::{
+    collect_export, generate, presets, DebugSup, DynErr, Err, HashMap, Options, Supplementary,
+    TypeSet,
+};
+
+#[derive(Deserialize)]
+struct Project {
+    // export directories that only contribute types
+    #[serde(default)]
+    types: Vec<PathBuf>,
+    binaries: Vec<Binary>,
+}
+
+#[derive(Deserialize)]
+struct Binary {
+    name: String,
+    // export directory with this binary's variables.json and any types of its own
+    export: PathBuf,
+    output: PathBuf,
+}
+
+fn merge<T: PartialEq>(
+    into: &mut HashMap<String, T>,
+    from: HashMap<String, T>,
+    origin: &str,
+) -> Err {
+    for (name, definition) in from {
+        match into.get(&name) {
+            Some(existing) if *existing != definition => {
+                return Err(format!(
+                    "type `{}` from {} conflicts with an earlier definition",
+                    name, origin
+                )
+                .into());
+            }
+            Some(_) => {}
+            None => _ = into.insert(name, definition),
+        }
+    }
+    Ok(())
+}
+
+// identical definitions from different exports collapse into one, differing
+// ones are an error since the debug files would disagree about the type
+fn pool(shared: &mut TypeSet, other: TypeSet, origin: &str) -> Result<TypeSet, DynErr> {
+    merge(&mut shared.structs, other.structs, origin)?;
+    merge(&mut shared.unions, other.unions, origin)?;
+    merge(&mut shared.integers, other.integers, origin)?;
+    merge(&mut shared.pointers, other.pointers, origin)?;
+    merge(&mut shared.typedefs, other.typedefs, origin)?;
+    merge(&mut shared.functions, other.functions, origin)?;
+    merge(&mut shared.enums, other.enums, origin)?;
+    merge(&mut shared.arrays, other.arrays, origin)?;
+    merge(&mut shared.vectors, other.vectors, origin)?;
+    merge(&mut shared.qualifiers, other.qualifiers, origin)?;
+    merge(&mut shared.classes, other.classes, origin)?;
+    // what's left is the export's own
+    Ok(TypeSet {
+        variables: other.variables,
+        constants: other.constants,
+        subprograms: other.subprograms,
+        ..Default::default()
+    })
+}
+
+pub fn run(options: &Options, manifest: &Path) -> Err {
+    if options.debug_sup.is_some() && options.verify_binja {
+        return Err("--verify-binja can't follow references into a --debug-sup file".into());
+    }
+    // each binary has its own stubs and frames at its own addresses
+    if options.trampolines.is_some() {
+        return Err("--trampolines doesn't apply to --project".into());
+    }
+    if options.frames.is_some() {
+        return Err("--frames doesn't apply to --project".into());
+    }
+
+    let project: Project = match fs::read_to_string(manifest) {
+        Ok(json) => serde_json::from_str(&json)?,
+        Err(e) => return Err(format!("{}: {}", manifest.display(), e).into()),
+    };
+    let root = manifest.parent().unwrap_or(Path::new("."));
+
+    let mut pooled = TypeSet::default();
+    let mut common_constants = HashMap::new();
+    for dir in project.types.iter() {
+        // variables and subprograms in a types-only export have no binary to
+        // belong to, constants need no address and go into every binary
+        let own = pool(
+            &mut pooled,
+            collect_export(
+                &root.join(dir),
+                options.format,
+                false,
+                options.legacy_export,
+                &options.defines,
+            )?,
+            &dir.display().to_string(),
+        )?;
+        common_constants.extend(own.constants);
+    }
+
+    let mut variables = Vec::new();
+    let mut constants = Vec::new();
+    let mut subprograms = Vec::new();
+    for binary in project.binaries.iter() {
+        let own = pool(
+            &mut pooled,
+            collect_export(
+                &root.join(&binary.export),
+                options.format,
+                false,
+                options.legacy_export,
+                &options.defines,
+            )?,
+            &binary.name,
+        )?;
+        let mut binary_constants = common_constants.clone();
+        binary_constants.extend(own.constants);
+        variables.push(own.variables);
+        constants.push(binary_constants);
+        subprograms.push(own.subprograms);
+    }
+
+    // exports win over the preset, same as outside project mode
+    let mut shared = match &options.preset {
+        Some(preset) => presets::load(preset)?,
+        None => TypeSet::default(),
+    };
+    shared.extend(pooled);
+    let (mut types, _) = shared.split();
+
+    // overlays fix the pooled types once, and each binary's variables where
+    // it has them
+    for path in options.overlays.iter() {
+        let overlay = Overlay::load(path)?;
+        if overlay.adds_variables() {
+            return Err(format!("{}: can't add variables in project mode", path.display()).into());
+        }
+        overlay.apply_types(&mut types)?;
+        let mut patched = Vec::new();
+        for variables in variables.iter_mut() {
+            patched.extend(overlay.apply_variables(&types, variables));
+        }
+        overlay.check_variables(&patched)?;
+    }
+
+    let supplementary = match &options.debug_sup {
+        Some(path) => {
+            info!("shared types: {}", root.join(path).display());
+            let types = generate(
+                options,
+                &types,
+                &HashMap::new(),
+                &HashMap::new(),
+                &HashMap::new(),
+                &root.join(path),
+                DebugSup::Supplementary,
+            )?;
+            Some(Supplementary {
+                filename: path.display().to_string(),
+                types,
+            })
+        }
+        None => None,
+    };
+
+    for (((binary, variables), constants), subprograms) in project
+        .binaries
+        .iter()
+        .zip(variables.iter())
+        .zip(constants.iter())
+        .zip(subprograms.iter())
+    {
+        let output = root.join(&binary.output);
+        info!("{}: {}", binary.name, output.display());
+        let sup = match &supplementary {
+            Some(supplementary) => DebugSup::Reference(supplementary),
+            None => DebugSup::None,
+        };
+        generate(
+            options,
+            &types,
+            variables,
+            constants,
+            subprograms,
+            &output,
+            sup,
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,897 @@
+// reads DWARF back into plain DIE trees, either straight from the sections we
+// just generated or from an existing binary, and converts those into the same
+// type model the json exports deserialize into.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use gimli::read::{AttributeValue as ReadValue, EndianSlice, Operation};
+use gimli::write::{EndianVec, Sections, Writer};
+use gimli::{DwTag, RunTimeEndian, SectionId, UnitSectionOffset, UnitType};
+use goblin::elf::Elf;
+
+use crate::layout::size_of;
+use crate::{
+    Access, Array, Base, Class, Convention, DynErr, Encoding, Endianity, Enum, EnumField, Field,
+    Function, GlobalVariable, HashMap, Integer, Method, Parameter, Pointer, Qualified,
+    ReferenceKind, Structure, Subprogram, TypeSet, Typedef, Vector,
+};
+
+type Reader<'a> = EndianSlice<'a, RunTimeEndian>;
+
+// DIEs of type units are keyed past every .debug_info offset
+const TYPES: usize = 1 << (usize::BITS - 1);
+
+const AGGREGATES: [DwTag; 3] = [
+    gimli::DW_TAG_structure_type,
+    gimli::DW_TAG_class_type,
+    gimli::DW_TAG_union_type,
+];
+
+const SCOPED: [DwTag; 5] = [
+    gimli::DW_TAG_structure_type,
+    gimli::DW_TAG_class_type,
+    gimli::DW_TAG_union_type,
+    gimli::DW_TAG_enumeration_type,
+    gimli::DW_TAG_typedef,
+];
+
+// DIEs are keyed by their .debug_info offset so references work across units
+pub struct Die {
+    pub tag: DwTag,
+    pub name: Option<String>,
+    pub size: Option<u64>,
+    pub target: Option<usize>,
+    pub location: Option<u64>,
+    // bitfields, the offset in bits from the start of the aggregate
+    pub bit_size: Option<u64>,
+    pub bit_offset: Option<u64>,
+    // enumerators, up to 128 bits
+    pub value: Option<i128>,
+    pub count: Option<u64>,
+    // a variable's location or a subprogram's low_pc
+    pub address: Option<u64>,
+    // how far a subprogram's code goes past its low_pc
+    pub length: Option<u64>,
+    pub external: bool,
+    pub signed: bool,
+    pub encoding: Option<Encoding>,
+    pub endianity: Option<Endianity>,
+    pub declaration: bool,
+    pub convention: Option<Convention>,
+    // only the protected and private ones
+    pub access: Option<Access>,
+    // a class's DW_AT_containing_type
+    pub containing: Option<usize>,
+    pub artificial: bool,
+    // an array's DW_AT_GNU_vector
+    pub vector: bool,
+    // virtual methods' slot, and whether they're pure
+    pub vtable_index: Option<u64>,
+    pub pure: bool,
+    pub children: Vec<usize>,
+}
+
+pub struct Dies {
+    pub dies: HashMap<usize, Die>,
+    // direct children of each compile unit
+    pub roots: Vec<usize>,
+    pub address_size: u64,
+    pub endianity: Endianity,
+}
+
+impl Dies {
+    // gimli doesn't write .debug_types, see type_units
+    pub fn from_sections(
+        sections: &Sections<EndianVec<RunTimeEndian>>,
+        debug_types: &[u8],
+    ) -> Result<Dies, DynErr> {
+        Dies::load(
+            |id| match id {
+                SectionId::DebugTypes => debug_types,
+                _ => sections.get(id).map(|w| w.slice()).unwrap_or(&[]),
+            },
+            sections.debug_info.0.endian(),
+        )
+    }
+
+    pub fn from_elf(path: &Path) -> Result<Dies, DynErr> {
+        let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let elf = Elf::parse(&data)?;
+        if elf.section_headers.iter().any(|header| {
+            header.sh_flags & goblin::elf::section_header::SHF_COMPRESSED as u64 != 0
+                || elf
+                    .shdr_strtab
+                    .get_at(header.sh_name)
+                    .is_some_and(|name| name.starts_with(".zdebug_"))
+        }) {
+            return Err(format!(
+                "{} has compressed debug sections, objcopy --decompress-debug-sections it first",
+                path.display()
+            )
+            .into());
+        }
+        let endian = if elf.little_endian {
+            RunTimeEndian::Little
+        } else {
+            RunTimeEndian::Big
+        };
+
+        Dies::load(
+            |id| {
+                elf.section_headers
+                    .iter()
+                    .find(|header| elf.shdr_strtab.get_at(header.sh_name) == Some(id.name()))
+                    .and_then(|header| {
+                        data.get(header.sh_offset as usize..)?
+                            .get(..header.sh_size as usize)
+                    })
+                    .unwrap_or(&[])
+            },
+            endian,
+        )
+    }
+
+    fn load<'a>(
+        section: impl Fn(SectionId) -> &'a [u8],
+        endian: RunTimeEndian,
+    ) -> Result<Dies, DynErr> {
+        let dwarf = gimli::read::Dwarf::load(|id| -> Result<Reader, gimli::Error> {
+            Ok(EndianSlice::new(section(id), endian))
+        })?;
+
+        let mut dies = Dies {
+            dies: HashMap::new(),
+            roots: Vec::new(),
+            address_size: 8,
+            endianity: match endian {
+                RunTimeEndian::Little => Endianity::Little,
+                RunTimeEndian::Big => Endianity::Big,
+            },
+        };
+
+        let mut headers = Vec::new();
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            headers.push(header);
+        }
+        let mut type_units = dwarf.type_units();
+        while let Some(header) = type_units.next()? {
+            headers.push(header);
+        }
+        // type units by signature, and the DIEs referring to them
+        let mut signatures: HashMap<u64, usize> = HashMap::new();
+        let mut pending: Vec<(usize, u64)> = Vec::new();
+
+        for header in headers {
+            let unit = dwarf.unit(header)?;
+            dies.address_size = unit.encoding().address_size as u64;
+            let absolute = |offset: gimli::UnitOffset| match offset.to_unit_section_offset(&unit) {
+                UnitSectionOffset::DebugInfoOffset(offset) => Some(offset.0),
+                UnitSectionOffset::DebugTypesOffset(offset) => Some(TYPES + offset.0),
+            };
+            // only the type itself is a root of a type unit, not its copies
+            // of the types it uses
+            let type_die = match unit.header.type_() {
+                UnitType::Type {
+                    type_signature,
+                    type_offset,
+                } => {
+                    let offset = absolute(type_offset);
+                    if let Some(offset) = offset {
+                        _ = signatures.insert(type_signature.0, offset);
+                    }
+                    offset
+                }
+                _ => None,
+            };
+
+            let mut parents: Vec<usize> = Vec::new();
+            let mut namespaces: BTreeSet<usize> = BTreeSet::new();
+            let mut depth = 0isize;
+            let mut entries = unit.entries();
+            while let Some((delta, entry)) = entries.next_dfs()? {
+                depth += delta;
+                parents.truncate(depth.max(0) as usize);
+
+                let name = match entry.attr_value(gimli::DW_AT_name)? {
+                    Some(value) => Some(
+                        dwarf
+                            .attr_string(&unit, value)?
+                            .to_string_lossy()
+                            .into_owned(),
+                    ),
+                    None => None,
+                };
+                // what's in a namespace, or a type in a class, gets the
+                // scoped name it has in the export
+                let scope = (parents.last().map(|parent| &dies.dies[parent])).filter(|parent| {
+                    parent.tag == gimli::DW_TAG_namespace
+                        || AGGREGATES.contains(&parent.tag) && SCOPED.contains(&entry.tag())
+                });
+                let name = match (scope, name) {
+                    (Some(scope), Some(name)) => Some(format!(
+                        "{}::{}",
+                        scope.name.as_deref().unwrap_or("(anonymous namespace)"),
+                        name
+                    )),
+                    (_, name) => name,
+                };
+                let mut signature = None;
+                let target = match entry.attr_value(gimli::DW_AT_type)? {
+                    Some(ReadValue::UnitRef(offset)) => absolute(offset),
+                    Some(ReadValue::DebugInfoRef(offset)) => Some(offset.0),
+                    Some(ReadValue::DebugTypesRef(type_signature)) => {
+                        signature = Some(type_signature.0);
+                        None
+                    }
+                    _ => None,
+                };
+                let vtable_index = match entry.attr_value(gimli::DW_AT_vtable_elem_location)? {
+                    Some(ReadValue::Exprloc(expression)) => {
+                        match expression.operations(unit.encoding()).next()? {
+                            Some(Operation::UnsignedConstant { value }) => Some(value),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+                let containing = match entry.attr_value(gimli::DW_AT_containing_type)? {
+                    Some(ReadValue::UnitRef(offset)) => absolute(offset),
+                    Some(ReadValue::DebugInfoRef(offset)) => Some(offset.0),
+                    _ => None,
+                };
+                let udata = |at| -> Result<Option<u64>, gimli::Error> {
+                    Ok(entry.attr(at)?.and_then(|attr| {
+                        attr.udata_value()
+                            .or_else(|| attr.sdata_value().map(|value| value as u64))
+                    }))
+                };
+                let count = match udata(gimli::DW_AT_count)? {
+                    Some(count) => Some(count),
+                    None => udata(gimli::DW_AT_upper_bound)?.map(|bound| bound + 1),
+                };
+                let address = match entry.attr_value(gimli::DW_AT_location)? {
+                    Some(ReadValue::Exprloc(expression)) => {
+                        match expression.operations(unit.encoding()).next()? {
+                            Some(Operation::Address { address }) => Some(address),
+                            _ => None,
+                        }
+                    }
+                    _ => match entry.attr_value(gimli::DW_AT_low_pc)? {
+                        Some(ReadValue::Addr(address)) => Some(address),
+                        _ => None,
+                    },
+                };
+                // DWARF 4 on gives the end as a length from low_pc
+                let length = match (entry.attr_value(gimli::DW_AT_high_pc)?, address) {
+                    (Some(ReadValue::Addr(end)), Some(address)) => {
+                        Some(end.saturating_sub(address))
+                    }
+                    (Some(_), _) => udata(gimli::DW_AT_high_pc)?,
+                    _ => None,
+                };
+                let (signed, encoding) = match entry.attr_value(gimli::DW_AT_encoding)? {
+                    Some(ReadValue::Encoding(gimli::DW_ATE_signed)) => (true, None),
+                    Some(ReadValue::Encoding(gimli::DW_ATE_signed_char)) => {
+                        (true, Some(Encoding::Char))
+                    }
+                    Some(ReadValue::Encoding(gimli::DW_ATE_unsigned_char)) => {
+                        (false, Some(Encoding::Char))
+                    }
+                    Some(ReadValue::Encoding(gimli::DW_ATE_float)) => {
+                        (false, Some(Encoding::Float))
+                    }
+                    Some(ReadValue::Encoding(gimli::DW_ATE_boolean)) => {
+                        (false, Some(Encoding::Bool))
+                    }
+                    _ => (false, None),
+                };
+
+                let endianity = match entry.attr_value(gimli::DW_AT_endianity)? {
+                    Some(ReadValue::Endianity(gimli::DW_END_big)) => Some(Endianity::Big),
+                    Some(ReadValue::Endianity(gimli::DW_END_little)) => Some(Endianity::Little),
+                    _ => None,
+                };
+
+                // wider ones are their bytes in target order
+                let value = match entry.attr_value(gimli::DW_AT_const_value)? {
+                    Some(ReadValue::Block(block)) if block.len() <= 16 => {
+                        let mut buffer = [0u8; 16];
+                        let bytes = block.slice();
+                        match endian {
+                            RunTimeEndian::Big => {
+                                for (i, byte) in bytes.iter().rev().enumerate() {
+                                    buffer[i] = *byte;
+                                }
+                            }
+                            RunTimeEndian::Little => buffer[..bytes.len()].copy_from_slice(bytes),
+                        }
+                        Some(i128::from_le_bytes(buffer))
+                    }
+                    _ => udata(gimli::DW_AT_const_value)?.map(|value| value as i128),
+                };
+                let size = udata(gimli::DW_AT_byte_size)?;
+                let location = udata(gimli::DW_AT_data_member_location)?;
+                let bit_size = udata(gimli::DW_AT_bit_size)?;
+                // before DWARF 4 the bit offset counted from the most
+                // significant bit of a storage unit of DW_AT_byte_size
+                let bit_offset = match udata(gimli::DW_AT_data_bit_offset)? {
+                    Some(bit_offset) => Some(bit_offset),
+                    None => match (udata(gimli::DW_AT_bit_offset)?, bit_size, size) {
+                        (Some(bit_offset), Some(bits), Some(bytes)) => {
+                            let start = location.unwrap_or(0) * 8;
+                            Some(match endian {
+                                RunTimeEndian::Big => start + bit_offset,
+                                RunTimeEndian::Little => start + bytes * 8 - bit_offset - bits,
+                            })
+                        }
+                        _ => None,
+                    },
+                };
+
+                let die = Die {
+                    tag: entry.tag(),
+                    name,
+                    size,
+                    target,
+                    location,
+                    bit_size,
+                    bit_offset,
+                    value,
+                    count,
+                    address,
+                    length,
+                    external: matches!(
+                        entry.attr_value(gimli::DW_AT_external)?,
+                        Some(ReadValue::Flag(true))
+                    ),
+                    signed,
+                    encoding,
+                    endianity,
+                    declaration: matches!(
+                        entry.attr_value(gimli::DW_AT_declaration)?,
+                        Some(ReadValue::Flag(true))
+                    ),
+                    convention: match entry.attr_value(gimli::DW_AT_calling_convention)? {
+                        Some(ReadValue::CallingConvention(cc)) => Convention::from_dwarf(cc),
+                        _ => None,
+                    },
+                    access: match entry.attr_value(gimli::DW_AT_accessibility)? {
+                        Some(ReadValue::Accessibility(access)) => Access::from_dwarf(access),
+                        _ => None,
+                    },
+                    containing,
+                    artificial: matches!(
+                        entry.attr_value(gimli::DW_AT_artificial)?,
+                        Some(ReadValue::Flag(true))
+                    ),
+                    vector: matches!(
+                        entry.attr_value(gimli::DW_AT_GNU_vector)?,
+                        Some(ReadValue::Flag(true))
+                    ),
+                    vtable_index,
+                    pure: matches!(
+                        entry.attr_value(gimli::DW_AT_virtuality)?,
+                        Some(ReadValue::Virtuality(gimli::DW_VIRTUALITY_pure_virtual))
+                    ),
+                    children: Vec::new(),
+                };
+
+                let Some(offset) = absolute(entry.offset()) else {
+                    continue;
+                };
+                if let Some(signature) = signature {
+                    pending.push((offset, signature));
+                }
+                if let Some(parent) = parents.last() {
+                    dies.dies.get_mut(parent).unwrap().children.push(offset);
+                }
+                let top = depth == 1
+                    || parents
+                        .last()
+                        .is_some_and(|parent| namespaces.contains(parent));
+                if top && type_die.is_none_or(|type_die| type_die == offset) {
+                    dies.roots.push(offset);
+                }
+                // the namespaces' contents are roots too
+                if top && entry.tag() == gimli::DW_TAG_namespace {
+                    _ = namespaces.insert(offset);
+                }
+                dies.dies.insert(offset, die);
+                parents.push(offset);
+            }
+        }
+
+        for (offset, signature) in pending {
+            dies.dies.get_mut(&offset).unwrap().target = signatures.get(&signature).copied();
+        }
+
+        Ok(dies)
+    }
+
+    pub fn get(&self, offset: Option<usize>) -> Option<&Die> {
+        offset.and_then(|offset| self.dies.get(&offset))
+    }
+
+    // the DIEs by tag and name, made once to look up many. a root wins
+    // over a type of the same name scoped by a class
+    pub fn by_name(&self) -> HashMap<(DwTag, &str), &Die> {
+        let mut names = HashMap::new();
+        for die in (self.roots.iter().map(|offset| &self.dies[offset])).chain(self.dies.values()) {
+            if let Some(name) = &die.name {
+                _ = names.entry((die.tag, name.as_str())).or_insert(die);
+            }
+        }
+        names
+    }
+
+    pub fn children<'a>(&'a self, die: &'a Die) -> impl Iterator<Item = &'a Die> {
+        die.children.iter().map(|offset| &self.dies[offset])
+    }
+
+    pub fn to_type_set(&self) -> TypeSet {
+        let mut converter = Converter {
+            dies: self,
+            names: HashMap::new(),
+            anonymous: 0,
+            type_set: TypeSet::default(),
+        };
+
+        for offset in self.roots.iter() {
+            let die = &self.dies[offset];
+            // a variable's type might only be reachable from it, like a
+            // type scoped by a class
+            if die.tag == gimli::DW_TAG_variable {
+                converter.name(die.target, 0);
+                continue;
+            }
+            converter.name(Some(*offset), 0);
+        }
+
+        // the functions defined here, with their prototypes
+        let mut subprograms = HashMap::new();
+        for offset in self.roots.iter() {
+            let die = &self.dies[offset];
+            if let (gimli::DW_TAG_subprogram, Some(name), Some(address), false) =
+                (die.tag, &die.name, die.address, die.declaration)
+            {
+                let typename = converter.prototype(die, 0);
+                subprograms.insert(
+                    address,
+                    Subprogram {
+                        name: name.clone(),
+                        size: die.length.unwrap_or(0),
+                        typename,
+                        locals: Vec::new(),
+                        local: !die.external,
+                        decl: None,
+                        space: None,
+                    },
+                );
+            }
+        }
+
+        let mut type_set = converter.type_set;
+        let mut variables = HashMap::new();
+        for offset in self.roots.iter() {
+            let die = &self.dies[offset];
+            if let (gimli::DW_TAG_variable, Some(name), Some(address)) =
+                (die.tag, &die.name, die.address)
+            {
+                let typename = match die.target {
+                    Some(_) => converter.names.get(&die.target.unwrap()).cloned(),
+                    None => Some(String::new()),
+                };
+                variables.insert(address, (name.clone(), typename.unwrap_or_default()));
+            }
+        }
+
+        let (types, _) = std::mem::take(&mut type_set).split();
+        let variables = variables
+            .into_iter()
+            .map(|(address, (name, typename))| {
+                let size = size_of(&types, &typename).unwrap_or(0);
+                (
+                    address,
+                    GlobalVariable {
+                        name,
+                        size,
+                        typename,
+                        decl: None,
+                        space: None,
+                    },
+                )
+            })
+            .collect();
+
+        let mut type_set = TypeSet::join(types, variables);
+        type_set.subprograms = subprograms;
+        type_set
+    }
+}
+
+struct Converter<'a> {
+    dies: &'a Dies,
+    names: HashMap<usize, String>,
+    anonymous: usize,
+    type_set: TypeSet,
+}
+
+impl Converter<'_> {
+    fn taken(&self, name: &str) -> bool {
+        let set = &self.type_set;
+        set.structs.contains_key(name)
+            || set.unions.contains_key(name)
+            || set.integers.contains_key(name)
+            || set.pointers.contains_key(name)
+            || set.typedefs.contains_key(name)
+            || set.functions.contains_key(name)
+            || set.enums.contains_key(name)
+            || set.arrays.contains_key(name)
+            || set.vectors.contains_key(name)
+            || set.qualifiers.contains_key(name)
+            || set.classes.contains_key(name)
+    }
+
+    // names the type at `offset` the way binja would, converting it on first use
+    fn name(&mut self, offset: Option<usize>, depth: usize) -> String {
+        let Some(offset) = offset else {
+            return String::new();
+        };
+        if let Some(name) = self.names.get(&offset) {
+            return name.clone();
+        }
+        let Some(die) = self.dies.dies.get(&offset) else {
+            return String::new();
+        };
+        if depth > 64 {
+            return String::new();
+        }
+
+        let keyword = match die.tag {
+            gimli::DW_TAG_structure_type | gimli::DW_TAG_class_type => "struct",
+            gimli::DW_TAG_union_type => "union",
+            gimli::DW_TAG_enumeration_type => "enum",
+            _ => "",
+        };
+
+        // the writer's stand-in for "" members and parameters
+        if die.tag == gimli::DW_TAG_unspecified_type {
+            return String::new();
+        }
+
+        // _Atomic doesn't exist in the model, look through it
+        if die.tag == gimli::DW_TAG_atomic_type {
+            let name = self.name(die.target, depth + 1);
+            self.names.insert(offset, name.clone());
+            return name;
+        }
+        if matches!(
+            die.tag,
+            gimli::DW_TAG_const_type | gimli::DW_TAG_volatile_type | gimli::DW_TAG_restrict_type
+        ) {
+            let target = self.name(die.target, depth + 1);
+            // nested qualifiers are one type, `const volatile int`
+            let mut qualified = match self.type_set.qualifiers.get(&target) {
+                Some(inner) => inner.clone(),
+                None => Qualified {
+                    target,
+                    constant: false,
+                    volatile: false,
+                    restrict: false,
+                },
+            };
+            match die.tag {
+                gimli::DW_TAG_const_type => qualified.constant = true,
+                gimli::DW_TAG_volatile_type => qualified.volatile = true,
+                _ => qualified.restrict = true,
+            }
+            let qualifiers: Vec<&str> = [
+                (qualified.constant, "const"),
+                (qualified.volatile, "volatile"),
+                (qualified.restrict, "restrict"),
+            ]
+            .into_iter()
+            .filter_map(|(set, word)| set.then_some(word))
+            .collect();
+            // they follow the `*` of a pointer, `char* const`
+            let name = match qualified.target.as_str() {
+                "" => format!("{} void", qualifiers.join(" ")),
+                target if target.ends_with('*') => {
+                    format!("{} {}", target, qualifiers.join(" "))
+                }
+                target => format!("{} {}", qualifiers.join(" "), target),
+            };
+            self.names.insert(offset, name.clone());
+            self.type_set.qualifiers.insert(name.clone(), qualified);
+            return name;
+        }
+
+        let integer = Integer {
+            size: die.size.unwrap_or(0),
+            signed: die.signed,
+            endianity: die.endianity,
+            encoding: die.encoding,
+            bits: die.bit_size,
+        };
+        // register a name before converting so self references terminate
+        let name = match (&die.name, die.tag) {
+            (Some(name), _) if !self.taken(name) => name.clone(),
+            // every type unit has its own copy of the base types it uses
+            (Some(name), gimli::DW_TAG_base_type)
+                if self.type_set.integers.get(name) == Some(&integer) =>
+            {
+                name.clone()
+            }
+            (Some(name), _) if !keyword.is_empty() => format!("{} {}", keyword, name),
+            (Some(name), _) => format!("{}@{:#x}", name, offset),
+            (None, gimli::DW_TAG_structure_type)
+            | (None, gimli::DW_TAG_class_type)
+            | (None, gimli::DW_TAG_union_type)
+            | (None, gimli::DW_TAG_enumeration_type) => {
+                self.anonymous += 1;
+                format!("anon.{}", self.anonymous - 1)
+            }
+            (None, _) => String::new(),
+        };
+        if !name.is_empty() {
+            self.names.insert(offset, name.clone());
+        }
+
+        let dies = self.dies;
+        match die.tag {
+            gimli::DW_TAG_base_type => {
+                self.type_set.integers.insert(name.clone(), integer);
+                name
+            }
+            gimli::DW_TAG_structure_type | gimli::DW_TAG_class_type | gimli::DW_TAG_union_type => {
+                // placeholder until the fields are known, for self references
+                let structure = Structure {
+                    size: die.size.unwrap_or(0),
+                    anon: die.name.is_none(),
+                    fields: Vec::new(),
+                    variant: None,
+                    declaration: die.declaration,
+                    decl: None,
+                    printer: None,
+                };
+                let union = die.tag == gimli::DW_TAG_union_type;
+                // structs with bases or member functions are classes too
+                let class = !union
+                    && (die.tag == gimli::DW_TAG_class_type
+                        || dies.children(die).any(|child| {
+                            matches!(
+                                child.tag,
+                                gimli::DW_TAG_inheritance | gimli::DW_TAG_subprogram
+                            )
+                        }));
+                if union {
+                    self.type_set.unions.insert(name.clone(), structure);
+                } else if class {
+                    self.type_set.classes.insert(
+                        name.clone(),
+                        Class {
+                            layout: structure,
+                            bases: Vec::new(),
+                            methods: Vec::new(),
+                            containing_type: None,
+                            vtable_pointer: None,
+                            vtable: None,
+                        },
+                    );
+                } else {
+                    self.type_set.structs.insert(name.clone(), structure);
+                }
+
+                let fields: Vec<Field> = dies
+                    .children(die)
+                    .filter(|child| child.tag == gimli::DW_TAG_member)
+                    .map(|member| {
+                        let bits = member.bit_size.map(|_| member.bit_offset.unwrap_or(0));
+                        Field {
+                            offset: match bits {
+                                Some(bits) => bits / 8,
+                                None => member.location.unwrap_or(0),
+                            },
+                            name: member.name.clone().unwrap_or_default(),
+                            typename: self.name(member.target, depth + 1),
+                            endianity: None,
+                            bit_offset: bits.map(|bits| bits % 8),
+                            bit_size: member.bit_size,
+                            access: member.access,
+                        }
+                    })
+                    .collect();
+                if class {
+                    let bases = dies
+                        .children(die)
+                        .filter(|child| child.tag == gimli::DW_TAG_inheritance)
+                        .map(|base| Base {
+                            offset: base.location.unwrap_or(0),
+                            typename: self.name(base.target, depth + 1),
+                            access: base.access,
+                        })
+                        .collect();
+                    let methods = dies
+                        .children(die)
+                        .filter(|child| child.tag == gimli::DW_TAG_subprogram)
+                        .map(|method| Method {
+                            name: method.name.clone().unwrap_or_default(),
+                            typename: self.prototype(method, depth + 1),
+                            access: method.access,
+                            vtable_index: method.vtable_index,
+                            pure: method.pure,
+                        })
+                        .collect();
+                    let containing_type =
+                        die.containing.map(|_| self.name(die.containing, depth + 1));
+                    let vtable_pointer = (dies.children(die))
+                        .find(|child| child.tag == gimli::DW_TAG_member && child.artificial)
+                        .and_then(|member| member.name.clone());
+                    let class = self.type_set.classes.get_mut(&name).unwrap();
+                    class.vtable_pointer = vtable_pointer;
+                    class.layout.fields = fields;
+                    class.bases = bases;
+                    class.methods = methods;
+                    class.containing_type = containing_type;
+                    return name;
+                }
+                let aggregates = if union {
+                    &mut self.type_set.unions
+                } else {
+                    &mut self.type_set.structs
+                };
+                aggregates.get_mut(&name).unwrap().fields = fields;
+                name
+            }
+            gimli::DW_TAG_enumeration_type => {
+                let signed = die.signed || dies.get(die.target).is_some_and(|base| base.signed);
+                let mut enumeration = Enum {
+                    size: die.size.unwrap_or(0),
+                    signed,
+                    fields: Vec::new(),
+                    decl: None,
+                };
+                enumeration.fields = dies
+                    .children(die)
+                    .filter(|child| child.tag == gimli::DW_TAG_enumerator)
+                    .map(|enumerator| EnumField {
+                        name: enumerator.name.clone().unwrap_or_default(),
+                        value: enumeration.normalize(enumerator.value.unwrap_or(0)),
+                    })
+                    .collect();
+                self.type_set.enums.insert(name.clone(), enumeration);
+                name
+            }
+            gimli::DW_TAG_typedef => {
+                let target = self.name(die.target, depth + 1);
+                // `typedef struct foo foo` is a single type to binja
+                if target == name {
+                    return name;
+                }
+                self.type_set
+                    .typedefs
+                    .insert(name.clone(), Typedef { target, decl: None });
+                name
+            }
+            gimli::DW_TAG_pointer_type
+            | gimli::DW_TAG_reference_type
+            | gimli::DW_TAG_rvalue_reference_type
+            | gimli::DW_TAG_ptr_to_member_type => {
+                let target = self.name(die.target, depth + 1);
+                let pointer = Pointer {
+                    size: die.size.unwrap_or(dies.address_size),
+                    target,
+                    reference: match die.tag {
+                        gimli::DW_TAG_reference_type => Some(ReferenceKind::Lvalue),
+                        gimli::DW_TAG_rvalue_reference_type => Some(ReferenceKind::Rvalue),
+                        _ => None,
+                    },
+                    member_of: (die.tag == gimli::DW_TAG_ptr_to_member_type)
+                        .then(|| self.name(die.containing, depth + 1)),
+                };
+                let name = pointer.spelled();
+                self.names.insert(offset, name.clone());
+                self.type_set.pointers.insert(name.clone(), pointer);
+                name
+            }
+            // gdb spells them this way, with the lane count
+            gimli::DW_TAG_array_type if die.vector => {
+                let vector = Vector {
+                    count: (dies.children(die))
+                        .find(|child| child.tag == gimli::DW_TAG_subrange_type)
+                        .and_then(|subrange| subrange.count)
+                        .unwrap_or(0),
+                    target: self.name(die.target, depth + 1),
+                };
+                let name = format!(
+                    "{} __attribute__ ((vector_size({})))",
+                    vector.target, vector.count
+                );
+                self.names.insert(offset, name.clone());
+                self.type_set.vectors.insert(name.clone(), vector);
+                name
+            }
+            gimli::DW_TAG_array_type => {
+                let target = self.name(die.target, depth + 1);
+                let mut counts = dies
+                    .children(die)
+                    .filter(|child| child.tag == gimli::DW_TAG_subrange_type)
+                    .map(|subrange| subrange.count.unwrap_or(0));
+                let array = Array {
+                    count: counts.next().unwrap_or(0),
+                    dimensions: counts.collect(),
+                    target,
+                    string: false,
+                    dynamic: None,
+                };
+                let name = format!(
+                    "{}{}",
+                    if array.target.is_empty() {
+                        "void"
+                    } else {
+                        &array.target
+                    },
+                    array.bounds()
+                );
+                self.names.insert(offset, name.clone());
+                self.type_set.arrays.insert(name.clone(), array);
+                name
+            }
+            gimli::DW_TAG_subroutine_type => {
+                let name = self.prototype(die, depth);
+                self.names.insert(offset, name.clone());
+                name
+            }
+            _ => name,
+        }
+    }
+
+    // the function type of a subroutine type or a method's declaration
+    fn prototype(&mut self, die: &Die, depth: usize) -> String {
+        let dies = self.dies;
+        let returntype = self.name(die.target, depth + 1);
+        let parameters: Vec<Parameter> = dies
+            .children(die)
+            .filter(|child| child.tag == gimli::DW_TAG_formal_parameter)
+            .map(|parameter| Parameter {
+                name: parameter.name.clone().unwrap_or_default(),
+                typename: self.name(parameter.target, depth + 1),
+            })
+            .collect();
+        let variadic = dies
+            .children(die)
+            .any(|child| child.tag == gimli::DW_TAG_unspecified_parameters);
+        let mut rendered: Vec<&str> = parameters
+            .iter()
+            .map(|parameter| match parameter.typename.as_str() {
+                "" => "void",
+                typename => typename,
+            })
+            .collect();
+        if variadic {
+            rendered.push("...");
+        }
+        let name = format!(
+            "{} ({})",
+            if returntype.is_empty() {
+                "void"
+            } else {
+                &returntype
+            },
+            rendered.join(", ")
+        );
+        self.type_set.functions.insert(
+            name.clone(),
+            Function {
+                parameters,
+                returntype,
+                variadic,
+                convention: die.convention,
+            },
+        );
+        name
+    }
+}
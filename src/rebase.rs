@@ -0,0 +1,94 @@
+// moves every address the debug file gets by the same amount, for exports
+// that don't use the addresses the debugger sees:
+//
+//   teemo --image-base 0x400000        # the export has offsets into the image
+//   teemo --rebase-delta -0x100000     # binja rebased it, the binary wasn't
+//
+// --image-base on its own is for the offsets of a PIE loaded at 0 (with --pdb
+// it's where the RVAs go instead, see pdb.rs), --rebase-delta for absolute
+// addresses off by a fixed amount, and the two add up. it happens as the
+// debug file is generated, so the export and the --lines, --frames and
+// --trampolines files stay as they were, and the variables, functions, the
+// ranges of their locals, vtables and everything derived from them (symbols,
+// location expressions, aranges, .gdb_index) all move together.
+
+use crate::trampolines::{Destination, Trampoline};
+use crate::{BinjaType, Class, DynErr, GlobalVariable, HashMap, Options, Subprogram};
+
+// what gets added to every address
+pub fn delta(options: &Options) -> i64 {
+    let image_base = match options.pdb {
+        Some(_) => 0,
+        None => options.image_base.unwrap_or(0) as i64,
+    };
+    image_base.wrapping_add(options.rebase_delta)
+}
+
+pub fn address(address: u64, delta: i64) -> Result<u64, DynErr> {
+    address.checked_add_signed(delta).ok_or_else(|| {
+        format!(
+            "{:#x} moved by {} is outside the address space",
+            address, delta
+        )
+        .into()
+    })
+}
+
+// the same entries under their moved addresses
+pub fn keyed<T>(entries: HashMap<u64, T>, delta: i64) -> Result<HashMap<u64, T>, DynErr> {
+    (entries.into_iter())
+        .map(|(key, value)| Ok((address(key, delta)?, value)))
+        .collect()
+}
+
+pub fn types(
+    types: &HashMap<String, BinjaType>,
+    delta: i64,
+) -> Result<HashMap<String, BinjaType>, DynErr> {
+    let mut types = types.clone();
+    for binja_type in types.values_mut() {
+        if let BinjaType::Class(Class {
+            vtable: Some(vtable),
+            ..
+        }) = binja_type
+        {
+            *vtable = address(*vtable, delta)?;
+        }
+    }
+    Ok(types)
+}
+
+pub fn variables(
+    variables: &HashMap<u64, GlobalVariable>,
+    delta: i64,
+) -> Result<HashMap<u64, GlobalVariable>, DynErr> {
+    keyed(variables.clone(), delta)
+}
+
+pub fn subprograms(
+    subprograms: &HashMap<u64, Subprogram>,
+    delta: i64,
+) -> Result<HashMap<u64, Subprogram>, DynErr> {
+    let mut subprograms = keyed(subprograms.clone(), delta)?;
+    for subprogram in subprograms.values_mut() {
+        for ranged in (subprogram.locals.iter_mut()).flat_map(|local| &mut local.ranges) {
+            ranged.start = address(ranged.start, delta)?;
+            ranged.end = address(ranged.end, delta)?;
+        }
+    }
+    Ok(subprograms)
+}
+
+// targets given by name are the debugger's to find
+pub fn trampolines(
+    trampolines: HashMap<u64, Trampoline>,
+    delta: i64,
+) -> Result<HashMap<u64, Trampoline>, DynErr> {
+    let mut trampolines = keyed(trampolines, delta)?;
+    for trampoline in trampolines.values_mut() {
+        if let Destination::Address(target) = &mut trampoline.target {
+            *target = address(*target, delta)?;
+        }
+    }
+    Ok(trampolines)
+}
@@ -0,0 +1,139 @@
+// register names of locals' locations, numbered the way each architecture's
+// DWARF does: rax is 0 and xmm0 17 on x86_64, x0 0 and v0 64 on aarch64. the
+// 32 bit names of x86_64 registers are the full ones, gdb reads the low bytes
+// for smaller types. a plain number is taken as the DWARF number itself.
+
+use goblin::elf::header::{
+    EM_386, EM_AARCH64, EM_ARM, EM_AVR, EM_MIPS, EM_MSP430, EM_RISCV, EM_X86_64,
+};
+
+use crate::target::Target;
+use crate::DynErr;
+
+const X86_64: &[(&str, u16)] = &[
+    ("rax", 0),
+    ("rdx", 1),
+    ("rcx", 2),
+    ("rbx", 3),
+    ("rsi", 4),
+    ("rdi", 5),
+    ("rbp", 6),
+    ("rsp", 7),
+    ("eax", 0),
+    ("edx", 1),
+    ("ecx", 2),
+    ("ebx", 3),
+    ("esi", 4),
+    ("edi", 5),
+    ("ebp", 6),
+    ("esp", 7),
+    ("rip", 16),
+];
+
+const I386: &[(&str, u16)] = &[
+    ("eax", 0),
+    ("ecx", 1),
+    ("edx", 2),
+    ("ebx", 3),
+    ("esp", 4),
+    ("ebp", 5),
+    ("esi", 6),
+    ("edi", 7),
+    ("eip", 8),
+];
+
+const AARCH64: &[(&str, u16)] = &[("fp", 29), ("lr", 30), ("sp", 31)];
+
+const ARM: &[(&str, u16)] = &[("fp", 11), ("ip", 12), ("sp", 13), ("lr", 14), ("pc", 15)];
+
+const RISCV: &[(&str, u16)] = &[
+    ("zero", 0),
+    ("ra", 1),
+    ("sp", 2),
+    ("gp", 3),
+    ("tp", 4),
+    ("t0", 5),
+    ("t1", 6),
+    ("t2", 7),
+    ("s0", 8),
+    ("fp", 8),
+    ("s1", 9),
+];
+
+const MIPS: &[(&str, u16)] = &[
+    ("zero", 0),
+    ("at", 1),
+    ("v0", 2),
+    ("v1", 3),
+    ("t8", 24),
+    ("t9", 25),
+    ("k0", 26),
+    ("k1", 27),
+    ("gp", 28),
+    ("sp", 29),
+    ("fp", 30),
+    ("s8", 30),
+    ("ra", 31),
+];
+
+const MSP430: &[(&str, u16)] = &[("pc", 0), ("sp", 1), ("sr", 2)];
+
+// `prefix` followed by `first` to `last`, numbered from `base`
+fn indexed(name: &str, prefix: &str, first: u16, last: u16, base: u16) -> Option<u16> {
+    let index: u16 = name.strip_prefix(prefix)?.parse().ok()?;
+    (first..=last)
+        .contains(&index)
+        .then(|| base + index - first)
+}
+
+fn named(name: &str, table: &[(&str, u16)]) -> Option<u16> {
+    table
+        .iter()
+        .find(|(register, _)| *register == name)
+        .map(|(_, number)| *number)
+}
+
+pub fn number(target: &Target, name: &str) -> Result<u16, DynErr> {
+    if let Ok(number) = name.parse() {
+        return Ok(number);
+    }
+    let found = match target.machine {
+        EM_X86_64 => named(name, X86_64)
+            .or_else(|| indexed(name, "r", 8, 15, 8))
+            .or_else(|| indexed(name, "xmm", 0, 15, 17))
+            .or_else(|| indexed(name, "st", 0, 7, 33)),
+        EM_386 => named(name, I386)
+            .or_else(|| indexed(name, "st", 0, 7, 11))
+            .or_else(|| indexed(name, "xmm", 0, 7, 21)),
+        EM_AARCH64 => named(name, AARCH64)
+            .or_else(|| indexed(name, "x", 0, 30, 0))
+            .or_else(|| indexed(name, "w", 0, 30, 0))
+            .or_else(|| {
+                ["v", "q", "d", "s"]
+                    .iter()
+                    .find_map(|prefix| indexed(name, prefix, 0, 31, 64))
+            }),
+        EM_ARM => named(name, ARM)
+            .or_else(|| indexed(name, "r", 0, 15, 0))
+            .or_else(|| indexed(name, "s", 0, 31, 64))
+            .or_else(|| indexed(name, "d", 0, 31, 256)),
+        EM_RISCV => named(name, RISCV)
+            .or_else(|| indexed(name, "x", 0, 31, 0))
+            .or_else(|| indexed(name, "a", 0, 7, 10))
+            .or_else(|| indexed(name, "s", 2, 11, 18))
+            .or_else(|| indexed(name, "t", 3, 6, 28))
+            .or_else(|| indexed(name, "fa", 0, 7, 42))
+            .or_else(|| indexed(name, "f", 0, 31, 32)),
+        EM_MIPS => named(name, MIPS)
+            .or_else(|| indexed(name, "$", 0, 31, 0))
+            .or_else(|| indexed(name, "a", 0, 3, 4))
+            .or_else(|| indexed(name, "t", 0, 7, 8))
+            .or_else(|| indexed(name, "s", 0, 7, 16))
+            .or_else(|| indexed(name, "$f", 0, 31, 32))
+            .or_else(|| indexed(name, "f", 0, 31, 32)),
+        EM_AVR => indexed(name, "r", 0, 31, 0),
+        EM_MSP430 => named(name, MSP430).or_else(|| indexed(name, "r", 0, 15, 0)),
+        _ => None,
+    };
+    found.ok_or_else(|| format!("unknown register `{}` for this --arch", name).into())
+}
@@ -0,0 +1,466 @@
+// models how binary ninja's dwarf_import plugin reads our output back, so
+// encoding choices that would silently degrade for a teammate re-importing
+// the debug file get caught before it is handed out.
+//
+// the rules mirrored here:
+// - a missing DW_AT_type on a pointer or subroutine means void
+// - typedefs whose name matches their target collapse into the target
+// - anonymous aggregates have no name and are identified by their layout
+// - array lengths come from DW_AT_count, else DW_AT_upper_bound + 1
+// - members without DW_AT_data_member_location sit at offset 0
+// - base type signedness comes from DW_AT_encoding alone
+// - members inside a DW_TAG_variant_part are not imported
+// - const and volatile become flags on the type, restrict is dropped
+
+use gimli::write::{EndianVec, Sections};
+use gimli::RunTimeEndian;
+
+use crate::readback::{Die, Dies};
+use crate::{
+    BinjaType, Class, DynErr, Enum, EnumField, Function, GlobalVariable, HashMap, Integer,
+    Parameter, ReferenceKind, Structure, Typedef,
+};
+
+trait Imported {
+    fn members(&self, die: &Die, depth: usize) -> Vec<String>;
+    fn render(&self, offset: Option<usize>, depth: usize) -> String;
+}
+
+impl Imported for Dies {
+    fn members(&self, die: &Die, depth: usize) -> Vec<String> {
+        self.children(die)
+            .filter(|child| child.tag == gimli::DW_TAG_member)
+            .map(|member| {
+                format!(
+                    "{}:{}:{}",
+                    member.location.unwrap_or(0),
+                    member.name.as_deref().unwrap_or(""),
+                    self.render(member.target, depth + 1),
+                )
+            })
+            .collect()
+    }
+
+    // the name binja would show for a reference to `offset`
+    fn render(&self, offset: Option<usize>, depth: usize) -> String {
+        let Some(die) = self.get(offset) else {
+            return String::from("void");
+        };
+        if depth > 16 {
+            return String::from("...");
+        }
+
+        match die.tag {
+            gimli::DW_TAG_structure_type | gimli::DW_TAG_union_type if die.name.is_none() => {
+                format!(
+                    "{} {{ {} }}",
+                    if die.tag == gimli::DW_TAG_structure_type {
+                        "struct"
+                    } else {
+                        "union"
+                    },
+                    self.members(die, depth).join("; "),
+                )
+            }
+            // binja has no member pointers, they become plain ones
+            gimli::DW_TAG_pointer_type | gimli::DW_TAG_ptr_to_member_type => {
+                format!("{}*", self.render(die.target, depth + 1))
+            }
+            gimli::DW_TAG_reference_type => format!("{}&", self.render(die.target, depth + 1)),
+            gimli::DW_TAG_rvalue_reference_type => {
+                format!("{}&&", self.render(die.target, depth + 1))
+            }
+            gimli::DW_TAG_const_type => format!("const {}", self.render(die.target, depth + 1)),
+            gimli::DW_TAG_volatile_type => {
+                format!("volatile {}", self.render(die.target, depth + 1))
+            }
+            gimli::DW_TAG_restrict_type => self.render(die.target, depth + 1),
+            gimli::DW_TAG_array_type => {
+                let bounds: String = self
+                    .children(die)
+                    .filter(|child| child.tag == gimli::DW_TAG_subrange_type)
+                    .map(|subrange| format!("[{}]", subrange.count.unwrap_or(0)))
+                    .collect();
+                format!("{}{}", self.render(die.target, depth + 1), bounds)
+            }
+            gimli::DW_TAG_subroutine_type => {
+                let parameters: Vec<String> = self
+                    .children(die)
+                    .filter_map(|child| match child.tag {
+                        gimli::DW_TAG_formal_parameter => {
+                            Some(self.render(child.target, depth + 1))
+                        }
+                        gimli::DW_TAG_unspecified_parameters => Some(String::from("...")),
+                        _ => None,
+                    })
+                    .collect();
+                format!(
+                    "{}({})",
+                    self.render(die.target, depth + 1),
+                    parameters.join(", ")
+                )
+            }
+            _ => die.name.clone().unwrap_or_else(|| String::from("?")),
+        }
+    }
+}
+
+fn render_fields(
+    types: &HashMap<String, BinjaType>,
+    structure: &Structure,
+    depth: usize,
+) -> Vec<String> {
+    structure
+        .fields
+        .iter()
+        // a tagged union's arms sit in the variant part, not among the members
+        .filter(|field| {
+            structure
+                .variant
+                .as_ref()
+                .is_none_or(|variant| variant.union != field.name)
+        })
+        .map(|field| {
+            let mut typename = &field.typename;
+            if field.endianity.is_some() {
+                // members in another byte order point at a copy of the integer
+                for _ in 0..64 {
+                    match types.get(typename) {
+                        Some(BinjaType::Typedef(Typedef { target, .. })) if target != typename => {
+                            typename = target
+                        }
+                        Some(BinjaType::Qualified(qualified)) => typename = &qualified.target,
+                        _ => break,
+                    }
+                }
+            }
+            format!(
+                "{}:{}:{}",
+                field.offset,
+                field.name,
+                render(types, typename, depth + 1)
+            )
+        })
+        .collect()
+}
+
+// the exported counterpart of `Imported::render`
+fn render(types: &HashMap<String, BinjaType>, name: &String, depth: usize) -> String {
+    if name.is_empty() {
+        return String::from("void");
+    }
+    if depth > 16 {
+        return String::from("...");
+    }
+
+    match types.get(name) {
+        Some(BinjaType::Structure(structure @ Structure { anon: true, .. })) => format!(
+            "struct {{ {} }}",
+            render_fields(types, structure, depth).join("; ")
+        ),
+        Some(BinjaType::Union(structure @ Structure { anon: true, .. })) => format!(
+            "union {{ {} }}",
+            render_fields(types, structure, depth).join("; ")
+        ),
+        Some(BinjaType::Pointer(pointer)) => format!(
+            "{}{}",
+            render(types, &pointer.target, depth + 1),
+            match pointer.reference {
+                Some(ReferenceKind::Lvalue) => "&",
+                Some(ReferenceKind::Rvalue) => "&&",
+                None => "*",
+            }
+        ),
+        Some(BinjaType::Qualified(qualified)) => format!(
+            "{}{}{}",
+            if qualified.constant { "const " } else { "" },
+            if qualified.volatile { "volatile " } else { "" },
+            render(types, &qualified.target, depth + 1)
+        ),
+        Some(BinjaType::Array(array)) => {
+            format!(
+                "{}{}",
+                render(types, &array.target, depth + 1),
+                array.bounds()
+            )
+        }
+        // binja reads vectors as plain arrays
+        Some(BinjaType::Vector(vector)) => {
+            format!(
+                "{}[{}]",
+                render(types, &vector.target, depth + 1),
+                vector.count
+            )
+        }
+        Some(BinjaType::Function(Function {
+            parameters,
+            returntype,
+            variadic,
+            ..
+        })) => {
+            let mut parameters: Vec<String> = parameters
+                .iter()
+                .map(|Parameter { typename, .. }| render(types, typename, depth + 1))
+                .collect();
+            if *variadic {
+                parameters.push(String::from("..."));
+            }
+            format!(
+                "{}({})",
+                render(types, returntype, depth + 1),
+                parameters.join(", ")
+            )
+        }
+        _ => name.clone(),
+    }
+}
+
+pub fn check(
+    sections: &Sections<EndianVec<RunTimeEndian>>,
+    debug_types: &[u8],
+    types: &HashMap<String, BinjaType>,
+    variables: &HashMap<u64, GlobalVariable>,
+) -> Result<Vec<String>, DynErr> {
+    let imported = Dies::from_sections(sections, debug_types)?;
+    let names = imported.by_name();
+    let mut issues = Vec::new();
+
+    for (name, binja_type) in types.iter() {
+        let (tag, kind) = match binja_type {
+            BinjaType::Structure(Structure { anon: false, .. }) => {
+                (gimli::DW_TAG_structure_type, "struct")
+            }
+            BinjaType::Union(Structure { anon: false, .. }) => (gimli::DW_TAG_union_type, "union"),
+            BinjaType::Class(Class {
+                layout: Structure { anon: false, .. },
+                ..
+            }) => (gimli::DW_TAG_class_type, "class"),
+            BinjaType::Integer(_) => (gimli::DW_TAG_base_type, "integer"),
+            BinjaType::Enum(_) => (gimli::DW_TAG_enumeration_type, "enum"),
+            BinjaType::Typedef(_) => (gimli::DW_TAG_typedef, "typedef"),
+            _ => continue,
+        };
+
+        let Some(die) = names.get(&(tag, name.as_str())).copied() else {
+            issues.push(format!("{} `{}` is not recovered on re-import", kind, name));
+            continue;
+        };
+
+        match binja_type {
+            BinjaType::Structure(structure)
+            | BinjaType::Union(structure)
+            | BinjaType::Class(Class {
+                layout: structure, ..
+            }) => {
+                let size = structure.size;
+                if structure.declaration {
+                    if !die.declaration {
+                        issues.push(format!(
+                            "{} `{}` re-imports as a definition instead of a declaration",
+                            kind, name
+                        ));
+                    }
+                    continue;
+                }
+                if die.size != Some(size) {
+                    issues.push(format!(
+                        "{} `{}` re-imports with size {:?} instead of {}",
+                        kind, name, die.size, size
+                    ));
+                }
+                let expected = render_fields(types, structure, 0);
+                let found = imported.members(die, 0);
+                for (expected, found) in expected.iter().zip(found.iter()) {
+                    if expected != found {
+                        issues.push(format!(
+                            "{} `{}` member `{}` re-imports as `{}`",
+                            kind, name, expected, found
+                        ));
+                    }
+                }
+                if expected.len() != found.len() {
+                    issues.push(format!(
+                        "{} `{}` re-imports with {} members instead of {}",
+                        kind,
+                        name,
+                        found.len(),
+                        expected.len()
+                    ));
+                }
+                if let BinjaType::Class(class) = binja_type {
+                    let expected: Vec<String> = (class.bases.iter())
+                        .map(|base| format!("{}:{}", base.offset, render(types, &base.typename, 0)))
+                        .collect();
+                    let found: Vec<String> = imported
+                        .children(die)
+                        .filter(|child| child.tag == gimli::DW_TAG_inheritance)
+                        .map(|base| {
+                            format!(
+                                "{}:{}",
+                                base.location.unwrap_or(0),
+                                imported.render(base.target, 0)
+                            )
+                        })
+                        .collect();
+                    if expected != found {
+                        issues.push(format!(
+                            "class `{}` re-imports with bases [{}] instead of [{}]",
+                            name,
+                            found.join(", "),
+                            expected.join(", ")
+                        ));
+                    }
+                    let expected: Vec<&str> = class
+                        .methods
+                        .iter()
+                        .map(|method| method.name.as_str())
+                        .collect();
+                    let found: Vec<&str> = imported
+                        .children(die)
+                        .filter(|child| child.tag == gimli::DW_TAG_subprogram)
+                        .map(|method| method.name.as_deref().unwrap_or(""))
+                        .collect();
+                    if expected != found {
+                        issues.push(format!(
+                            "class `{}` re-imports with methods [{}] instead of [{}]",
+                            name,
+                            found.join(", "),
+                            expected.join(", ")
+                        ));
+                    }
+                }
+            }
+            BinjaType::Integer(Integer { size, signed, .. })
+                if die.size != Some(*size) || die.signed != *signed =>
+            {
+                issues.push(format!(
+                    "integer `{}` re-imports as {} {:?} bytes",
+                    name,
+                    if die.signed { "signed" } else { "unsigned" },
+                    die.size
+                ));
+            }
+            BinjaType::Enum(enumeration @ Enum { size, fields, .. }) => {
+                if die.size != Some(*size) {
+                    issues.push(format!(
+                        "enum `{}` re-imports with size {:?} instead of {}",
+                        name, die.size, size
+                    ));
+                }
+                let found: Vec<(Option<&str>, Option<i128>)> = imported
+                    .children(die)
+                    .filter(|child| child.tag == gimli::DW_TAG_enumerator)
+                    .map(|child| {
+                        (
+                            child.name.as_deref(),
+                            child.value.map(|v| enumeration.normalize(v)),
+                        )
+                    })
+                    .collect();
+                let expected: Vec<(Option<&str>, Option<i128>)> = fields
+                    .iter()
+                    .map(|EnumField { name, value }| {
+                        (Some(name.as_str()), Some(enumeration.normalize(*value)))
+                    })
+                    .collect();
+                if found != expected {
+                    issues.push(format!(
+                        "enum `{}` re-imports with different enumerators",
+                        name
+                    ));
+                }
+            }
+            BinjaType::Typedef(Typedef { target, .. }) => {
+                let expected = render(types, target, 0);
+                let found = imported.render(die.target, 0);
+                if expected == *name {
+                    issues.push(format!(
+                        "typedef `{}` has the same name as its target and collapses on re-import",
+                        name
+                    ));
+                } else if expected != found {
+                    issues.push(format!(
+                        "typedef `{}` re-imports as an alias of `{}` instead of `{}`",
+                        name, found, expected
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let recovered: HashMap<u64, &Die> = imported
+        .roots
+        .iter()
+        .map(|offset| &imported.dies[offset])
+        .filter(|die| die.tag == gimli::DW_TAG_variable)
+        .filter_map(|die| die.address.map(|address| (address, die)))
+        .collect();
+    for (address, variable) in variables.iter() {
+        let Some(die) = recovered.get(address) else {
+            issues.push(format!(
+                "variable `{}` at {:#x} is not recovered on re-import",
+                variable.name, address
+            ));
+            continue;
+        };
+        let expected = render(types, &variable.typename, 0);
+        let found = imported.render(die.target, 0);
+        if die.name.as_deref() != Some(variable.name.as_str()) || expected != found {
+            issues.push(format!(
+                "variable `{}: {}` at {:#x} re-imports as `{}: {}`",
+                variable.name,
+                expected,
+                address,
+                die.name.as_deref().unwrap_or(""),
+                found
+            ));
+        }
+    }
+
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{generate, target, CombinedExport, DebugSup, HashMap, Options};
+
+    const TYPES: &str = r#"{
+        "types": {
+            "int": {"kind": "integer", "size": 4, "signed": true},
+            "color": {"kind": "enum", "size": 4, "signed": false, "fields": [
+                {"name": "RED", "value": 0},
+                {"name": "GREEN", "value": 1}
+            ]},
+            "node": {"kind": "struct", "size": 16, "anon": false, "fields": [
+                {"offset": 0, "name": "next", "typename": "node*"},
+                {"offset": 8, "name": "key", "typename": "int"},
+                {"offset": 12, "name": "color", "typename": "color"}
+            ]},
+            "node*": {"kind": "pointer", "size": 8, "target": "node"},
+            "node_t": {"kind": "typedef", "target": "node"}
+        },
+        "variables": {"16384": {"name": "head", "size": 8, "typename": "node*"}}
+    }"#;
+
+    #[test]
+    fn struct_enum_and_typedef_round_trip() {
+        let export: CombinedExport = serde_json::from_str(TYPES).unwrap();
+        let mut options = Options::new(target::lookup("x86_64").unwrap());
+        // generate fails on any issue, and with --check writes nothing
+        options.verify_binja = true;
+        options.check = true;
+        generate(
+            &options,
+            &export.types,
+            &export.variables,
+            &HashMap::new(),
+            &HashMap::new(),
+            Path::new("reimport.o"),
+            DebugSup::None,
+        )
+        .unwrap();
+    }
+}
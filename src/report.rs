@@ -0,0 +1,88 @@
+// what a run put into its debug file, logged after writing it and with
+// `--report report.json` saved as well:
+//
+// {"output": "test.o", "types": {"integer": 12, "struct": 3, ...},
+//  "variables": 3, "functions": 1, "unresolved": 0,
+//  "debug_info": 1234, "sections": {".debug_abbrev": 222, ...}}
+//
+// unresolved counts the references --lenient stood in for. section sizes are
+// before --compress-debug-sections.
+
+use std::fs;
+use std::path::Path;
+
+use log::info;
+use serde::Serialize;
+
+use crate::{layout, BinjaType, Err, HashMap};
+
+#[derive(Serialize)]
+pub struct Report {
+    output: String,
+    types: HashMap<&'static str, usize>,
+    variables: usize,
+    functions: usize,
+    unresolved: usize,
+    debug_info: u64,
+    sections: HashMap<String, u64>,
+}
+
+impl Report {
+    pub fn new<'a>(
+        output: &Path,
+        types: &HashMap<String, BinjaType>,
+        counts: (usize, usize, usize),
+        sections: impl Iterator<Item = (&'a str, &'a [u8])>,
+    ) -> Report {
+        let (variables, functions, unresolved) = counts;
+        let mut kinds = HashMap::new();
+        for binja_type in types.values() {
+            *kinds.entry(layout::kind(binja_type)).or_default() += 1;
+        }
+        let sections: HashMap<String, u64> = sections
+            .map(|(name, data)| (name.to_string(), data.len() as u64))
+            .collect();
+        Report {
+            output: output.display().to_string(),
+            types: kinds,
+            variables,
+            functions,
+            unresolved,
+            debug_info: sections.get(".debug_info").copied().unwrap_or(0),
+            sections,
+        }
+    }
+
+    pub fn log(&self) {
+        let kinds: Vec<String> = (self.types.iter())
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect();
+        info!(
+            "wrote {}: {} types ({}), {} variables and {} functions",
+            self.output,
+            self.types.values().sum::<usize>(),
+            kinds.join(", "),
+            self.variables,
+            self.functions
+        );
+        if self.unresolved > 0 {
+            info!("  {} unresolved references stood in for", self.unresolved);
+        }
+        let sections: Vec<String> = (self.sections.iter())
+            .filter(|(_, size)| **size > 0)
+            .map(|(name, size)| format!("{} {}", name, size))
+            .collect();
+        info!(
+            "  .debug_info is {} bytes, {} in all: {}",
+            self.debug_info,
+            self.sections.values().sum::<u64>(),
+            sections.join(", ")
+        );
+    }
+
+    pub fn save(&self, path: &Path) -> Err {
+        let json = serde_json::to_string(self)?;
+        fs::write(path, json).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(())
+    }
+}
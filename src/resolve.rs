@@ -0,0 +1,174 @@
+// references to types the export doesn't define. the writer can't emit a
+// DW_AT_type pointing nowhere, so they're all collected before it starts,
+// each with what refers to it. `--lenient` stands in an empty struct of the
+// missing name instead, for exports that are still half done.
+//
+// types holding themselves by value, through fields, typedefs, qualifiers or
+// array elements, have no size and are reported the same way. pointers and
+// prototypes break such cycles.
+
+use crate::{BinjaType, Constant, GlobalVariable, HashMap, Structure, Subprogram};
+
+// (what refers to it, the missing name)
+pub fn unresolved(
+    types: &HashMap<String, BinjaType>,
+    variables: &HashMap<u64, GlobalVariable>,
+    constants: &HashMap<String, Constant>,
+    subprograms: &HashMap<u64, Subprogram>,
+    known: impl Fn(&str) -> bool,
+) -> Vec<(String, String)> {
+    let mut missing = Vec::new();
+    let mut check = |referrer: String, name: &String| {
+        if !name.is_empty() && !known(name) {
+            missing.push((referrer, name.clone()));
+        }
+    };
+
+    for (name, binja_type) in types.iter() {
+        match binja_type {
+            BinjaType::Structure(s) | BinjaType::Union(s) => {
+                for field in s.fields.iter() {
+                    check(format!("field `{}.{}`", name, field.name), &field.typename);
+                }
+            }
+            BinjaType::Class(class) => {
+                for base in class.bases.iter() {
+                    check(format!("base of `{}`", name), &base.typename);
+                }
+                for field in class.layout.fields.iter() {
+                    check(format!("field `{}.{}`", name, field.name), &field.typename);
+                }
+                for method in class.methods.iter() {
+                    check(
+                        format!("method `{}::{}`", name, method.name),
+                        &method.typename,
+                    );
+                }
+                if let Some(containing_type) = &class.containing_type {
+                    check(format!("containing type of `{}`", name), containing_type);
+                }
+            }
+            BinjaType::Pointer(pointer) => {
+                check(format!("pointer `{}`", name), &pointer.target);
+                if let Some(member_of) = &pointer.member_of {
+                    check(format!("class of member pointer `{}`", name), member_of);
+                }
+            }
+            BinjaType::Typedef(typedef) => check(format!("typedef `{}`", name), &typedef.target),
+            BinjaType::Qualified(qualified) => {
+                check(format!("qualified `{}`", name), &qualified.target)
+            }
+            BinjaType::Array(array) => check(format!("array `{}`", name), &array.target),
+            BinjaType::Vector(vector) => check(format!("vector `{}`", name), &vector.target),
+            BinjaType::Function(function) => {
+                check(format!("return type of `{}`", name), &function.returntype);
+                for (i, parameter) in function.parameters.iter().enumerate() {
+                    check(
+                        format!("parameter {} of `{}`", i, name),
+                        &parameter.typename,
+                    );
+                }
+            }
+            BinjaType::Integer(_) | BinjaType::Enum(_) => {}
+        }
+    }
+    for variable in variables.values() {
+        check(format!("variable `{}`", variable.name), &variable.typename);
+    }
+    for (name, constant) in constants.iter() {
+        check(format!("constant `{}`", name), &constant.typename);
+    }
+    for subprogram in subprograms.values() {
+        check(
+            format!("prototype of `{}`", subprogram.name),
+            &subprogram.typename,
+        );
+        for local in subprogram.locals.iter() {
+            check(
+                format!("local `{}` of `{}`", local.name, subprogram.name),
+                &local.typename,
+            );
+        }
+    }
+    missing
+}
+
+// `types` with an empty struct for every missing name
+pub fn substitute(
+    types: &HashMap<String, BinjaType>,
+    missing: &[(String, String)],
+) -> HashMap<String, BinjaType> {
+    let mut types = types.clone();
+    for (_, name) in missing.iter() {
+        _ = types
+            .entry(name.clone())
+            .or_insert(BinjaType::Structure(Structure {
+                size: 0,
+                anon: false,
+                fields: Vec::new(),
+                variant: None,
+                declaration: true,
+                decl: None,
+                printer: None,
+            }));
+    }
+    types
+}
+
+// the types `binja_type` needs the size of
+fn by_value(binja_type: &BinjaType) -> Vec<&String> {
+    match binja_type {
+        BinjaType::Pointer(_) | BinjaType::Function(_) => Vec::new(),
+        // methods and the vtable's class are only referred to
+        BinjaType::Class(class) => (class.bases.iter().map(|base| &base.typename))
+            .chain(class.layout.fields.iter().map(|field| &field.typename))
+            .collect(),
+        _ => binja_type.references(),
+    }
+}
+
+// every cycle of types containing each other by value, as the path around it
+pub fn cycles(types: &HashMap<String, BinjaType>) -> Vec<Vec<String>> {
+    enum State {
+        Open,
+        Done,
+    }
+    let mut state: HashMap<&str, State> = HashMap::new();
+    let mut cycles = Vec::new();
+    for root in types.keys() {
+        if state.contains_key(root.as_str()) {
+            continue;
+        }
+        // the path from `root`, each type with the references left to follow
+        let mut path: Vec<(&str, Vec<&String>)> = Vec::new();
+        _ = state.insert(root, State::Open);
+        path.push((root, by_value(&types[root])));
+        while let Some((_, pending)) = path.last_mut() {
+            let Some(next) = pending.pop() else {
+                let (done, _) = path.pop().unwrap();
+                _ = state.insert(done, State::Done);
+                continue;
+            };
+            let Some(binja_type) = types.get(next) else {
+                continue;
+            };
+            match state.get(next.as_str()) {
+                Some(State::Done) => {}
+                Some(State::Open) => {
+                    let start = path.iter().position(|(name, _)| *name == next).unwrap();
+                    let mut cycle: Vec<String> = path[start..]
+                        .iter()
+                        .map(|(name, _)| name.to_string())
+                        .collect();
+                    cycle.push(next.clone());
+                    cycles.push(cycle);
+                }
+                None => {
+                    _ = state.insert(next, State::Open);
+                    path.push((next, by_value(binja_type)));
+                }
+            }
+        }
+    }
+    cycles
+}
@@ -0,0 +1,142 @@
+// the units' own DIEs once everything is in them: their names, languages
+// and producers from --units, the code they cover, and their line programs,
+// which name the files of the declarations.
+
+use gimli::write::{Address, AttributeValue, LineProgram, LineString, Range, RangeList};
+use gimli::LineEncoding;
+
+use crate::{lines, rust, units, Compat, Declaration, Err, Generator, HashMap};
+
+impl Generator<'_> {
+    pub fn roots(
+        &mut self,
+        functions: &[Vec<(u64, u64)>],
+        source_dir: Option<&String>,
+        lines: &HashMap<u64, lines::Line>,
+    ) -> Err {
+        let declared = std::mem::take(&mut self.declared);
+        for (((spec, cu), functions), declared) in (self.specs.iter())
+            .zip(self.unit_ids.iter())
+            .zip(functions.iter())
+            .zip(declared)
+        {
+            let unit = self.dwarf.units.get_mut(*cu);
+            let root = unit.root();
+
+            // set CU attributes
+            let comp_dir = source_dir.unwrap_or(&spec.comp_dir);
+            let comp_dir_name_id = self.dwarf.strings.add(comp_dir.clone());
+            unit.get_mut(root).set(
+                gimli::DW_AT_comp_dir,
+                AttributeValue::StringRef(comp_dir_name_id),
+            );
+
+            let comp_file_name_id = self.dwarf.strings.add(spec.name.clone());
+            unit.get_mut(root).set(
+                gimli::DW_AT_name,
+                AttributeValue::StringRef(comp_file_name_id),
+            );
+
+            // a single stretch of code is a plain low/high pc, several a range list
+            let ranges = units::coalesce(
+                functions
+                    .iter()
+                    .map(|(start, size)| (*start, start.saturating_add(*size)))
+                    .collect(),
+            );
+            match ranges.as_slice() {
+                [] => {}
+                [(start, end)] => {
+                    unit.get_mut(root).set(
+                        gimli::DW_AT_low_pc,
+                        AttributeValue::Address(Address::Constant(*start)),
+                    );
+                    unit.get_mut(root)
+                        .set(gimli::DW_AT_high_pc, AttributeValue::Udata(end - start));
+                }
+                _ => {
+                    let list = RangeList(
+                        ranges
+                            .iter()
+                            .map(|(start, end)| Range::StartLength {
+                                begin: Address::Constant(*start),
+                                length: end - start,
+                            })
+                            .collect(),
+                    );
+                    let list = unit.ranges.add(list);
+                    unit.get_mut(root).set(
+                        gimli::DW_AT_low_pc,
+                        AttributeValue::Address(Address::Constant(0)),
+                    );
+                    unit.get_mut(root)
+                        .set(gimli::DW_AT_ranges, AttributeValue::RangeListRef(list));
+                }
+            }
+            unit.get_mut(root).set(
+                gimli::DW_AT_language,
+                AttributeValue::Language(units::language(&spec.language)?),
+            );
+            if spec.language == "rust" {
+                rust::rename(unit, &mut self.dwarf.strings);
+            }
+
+            let producer_id = self.dwarf.strings.add(spec.producer.clone());
+            unit.get_mut(root).set(
+                gimli::DW_AT_producer,
+                AttributeValue::StringRef(producer_id),
+            );
+
+            match lines::program(self.encoding, comp_dir, &spec.name, lines, functions) {
+                Some(program) => unit.line_program = program,
+                // the declarations' files are listed in its header
+                None if self.options.compat == Compat::Ghidra || !declared.is_empty() => {
+                    // DWARF 4 line headers only allow inline strings
+                    let strings = &self.dwarf.strings;
+                    let comp_dir = LineString::String(strings.get(comp_dir_name_id).to_vec());
+                    let comp_file = LineString::String(strings.get(comp_file_name_id).to_vec());
+                    unit.line_program = LineProgram::new(
+                        self.encoding,
+                        LineEncoding::default(),
+                        comp_dir,
+                        comp_file.clone(),
+                        None,
+                    );
+                    // ghidra's sequences cover the unit's code, in the unit's
+                    // file at line 0, which is no line in particular
+                    if self.options.compat == Compat::Ghidra {
+                        let program = &mut unit.line_program;
+                        let directory = program.default_directory();
+                        let file = program.add_file(comp_file, directory, None);
+                        for (start, end) in ranges.iter() {
+                            program.begin_sequence(Some(Address::Constant(*start)));
+                            program.row().file = file;
+                            program.row().line = 0;
+                            program.generate_row();
+                            program.end_sequence(end - start);
+                        }
+                    }
+                }
+                None => {}
+            }
+            for (id, Declaration { file, line, column }) in declared {
+                let directory = unit.line_program.default_directory();
+                let file = unit.line_program.add_file(
+                    LineString::String(file.into_bytes()),
+                    directory,
+                    None,
+                );
+                let entry = unit.get_mut(id);
+                entry.set(
+                    gimli::DW_AT_decl_file,
+                    AttributeValue::FileIndex(Some(file)),
+                );
+                entry.set(gimli::DW_AT_decl_line, AttributeValue::Udata(line));
+                if let Some(column) = column {
+                    entry.set(gimli::DW_AT_decl_column, AttributeValue::Udata(column));
+                }
+            }
+        }
+        Ok(())
+    }
+}
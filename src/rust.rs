@@ -0,0 +1,108 @@
+// units in rust (`--language rust`, or `"language": "rust"` in --units) look
+// like rustc's output, which is what gdb's rust support expects: the base
+// types get rust's primitive names, `i32` for an int32_t and `()` for void,
+// and structs with a variant part are only that, their discriminant is
+// artificial so gdb prints the active arm like `Some(1)` instead of a struct.
+// with `--language rust` the export's integers are renamed too, so what the
+// expressions, `explain` and `--verify-binja` see matches the DWARF. types
+// keep the names of the export otherwise, rust has no typedefs but they still
+// work in expressions.
+
+use gimli::write::{AttributeValue, StringTable, Unit};
+
+use crate::{BinjaType, HashMap, Integer, TypeSet};
+
+// the name rustc gives a base type of `size` bytes
+fn primitive(size: u64, encoding: gimli::DwAte) -> Option<String> {
+    let bits = size * 8;
+    match encoding {
+        gimli::DW_ATE_boolean => Some(String::from("bool")),
+        gimli::DW_ATE_float => Some(format!("f{}", bits)),
+        gimli::DW_ATE_signed | gimli::DW_ATE_signed_char => Some(format!("i{}", bits)),
+        gimli::DW_ATE_unsigned | gimli::DW_ATE_unsigned_char => Some(format!("u{}", bits)),
+        _ => None,
+    }
+}
+
+// renames every integer of `type_set` whose rust name is free, or names the
+// same integer already
+pub fn primitives(type_set: &mut TypeSet) {
+    let (mut types, mut variables) = type_set.split_types();
+    let renames: HashMap<String, String> = (types.iter())
+        .filter_map(|(name, binja_type)| match binja_type {
+            BinjaType::Integer(
+                integer @ Integer {
+                    endianity: None,
+                    bits: None,
+                    ..
+                },
+            ) => {
+                let new = primitive(integer.size, integer.dwarf_encoding())?;
+                match types.get(&new) {
+                    _ if new == *name => None,
+                    Some(BinjaType::Integer(existing)) if existing == integer => {
+                        Some((name.clone(), new))
+                    }
+                    Some(_) => None,
+                    None => Some((name.clone(), new)),
+                }
+            }
+            _ => None,
+        })
+        .collect();
+    for (old, new) in renames.iter() {
+        let integer = types.remove(old).unwrap();
+        _ = types.insert(new.clone(), integer);
+    }
+
+    let rename = |typename: &mut String| {
+        if let Some(new) = renames.get(typename) {
+            *typename = new.clone();
+        }
+    };
+    for binja_type in types.values_mut() {
+        binja_type.references_mut().into_iter().for_each(rename);
+    }
+    for variable in variables.values_mut() {
+        rename(&mut variable.typename);
+    }
+    for constant in type_set.constants.values_mut() {
+        rename(&mut constant.typename);
+    }
+    for subprogram in type_set.subprograms.values_mut() {
+        rename(&mut subprogram.typename);
+        for local in subprogram.locals.iter_mut() {
+            rename(&mut local.typename);
+        }
+    }
+    type_set.join_types(types, variables);
+}
+
+// the base types the writer made up, and void
+pub fn rename(unit: &mut Unit, strings: &mut StringTable) {
+    let root = unit.root();
+    let children: Vec<_> = unit.get(root).children().copied().collect();
+    for id in children {
+        let entry = unit.get_mut(id);
+        let name = match entry.tag() {
+            gimli::DW_TAG_unspecified_type => Some(String::from("()")),
+            // byte swapped ones keep theirs, they aren't rust's
+            gimli::DW_TAG_base_type if entry.get(gimli::DW_AT_endianity).is_none() => match (
+                entry.get(gimli::DW_AT_byte_size),
+                entry.get(gimli::DW_AT_encoding),
+            ) {
+                (Some(AttributeValue::Udata(size)), Some(AttributeValue::Encoding(encoding))) => {
+                    primitive(*size, *encoding)
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(name) = name {
+            entry.set(
+                gimli::DW_AT_name,
+                AttributeValue::StringRef(strings.add(name)),
+            );
+        }
+    }
+}
@@ -0,0 +1,219 @@
+// the sections generate ends up with once the DIEs are done: the DWARF
+// itself, with --type-units and --split-dwarf taking their parts out of it
+// and --verify and --verify-binja reading it back, the .dwo, the scripts gdb
+// and lldb load, and the .debug_sup and build-id notes.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use gimli::write::UnitId;
+use gimli::DebugInfoOffset;
+use log::{error, info};
+
+use crate::{
+    aranges, build_id, debug_sup, frames, gdb_index, gdb_scripts, lldb, printers, reimport, split,
+    type_units, verify, write_sections, DebugSup, DynErr, ElfWriter, Endianity, Err, Generator,
+    GlobalVariable, HashMap,
+};
+
+impl Generator<'_> {
+    // the .debug_info offset of every type a supplementary file has
+    pub fn sections(
+        &mut self,
+        output: &Path,
+        functions: Vec<Vec<(u64, u64)>>,
+        data: Vec<Vec<(u64, u64)>>,
+        global_variables: &HashMap<u64, GlobalVariable>,
+        frames: &HashMap<u64, frames::Frame>,
+    ) -> Result<HashMap<String, DebugInfoOffset>, DynErr> {
+        let options = self.options;
+        let mut type_units = match options.type_units {
+            true => type_units::split(&mut self.dwarf, &self.unit_ids),
+            false => Vec::new(),
+        };
+
+        // with --split-dwarf the output only gets a skeleton of each unit
+        let dwo = match options.split_dwarf {
+            true => Some(split::dwo_path(output)?),
+            false => None,
+        };
+        let skeletons = dwo
+            .as_ref()
+            .map(|dwo| split::skeletons(&mut self.dwarf, &self.unit_ids, dwo));
+
+        let (mut dwarf_sections, offsets) =
+            write_sections(&mut self.dwarf, options.target.dwarf_endian())?;
+        let debug_types = type_units::extract(
+            &mut dwarf_sections,
+            &offsets,
+            self.encoding,
+            &mut type_units,
+        )?;
+        // only a supplementary file's are looked up, type units moved some away
+        let type_offsets = match self.sup {
+            DebugSup::Supplementary => (self.dwarf_types.iter())
+                .map(|(name, (cu, id))| (name.clone(), offsets.entry(*cu, *id)))
+                .collect(),
+            _ => HashMap::new(),
+        };
+        let (skeleton_sections, unit_offsets): (_, Vec<(UnitId, DebugInfoOffset)>) = match skeletons
+        {
+            Some((mut skeletons, ids)) => {
+                let (sections, offsets) =
+                    write_sections(&mut skeletons, options.target.dwarf_endian())?;
+                let unit_offsets = (self.unit_ids.iter())
+                    .zip(ids)
+                    .map(|(cu, id)| (*cu, offsets.unit(id)))
+                    .collect();
+                (Some(sections), unit_offsets)
+            }
+            None => (
+                None,
+                (self.unit_ids.iter())
+                    .map(|cu| (*cu, offsets.unit(*cu)))
+                    .collect(),
+            ),
+        };
+        let main_sections = skeleton_sections.as_ref().unwrap_or(&dwarf_sections);
+        let index = options.gdb_index.then(|| {
+            gdb_index::write(
+                &self.dwarf,
+                &unit_offsets,
+                &type_units,
+                &functions,
+                main_sections.debug_info.slice().len(),
+            )
+        });
+        let covered: Vec<(DebugInfoOffset, Vec<(u64, u64)>)> = unit_offsets
+            .iter()
+            .zip(functions.into_iter().zip(data))
+            .map(|((_, offset), (mut functions, data))| {
+                functions.extend(data);
+                (*offset, functions)
+            })
+            .collect();
+        let aranges = aranges::write(self.encoding, options.target.dwarf_endian(), &covered)?;
+
+        if options.verify {
+            let issues = verify::check(&dwarf_sections, &debug_types)?;
+            for issue in issues.iter() {
+                error!("verify: {}", issue);
+            }
+            if !issues.is_empty() {
+                return Err(format!("{} issue(s) in the generated DWARF", issues.len()).into());
+            }
+        }
+        if options.verify_binja {
+            let issues = reimport::check(
+                &dwarf_sections,
+                &debug_types,
+                self.mappings,
+                global_variables,
+            )?;
+            for issue in issues.iter() {
+                error!("binja re-import: {}", issue);
+            }
+            if !issues.is_empty() {
+                return Err(format!("{} issue(s) on binja re-import", issues.len()).into());
+            }
+        }
+
+        // Finally, write the DWARF data to the sections.
+        main_sections.for_each(|id, data| {
+            self.writer.add_section(id.name(), data.clone().into_vec());
+            Err::Ok(())
+        })?;
+        if let Some(dwo) = dwo {
+            let mut dwo_writer = ElfWriter::new(options.target);
+            if let Some(style) = options.compression {
+                dwo_writer.compress(style);
+            }
+            dwarf_sections.for_each(|id, data| {
+                match id.dwo_name() {
+                    Some(name) => dwo_writer.add_section(name, data.clone().into_vec()),
+                    None if data.slice().is_empty() => {}
+                    None => return Err(format!("{} can't go into a .dwo", id.name()).into()),
+                }
+                Err::Ok(())
+            })?;
+            if !options.check {
+                dwo_writer.write_to(&dwo)?;
+                info!("wrote {}", dwo.display());
+            }
+        }
+
+        // in place of the empty one
+        if !frames.is_empty() {
+            self.writer
+                .add_section(".debug_frame", frames::write(frames, &options.target)?);
+        }
+        if !debug_types.is_empty() {
+            self.writer.add_section(".debug_types", debug_types);
+        }
+        if !aranges.is_empty() {
+            self.writer.add_section(".debug_aranges", aranges);
+        }
+        if let Some(index) = index {
+            self.writer.add_section(".gdb_index", index);
+        }
+        Ok(type_offsets)
+    }
+
+    // run by gdb as it loads the file
+    pub fn scripts(&mut self) -> Err {
+        let options = self.options;
+        let mut scripts = Vec::new();
+        if let Some(path) = &options.gdb_printers {
+            let script = printers::script(self.mappings)?;
+            if !options.check {
+                fs::write(path, &script).map_err(|e| format!("{}: {}", path.display(), e))?;
+            }
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            scripts.push((name.into_owned(), script));
+        }
+        for path in options.gdb_scripts.iter() {
+            scripts.push(gdb_scripts::load(path)?);
+        }
+        if !scripts.is_empty() {
+            self.writer
+                .add_section(".debug_gdb_scripts", gdb_scripts::section(&scripts));
+        }
+        // the same for lldb, which loads them with `command script import`
+        if let Some(path) = &options.lldb_summaries {
+            let script = lldb::script(self.mappings)?;
+            if !options.check {
+                fs::write(path, script).map_err(|e| format!("{}: {}", path.display(), e))?;
+            }
+        }
+        Ok(())
+    }
+
+    // the build-id is read from `binary` or made up from the contents so far
+    pub fn notes(&mut self, binary: Option<&PathBuf>) -> Err {
+        match self.sup {
+            DebugSup::None => {}
+            DebugSup::Supplementary => self.writer.add_section(".debug_sup", debug_sup(true, "")),
+            DebugSup::Reference(supplementary) => self
+                .writer
+                .add_section(".debug_sup", debug_sup(false, &supplementary.filename)),
+        }
+
+        if self.options.build_id {
+            let id = match binary {
+                Some(binary) => build_id::read(binary)?,
+                None => None,
+            };
+            let id = id.unwrap_or_else(|| build_id::compute(self.writer.contents()));
+            self.writer.add_note(
+                ".note.gnu.build-id",
+                build_id::note(&id, self.options.target.endianity == Endianity::Little),
+            );
+            info!(
+                "build-id {}, for a debug directory as {}",
+                build_id::hex(&id),
+                build_id::path(&id)
+            );
+        }
+        Ok(())
+    }
+}
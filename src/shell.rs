@@ -0,0 +1,476 @@
+// `teemo shell`: keeps a type set loaded so layouts can be queried and
+// corrected while exploiting, regenerating the debug file without a restart.
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use crate::explain::explain;
+use crate::layout::{encoding, kind, size_of};
+use crate::{
+    collect, export_base, generate, BinjaType, Class, Constant, DebugSup, DynErr, Err, Field,
+    GlobalVariable, HashMap, Options, Subprogram, TypeSet,
+};
+
+const HELP: &str = "\
+load                            reload the export (and preset) from disk
+types [filter]                  list types whose name contains filter
+show <type>                     print a type's layout
+vars                            list global variables
+explain <address>               the variable and field path at an address
+rename <type> <new>             rename a type and every reference to it
+retype <type> <field> <type>    change a field's type
+move <type> <field> <offset>    change a field's offset
+resize <type> <size>            change a struct or union's size
+save [dir]                      write the export's types back out (default --types-dir)
+write [output]                  regenerate the debug file (default --output)
+quit
+
+names containing spaces can be quoted: show \"int32_t (*)(int32_t)\"";
+
+struct Shell<'a> {
+    options: &'a Options,
+    types: HashMap<String, BinjaType>,
+    variables: HashMap<u64, GlobalVariable>,
+    constants: HashMap<String, Constant>,
+    subprograms: HashMap<u64, Subprogram>,
+    export: Export,
+}
+
+// which of the loaded types are the export's own, so `save` writes back what
+// it read and leaves the preset's, the overlays' and what variants.json and
+// templates.json add to them out
+struct Export {
+    // as on disk, and whether that's the one types.json
+    base: TypeSet,
+    combined: bool,
+    // as loaded, to tell the edited ones from those variants replaced
+    loaded: TypeSet,
+    // the export's name of every renamed type
+    renamed: HashMap<String, String>,
+}
+
+// the entries of `current` the export has under their `original` key, as on
+// disk unless they were edited
+fn own<K: Ord + Clone, V: Clone + PartialEq>(
+    current: &HashMap<K, V>,
+    loaded: &HashMap<K, V>,
+    base: &HashMap<K, V>,
+    original: impl Fn(&K) -> K,
+) -> HashMap<K, V> {
+    (current.iter())
+        .filter_map(|(key, value)| {
+            let original = original(key);
+            let on_disk = base.get(&original)?;
+            Some((
+                key.clone(),
+                match loaded.get(&original) == Some(value) {
+                    true => on_disk.clone(),
+                    false => value.clone(),
+                },
+            ))
+        })
+        .collect()
+}
+
+// splits on whitespace, keeping double quoted runs together
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut started = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    tokens.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            c => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+pub fn parse_number(text: &str) -> Result<u64, String> {
+    let parsed = match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("not a number: {}", text))
+}
+
+impl Shell<'_> {
+    fn load(options: &Options) -> Result<Shell<'_>, DynErr> {
+        let mut type_set = collect(options)?;
+        let (types, variables) = type_set.split_types();
+        let (base, combined) = export_base(options)?;
+        let mut loaded = TypeSet::join(types.clone(), variables.clone());
+        loaded.constants = type_set.constants.clone();
+        loaded.subprograms = type_set.subprograms.clone();
+        Ok(Shell {
+            options,
+            types,
+            variables,
+            constants: type_set.constants,
+            subprograms: type_set.subprograms,
+            export: Export {
+                base,
+                combined,
+                loaded,
+                renamed: HashMap::new(),
+            },
+        })
+    }
+
+    // the export with the edits, in the format it was read in. variants.json
+    // and templates.json go along to another directory
+    fn save(&self, dir: &Path) -> Err {
+        let export = &self.export;
+        let (base_types, base_variables) = export.base.clone().split();
+        let (loaded_types, loaded_variables) = export.loaded.clone().split();
+        let types = own(&self.types, &loaded_types, &base_types, |name| {
+            export.renamed.get(name).unwrap_or(name).clone()
+        });
+        let variables = own(&self.variables, &loaded_variables, &base_variables, |a| *a);
+        let mut type_set = TypeSet::join(types, variables);
+        type_set.constants = own(
+            &self.constants,
+            &export.loaded.constants,
+            &export.base.constants,
+            String::clone,
+        );
+        type_set.subprograms = own(
+            &self.subprograms,
+            &export.loaded.subprograms,
+            &export.base.subprograms,
+            |a| *a,
+        );
+        match export.combined {
+            true => type_set.save_combined(dir)?,
+            false => type_set.save(dir)?,
+        }
+
+        let types_dir = &self.options.types_dir;
+        if fs::canonicalize(dir)? != fs::canonicalize(types_dir)? {
+            for file in ["variants.json", "templates.json"] {
+                if types_dir.join(file).exists() {
+                    _ = fs::copy(types_dir.join(file), dir.join(file))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn field(&mut self, name: &str, field: &str) -> Result<&mut Field, String> {
+        match self.types.get_mut(name) {
+            Some(
+                BinjaType::Structure(s)
+                | BinjaType::Union(s)
+                | BinjaType::Class(Class { layout: s, .. }),
+            ) => s
+                .fields
+                .iter_mut()
+                .find(|f| f.name == field)
+                .ok_or_else(|| format!("{} has no field {}", name, field)),
+            Some(_) => Err(format!("{} is not a struct or union", name)),
+            None => Err(format!("no type named {}", name)),
+        }
+    }
+
+    fn show(&self, name: &str) -> Result<(), String> {
+        let binja_type = self
+            .types
+            .get(name)
+            .ok_or_else(|| format!("no type named {}", name))?;
+        let size = |name: &str| match size_of(&self.types, name) {
+            Some(size) => format!("{:#x}", size),
+            None => String::from("?"),
+        };
+
+        println!("{} {} ({} bytes)", kind(binja_type), name, size(name));
+        match binja_type {
+            BinjaType::Structure(s)
+            | BinjaType::Union(s)
+            | BinjaType::Class(Class { layout: s, .. }) => {
+                for field in s.fields.iter() {
+                    let bits = match (field.bit_offset, field.bit_size) {
+                        (bit_offset, Some(bits)) => {
+                            format!(
+                                " (bits {}..{})",
+                                bit_offset.unwrap_or(0),
+                                bit_offset.unwrap_or(0) + bits
+                            )
+                        }
+                        _ => String::new(),
+                    };
+                    println!(
+                        "  {:#06x}  {:>6}  {}: {}{}",
+                        field.offset,
+                        size(&field.typename),
+                        field.name,
+                        field.typename,
+                        bits
+                    );
+                }
+            }
+            BinjaType::Enum(e) => {
+                for field in e.fields.iter() {
+                    println!("  {} = {}", field.name, field.shown());
+                }
+            }
+            BinjaType::Integer(i) => println!("  {}", encoding(i)),
+            BinjaType::Pointer(p) => println!("  -> {}", p.target),
+            BinjaType::Typedef(t) => println!("  = {}", t.target),
+            BinjaType::Qualified(q) => println!("  = {}", q.target),
+            BinjaType::Array(a) => println!("  {}{}", a.target, a.bounds()),
+            BinjaType::Vector(v) => println!("  {} x{}", v.target, v.count),
+            BinjaType::Function(f) => {
+                let parameters: Vec<String> = f
+                    .parameters
+                    .iter()
+                    .map(|p| format!("{}: {}", p.name, p.typename))
+                    .collect();
+                println!("  ({}) -> {}", parameters.join(", "), f.returntype);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn rename(&mut self, old: &str, new: &str) -> Result<(), String> {
+        if self.types.contains_key(new) {
+            return Err(format!("{} already exists", new));
+        }
+        let binja_type = self
+            .types
+            .remove(old)
+            .ok_or_else(|| format!("no type named {}", old))?;
+        self.types.insert(new.to_string(), binja_type);
+        let original = (self.export.renamed.remove(old)).unwrap_or_else(|| old.to_string());
+        _ = self.export.renamed.insert(new.to_string(), original);
+
+        for binja_type in self.types.values_mut() {
+            for reference in binja_type.references_mut() {
+                if reference == old {
+                    *reference = new.to_string();
+                }
+            }
+        }
+        for variable in self.variables.values_mut() {
+            if variable.typename == old {
+                variable.typename = new.to_string();
+            }
+        }
+        for constant in self.constants.values_mut() {
+            if constant.typename == old {
+                constant.typename = new.to_string();
+            }
+        }
+        for subprogram in self.subprograms.values_mut() {
+            if subprogram.typename == old {
+                subprogram.typename = new.to_string();
+            }
+            for local in subprogram.locals.iter_mut() {
+                if local.typename == old {
+                    local.typename = new.to_string();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn execute(&mut self, tokens: &[String]) -> Err {
+        let args: Vec<&str> = tokens.iter().map(String::as_str).collect();
+        match args.as_slice() {
+            ["help"] => println!("{}", HELP),
+            ["load"] => {
+                *self = Shell::load(self.options)?;
+                println!(
+                    "{} types, {} variables",
+                    self.types.len(),
+                    self.variables.len()
+                );
+            }
+            ["types"] | ["types", _] => {
+                let filter = args.get(1).copied().unwrap_or("");
+                for (name, binja_type) in self.types.iter() {
+                    if name.contains(filter) {
+                        println!("{:>8}  {}", kind(binja_type), name);
+                    }
+                }
+            }
+            ["show", name] => self.show(name)?,
+            ["vars"] => {
+                for (address, variable) in self.variables.iter() {
+                    println!("{:#x}  {}: {}", address, variable.name, variable.typename);
+                }
+            }
+            ["explain", address] => {
+                let address = parse_number(address)?;
+                for line in explain(&self.types, &self.variables, address)? {
+                    println!("{}", line);
+                }
+            }
+            ["rename", old, new] => self.rename(old, new)?,
+            ["retype", name, field, typename] => {
+                if !typename.is_empty() && !self.types.contains_key(*typename) {
+                    return Err(format!("no type named {}", typename).into());
+                }
+                self.field(name, field)?.typename = typename.to_string();
+            }
+            ["move", name, field, offset] => {
+                let offset = parse_number(offset)?;
+                self.field(name, field)?.offset = offset;
+            }
+            ["resize", name, size] => {
+                let size = parse_number(size)?;
+                match self.types.get_mut(*name) {
+                    Some(
+                        BinjaType::Structure(s)
+                        | BinjaType::Union(s)
+                        | BinjaType::Class(Class { layout: s, .. }),
+                    ) => s.size = size,
+                    Some(_) => return Err(format!("{} is not a struct or union", name).into()),
+                    None => return Err(format!("no type named {}", name).into()),
+                }
+            }
+            ["save"] | ["save", _] => {
+                let dir = match args.get(1) {
+                    Some(dir) => Path::new(dir),
+                    None => self.options.types_dir.as_path(),
+                };
+                self.save(dir)?;
+            }
+            ["write"] | ["write", _] => {
+                let output = match args.get(1) {
+                    Some(output) => Path::new(output),
+                    None => self.options.output.as_path(),
+                };
+                generate(
+                    self.options,
+                    &self.types,
+                    &self.variables,
+                    &self.constants,
+                    &self.subprograms,
+                    output,
+                    DebugSup::None,
+                )?;
+                println!("wrote {}", output.display());
+            }
+            _ => return Err(format!("unknown command, try `help`: {}", tokens.join(" ")).into()),
+        }
+
+        Ok(())
+    }
+}
+
+pub fn run(options: &Options) -> Err {
+    let mut shell = Shell::load(options)?;
+    println!(
+        "{} types, {} variables loaded, `help` lists commands",
+        shell.types.len(),
+        shell.variables.len()
+    );
+
+    let stdin = io::stdin();
+    loop {
+        print!("teemo> ");
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        let tokens = tokenize(&line);
+        match tokens.first().map(String::as_str) {
+            None => continue,
+            Some("quit") | Some("exit") => break,
+            Some(_) => {
+                // a bad command shouldn't cost the session
+                if let Err(e) = shell.execute(&tokens) {
+                    println!("error: {}", e);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{target, CombinedExport};
+
+    const TYPES: &str = r#"{
+        "types": {
+            "int": {"kind": "integer", "size": 4, "signed": true},
+            "Thing": {"kind": "struct", "size": 4, "anon": false, "fields": [
+                {"offset": 0, "name": "count", "typename": "int"}
+            ]}
+        },
+        "variables": {"16384": {"name": "thing", "size": 4, "typename": "Thing"}}
+    }"#;
+
+    const VARIANTS: &str = r#"{
+        "defaults": {"EXTRA": "1"},
+        "variants": [{"when": {"EXTRA": "1"}, "types": {"structs": {
+            "extra": {"size": 4, "anon": false, "fields": [
+                {"offset": 0, "name": "count", "typename": "int"}
+            ]}
+        }}}]
+    }"#;
+
+    #[test]
+    fn save_round_trips_the_combined_export() {
+        let dir = std::env::temp_dir().join(format!("teemo-shell-{}", std::process::id()));
+        let other = dir.join("other");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("types.json"), TYPES).unwrap();
+        fs::write(dir.join("variants.json"), VARIANTS).unwrap();
+        let mut options = Options::new(target::lookup("x86_64").unwrap());
+        options.types_dir = dir.clone();
+        options.preset = Some(String::from("glibc-heap-2.35"));
+
+        let mut shell = Shell::load(&options).unwrap();
+        assert!(shell.types.len() > 3);
+        for line in ["rename Thing Thang", "save"] {
+            shell.execute(&tokenize(line)).unwrap();
+        }
+        shell
+            .execute(&tokenize(&format!("save {}", other.display())))
+            .unwrap();
+
+        // the edit survives a reload, the variant still comes from variants.json
+        let shell = Shell::load(&options).unwrap();
+        assert!(!shell.types.contains_key("Thing"));
+        assert_eq!(shell.variables[&16384].typename, "Thang");
+        assert!(shell.types.contains_key("extra"));
+        // and neither it nor the preset's types went into the export
+        for dir in [&dir, &other] {
+            let json = fs::read_to_string(dir.join("types.json")).unwrap();
+            let export: CombinedExport = serde_json::from_str(&json).unwrap();
+            let names: Vec<&str> = export.types.keys().map(String::as_str).collect();
+            assert_eq!(names, ["Thang", "int"]);
+            assert_eq!(export.variables.len(), 1);
+            assert!(!dir.join("structs.json").exists());
+        }
+        assert!(other.join("variants.json").exists());
+        _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,121 @@
+// `--spaces spaces.json` names the address spaces of a firmware image, for
+// Harvard cores and overlays where flash and RAM (or two overlay banks)
+// reuse the same numbers:
+//
+// {
+//     "flash": {"class": 1},
+//     "ram": {"class": 2, "offset": 8388608}
+// }
+//
+// variables and subprograms then say which they're in, `"space": "ram"`. a
+// space's offset is added to their addresses, in the .symtab and the DWARF
+// alike, the way avr-gcc puts RAM at 0x800000, so the two don't share
+// symbol values. `class` is the target's own number for the space and
+// becomes their DW_AT_address_class. the lines, frames and local ranges of a
+// function move along with it.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{DynErr, Err, GlobalVariable, HashMap, Subprogram};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Space {
+    #[serde(default)]
+    pub class: Option<u64>,
+    #[serde(default)]
+    pub offset: u64,
+}
+
+pub fn load(path: &Path) -> Result<HashMap<String, Space>, DynErr> {
+    match fs::read_to_string(path) {
+        Ok(json) => {
+            Ok(serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))?)
+        }
+        Err(e) => Err(format!("{}: {}", path.display(), e).into()),
+    }
+}
+
+pub struct Segmented {
+    pub variables: HashMap<u64, GlobalVariable>,
+    pub subprograms: HashMap<u64, Subprogram>,
+    // the (start, end, offset) of each function that moved
+    pub moves: Vec<(u64, u64, u64)>,
+}
+
+fn offset(
+    spaces: &HashMap<String, Space>,
+    space: &Option<String>,
+    name: &str,
+) -> Result<u64, DynErr> {
+    match space {
+        None => Ok(0),
+        Some(space) => match spaces.get(space) {
+            Some(space) => Ok(space.offset),
+            None => Err(format!("`{}` is in space `{}`, which --spaces lacks", name, space).into()),
+        },
+    }
+}
+
+fn insert<T>(moved: &mut HashMap<u64, T>, address: u64, value: T, name: &str) -> Err {
+    match moved.insert(address, value) {
+        Some(_) => Err(format!("`{}` lands on {:#x} along with another", name, address).into()),
+        None => Ok(()),
+    }
+}
+
+// the variables and subprograms at their addresses in their spaces, None if
+// none of them names one
+pub fn segment(
+    spaces: &HashMap<String, Space>,
+    variables: &HashMap<u64, GlobalVariable>,
+    subprograms: &HashMap<u64, Subprogram>,
+) -> Result<Option<Segmented>, DynErr> {
+    if (variables.values()).all(|variable| variable.space.is_none())
+        && (subprograms.values()).all(|subprogram| subprogram.space.is_none())
+    {
+        return Ok(None);
+    }
+    let mut segmented = Segmented {
+        variables: HashMap::new(),
+        subprograms: HashMap::new(),
+        moves: Vec::new(),
+    };
+    for (address, variable) in variables.iter() {
+        let offset = offset(spaces, &variable.space, &variable.name)?;
+        let moved = (address.checked_add(offset)).ok_or("a space's offset overflows")?;
+        let name = &variable.name;
+        insert(&mut segmented.variables, moved, variable.clone(), name)?;
+    }
+    for (address, subprogram) in subprograms.iter() {
+        let offset = offset(spaces, &subprogram.space, &subprogram.name)?;
+        let moved = (address.checked_add(offset)).ok_or("a space's offset overflows")?;
+        let mut subprogram = subprogram.clone();
+        for ranged in (subprogram.locals.iter_mut()).flat_map(|local| &mut local.ranges) {
+            ranged.start = ranged.start.wrapping_add(offset);
+            ranged.end = ranged.end.wrapping_add(offset);
+        }
+        if offset != 0 {
+            let end = address.saturating_add(subprogram.size);
+            segmented.moves.push((*address, end, offset));
+        }
+        let name = subprogram.name.clone();
+        insert(&mut segmented.subprograms, moved, subprogram, &name)?;
+    }
+    Ok(Some(segmented))
+}
+
+// entries keyed by a code address, moved with the function they're in
+pub fn follow<T>(entries: HashMap<u64, T>, moves: &[(u64, u64, u64)]) -> HashMap<u64, T> {
+    (entries.into_iter())
+        .map(|(address, value)| {
+            let offset = (moves.iter())
+                .find(|(start, end, _)| *start <= address && address < *end)
+                .map_or(0, |(_, _, offset)| *offset);
+            (address.wrapping_add(offset), value)
+        })
+        .collect()
+}
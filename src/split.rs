@@ -0,0 +1,104 @@
+// `--split-dwarf` writes the DIEs into `<output>.dwo` and leaves a skeleton
+// unit in the output, the GNU way `-gsplit-dwarf` does it for DWARF 4: the
+// skeleton has the unit's ranges, its line program and the DW_AT_GNU_dwo_name
+// and DW_AT_GNU_dwo_id gdb finds the full unit by.
+//
+// the addresses are final already, so unlike a compiler's .dwo this one keeps
+// plain DW_FORM_addr instead of indices into a .debug_addr. strings are inlined
+// since .dwo files can't point into .debug_str. `dwp` or `llvm-dwp` packs the
+// result into a .dwp like for any other split build.
+//
+// DW_AT_GNU_dwo_name is the .dwo's file name alone, so generating from
+// another directory gives the same bytes. gdb tries it below the comp_dir and
+// then below the directory it runs in and `debug-file-directory`, run it next
+// to the output or `set debug-file-directory` to where the .dwo is.
+
+use std::path::{Path, PathBuf};
+
+use gimli::write::{AttributeValue, Dwarf, LineProgram, Unit, UnitId};
+
+use crate::DynErr;
+
+// the attributes the skeleton takes over from the full unit
+const MOVED: [gimli::DwAt; 3] = [
+    gimli::DW_AT_low_pc,
+    gimli::DW_AT_high_pc,
+    gimli::DW_AT_ranges,
+];
+
+// where the full units go, next to the output
+pub fn dwo_path(output: &Path) -> Result<PathBuf, DynErr> {
+    let name = output
+        .file_name()
+        .ok_or_else(|| format!("{} is no file name", output.display()))?;
+    let mut name = name.to_os_string();
+    name.push(".dwo");
+    Ok(output.with_file_name(name))
+}
+
+// FNV-1a, stable across runs so a rebuilt .dwo still matches
+fn dwo_id(dwo_name: &str, index: usize) -> u64 {
+    format!("{}:{}", dwo_name, index)
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+// turns `dwarf` into the contents of the .dwo and returns the skeletons, in
+// the order of `units`
+pub fn skeletons(dwarf: &mut Dwarf, units: &[UnitId], dwo: &Path) -> (Dwarf, Vec<UnitId>) {
+    let mut skeletons = Dwarf::new();
+    let mut ids = Vec::new();
+    let dwo_name =
+        (dwo.file_name()).map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    for (index, cu) in units.iter().enumerate() {
+        let id = dwo_id(&dwo_name, index);
+        let unit = dwarf.units.get_mut(*cu);
+        let mut skeleton = Unit::new(
+            unit.encoding(),
+            std::mem::replace(&mut unit.line_program, LineProgram::none()),
+        );
+        skeleton.ranges = std::mem::take(&mut unit.ranges);
+
+        let root = unit.root();
+        let skeleton_root = skeleton.root();
+        for name in [gimli::DW_AT_name, gimli::DW_AT_comp_dir] {
+            if let Some(AttributeValue::StringRef(string)) = unit.get(root).get(name) {
+                let string = skeletons.strings.add(dwarf.strings.get(*string));
+                skeleton
+                    .get_mut(skeleton_root)
+                    .set(name, AttributeValue::StringRef(string));
+            }
+        }
+        for name in MOVED {
+            if let Some(value) = unit.get(root).get(name) {
+                skeleton.get_mut(skeleton_root).set(name, value.clone());
+                unit.get_mut(root).delete(name);
+            }
+        }
+        skeleton.get_mut(skeleton_root).set(
+            gimli::DW_AT_GNU_dwo_name,
+            AttributeValue::StringRef(skeletons.strings.add(dwo_name.clone())),
+        );
+        for entry in [skeleton.get_mut(skeleton_root), unit.get_mut(root)] {
+            entry.set(gimli::DW_AT_GNU_dwo_id, AttributeValue::Data8(id));
+        }
+
+        // inline every string of the full unit
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            let entry = unit.get_mut(id);
+            for attribute in entry.attrs_mut() {
+                if let AttributeValue::StringRef(string) = attribute.get() {
+                    let string = dwarf.strings.get(*string).to_vec();
+                    attribute.set(AttributeValue::String(string));
+                }
+            }
+            stack.extend(entry.children().copied());
+        }
+
+        ids.push(skeletons.units.add(skeleton));
+    }
+    (skeletons, ids)
+}
@@ -0,0 +1,314 @@
+// the subprograms' DIEs and symbols, with their parameters and locals, and
+// the trampolines', which all go into the first unit. a subprogram goes into
+// the unit listing it, else the one named like the file of its first line.
+
+use gimli::write::{Address, AttributeValue, Expression, LocationList};
+use log::debug;
+
+use crate::{
+    global_scope, global_type, lines, place, trampolines, BinjaType, Declaration, DynErr, Err,
+    Function, Generator, HashMap, Local, Parameter, RawSymbol, Subprogram,
+};
+
+impl Generator<'_> {
+    // (start, size) of the code in each unit, its ranges have to cover it
+    // for gdb to find it there
+    pub fn subprograms(
+        &mut self,
+        prototypes: &HashMap<String, BinjaType>,
+        subprograms: &HashMap<u64, Subprogram>,
+        lines: &HashMap<u64, lines::Line>,
+        sources: &lines::Sources,
+    ) -> Result<Vec<Vec<(u64, u64)>>, DynErr> {
+        let options = self.options;
+        let mut functions = vec![Vec::new(); self.unit_ids.len()];
+        for (
+            address,
+            Subprogram {
+                name,
+                size,
+                typename,
+                locals,
+                local,
+                decl,
+                space,
+            },
+        ) in subprograms.iter()
+        {
+            debug!("function `{}` at {:#x}", name, address);
+            if *size == 0
+                || address
+                    .checked_add(size - 1)
+                    .is_none_or(|end| end > options.target.max_address())
+            {
+                return Err(format!(
+                    "subprogram `{}` at {:#x} doesn't fit a {} byte address",
+                    name, address, options.target.address_size
+                )
+                .into());
+            }
+            let function = match prototypes.get(typename) {
+                Some(BinjaType::Function(function)) => Some(function),
+                None if typename.is_empty() || options.lenient => None,
+                _ => {
+                    return Err(format!(
+                        "subprogram `{}` has `{}` as prototype, which isn't a function type",
+                        name, typename
+                    )
+                    .into())
+                }
+            };
+            // the unit listing it (as a variable too, like it used to), else the
+            // one named after the file of its first line
+            let first_file = lines
+                .range(*address..address + size)
+                .next()
+                .map(|(_, line)| line.file.as_str());
+            let cu = match (self.placement.functions)
+                .get(name.as_str())
+                .or(self.placement.variables.get(name.as_str()))
+            {
+                Some(cu) => *cu,
+                None => (self.specs.iter())
+                    .position(|spec| Some(spec.name.as_str()) == first_file)
+                    .map_or(self.unit_ids[0], |index| self.unit_ids[index]),
+            };
+            let index = self.index(cu);
+            functions[index].push((*address, *size));
+
+            let (parent, unqualified) = global_scope(
+                &mut self.dwarf,
+                self.mappings,
+                &mut self.namespaces,
+                cu,
+                name,
+            );
+            let unit = self.dwarf.units.get_mut(cu);
+            let id = unit.add(parent, gimli::DW_TAG_subprogram);
+            // the export's own declaration over the line of the pseudo-C
+            if let Some(decl) = decl {
+                self.declared[index].push((id, decl.clone()));
+            } else if let Some((file, line)) = sources.get(address) {
+                let decl = Declaration {
+                    file: file.clone(),
+                    line: *line,
+                    column: None,
+                };
+                self.declared[index].push((id, decl));
+            }
+            let unit = unit.get_mut(id);
+            unit.set(
+                gimli::DW_AT_name,
+                AttributeValue::StringRef(self.dwarf.strings.add(unqualified)),
+            );
+            if !local {
+                unit.set(gimli::DW_AT_external, AttributeValue::Flag(true));
+            }
+            unit.set(
+                gimli::DW_AT_low_pc,
+                AttributeValue::Address(Address::Constant(*address)),
+            );
+            unit.set(gimli::DW_AT_high_pc, AttributeValue::Udata(*size));
+            if let Some(class) = (space.as_ref()).and_then(|space| self.spaces.get(space)?.class) {
+                unit.set(
+                    gimli::DW_AT_address_class,
+                    AttributeValue::AddressClass(gimli::DwAddr(class)),
+                );
+            }
+            if !locals.is_empty() {
+                // the unwinder knows the CFA from the binary's own CFI, a
+                // frame pointer may not exist
+                let mut frame_base = Expression::new();
+                frame_base.op(gimli::DW_OP_call_frame_cfa);
+                unit.set(gimli::DW_AT_frame_base, AttributeValue::Exprloc(frame_base));
+            }
+
+            if let Some(Function {
+                parameters,
+                returntype,
+                variadic,
+                convention,
+            }) = function
+            {
+                let unknown = |typename: &String| {
+                    format!("subprogram `{}` has unknown type `{}`", name, typename)
+                };
+                unit.set(gimli::DW_AT_prototyped, AttributeValue::Flag(true));
+                if let Some(convention) = convention {
+                    unit.set(
+                        gimli::DW_AT_calling_convention,
+                        AttributeValue::CallingConvention(convention.dwarf()),
+                    );
+                }
+                if !returntype.is_empty() {
+                    unit.set(
+                        gimli::DW_AT_type,
+                        global_type(self.sup, &self.dwarf_types, cu, returntype)
+                            .ok_or_else(|| unknown(returntype))?,
+                    );
+                }
+                for Parameter { name, typename } in parameters.iter() {
+                    let unit = self.dwarf.units.get_mut(cu);
+                    let parameter = unit.add(id, gimli::DW_TAG_formal_parameter);
+                    let parameter = unit.get_mut(parameter);
+                    if !name.is_empty() {
+                        parameter.set(
+                            gimli::DW_AT_name,
+                            AttributeValue::StringRef(self.dwarf.strings.add(name.clone())),
+                        );
+                    }
+                    if !typename.is_empty() {
+                        parameter.set(
+                            gimli::DW_AT_type,
+                            global_type(self.sup, &self.dwarf_types, cu, typename)
+                                .ok_or_else(|| unknown(typename))?,
+                        );
+                    }
+                }
+                if *variadic {
+                    let unit = self.dwarf.units.get_mut(cu);
+                    _ = unit.add(id, gimli::DW_TAG_unspecified_parameters);
+                }
+            }
+
+            for Local {
+                name: local,
+                typename,
+                offset,
+                location,
+                ranges,
+            } in locals.iter()
+            {
+                let local_type = global_type(self.sup, &self.dwarf_types, cu, typename)
+                    .ok_or_else(|| {
+                        format!(
+                            "local `{}` of `{}` has unknown type `{}`",
+                            local, name, typename
+                        )
+                    })?;
+                let unit = self.dwarf.units.get_mut(cu);
+                let id = unit.add(id, gimli::DW_TAG_variable);
+                let unit = unit.get_mut(id);
+                unit.set(
+                    gimli::DW_AT_name,
+                    AttributeValue::StringRef(self.dwarf.strings.add(local.clone())),
+                );
+                unit.set(gimli::DW_AT_type, local_type);
+                let failed = |e: DynErr| format!("local `{}` of `{}`: {}", local, name, e);
+                if ranges.is_empty() {
+                    let location = place(location, *offset, &options.target).map_err(failed)?;
+                    unit.set(gimli::DW_AT_location, AttributeValue::Exprloc(location));
+                    continue;
+                }
+                // the entries are relative to the unit's low_pc otherwise
+                let mut list = vec![gimli::write::Location::BaseAddress {
+                    address: Address::Constant(0),
+                }];
+                for range in ranges.iter() {
+                    if range.start >= range.end
+                        || range.start < *address
+                        || range.end > address + size
+                    {
+                        return Err(format!(
+                            "local `{}` of `{}` has range {:#x}..{:#x} outside the function",
+                            local, name, range.start, range.end
+                        )
+                        .into());
+                    }
+                    list.push(gimli::write::Location::StartEnd {
+                        begin: Address::Constant(range.start),
+                        end: Address::Constant(range.end),
+                        data: place(&range.location, range.offset, &options.target)
+                            .map_err(failed)?,
+                    });
+                }
+                // gimli writes .debug_loc or .debug_loclists for the unit's version
+                let list = self
+                    .dwarf
+                    .units
+                    .get_mut(cu)
+                    .locations
+                    .add(LocationList(list));
+                let unit = self.dwarf.units.get_mut(cu).get_mut(id);
+                unit.set(gimli::DW_AT_location, AttributeValue::LocationListRef(list));
+            }
+
+            self.writer.add_symbol(
+                name.clone(),
+                RawSymbol {
+                    st_name: 0,
+                    // 0x10 <- global binding, 0x00 local
+                    // 0x02 <- function type
+                    st_info: if *local { 0x02 } else { 0x12 },
+                    st_other: 0,
+                    st_shndx: 0,
+                    st_size: *size,
+                    st_value: *address,
+                },
+            );
+        }
+        Ok(functions)
+    }
+
+    // stubs all go into the first unit
+    pub fn trampolines(
+        &mut self,
+        trampolines: HashMap<u64, trampolines::Trampoline>,
+        functions: &mut [Vec<(u64, u64)>],
+    ) -> Err {
+        let options = self.options;
+        for (address, trampolines::Trampoline { name, size, target }) in trampolines.into_iter() {
+            if address
+                .checked_add(size - 1)
+                .is_none_or(|end| end > options.target.max_address())
+            {
+                return Err(format!(
+                    "trampoline `{}` at {:#x} doesn't fit a {} byte address",
+                    name, address, options.target.address_size
+                )
+                .into());
+            }
+            functions[0].push((address, size));
+
+            let unit = self.dwarf.units.get_mut(self.unit_ids[0]);
+            let id = unit.add(unit.root(), gimli::DW_TAG_subprogram);
+            let unit = unit.get_mut(id);
+            unit.set(
+                gimli::DW_AT_name,
+                AttributeValue::StringRef(self.dwarf.strings.add(name.clone())),
+            );
+            unit.set(gimli::DW_AT_external, AttributeValue::Flag(true));
+            unit.set(
+                gimli::DW_AT_low_pc,
+                AttributeValue::Address(Address::Constant(address)),
+            );
+            unit.set(gimli::DW_AT_high_pc, AttributeValue::Udata(size));
+            unit.set(
+                gimli::DW_AT_trampoline,
+                match target {
+                    trampolines::Destination::Name(target) => {
+                        AttributeValue::StringRef(self.dwarf.strings.add(target))
+                    }
+                    trampolines::Destination::Address(target) => {
+                        AttributeValue::Address(Address::Constant(target))
+                    }
+                },
+            );
+
+            self.writer.add_symbol(
+                name,
+                RawSymbol {
+                    st_name: 0,
+                    // 0x10 <- global binding
+                    // 0x02 <- function type
+                    st_info: 0x12,
+                    st_other: 0,
+                    st_shndx: 0,
+                    st_size: size,
+                    st_value: address,
+                },
+            );
+        }
+        Ok(())
+    }
+}
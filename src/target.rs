@@ -0,0 +1,370 @@
+// what the output claims to be built for. the writer works in elf64 structs
+// throughout and narrows them here for 32-bit targets, writing them in the
+// target's byte order.
+
+use gimli::RunTimeEndian;
+use goblin::elf::compression_header::{compression_header32, compression_header64};
+use goblin::elf32;
+use goblin::elf64::header::{
+    self, Header, ELFCLASS32, ELFCLASS64, ELFDATA2LSB, ELFDATA2MSB, EM_X86_64,
+};
+use scroll::ctx::TryIntoCtx;
+use scroll::{Endian, Pwrite};
+
+use crate::{DynErr, Endianity, RawSection, RawSegment, RawSymbol};
+
+#[derive(Clone, Copy)]
+pub struct Target {
+    pub machine: u16,
+    pub flags: u32,
+    pub class: u8,
+    // width of DW_AT_low_pc, DW_OP_addr and friends
+    pub address_size: u8,
+    // pointers exported with size 0 get this one
+    pub pointer_size: u64,
+    // index type of array subranges, the target's size_t
+    pub size_type: u64,
+    // byte order of the output, and of memory for `inspect`
+    pub endianity: Endianity,
+}
+
+pub const X86_64: Target = Target {
+    machine: EM_X86_64,
+    flags: 0,
+    class: ELFCLASS64,
+    address_size: 8,
+    pointer_size: 8,
+    size_type: 8,
+    endianity: Endianity::Little,
+};
+
+// avr-gcc puts data at 0x800000 in the ELF address space, so addresses in the
+// debug info need 4 bytes while pointers in memory are 2. 3-byte __memx
+// pointers come from the export with their own size
+const EF_AVR_ARCH_AVR5: u32 = 5;
+const EF_AVR_ARCH_AVR6: u32 = 6;
+
+// what the usual linux toolchains put in e_flags: EABI version 5 on arm,
+// compressed instructions and double float ABI on riscv, o32 mips32r2
+const EF_ARM_EABI_VER5: u32 = 0x0500_0000;
+const EF_RISCV_RVC_DOUBLE: u32 = 0x5;
+const EF_MIPS_O32_32R2: u32 = 0x7000_1000;
+
+const fn linux(machine: u16, flags: u32, bits: u8, endianity: Endianity) -> Target {
+    let (class, size) = match bits {
+        32 => (ELFCLASS32, 4),
+        _ => (ELFCLASS64, 8),
+    };
+    Target {
+        machine,
+        flags,
+        class,
+        address_size: size,
+        pointer_size: size as u64,
+        size_type: size as u64,
+        endianity,
+    }
+}
+
+const TARGETS: &[(&str, Target)] = &[
+    ("x86_64", X86_64),
+    ("i386", linux(header::EM_386, 0, 32, Endianity::Little)),
+    (
+        "aarch64",
+        linux(header::EM_AARCH64, 0, 64, Endianity::Little),
+    ),
+    (
+        "arm",
+        linux(header::EM_ARM, EF_ARM_EABI_VER5, 32, Endianity::Little),
+    ),
+    (
+        "riscv64",
+        linux(header::EM_RISCV, EF_RISCV_RVC_DOUBLE, 64, Endianity::Little),
+    ),
+    (
+        "mips",
+        linux(header::EM_MIPS, EF_MIPS_O32_32R2, 32, Endianity::Big),
+    ),
+    (
+        "mipsel",
+        linux(header::EM_MIPS, EF_MIPS_O32_32R2, 32, Endianity::Little),
+    ),
+    (
+        "avr",
+        Target {
+            machine: header::EM_AVR,
+            flags: EF_AVR_ARCH_AVR5,
+            class: ELFCLASS32,
+            address_size: 4,
+            pointer_size: 2,
+            size_type: 2,
+            endianity: Endianity::Little,
+        },
+    ),
+    // parts with more than 128k of flash and a 3-byte program counter
+    (
+        "avr6",
+        Target {
+            machine: header::EM_AVR,
+            flags: EF_AVR_ARCH_AVR6,
+            class: ELFCLASS32,
+            address_size: 4,
+            pointer_size: 2,
+            size_type: 2,
+            endianity: Endianity::Little,
+        },
+    ),
+    (
+        "msp430",
+        Target {
+            machine: header::EM_MSP430,
+            flags: 0,
+            class: ELFCLASS32,
+            address_size: 2,
+            pointer_size: 2,
+            size_type: 2,
+            endianity: Endianity::Little,
+        },
+    ),
+    // large memory model, 20-bit pointers stored in 4 bytes
+    (
+        "msp430x",
+        Target {
+            machine: header::EM_MSP430,
+            flags: 0,
+            class: ELFCLASS32,
+            address_size: 4,
+            pointer_size: 4,
+            size_type: 4,
+            endianity: Endianity::Little,
+        },
+    ),
+];
+
+pub fn lookup(name: &str) -> Result<Target, DynErr> {
+    match TARGETS.iter().find(|(target, _)| *target == name) {
+        Some((_, target)) => Ok(*target),
+        None => {
+            let available: Vec<&str> = TARGETS.iter().map(|(name, _)| *name).collect();
+            Err(format!(
+                "unknown arch `{}`, available: {}",
+                name,
+                available.join(", ")
+            )
+            .into())
+        }
+    }
+}
+
+impl Target {
+    // the same machine with `--elf-class`, switching address and pointer
+    // width along with it unless the class already matches
+    pub fn with_class(self, bits: &str) -> Target {
+        let (class, size) = match bits {
+            "32" => (ELFCLASS32, 4),
+            _ => (ELFCLASS64, 8),
+        };
+        if class == self.class {
+            return self;
+        }
+        Target {
+            class,
+            address_size: size,
+            pointer_size: size as u64,
+            size_type: size as u64,
+            ..self
+        }
+    }
+
+    pub fn sizeof_ehdr(&self) -> usize {
+        match self.class {
+            ELFCLASS32 => elf32::header::SIZEOF_EHDR,
+            _ => header::SIZEOF_EHDR,
+        }
+    }
+
+    pub fn sizeof_shdr(&self) -> usize {
+        match self.class {
+            ELFCLASS32 => elf32::section_header::SIZEOF_SHDR,
+            _ => crate::SIZEOF_SHDR,
+        }
+    }
+
+    pub fn sizeof_phdr(&self) -> usize {
+        match self.class {
+            ELFCLASS32 => elf32::program_header::SIZEOF_PHDR,
+            _ => crate::SIZEOF_PHDR,
+        }
+    }
+
+    pub fn sizeof_sym(&self) -> usize {
+        match self.class {
+            ELFCLASS32 => elf32::sym::SIZEOF_SYM,
+            _ => crate::SIZEOF_SYM,
+        }
+    }
+
+    pub fn max_address(&self) -> u64 {
+        match self.address_size {
+            8 => u64::MAX,
+            bytes => (1 << (bytes as u64 * 8)) - 1,
+        }
+    }
+
+    fn endian(&self) -> Endian {
+        match self.endianity {
+            Endianity::Little => Endian::Little,
+            Endianity::Big => Endian::Big,
+        }
+    }
+
+    pub fn ident_data(&self) -> u8 {
+        match self.endianity {
+            Endianity::Little => ELFDATA2LSB,
+            Endianity::Big => ELFDATA2MSB,
+        }
+    }
+
+    // the header takes its byte order from e_ident, see `ident_data`
+    pub fn header(&self, header: Header) -> Result<Vec<u8>, DynErr> {
+        match self.class {
+            ELFCLASS32 => write(
+                elf32::header::Header {
+                    e_ident: header.e_ident,
+                    e_type: header.e_type,
+                    e_machine: header.e_machine,
+                    e_version: header.e_version,
+                    e_entry: header.e_entry as u32,
+                    e_phoff: header.e_phoff as u32,
+                    e_shoff: header.e_shoff as u32,
+                    e_flags: header.e_flags,
+                    e_ehsize: header.e_ehsize,
+                    e_phentsize: header.e_phentsize,
+                    e_phnum: header.e_phnum,
+                    e_shentsize: header.e_shentsize,
+                    e_shnum: header.e_shnum,
+                    e_shstrndx: header.e_shstrndx,
+                },
+                elf32::header::SIZEOF_EHDR,
+                self.endian(),
+            ),
+            _ => write(header, header::SIZEOF_EHDR, self.endian()),
+        }
+    }
+
+    pub fn section(&self, section: RawSection) -> Result<Vec<u8>, DynErr> {
+        match self.class {
+            ELFCLASS32 => write(
+                elf32::section_header::SectionHeader {
+                    sh_name: section.sh_name,
+                    sh_type: section.sh_type,
+                    sh_flags: section.sh_flags as u32,
+                    sh_addr: section.sh_addr as u32,
+                    sh_offset: section.sh_offset as u32,
+                    sh_size: section.sh_size as u32,
+                    sh_link: section.sh_link,
+                    sh_info: section.sh_info,
+                    sh_addralign: section.sh_addralign as u32,
+                    sh_entsize: section.sh_entsize as u32,
+                },
+                elf32::section_header::SIZEOF_SHDR,
+                self.endian(),
+            ),
+            _ => write(section, crate::SIZEOF_SHDR, self.endian()),
+        }
+    }
+
+    pub fn segment(&self, segment: RawSegment) -> Result<Vec<u8>, DynErr> {
+        match self.class {
+            ELFCLASS32 => write(
+                elf32::program_header::ProgramHeader {
+                    p_type: segment.p_type,
+                    p_offset: segment.p_offset as u32,
+                    p_vaddr: segment.p_vaddr as u32,
+                    p_paddr: segment.p_paddr as u32,
+                    p_filesz: segment.p_filesz as u32,
+                    p_memsz: segment.p_memsz as u32,
+                    p_flags: segment.p_flags,
+                    p_align: segment.p_align as u32,
+                },
+                elf32::program_header::SIZEOF_PHDR,
+                self.endian(),
+            ),
+            _ => write(segment, crate::SIZEOF_PHDR, self.endian()),
+        }
+    }
+
+    pub fn symbol(&self, symbol: RawSymbol) -> Result<Vec<u8>, DynErr> {
+        match self.class {
+            ELFCLASS32 => write(
+                elf32::sym::Sym {
+                    st_name: symbol.st_name,
+                    st_value: symbol.st_value as u32,
+                    st_size: symbol.st_size as u32,
+                    st_info: symbol.st_info,
+                    st_other: symbol.st_other,
+                    st_shndx: symbol.st_shndx,
+                },
+                elf32::sym::SIZEOF_SYM,
+                self.endian(),
+            ),
+            _ => write(symbol, crate::SIZEOF_SYM, self.endian()),
+        }
+    }
+
+    // the Elf_Chdr in front of an SHF_COMPRESSED section
+    pub fn compression_header(
+        &self,
+        ch_type: u32,
+        size: u64,
+        alignment: u64,
+    ) -> Result<Vec<u8>, DynErr> {
+        match self.class {
+            ELFCLASS32 => write(
+                compression_header32::CompressionHeader {
+                    ch_type,
+                    ch_size: size as u32,
+                    ch_addralign: alignment as u32,
+                },
+                compression_header32::SIZEOF_CHDR,
+                self.endian(),
+            ),
+            _ => write(
+                compression_header64::CompressionHeader {
+                    ch_type,
+                    ch_reserved: 0,
+                    ch_size: size,
+                    ch_addralign: alignment,
+                },
+                compression_header64::SIZEOF_CHDR,
+                self.endian(),
+            ),
+        }
+    }
+
+    // what a compression header aligns its section to
+    pub fn chdr_alignment(&self) -> u64 {
+        match self.class {
+            ELFCLASS32 => 4,
+            _ => 8,
+        }
+    }
+
+    // byte order for the DWARF sections
+    pub fn dwarf_endian(&self) -> RunTimeEndian {
+        match self.endianity {
+            Endianity::Little => RunTimeEndian::Little,
+            Endianity::Big => RunTimeEndian::Big,
+        }
+    }
+}
+
+fn write<T>(value: T, size: usize, endian: Endian) -> Result<Vec<u8>, DynErr>
+where
+    T: TryIntoCtx<Endian>,
+    T::Error: From<scroll::Error> + std::error::Error + 'static,
+{
+    let mut raw = vec![0; size];
+    raw.pwrite_with(value, 0, endian)?;
+    Ok(raw)
+}
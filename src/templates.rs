@@ -0,0 +1,177 @@
+// parameterized types: an export may carry a templates.json so container
+// heavy binaries don't need every instantiation exported fully expanded.
+//
+// {
+//     "templates": {
+//         "list": {
+//             "parameters": ["T"],
+//             "types": {
+//                 "structs": {
+//                     "list<T>": {"size": 16, "anon": false, "fields": [[0, "head", "list_node<T>*"], [8, "size", "uint64_t"]]},
+//                     "list_node<T>": {"size": 16, "anon": false, "fields": [[0, "next", "list_node<T>*"], [8, "value", "T"]]}
+//                 },
+//                 "pointers": {"list_node<T>*": {"size": 8, "target": "list_node<T>"}}
+//             }
+//         }
+//     },
+//     "instantiations": ["list<int32_t>", "list<node*>"]
+// }
+//
+// `types` is written in the export's own format with the parameters standing
+// in for whole identifiers in names and type references. besides the listed
+// instantiations, any reference to a missing type of the form `name<args>`
+// for a known template is instantiated too, so templates can use each other.
+
+use serde::Deserialize;
+
+use crate::{BinjaType, DynErr, HashMap, TypeSet};
+
+#[derive(Deserialize)]
+struct Template {
+    parameters: Vec<String>,
+    types: TypeSet,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Templates {
+    templates: HashMap<String, Template>,
+    instantiations: Vec<String>,
+}
+
+// `map<char*, list<int32_t>>` into `map` and its top level arguments
+fn parse(name: &str) -> Option<(&str, Vec<&str>)> {
+    let (base, rest) = name.split_once('<')?;
+    let inner = rest.strip_suffix('>')?;
+
+    let mut args = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth = depth.checked_sub(1)?,
+            ',' if depth == 0 => {
+                args.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(inner[start..].trim());
+
+    Some((base.trim(), args))
+}
+
+// replaces whole identifiers only, so `T` doesn't hit `Tree`
+fn substitute(text: &str, bindings: &HashMap<&str, &str>) -> String {
+    let mut out = String::new();
+    let mut identifier = String::new();
+    let flush = |identifier: &mut String, out: &mut String| {
+        match bindings.get(identifier.as_str()) {
+            Some(arg) => out.push_str(arg),
+            None => out.push_str(identifier),
+        }
+        identifier.clear();
+    };
+
+    for c in text.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            identifier.push(c);
+        } else {
+            flush(&mut identifier, &mut out);
+            out.push(c);
+        }
+    }
+    flush(&mut identifier, &mut out);
+
+    out
+}
+
+impl Templates {
+    // the types of one instantiation, None if `name` isn't of a known template
+    fn instantiate(&self, name: &str) -> Result<Option<HashMap<String, BinjaType>>, DynErr> {
+        let Some((base, args)) = parse(name) else {
+            return Ok(None);
+        };
+        let Some(template) = self.templates.get(base) else {
+            return Ok(None);
+        };
+        if args.len() != template.parameters.len() {
+            return Err(format!(
+                "{} takes {} template arguments, got {} in `{}`",
+                base,
+                template.parameters.len(),
+                args.len(),
+                name
+            )
+            .into());
+        }
+
+        let bindings: HashMap<&str, &str> = template
+            .parameters
+            .iter()
+            .map(String::as_str)
+            .zip(args)
+            .collect();
+        let (types, _) = template.types.clone().split();
+
+        Ok(Some(
+            types
+                .into_iter()
+                .map(|(name, mut binja_type)| {
+                    for reference in binja_type.references_mut() {
+                        *reference = substitute(reference, &bindings);
+                    }
+                    (substitute(&name, &bindings), binja_type)
+                })
+                .collect(),
+        ))
+    }
+
+    pub fn expand(&self, mut type_set: TypeSet) -> Result<TypeSet, DynErr> {
+        if self.templates.is_empty() {
+            return Ok(type_set);
+        }
+        let (mut types, variables) = type_set.split_types();
+
+        let mut pending: Vec<String> = self.instantiations.clone();
+        pending.extend(
+            types
+                .values()
+                .flat_map(BinjaType::references)
+                .chain(variables.values().map(|variable| &variable.typename))
+                .cloned(),
+        );
+
+        let mut instantiated = 0;
+        while let Some(name) = pending.pop() {
+            if types.contains_key(&name) {
+                continue;
+            }
+            let Some(instance) = self.instantiate(&name)? else {
+                continue;
+            };
+            // a template referencing e.g. `list<T*>` would never stop
+            instantiated += 1;
+            if instantiated > 4096 {
+                return Err(
+                    format!("template instantiation doesn't terminate at `{}`", name).into(),
+                );
+            }
+            if !instance.contains_key(&name) {
+                return Err(
+                    format!("template instantiation `{}` doesn't define itself", name).into(),
+                );
+            }
+            for (name, binja_type) in instance {
+                pending.extend(binja_type.references().into_iter().cloned());
+                // exported definitions win over instantiated ones
+                _ = types.entry(name).or_insert(binja_type);
+            }
+        }
+
+        type_set.join_types(types, variables);
+        Ok(type_set)
+    }
+}
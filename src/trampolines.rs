@@ -0,0 +1,65 @@
+// `--trampolines trampolines.json` marks code that only forwards to another
+// function (PLT stubs, hand-written thunks), so gdb's `step` goes through it
+// into the real target instead of stopping in the stub.
+//
+// {
+//     "4198448": {"name": "puts@plt", "size": 16, "target": "puts"},
+//     "4198512": {"name": "vtable_thunk", "size": 5, "target": 4199936}
+// }
+//
+// keyed by address like variables.json. a target given by name is looked up
+// by the debugger among the symbols it knows, one given by address needs no
+// symbol at all.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{DynErr, HashMap};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Destination {
+    Name(String),
+    Address(u64),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Trampoline {
+    pub name: String,
+    pub size: u64,
+    pub target: Destination,
+}
+
+pub fn load(path: &Path) -> Result<HashMap<u64, Trampoline>, DynErr> {
+    let trampolines: HashMap<u64, Trampoline> = match fs::read_to_string(path) {
+        Ok(json) => {
+            serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))?
+        }
+        Err(e) => return Err(format!("{}: {}", path.display(), e).into()),
+    };
+    for (address, trampoline) in trampolines.iter() {
+        if trampoline.size == 0 {
+            return Err(format!(
+                "{}: trampoline `{}` at {:#x} has no size",
+                path.display(),
+                trampoline.name,
+                address
+            )
+            .into());
+        }
+        if let Destination::Name(target) = &trampoline.target {
+            if *target == trampoline.name {
+                return Err(format!(
+                    "{}: trampoline `{}` forwards to itself",
+                    path.display(),
+                    target
+                )
+                .into());
+            }
+        }
+    }
+    Ok(trampolines)
+}
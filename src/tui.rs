@@ -0,0 +1,448 @@
+// `teemo tui [file]`: a terminal browser for the type graph. without a file it
+// shows the export (and preset) the generator would read, with one it reads
+// the DWARF already in that file back.
+//
+// list:   up/down select, / filter, enter open
+// detail: enter follows the selected type, right/left expand or collapse a
+//         nested struct in place, backspace goes back, q quits
+
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, execute, queue};
+
+use crate::layout::{encoding, kind, size_of};
+use crate::readback::Dies;
+use crate::{collect, BinjaType, Class, Err, GlobalVariable, HashMap, Options, Structure};
+
+#[derive(Clone)]
+enum Entry {
+    Type(String),
+    Variable(u64),
+}
+
+struct Row {
+    text: String,
+    // where enter goes
+    target: Option<Entry>,
+    // identifies a nested struct that can be expanded in place
+    expand: Option<Vec<usize>>,
+}
+
+struct Location {
+    // None is the list
+    entry: Option<Entry>,
+    selected: usize,
+    expanded: BTreeSet<Vec<usize>>,
+}
+
+struct Browser {
+    types: HashMap<String, BinjaType>,
+    variables: HashMap<u64, GlobalVariable>,
+    referrers: HashMap<String, BTreeSet<String>>,
+    filter: String,
+    filtering: bool,
+    location: Location,
+    history: Vec<Location>,
+    scroll: usize,
+}
+
+impl Browser {
+    fn new(types: HashMap<String, BinjaType>, variables: HashMap<u64, GlobalVariable>) -> Browser {
+        let mut referrers: HashMap<String, BTreeSet<String>> = HashMap::new();
+        for (name, binja_type) in types.iter() {
+            for reference in binja_type.references() {
+                _ = referrers
+                    .entry(reference.clone())
+                    .or_default()
+                    .insert(name.clone());
+            }
+        }
+
+        Browser {
+            types,
+            variables,
+            referrers,
+            filter: String::new(),
+            filtering: false,
+            location: Location {
+                entry: None,
+                selected: 0,
+                expanded: BTreeSet::new(),
+            },
+            history: Vec::new(),
+            scroll: 0,
+        }
+    }
+
+    fn size(&self, name: &str) -> String {
+        match size_of(&self.types, name) {
+            Some(size) => format!("{:#x}", size),
+            None => String::from("?"),
+        }
+    }
+
+    fn open(&self, name: &str) -> Option<Entry> {
+        self.types
+            .contains_key(name)
+            .then(|| Entry::Type(name.to_string()))
+    }
+
+    // looks through typedefs, nested structs are expanded by their definition
+    fn aggregate(&self, name: &str) -> Option<&Structure> {
+        let mut name = name;
+        for _ in 0..64 {
+            match self.types.get(name)? {
+                BinjaType::Structure(s)
+                | BinjaType::Union(s)
+                | BinjaType::Class(Class { layout: s, .. }) => return Some(s),
+                BinjaType::Typedef(t) => name = &t.target,
+                BinjaType::Qualified(q) => name = &q.target,
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    fn fields(&self, rows: &mut Vec<Row>, structure: &Structure, base: u64, path: &[usize]) {
+        let indent = "  ".repeat(path.len());
+        for (i, field) in structure.fields.iter().enumerate() {
+            let mut path = path.to_vec();
+            path.push(i);
+
+            let nested = self.aggregate(&field.typename);
+            let expanded = nested.is_some() && self.location.expanded.contains(&path);
+            let marker = match (nested, expanded) {
+                (None, _) => " ",
+                (Some(_), false) => "+",
+                (Some(_), true) => "-",
+            };
+            rows.push(Row {
+                text: format!(
+                    "{}{} {:#06x}  {:>6}  {}: {}",
+                    indent,
+                    marker,
+                    base + field.offset,
+                    self.size(&field.typename),
+                    field.name,
+                    if field.typename.is_empty() {
+                        "void"
+                    } else {
+                        &field.typename
+                    }
+                ),
+                target: self.open(&field.typename),
+                expand: nested.map(|_| path.clone()),
+            });
+
+            // paths are bounded by the nesting, a struct can't contain itself
+            if let (Some(nested), true) = (nested, expanded) {
+                if path.len() < 32 {
+                    self.fields(rows, nested, base + field.offset, &path);
+                }
+            }
+        }
+    }
+
+    fn rows(&self) -> (String, Vec<Row>) {
+        let mut rows = Vec::new();
+        let text = |text: String, target: Option<Entry>| Row {
+            text,
+            target,
+            expand: None,
+        };
+
+        match &self.location.entry {
+            None => {
+                for (name, binja_type) in self.types.iter() {
+                    if name.contains(&self.filter) {
+                        rows.push(text(
+                            format!("{:>8}  {:>6}  {}", kind(binja_type), self.size(name), name),
+                            Some(Entry::Type(name.clone())),
+                        ));
+                    }
+                }
+                for (address, variable) in self.variables.iter() {
+                    if variable.name.contains(&self.filter) {
+                        rows.push(text(
+                            format!(
+                                "{:>8}  {:#x}  {}: {}",
+                                "variable", address, variable.name, variable.typename
+                            ),
+                            Some(Entry::Variable(*address)),
+                        ));
+                    }
+                }
+                let title = match self.filter.as_str() {
+                    "" => format!(
+                        "{} types, {} variables",
+                        self.types.len(),
+                        self.variables.len()
+                    ),
+                    filter => format!("{} matching `{}`", rows.len(), filter),
+                };
+                (title, rows)
+            }
+            Some(Entry::Variable(address)) => {
+                let variable = &self.variables[address];
+                rows.push(text(
+                    format!("type: {}", variable.typename),
+                    self.open(&variable.typename),
+                ));
+                rows.push(text(format!("size: {:#x}", variable.size), None));
+                (
+                    format!("variable {} at {:#x}", variable.name, address),
+                    rows,
+                )
+            }
+            Some(Entry::Type(name)) => {
+                let binja_type = &self.types[name];
+                match binja_type {
+                    BinjaType::Structure(s)
+                    | BinjaType::Union(s)
+                    | BinjaType::Class(Class { layout: s, .. }) => {
+                        self.fields(&mut rows, s, 0, &[])
+                    }
+                    BinjaType::Enum(e) => {
+                        for field in e.fields.iter() {
+                            rows.push(text(format!("{} = {}", field.name, field.shown()), None));
+                        }
+                    }
+                    BinjaType::Integer(i) => rows.push(text(String::from(encoding(i)), None)),
+                    BinjaType::Pointer(p) => {
+                        rows.push(text(format!("-> {}", p.target), self.open(&p.target)))
+                    }
+                    BinjaType::Typedef(t) => {
+                        rows.push(text(format!("= {}", t.target), self.open(&t.target)))
+                    }
+                    BinjaType::Qualified(q) => {
+                        rows.push(text(format!("= {}", q.target), self.open(&q.target)))
+                    }
+                    BinjaType::Array(a) => rows.push(text(
+                        format!("{}{}", a.target, a.bounds()),
+                        self.open(&a.target),
+                    )),
+                    BinjaType::Vector(v) => rows.push(text(
+                        format!("{} x{}", v.target, v.count),
+                        self.open(&v.target),
+                    )),
+                    BinjaType::Function(f) => {
+                        rows.push(text(
+                            format!("returns {}", f.returntype),
+                            self.open(&f.returntype),
+                        ));
+                        for parameter in f.parameters.iter() {
+                            rows.push(text(
+                                format!("  {}: {}", parameter.name, parameter.typename),
+                                self.open(&parameter.typename),
+                            ));
+                        }
+                    }
+                }
+
+                let referrers = self.referrers.get(name);
+                let variables: Vec<(&u64, &GlobalVariable)> = self
+                    .variables
+                    .iter()
+                    .filter(|(_, variable)| variable.typename == *name)
+                    .collect();
+                if referrers.is_some() || !variables.is_empty() {
+                    rows.push(text(String::new(), None));
+                    rows.push(text(String::from("referenced by:"), None));
+                }
+                for referrer in referrers.into_iter().flatten() {
+                    rows.push(text(
+                        format!("  {:>8}  {}", kind(&self.types[referrer]), referrer),
+                        Some(Entry::Type(referrer.clone())),
+                    ));
+                }
+                for (address, variable) in variables {
+                    rows.push(text(
+                        format!("  {:>8}  {} at {:#x}", "variable", variable.name, address),
+                        Some(Entry::Variable(*address)),
+                    ));
+                }
+
+                (
+                    format!("{} {} ({} bytes)", kind(binja_type), name, self.size(name)),
+                    rows,
+                )
+            }
+        }
+    }
+
+    fn go(&mut self, entry: Entry) {
+        let location = Location {
+            entry: Some(entry),
+            selected: 0,
+            expanded: BTreeSet::new(),
+        };
+        self.history
+            .push(std::mem::replace(&mut self.location, location));
+        self.scroll = 0;
+    }
+
+    fn back(&mut self) {
+        if let Some(location) = self.history.pop() {
+            self.location = location;
+        }
+    }
+
+    fn draw(&mut self, out: &mut impl Write) -> Err {
+        let (width, height) = terminal::size()?;
+        let (width, height) = (width as usize, height as usize);
+        let (title, rows) = self.rows();
+
+        // title and status line take a row each
+        let visible = height.saturating_sub(2).max(1);
+        self.location.selected = self.location.selected.min(rows.len().saturating_sub(1));
+        if self.location.selected < self.scroll {
+            self.scroll = self.location.selected;
+        } else if self.location.selected >= self.scroll + visible {
+            self.scroll = self.location.selected + 1 - visible;
+        }
+
+        let clip = |text: &str| text.chars().take(width).collect::<String>();
+        queue!(out, terminal::Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+        queue!(
+            out,
+            SetAttribute(Attribute::Bold),
+            Print(clip(&title)),
+            SetAttribute(Attribute::Reset)
+        )?;
+        for (line, (i, row)) in rows
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(visible)
+            .enumerate()
+        {
+            queue!(out, cursor::MoveTo(0, line as u16 + 1))?;
+            if i == self.location.selected {
+                queue!(
+                    out,
+                    SetAttribute(Attribute::Reverse),
+                    Print(clip(&format!("{:width$}", row.text, width = width))),
+                    SetAttribute(Attribute::Reset)
+                )?;
+            } else {
+                queue!(out, Print(clip(&row.text)))?;
+            }
+        }
+
+        let status = if self.filtering {
+            format!("/{}", self.filter)
+        } else if self.location.entry.is_none() {
+            String::from("enter open  / filter  q quit")
+        } else {
+            String::from("enter follow  right/left expand  backspace back  q quit")
+        };
+        queue!(
+            out,
+            cursor::MoveTo(0, height.saturating_sub(1) as u16),
+            Print(clip(&status))
+        )?;
+        out.flush()?;
+
+        Ok(())
+    }
+
+    // false once the user quits
+    fn key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        if self.filtering {
+            match code {
+                KeyCode::Enter | KeyCode::Esc => self.filtering = false,
+                KeyCode::Backspace => _ = self.filter.pop(),
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => {}
+            }
+            self.location.selected = 0;
+            return true;
+        }
+
+        let (_, rows) = self.rows();
+        let row = rows.get(self.location.selected);
+        let page = terminal::size().map_or(20, |(_, height)| height as usize / 2);
+        match code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.location.selected = self.location.selected.saturating_sub(1)
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.location.selected += 1,
+            KeyCode::PageUp => self.location.selected = self.location.selected.saturating_sub(page),
+            KeyCode::PageDown => self.location.selected += page,
+            KeyCode::Home => self.location.selected = 0,
+            KeyCode::End => self.location.selected = rows.len(),
+            KeyCode::Char('/') if self.location.entry.is_none() => self.filtering = true,
+            KeyCode::Enter => {
+                if let Some(target) = row.and_then(|row| row.target.clone()) {
+                    self.go(target);
+                }
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                if let Some(path) = row.and_then(|row| row.expand.clone()) {
+                    _ = self.location.expanded.insert(path);
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                if let Some(path) = row.and_then(|row| row.expand.clone()) {
+                    // collapses everything nested below this field too
+                    self.location
+                        .expanded
+                        .retain(|expanded| !expanded.starts_with(&path));
+                }
+            }
+            KeyCode::Backspace | KeyCode::Esc => self.back(),
+            _ => {}
+        }
+        true
+    }
+}
+
+// puts the terminal back even when drawing fails
+struct Screen;
+
+impl Screen {
+    fn enter() -> Result<Screen, io::Error> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(Screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        _ = terminal::disable_raw_mode();
+    }
+}
+
+pub fn run(options: &Options, file: Option<&Path>) -> Err {
+    let type_set = match file {
+        Some(file) => Dies::from_elf(file)?.to_type_set(),
+        None => collect(options)?,
+    };
+    let (types, variables) = type_set.split();
+    let mut browser = Browser::new(types, variables);
+
+    let _screen = Screen::enter()?;
+    let mut out = io::stdout();
+    loop {
+        browser.draw(&mut out)?;
+        match event::read()? {
+            Event::Key(key)
+                if key.kind == KeyEventKind::Press && !browser.key(key.code, key.modifiers) =>
+            {
+                break
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
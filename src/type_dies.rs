@@ -0,0 +1,995 @@
+// the types' DIEs get their attributes and children on worker threads, each
+// unit's types split into as many batches as there are cores. `visit` has
+// added every type's DIE already, so a batch can refer to any of them while
+// it fills stand-ins for its own on a unit of its own. the batches are
+// copied into their units after the threads are done, in order, and the
+// names they wrote inline go into .debug_str unit by unit, so the output
+// doesn't depend on how the threads ran or how many there were.
+//
+// the base types and void made up where the export has none are made up per
+// unit, a unit using one another made refers to its own. a batch making one
+// an earlier batch of its unit made too gets the earlier one's when copied.
+
+use gimli::write::{
+    AttributeValue, DebuggingInformationEntry, Dwarf, Expression, LineProgram, Unit, UnitEntryId,
+    UnitId,
+};
+use gimli::Encoding;
+use log::{debug, warn};
+
+use crate::{
+    layout, type_ref, unqualified, wide_bytes, Access, BinjaType, Class, DynErr, Endianity, Enum,
+    Err, Field, Function, HashMap, Method, Options, Parameter, Pointer, Structure, TypeIds,
+    Typedef, Union,
+};
+
+// what every worker reads
+struct Shared<'a> {
+    mappings: &'a HashMap<String, BinjaType>,
+    dwarf_types: &'a TypeIds,
+    options: &'a Options,
+    encoding: Encoding,
+}
+
+// one batch of a unit's types
+struct Builder<'a> {
+    shared: &'a Shared<'a>,
+    unit: &'a mut Unit,
+    cu: UnitId,
+    rust: bool,
+    // the stand-ins of the batch's types on `unit`
+    ids: HashMap<&'a str, UnitEntryId>,
+    // the base types and void made up in this unit
+    made: TypeIds,
+    // byte swapped copies of integers
+    swapped: Vec<(String, Endianity, UnitEntryId)>,
+}
+
+// DW_AT_data_member_location, or for bitfields their width and
+// DW_AT_data_bit_offset counted from the start of the aggregate
+fn member_location(entry: &mut DebuggingInformationEntry, owner: &str, field: &Field) -> Err {
+    match (field.bit_offset, field.bit_size) {
+        (None, None) => entry.set(
+            gimli::DW_AT_data_member_location,
+            AttributeValue::Udata(field.offset),
+        ),
+        (bit_offset, Some(bit_size)) => {
+            entry.set(gimli::DW_AT_bit_size, AttributeValue::Udata(bit_size));
+            entry.set(
+                gimli::DW_AT_data_bit_offset,
+                AttributeValue::Udata(field.offset * 8 + bit_offset.unwrap_or(0)),
+            );
+        }
+        (Some(_), None) => {
+            return Err(format!(
+                "field `{}.{}` has a bit_offset but no bit_size",
+                owner, field.name
+            )
+            .into())
+        }
+    }
+    Ok(())
+}
+
+impl Builder<'_> {
+    fn type_ref(&self, name: &str) -> AttributeValue {
+        type_ref(self.shared.dwarf_types, self.cu, name)
+    }
+
+    // the intN_t the export provides, or one made up for the writer's own use
+    // (enum backing types, array indices) when the target's export lacks it.
+    // made up ones are kept as `<intN_t>` when the export's intN_t is
+    // something else, like a float or a struct
+    fn base_type(&mut self, bytes: u64, signed: bool) -> AttributeValue {
+        let mappings = self.shared.mappings;
+        let name = format!("{}int{}_t", if signed { "" } else { "u" }, bytes * 8);
+        let mut resolved = mappings.get(&name);
+        for _ in 0..mappings.len() {
+            match resolved {
+                Some(BinjaType::Typedef(typedef)) => resolved = mappings.get(&typedef.target),
+                _ => break,
+            }
+        }
+        let key = match resolved {
+            Some(BinjaType::Integer(integer))
+                if integer.size == bytes
+                    && integer.signed == signed
+                    && integer.encoding.is_none()
+                    && integer.bits.is_none() =>
+            {
+                name.clone()
+            }
+            Some(_) => format!("<{}>", name),
+            None => name.clone(),
+        };
+        if self.shared.dwarf_types.contains_key(&key) {
+            return self.type_ref(&key);
+        }
+        if let Some((_, id)) = self.made.get(&key) {
+            return AttributeValue::UnitRef(*id);
+        }
+
+        if key.starts_with('<') {
+            warn!(
+                "`{}` isn't a plain integer, made up {} to use instead",
+                name, key
+            );
+        } else {
+            debug!("made up {}, the export has none", name);
+        }
+        let id = self.unit.add(self.unit.root(), gimli::DW_TAG_base_type);
+        let entry = self.unit.get_mut(id);
+        entry.set(gimli::DW_AT_name, AttributeValue::String(name.into()));
+        entry.set(gimli::DW_AT_byte_size, AttributeValue::Udata(bytes));
+        entry.set(
+            gimli::DW_AT_encoding,
+            AttributeValue::Encoding(if signed {
+                gimli::DW_ATE_signed
+            } else {
+                gimli::DW_ATE_unsigned
+            }),
+        );
+        _ = self.made.insert(key, (self.cu, id));
+        AttributeValue::UnitRef(id)
+    }
+
+    // what "" stands for where DWARF needs a type: members, array elements
+    // and parameters of unknown type. pointers, typedefs and returns to void
+    // leave DW_AT_type out instead
+    fn void_type(&mut self) -> AttributeValue {
+        if let Some((_, id)) = self.made.get("") {
+            return AttributeValue::UnitRef(*id);
+        }
+
+        let id = (self.unit).add(self.unit.root(), gimli::DW_TAG_unspecified_type);
+        (self.unit.get_mut(id)).set(gimli::DW_AT_name, AttributeValue::String("void".into()));
+        _ = self.made.insert(String::new(), (self.cu, id));
+        AttributeValue::UnitRef(id)
+    }
+
+    // a member's type, void for none
+    fn member_type(&mut self, field: &Field) -> Result<AttributeValue, DynErr> {
+        Ok(match field.endianity {
+            None if field.typename.is_empty() => self.void_type(),
+            None => self.type_ref(&field.typename),
+            Some(endianity) => self.swapped_type(&field.typename, endianity)?,
+        })
+    }
+
+    // the integer behind `typename` (through typedefs) stored in another
+    // byte order. gdb only honours DW_AT_endianity on base types, so a member
+    // gets its own copy of the base type rather than the attribute itself
+    fn swapped_type(
+        &mut self,
+        typename: &str,
+        endianity: Endianity,
+    ) -> Result<AttributeValue, DynErr> {
+        let mappings = self.shared.mappings;
+        let mut name = typename;
+        let mut depth = 0;
+        let integer = loop {
+            depth += 1;
+            match mappings.get(name) {
+                _ if depth > 64 => {
+                    return Err(format!("typedef cycle through `{}`", typename).into())
+                }
+                Some(BinjaType::Typedef(typedef)) if name != typedef.target => {
+                    name = &typedef.target
+                }
+                Some(BinjaType::Qualified(qualified)) => name = &qualified.target,
+                Some(BinjaType::Integer(integer)) => break integer,
+                _ => {
+                    return Err(
+                        format!("endianity only applies to integers, not `{}`", typename).into(),
+                    )
+                }
+            }
+        };
+
+        if let Some((.., id)) =
+            (self.swapped.iter()).find(|(swapped, order, _)| swapped == name && *order == endianity)
+        {
+            return Ok(AttributeValue::UnitRef(*id));
+        }
+
+        let id = self.unit.add(self.unit.root(), gimli::DW_TAG_base_type);
+        let entry = self.unit.get_mut(id);
+        entry.set(gimli::DW_AT_name, AttributeValue::String(name.into()));
+        entry.set(gimli::DW_AT_byte_size, AttributeValue::Udata(integer.size));
+        entry.set(
+            gimli::DW_AT_encoding,
+            AttributeValue::Encoding(integer.dwarf_encoding()),
+        );
+        entry.set(
+            gimli::DW_AT_endianity,
+            AttributeValue::Endianity(endianity.dwarf()),
+        );
+        self.swapped.push((name.to_string(), endianity, id));
+        Ok(AttributeValue::UnitRef(id))
+    }
+
+    // a class's DW_TAG_inheritance children, one per base at its offset, and
+    // the class holding its vtable pointer
+    fn inheritance(&mut self, parent: UnitEntryId, class: &Class) {
+        for base in class.bases.iter() {
+            let base_type = self.type_ref(&base.typename);
+            let id = self.unit.add(parent, gimli::DW_TAG_inheritance);
+            let entry = self.unit.get_mut(id);
+            entry.set(gimli::DW_AT_type, base_type);
+            entry.set(
+                gimli::DW_AT_data_member_location,
+                AttributeValue::Udata(base.offset),
+            );
+            entry.set(
+                gimli::DW_AT_accessibility,
+                AttributeValue::Accessibility(Access::dwarf(base.access)),
+            );
+        }
+        if let Some(containing) = &class.containing_type {
+            let reference = self.type_ref(containing);
+            (self.unit.get_mut(parent)).set(gimli::DW_AT_containing_type, reference);
+        }
+    }
+
+    // the member functions' declarations, DW_TAG_subprogram children taking
+    // their prototype from a function type
+    fn methods(&mut self, parent: UnitEntryId, owner: &str, methods: &[Method]) -> Err {
+        for method in methods {
+            let Some(BinjaType::Function(function)) = self.shared.mappings.get(&method.typename)
+            else {
+                return Err(format!(
+                    "method `{}::{}` has type `{}`, which isn't a function",
+                    owner, method.name, method.typename
+                )
+                .into());
+            };
+            let returntype = match function.returntype.is_empty() {
+                true => None,
+                false => Some(self.type_ref(&function.returntype)),
+            };
+            let id = self.unit.add(parent, gimli::DW_TAG_subprogram);
+            let entry = self.unit.get_mut(id);
+            entry.set(
+                gimli::DW_AT_name,
+                AttributeValue::String(method.name.clone().into()),
+            );
+            entry.set(gimli::DW_AT_declaration, AttributeValue::Flag(true));
+            entry.set(gimli::DW_AT_external, AttributeValue::Flag(true));
+            entry.set(
+                gimli::DW_AT_accessibility,
+                AttributeValue::Accessibility(Access::dwarf(method.access)),
+            );
+            if let Some(returntype) = returntype {
+                entry.set(gimli::DW_AT_type, returntype);
+            }
+            if let Some(convention) = function.convention {
+                entry.set(
+                    gimli::DW_AT_calling_convention,
+                    AttributeValue::CallingConvention(convention.dwarf()),
+                );
+            }
+            if let Some(index) = method.vtable_index {
+                entry.set(
+                    gimli::DW_AT_virtuality,
+                    AttributeValue::Virtuality(match method.pure {
+                        true => gimli::DW_VIRTUALITY_pure_virtual,
+                        false => gimli::DW_VIRTUALITY_virtual,
+                    }),
+                );
+                let mut location = Expression::new();
+                location.op_constu(index);
+                entry.set(
+                    gimli::DW_AT_vtable_elem_location,
+                    AttributeValue::Exprloc(location),
+                );
+                entry.set(
+                    gimli::DW_AT_containing_type,
+                    AttributeValue::UnitRef(parent),
+                );
+            }
+
+            for parameter in function.parameters.iter() {
+                let parameter_type = if parameter.typename.is_empty() {
+                    self.void_type()
+                } else {
+                    self.type_ref(&parameter.typename)
+                };
+                let child = self.unit.add(id, gimli::DW_TAG_formal_parameter);
+                let entry = self.unit.get_mut(child);
+                if !parameter.name.is_empty() {
+                    entry.set(
+                        gimli::DW_AT_name,
+                        AttributeValue::String(parameter.name.clone().into()),
+                    );
+                }
+                entry.set(gimli::DW_AT_type, parameter_type);
+                if parameter.name == "this" {
+                    entry.set(gimli::DW_AT_artificial, AttributeValue::Flag(true));
+                    (self.unit.get_mut(id))
+                        .set(gimli::DW_AT_object_pointer, AttributeValue::UnitRef(child));
+                }
+            }
+            if function.variadic {
+                _ = self.unit.add(id, gimli::DW_TAG_unspecified_parameters);
+            }
+        }
+        Ok(())
+    }
+
+    // the arms of `variant.union` as members of a DW_TAG_variant per
+    // discriminant value, placed where the union member is in the struct
+    fn variant_part(
+        &mut self,
+        parent: UnitEntryId,
+        name: &str,
+        members: &HashMap<String, UnitEntryId>,
+    ) -> Err {
+        let mappings = self.shared.mappings;
+        let (Some(BinjaType::Structure(Structure {
+            fields,
+            variant: Some(variant),
+            ..
+        }))
+        | Some(BinjaType::Class(Class {
+            layout:
+                Structure {
+                    fields,
+                    variant: Some(variant),
+                    ..
+                },
+            ..
+        }))) = mappings.get(name)
+        else {
+            unreachable!()
+        };
+        let discriminant = members.get(&variant.discriminant).ok_or_else(|| {
+            format!(
+                "`{}` has no discriminant member `{}`",
+                name, variant.discriminant
+            )
+        })?;
+        let member = fields
+            .iter()
+            .find(|f| f.name == variant.union)
+            .ok_or_else(|| format!("`{}` has no union member `{}`", name, variant.union))?;
+
+        let mut typename = &member.typename;
+        let mut depth = 0;
+        let union = loop {
+            depth += 1;
+            match mappings.get(typename) {
+                _ if depth > 64 => {
+                    return Err(format!("typedef cycle through `{}`", typename).into())
+                }
+                Some(BinjaType::Typedef(typedef)) if *typename != typedef.target => {
+                    typename = &typedef.target
+                }
+                Some(BinjaType::Union(union)) => break union,
+                _ => {
+                    return Err(format!(
+                        "variant part of `{}`: `{}` isn't a union",
+                        name, variant.union
+                    )
+                    .into())
+                }
+            }
+        };
+
+        let part = self.unit.add(parent, gimli::DW_TAG_variant_part);
+        (self.unit.get_mut(part)).set(gimli::DW_AT_discr, AttributeValue::UnitRef(*discriminant));
+
+        let arms = variant
+            .arms
+            .iter()
+            .map(|(value, arm)| (Some(*value), arm))
+            .chain(variant.default.iter().map(|arm| (None, arm)));
+        for (value, arm) in arms {
+            let field = union
+                .fields
+                .iter()
+                .find(|f| f.name == *arm)
+                .ok_or_else(|| {
+                    format!(
+                        "variant part of `{}`: union `{}` has no arm `{}`",
+                        name, typename, arm
+                    )
+                })?;
+            let arm_type = self.type_ref(&field.typename);
+
+            let id = self.unit.add(part, gimli::DW_TAG_variant);
+            // the default arm is the variant without a value
+            if let Some(value) = value {
+                (self.unit.get_mut(id)).set(gimli::DW_AT_discr_value, AttributeValue::Udata(value));
+            }
+            let id = self.unit.add(id, gimli::DW_TAG_member);
+            let entry = self.unit.get_mut(id);
+            entry.set(
+                gimli::DW_AT_name,
+                AttributeValue::String(arm.as_str().into()),
+            );
+            entry.set(gimli::DW_AT_type, arm_type);
+            entry.set(
+                gimli::DW_AT_data_member_location,
+                AttributeValue::Udata(member.offset + field.offset),
+            );
+        }
+
+        Ok(())
+    }
+
+    // the members of a struct, union or class
+    fn members(
+        &mut self,
+        parent: UnitEntryId,
+        name: &str,
+        fields: &[Field],
+        skipped: Option<&str>,
+        class: Option<&Class>,
+    ) -> Result<HashMap<String, UnitEntryId>, DynErr> {
+        let mut members = HashMap::new();
+        for field in fields {
+            // the union's arms are emitted inside the variant part
+            if skipped == Some(field.name.as_str()) {
+                continue;
+            }
+            let member_type = self.member_type(field)?;
+            let id = self.unit.add(parent, gimli::DW_TAG_member);
+            let entry = self.unit.get_mut(id);
+            // an anonymous member, whose members are the parent's
+            if !field.name.is_empty() {
+                entry.set(
+                    gimli::DW_AT_name,
+                    AttributeValue::String(field.name.clone().into()),
+                );
+            }
+            entry.set(gimli::DW_AT_type, member_type);
+            member_location(entry, name, field)?;
+            // class members are private unless they say otherwise
+            if let Some(class) = class {
+                entry.set(
+                    gimli::DW_AT_accessibility,
+                    AttributeValue::Accessibility(Access::dwarf(field.access)),
+                );
+                if class.vtable_pointer.as_ref() == Some(&field.name) {
+                    entry.set(gimli::DW_AT_artificial, AttributeValue::Flag(true));
+                }
+            }
+            _ = members.insert(field.name.clone(), id);
+        }
+        Ok(members)
+    }
+
+    fn fill(&mut self, name: &str, binja_type: &BinjaType) -> Err {
+        debug!("{} `{}`", layout::kind(binja_type), name);
+        let options = self.shared.options;
+        let id = self.ids[name];
+        // a class is laid out like a struct, with its bases and methods
+        // around the members
+        let (binja_type, class) = match binja_type {
+            BinjaType::Class(class) => (&BinjaType::Structure(class.layout.clone()), Some(class)),
+            binja_type => (binja_type, None),
+        };
+        match binja_type {
+            BinjaType::Structure(Structure {
+                size,
+                anon,
+                fields,
+                variant,
+                declaration,
+                ..
+            }) => {
+                let entry = self.unit.get_mut(id);
+                if !anon {
+                    entry.set(
+                        gimli::DW_AT_name,
+                        AttributeValue::String(unqualified(name).into()),
+                    );
+                }
+                if *declaration {
+                    entry.set(gimli::DW_AT_declaration, AttributeValue::Flag(true));
+                    return Ok(());
+                }
+                entry.set(gimli::DW_AT_byte_size, AttributeValue::Udata(*size));
+                if let Some(class) = class {
+                    self.inheritance(id, class);
+                }
+
+                let skipped = variant.as_ref().map(|variant| variant.union.as_str());
+                let members = self.members(id, name, fields, skipped, class)?;
+
+                if let Some(variant) = variant {
+                    self.variant_part(id, name, &members)?;
+                    // gdb shows a rust enum's one member that isn't artificial
+                    if self.rust {
+                        let discriminant = members[&variant.discriminant];
+                        (self.unit.get_mut(discriminant))
+                            .set(gimli::DW_AT_artificial, AttributeValue::Flag(true));
+                    }
+                }
+                if let Some(class) = class {
+                    self.methods(id, name, &class.methods)?;
+                }
+            }
+            BinjaType::Class(_) => unreachable!(),
+            BinjaType::Union(Union {
+                size,
+                anon,
+                fields,
+                variant,
+                declaration,
+                ..
+            }) => {
+                if variant.is_some() {
+                    return Err(format!(
+                        "union `{}` can't have a variant part, put it on the struct holding it",
+                        name
+                    )
+                    .into());
+                }
+                let entry = self.unit.get_mut(id);
+                if !anon {
+                    entry.set(
+                        gimli::DW_AT_name,
+                        AttributeValue::String(unqualified(name).into()),
+                    );
+                }
+                if *declaration {
+                    entry.set(gimli::DW_AT_declaration, AttributeValue::Flag(true));
+                    return Ok(());
+                }
+                entry.set(gimli::DW_AT_byte_size, AttributeValue::Udata(*size));
+                self.members(id, name, fields, None, None)?;
+            }
+            BinjaType::Integer(integer) => {
+                let entry = self.unit.get_mut(id);
+                entry.set(gimli::DW_AT_name, AttributeValue::String(name.into()));
+                entry.set(gimli::DW_AT_byte_size, AttributeValue::Udata(integer.size));
+                entry.set(
+                    gimli::DW_AT_encoding,
+                    AttributeValue::Encoding(integer.dwarf_encoding()),
+                );
+                if let Some(bits) = integer.bits {
+                    if bits == 0 || bits > integer.size * 8 {
+                        return Err(format!(
+                            "integer `{}` has {} bits in {} bytes",
+                            name, bits, integer.size
+                        )
+                        .into());
+                    }
+                    entry.set(gimli::DW_AT_bit_size, AttributeValue::Udata(bits));
+                }
+                if let Some(endianity) = integer.endianity {
+                    entry.set(
+                        gimli::DW_AT_endianity,
+                        AttributeValue::Endianity(endianity.dwarf()),
+                    );
+                }
+            }
+            BinjaType::Pointer(Pointer {
+                size,
+                target,
+                reference,
+                member_of,
+            }) => {
+                if reference.is_some() && member_of.is_some() {
+                    return Err(
+                        format!("`{}` is both a reference and a member pointer", name).into(),
+                    );
+                }
+                // the itanium ABI's are a function pointer and a `this` adjustment
+                let method = member_of.is_some()
+                    && matches!(
+                        self.shared.mappings.get(target),
+                        Some(BinjaType::Function(_))
+                    );
+                let size = match size {
+                    0 if method => 2 * options.target.pointer_size,
+                    0 => options.target.pointer_size,
+                    size => *size,
+                };
+                let containing = member_of.as_ref().map(|class| self.type_ref(class));
+                let target = (!target.is_empty()).then(|| self.type_ref(target));
+                let entry = self.unit.get_mut(id);
+                entry.set(gimli::DW_AT_byte_size, AttributeValue::Udata(size));
+                if let Some(target) = target {
+                    entry.set(gimli::DW_AT_type, target);
+                }
+                if let Some(containing) = containing {
+                    entry.set(gimli::DW_AT_containing_type, containing);
+                }
+            }
+            BinjaType::Typedef(Typedef { target, .. }) => {
+                let target = (!target.is_empty()).then(|| self.type_ref(target));
+                let entry = self.unit.get_mut(id);
+                entry.set(
+                    gimli::DW_AT_name,
+                    AttributeValue::String(unqualified(name).into()),
+                );
+                if let Some(target) = target {
+                    entry.set(gimli::DW_AT_type, target);
+                }
+            }
+            BinjaType::Function(Function {
+                parameters,
+                returntype,
+                variadic,
+                convention,
+            }) => {
+                let returntype = (!returntype.is_empty()).then(|| self.type_ref(returntype));
+                let entry = self.unit.get_mut(id);
+                entry.set(gimli::DW_AT_prototyped, AttributeValue::Flag(true));
+                if let Some(returntype) = returntype {
+                    entry.set(gimli::DW_AT_type, returntype);
+                }
+                if let Some(convention) = convention {
+                    entry.set(
+                        gimli::DW_AT_calling_convention,
+                        AttributeValue::CallingConvention(convention.dwarf()),
+                    );
+                }
+
+                for Parameter { name, typename } in parameters {
+                    let parameter_type = if typename.is_empty() {
+                        self.void_type()
+                    } else {
+                        self.type_ref(typename)
+                    };
+                    let id = self.unit.add(id, gimli::DW_TAG_formal_parameter);
+                    let entry = self.unit.get_mut(id);
+                    if !name.is_empty() {
+                        entry.set(
+                            gimli::DW_AT_name,
+                            AttributeValue::String(name.clone().into()),
+                        );
+                    }
+                    entry.set(gimli::DW_AT_type, parameter_type);
+                }
+                if *variadic {
+                    _ = self.unit.add(id, gimli::DW_TAG_unspecified_parameters);
+                }
+            }
+            BinjaType::Enum(enumeration @ Enum { size, signed, .. }) => {
+                let (size, signed) = (*size, *signed);
+                let base = self.base_type(size, signed);
+                let entry = self.unit.get_mut(id);
+                entry.set(
+                    gimli::DW_AT_name,
+                    AttributeValue::String(unqualified(name).into()),
+                );
+                entry.set(gimli::DW_AT_byte_size, AttributeValue::Udata(size));
+                entry.set(
+                    gimli::DW_AT_encoding,
+                    AttributeValue::Encoding(if signed {
+                        gimli::DW_ATE_signed
+                    } else {
+                        gimli::DW_ATE_unsigned
+                    }),
+                );
+                entry.set(gimli::DW_AT_type, base);
+
+                for field in enumeration.fields.iter() {
+                    let value = enumeration.normalize(field.value);
+                    // so gdb shows -1 instead of 18446744073709551615, and
+                    // wider ones as the bytes in target memory order
+                    let value = match (size, signed) {
+                        (0..=8, true) => AttributeValue::Sdata(value as i64),
+                        (0..=8, false) => AttributeValue::Udata(value as u64),
+                        _ => {
+                            AttributeValue::Block(wide_bytes(value, size, options.target.endianity))
+                        }
+                    };
+                    let id = self.unit.add(id, gimli::DW_TAG_enumerator);
+                    let entry = self.unit.get_mut(id);
+                    entry.set(
+                        gimli::DW_AT_name,
+                        AttributeValue::String(field.name.clone().into()),
+                    );
+                    entry.set(gimli::DW_AT_const_value, value);
+                }
+            }
+            BinjaType::Qualified(qualified) => {
+                let tags = qualified.tags();
+                if tags.is_empty() {
+                    return Err(format!(
+                        "qualified `{}` is neither const, volatile nor restrict",
+                        name
+                    )
+                    .into());
+                }
+                // `const volatile T` is a const_type of a volatile_type of T
+                let mut id = id;
+                for tag in tags.into_iter().skip(1) {
+                    let inner = self.unit.add(self.unit.root(), tag);
+                    (self.unit.get_mut(id)).set(gimli::DW_AT_type, AttributeValue::UnitRef(inner));
+                    id = inner;
+                }
+                if !qualified.target.is_empty() {
+                    let target = self.type_ref(&qualified.target);
+                    self.unit.get_mut(id).set(gimli::DW_AT_type, target);
+                }
+            }
+            BinjaType::Array(array) if array.string => {
+                let size = match &array.dynamic {
+                    Some(_) => 0,
+                    None => layout::size_of(self.shared.mappings, name).unwrap_or(array.count),
+                };
+                let entry = self.unit.get_mut(id);
+                match &array.dynamic {
+                    Some(dynamic) => {
+                        entry.set(
+                            gimli::DW_AT_data_location,
+                            AttributeValue::Exprloc(dynamic.data_location()),
+                        );
+                        entry.set(
+                            gimli::DW_AT_string_length,
+                            AttributeValue::Exprloc(dynamic.count_location()),
+                        );
+                        // DWARF 4 has the count's size in DW_AT_byte_size
+                        let count_size = dynamic.count_size(&options.target) as u64;
+                        entry.set(
+                            match self.shared.encoding.version {
+                                4 => gimli::DW_AT_byte_size,
+                                _ => gimli::DW_AT_string_length_byte_size,
+                            },
+                            AttributeValue::Udata(count_size),
+                        );
+                    }
+                    None => entry.set(gimli::DW_AT_byte_size, AttributeValue::Udata(size)),
+                }
+            }
+            BinjaType::Array(array) => {
+                let target = &array.target;
+                let element = if target.is_empty() {
+                    self.void_type()
+                } else {
+                    self.type_ref(target)
+                };
+                self.unit.get_mut(id).set(gimli::DW_AT_type, element);
+
+                let base = self.base_type(options.target.size_type, false);
+                if let Some(dynamic) = &array.dynamic {
+                    if !array.dimensions.is_empty() {
+                        return Err(format!("dynamic array `{}` has dimensions", name).into());
+                    }
+                    let mut count = dynamic.count_location();
+                    count.op_deref_size(dynamic.count_size(&options.target));
+                    self.unit.get_mut(id).set(
+                        gimli::DW_AT_data_location,
+                        AttributeValue::Exprloc(dynamic.data_location()),
+                    );
+                    let subrange = self.unit.add(id, gimli::DW_TAG_subrange_type);
+                    let entry = self.unit.get_mut(subrange);
+                    entry.set(gimli::DW_AT_type, base);
+                    entry.set(gimli::DW_AT_count, AttributeValue::Exprloc(count));
+                    return Ok(());
+                }
+                for count in array.counts() {
+                    let subrange = self.unit.add(id, gimli::DW_TAG_subrange_type);
+                    let entry = self.unit.get_mut(subrange);
+                    entry.set(gimli::DW_AT_type, base.clone());
+                    entry.set(gimli::DW_AT_upper_bound, AttributeValue::Udata(count - 1));
+                }
+            }
+            // what gcc emits for vector_size, lanes of a scalar
+            BinjaType::Vector(vector) => {
+                if vector.target.is_empty() || vector.count == 0 {
+                    return Err(format!("vector `{}` needs a lane type and count", name).into());
+                }
+                let lane = self.type_ref(&vector.target);
+                let base = self.base_type(options.target.size_type, false);
+                let entry = self.unit.get_mut(id);
+                entry.set(gimli::DW_AT_GNU_vector, AttributeValue::Flag(true));
+                entry.set(gimli::DW_AT_type, lane);
+                let subrange = self.unit.add(id, gimli::DW_TAG_subrange_type);
+                let entry = self.unit.get_mut(subrange);
+                entry.set(gimli::DW_AT_type, base);
+                entry.set(
+                    gimli::DW_AT_upper_bound,
+                    AttributeValue::Udata(vector.count - 1),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+// what a batch built on its own unit: the stand-ins of its types with the
+// DIEs `visit` added for them, and the types it made up
+struct Built {
+    unit: Unit,
+    ids: Vec<(UnitEntryId, UnitEntryId)>,
+    made: TypeIds,
+    swapped: Vec<(String, Endianity, UnitEntryId)>,
+}
+
+// the types a unit's batches made up so far, from the first to make each
+#[derive(Default)]
+struct Made {
+    made: TypeIds,
+    swapped: Vec<(String, Endianity, UnitEntryId)>,
+}
+
+// copies a batch into the unit it was built for, the stand-ins' attributes
+// and children onto the DIEs they stand in for and what it made up under the
+// root, unless an earlier batch made the same
+fn merge(unit: &mut Unit, cu: UnitId, built: Built, made: &mut Made) {
+    let standins: std::collections::HashMap<_, _> = built.ids.into_iter().collect();
+    // what the batch's DIEs became, those made up before are left out
+    let mut ids = std::collections::HashMap::new();
+    let mut made_up = std::collections::HashMap::new();
+    let mut swapped = std::collections::HashMap::new();
+    for (key, (_, id)) in built.made {
+        match made.made.get(&key) {
+            Some((_, earlier)) => _ = ids.insert(id, *earlier),
+            None => _ = made_up.insert(id, key),
+        }
+    }
+    for (name, endianity, id) in built.swapped {
+        let earlier = (made.swapped.iter())
+            .find(|(swapped, order, _)| *swapped == name && *order == endianity);
+        match earlier {
+            Some((.., earlier)) => _ = ids.insert(id, *earlier),
+            None => _ = swapped.insert(id, (name, endianity)),
+        }
+    }
+
+    // the DIEs first, so the attributes can refer to any of them
+    let batch = &built.unit;
+    let mut pending = Vec::new();
+    for id in batch.get(batch.root()).children() {
+        if let Some(to) = standins.get(id) {
+            pending.push((*id, *to));
+            continue;
+        }
+        if ids.contains_key(id) {
+            continue;
+        }
+        let to = unit.add(unit.root(), batch.get(*id).tag());
+        _ = ids.insert(*id, to);
+        pending.push((*id, to));
+        if let Some(key) = made_up.remove(id) {
+            _ = made.made.insert(key, (cu, to));
+        }
+        if let Some((name, endianity)) = swapped.remove(id) {
+            made.swapped.push((name, endianity, to));
+        }
+    }
+    let mut copied = Vec::new();
+    while let Some((from, to)) = pending.pop() {
+        copied.push((from, to));
+        for id in batch.get(from).children() {
+            let child = unit.add(to, batch.get(*id).tag());
+            _ = ids.insert(*id, child);
+            pending.push((*id, child));
+        }
+    }
+    for (from, to) in copied {
+        for attribute in batch.get(from).attrs() {
+            let value = match attribute.get() {
+                AttributeValue::UnitRef(id) => {
+                    AttributeValue::UnitRef(*ids.get(id).or(standins.get(id)).unwrap_or(id))
+                }
+                value => value.clone(),
+            };
+            unit.get_mut(to).set(attribute.name(), value);
+        }
+    }
+}
+
+// moves the unit's inline names into .debug_str, in the order of its DIEs
+fn intern(dwarf: &mut Dwarf, cu: UnitId) {
+    let unit = dwarf.units.get_mut(cu);
+    let mut stack = vec![unit.root()];
+    while let Some(id) = stack.pop() {
+        let entry = unit.get_mut(id);
+        for attribute in entry.attrs_mut() {
+            if let AttributeValue::String(string) = attribute.get() {
+                let string = dwarf.strings.add(string.clone());
+                attribute.set(AttributeValue::StringRef(string));
+            }
+        }
+        stack.extend(entry.children().rev().copied());
+    }
+}
+
+// fills in the DIEs of every type in `mappings`, each unit's batches spread
+// over as many threads as there are cores. the made up types go into
+// `dwarf_types` too, the first unit's where several made one up
+pub fn emit(
+    dwarf: &mut Dwarf,
+    unit_ids: &[UnitId],
+    mappings: &HashMap<String, BinjaType>,
+    dwarf_types: &mut TypeIds,
+    rust: &[UnitId],
+    options: &Options,
+    encoding: Encoding,
+) -> Err {
+    let mut groups: Vec<Vec<(&String, &BinjaType)>> = vec![Vec::new(); unit_ids.len()];
+    for (name, binja_type) in mappings.iter() {
+        let cu = dwarf_types[name].0;
+        if let Some(index) = unit_ids.iter().position(|id| *id == cu) {
+            groups[index].push((name, binja_type));
+        }
+    }
+    let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+    let mut batches = Vec::new();
+    for (index, group) in groups.iter().enumerate() {
+        for batch in group.chunks(group.len().div_ceil(threads).max(1)) {
+            batches.push((index, batch));
+        }
+    }
+
+    let shared = Shared {
+        mappings,
+        dwarf_types,
+        options,
+        encoding,
+    };
+    let chunk = batches.len().div_ceil(threads).max(1);
+    let built = std::thread::scope(|scope| {
+        let handles: Vec<_> = (batches.chunks(chunk))
+            .map(|batches| {
+                let shared = &shared;
+                // DynErr doesn't cross threads, the errors come back as text
+                scope.spawn(move || -> Result<Vec<(usize, Built)>, String> {
+                    let mut built = Vec::new();
+                    for (index, batch) in batches {
+                        let cu = unit_ids[*index];
+                        let mut unit = Unit::new(encoding, LineProgram::none());
+                        let ids = (batch.iter())
+                            .map(|(name, binja_type)| {
+                                (name.as_str(), unit.add(unit.root(), binja_type.tag()))
+                            })
+                            .collect();
+                        let mut builder = Builder {
+                            shared,
+                            unit: &mut unit,
+                            cu,
+                            rust: rust.contains(&cu),
+                            ids,
+                            made: HashMap::new(),
+                            swapped: Vec::new(),
+                        };
+                        for (name, binja_type) in batch.iter() {
+                            builder.fill(name, binja_type).map_err(|e| e.to_string())?;
+                        }
+                        let ids = (batch.iter())
+                            .map(|(name, _)| {
+                                (builder.ids[name.as_str()], shared.dwarf_types[*name].1)
+                            })
+                            .collect();
+                        let (made, swapped) = (builder.made, builder.swapped);
+                        built.push((
+                            *index,
+                            Built {
+                                unit,
+                                ids,
+                                made,
+                                swapped,
+                            },
+                        ));
+                    }
+                    Ok(built)
+                })
+            })
+            .collect();
+        let mut built = Vec::new();
+        for handle in handles {
+            built.extend(handle.join().unwrap()?);
+        }
+        Ok::<_, DynErr>(built)
+    })?;
+
+    let mut made: Vec<Made> = (unit_ids.iter()).map(|_| Made::default()).collect();
+    for (index, built) in built {
+        let cu = unit_ids[index];
+        merge(dwarf.units.get_mut(cu), cu, built, &mut made[index]);
+    }
+    for cu in unit_ids.iter() {
+        intern(dwarf, *cu);
+    }
+    for (name, id) in made.into_iter().flat_map(|made| made.made) {
+        _ = dwarf_types.entry(name).or_insert(id);
+    }
+    Ok(())
+}
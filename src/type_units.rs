@@ -0,0 +1,456 @@
+// `--type-units` moves the named structs, unions and enums out of the compile
+// units into type units of their own in .debug_types, DWARF 4's way of sharing
+// types: each is keyed by a 64-bit signature of its contents and referred to
+// with DW_FORM_ref_sig8, so a debugger loading several outputs, or a linker
+// merging them, keeps a single copy of every type they have in common.
+//
+// the signature follows DWARF 5 section 7.32, hashed with FNV-1a instead of
+// MD5: the tag, attributes and children of the type and of everything it
+// refers to, with pointers to named types standing for just their name so
+// recursive types end. a type unit can't refer into .debug_info, so the base
+// types, pointers and typedefs a type uses are copied into its unit. types
+// whose members are referred to from elsewhere, or that refer to anything but
+// types, stay in their compile unit.
+//
+// gimli only writes compile units, so the type units are written as such at
+// the end of .debug_info and moved over afterwards: their headers get the
+// signature and the offset of the type, which shifts the references within
+// the unit, and their roots' abbreviation the tag DW_TAG_type_unit.
+
+// gimli's ids only hash
+use std::collections::{HashMap, HashSet};
+
+use gimli::read::{AttributeValue as ReadValue, DebugAbbrev, DebugInfo};
+use gimli::write::{
+    AttributeValue, DebugInfoOffsets, Dwarf, EndianVec, LineProgram, Reference, Sections, Unit,
+    UnitEntryId, UnitId, Writer,
+};
+use gimli::{DebugTypeSignature, DwTag, Encoding, RunTimeEndian};
+
+use crate::DynErr;
+
+type Key = (UnitId, UnitEntryId);
+
+// the types that get a unit of their own
+const SHARED: [DwTag; 3] = [
+    gimli::DW_TAG_structure_type,
+    gimli::DW_TAG_union_type,
+    gimli::DW_TAG_enumeration_type,
+];
+
+// the ones copied into the units of the types using them
+const COPIED: [DwTag; 14] = [
+    gimli::DW_TAG_base_type,
+    gimli::DW_TAG_unspecified_type,
+    gimli::DW_TAG_pointer_type,
+    gimli::DW_TAG_reference_type,
+    gimli::DW_TAG_rvalue_reference_type,
+    gimli::DW_TAG_ptr_to_member_type,
+    gimli::DW_TAG_const_type,
+    gimli::DW_TAG_volatile_type,
+    gimli::DW_TAG_restrict_type,
+    gimli::DW_TAG_atomic_type,
+    gimli::DW_TAG_typedef,
+    gimli::DW_TAG_array_type,
+    gimli::DW_TAG_string_type,
+    gimli::DW_TAG_subroutine_type,
+];
+
+// references to named types from these only hash the name
+const POINTERS: [DwTag; 4] = [
+    gimli::DW_TAG_pointer_type,
+    gimli::DW_TAG_reference_type,
+    gimli::DW_TAG_rvalue_reference_type,
+    gimli::DW_TAG_ptr_to_member_type,
+];
+
+pub struct TypeUnit {
+    pub unit: UnitId,
+    // the type, in `unit`
+    pub entry: UnitEntryId,
+    pub signature: u64,
+    // where `extract` put it in .debug_types
+    pub offset: u64,
+    pub type_offset: u64,
+}
+
+fn target(from: UnitId, value: &AttributeValue) -> Option<Key> {
+    match value {
+        AttributeValue::UnitRef(id) => Some((from, *id)),
+        AttributeValue::DebugInfoRef(Reference::Entry(unit, id)) => Some((*unit, *id)),
+        _ => None,
+    }
+}
+
+// every DIE below `key`, `key` first
+fn subtree(dwarf: &Dwarf, key: Key) -> Vec<Key> {
+    let unit = dwarf.units.get(key.0);
+    let mut entries = Vec::new();
+    let mut stack = vec![key.1];
+    while let Some(id) = stack.pop() {
+        entries.push((key.0, id));
+        stack.extend(unit.get(id).children().rev().copied());
+    }
+    entries
+}
+
+struct Types<'a> {
+    dwarf: &'a Dwarf,
+    // the top level DIE every DIE is part of
+    top: HashMap<Key, Key>,
+    candidates: Vec<Key>,
+    // the top level DIEs each candidate's unit is made of, itself first, and
+    // the other candidates it refers to
+    closures: HashMap<Key, (Vec<Key>, Vec<Key>)>,
+    signatures: HashMap<Key, u64>,
+}
+
+impl<'a> Types<'a> {
+    fn new(dwarf: &'a Dwarf, units: &[UnitId]) -> Types<'a> {
+        let mut types = Types {
+            dwarf,
+            top: HashMap::new(),
+            candidates: Vec::new(),
+            closures: HashMap::new(),
+            signatures: HashMap::new(),
+        };
+        for cu in units {
+            let unit = dwarf.units.get(*cu);
+            for id in unit.get(unit.root()).children() {
+                for key in subtree(dwarf, (*cu, *id)) {
+                    _ = types.top.insert(key, (*cu, *id));
+                }
+                let entry = unit.get(*id);
+                // a declaration stays with the units that refer to it
+                if SHARED.contains(&entry.tag())
+                    && entry.get(gimli::DW_AT_name).is_some()
+                    && entry.get(gimli::DW_AT_declaration).is_none()
+                {
+                    types.candidates.push((*cu, *id));
+                }
+            }
+        }
+        types
+    }
+
+    fn tag(&self, key: Key) -> DwTag {
+        self.dwarf.units.get(key.0).get(key.1).tag()
+    }
+
+    fn is_candidate(&self, key: Key) -> bool {
+        self.top.get(&key) == Some(&key) && self.candidates.contains(&key)
+    }
+
+    // None if the type can't go into a unit of its own
+    fn closure(&self, candidate: Key) -> Option<(Vec<Key>, Vec<Key>)> {
+        let mut closure = vec![candidate];
+        let mut uses = Vec::new();
+        let mut next = 0;
+        while let Some(key) = closure.get(next).copied() {
+            next += 1;
+            for (cu, id) in subtree(self.dwarf, key) {
+                for attribute in self.dwarf.units.get(cu).get(id).attrs() {
+                    if let AttributeValue::FileIndex(_) = attribute.get() {
+                        return None;
+                    }
+                    let Some(referred) = target(cu, attribute.get()) else {
+                        continue;
+                    };
+                    let top = *self.top.get(&referred)?;
+                    if closure.contains(&top) {
+                        continue;
+                    }
+                    if top == referred && self.is_candidate(top) {
+                        if !uses.contains(&top) {
+                            uses.push(top);
+                        }
+                    } else if COPIED.contains(&self.tag(top)) {
+                        closure.push(top);
+                    } else {
+                        return None;
+                    }
+                }
+            }
+        }
+        Some((closure, uses))
+    }
+
+    fn string(&self, value: &AttributeValue) -> Option<Vec<u8>> {
+        match value {
+            AttributeValue::StringRef(id) => Some(self.dwarf.strings.get(*id).to_vec()),
+            AttributeValue::String(string) => Some(string.clone()),
+            _ => None,
+        }
+    }
+
+    fn signature(&mut self, candidate: Key, pending: &mut Vec<Key>) -> u64 {
+        if let Some(signature) = self.signatures.get(&candidate) {
+            return *signature;
+        }
+        pending.push(candidate);
+        let (closure, _) = self.closures[&candidate].clone();
+        let entries: Vec<Key> = closure
+            .iter()
+            .flat_map(|key| subtree(self.dwarf, *key))
+            .collect();
+
+        let mut data = Vec::new();
+        for (cu, id) in entries.iter().copied() {
+            let entry = self.dwarf.units.get(cu).get(id);
+            data.push(b'D');
+            data.extend_from_slice(&entry.tag().0.to_le_bytes());
+            for attribute in entry.attrs() {
+                data.push(b'A');
+                data.extend_from_slice(&attribute.name().0.to_le_bytes());
+                let value = attribute.get();
+                if let Some(string) = self.string(value) {
+                    data.push(b'S');
+                    data.extend(string);
+                    data.push(0);
+                    continue;
+                }
+                let Some(referred) = target(cu, value) else {
+                    data.extend(format!("{:?}", value).bytes());
+                    continue;
+                };
+                if let Some(index) = entries.iter().position(|key| *key == referred) {
+                    data.push(b'R');
+                    data.extend_from_slice(&(index as u64).to_le_bytes());
+                    continue;
+                }
+                let name = self.dwarf.units.get(referred.0).get(referred.1);
+                if POINTERS.contains(&entry.tag()) || pending.contains(&referred) {
+                    data.push(b'N');
+                    data.extend_from_slice(&name.tag().0.to_le_bytes());
+                    data.extend(self.string(name.get(gimli::DW_AT_name).unwrap()).unwrap());
+                    data.push(0);
+                } else {
+                    data.push(b'T');
+                    data.extend_from_slice(&self.signature(referred, pending).to_le_bytes());
+                }
+            }
+            data.extend_from_slice(&(entry.children().len() as u64).to_le_bytes());
+        }
+        _ = pending.pop();
+
+        let signature = data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        _ = self.signatures.insert(candidate, signature);
+        signature
+    }
+}
+
+// moves the types of `units` into type units appended to `dwarf`
+pub fn split(dwarf: &mut Dwarf, units: &[UnitId]) -> Vec<TypeUnit> {
+    let mut types = Types::new(dwarf, units);
+
+    // members referred to from outside their type pin it
+    let mut pinned = HashSet::new();
+    for (key, top) in types.top.iter() {
+        for attribute in dwarf.units.get(key.0).get(key.1).attrs() {
+            if let Some(referred) = target(key.0, attribute.get()) {
+                match types.top.get(&referred) {
+                    Some(owner) if *owner != referred && owner != top => {
+                        _ = pinned.insert(*owner);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    for candidate in types.candidates.clone() {
+        if pinned.contains(&candidate) {
+            continue;
+        }
+        if let Some(closure) = types.closure(candidate) {
+            _ = types.closures.insert(candidate, closure);
+        }
+    }
+    // and so do the types a type that's staying uses
+    loop {
+        let staying: Vec<Key> = types
+            .closures
+            .iter()
+            .filter(|(_, (_, uses))| uses.iter().any(|key| !types.closures.contains_key(key)))
+            .map(|(key, _)| *key)
+            .collect();
+        if staying.is_empty() {
+            break;
+        }
+        for key in staying {
+            _ = types.closures.remove(&key);
+        }
+    }
+    let moving: Vec<Key> = types
+        .candidates
+        .iter()
+        .filter(|key| types.closures.contains_key(key))
+        .copied()
+        .collect();
+    let signatures: HashMap<Key, u64> = moving
+        .iter()
+        .map(|key| (*key, types.signature(*key, &mut Vec::new())))
+        .collect();
+
+    let reference = |cu: UnitId, value: &AttributeValue, copies: &HashMap<Key, UnitEntryId>| {
+        let referred = target(cu, value)?;
+        match copies.get(&referred) {
+            Some(id) => Some(AttributeValue::UnitRef(*id)),
+            None => signatures
+                .get(&referred)
+                .map(|signature| AttributeValue::DebugTypesRef(DebugTypeSignature(*signature))),
+        }
+    };
+    let mut type_units = Vec::new();
+    for key in moving.iter() {
+        let cu = dwarf.units.get(key.0);
+        let mut unit = Unit::new(cu.encoding(), LineProgram::none());
+        let root = unit.root();
+        if let Some(language) = cu.get(cu.root()).get(gimli::DW_AT_language) {
+            unit.get_mut(root)
+                .set(gimli::DW_AT_language, language.clone());
+        }
+
+        let mut copies = HashMap::new();
+        let mut pairs = Vec::new();
+        for top in types.closures[key].0.iter() {
+            let mut stack = vec![(*top, root)];
+            while let Some(((from, id), parent)) = stack.pop() {
+                let entry = dwarf.units.get(from).get(id);
+                let copy = unit.add(parent, entry.tag());
+                _ = copies.insert((from, id), copy);
+                pairs.push(((from, id), copy));
+                stack.extend(entry.children().rev().map(|child| ((from, *child), copy)));
+            }
+        }
+        for ((from, id), copy) in pairs {
+            for attribute in dwarf.units.get(from).get(id).attrs() {
+                let value = reference(from, attribute.get(), &copies)
+                    .unwrap_or_else(|| attribute.get().clone());
+                unit.get_mut(copy).set(attribute.name(), value);
+            }
+        }
+        type_units.push((copies[key], signatures[key], unit));
+    }
+
+    for cu in units {
+        let unit = dwarf.units.get_mut(*cu);
+        let root = unit.root();
+        for key in moving.iter().filter(|key| key.0 == *cu) {
+            unit.get_mut(root).delete_child(key.1);
+        }
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            let entry = unit.get_mut(id);
+            for attribute in entry.attrs_mut() {
+                if let Some(value) = reference(*cu, attribute.get(), &HashMap::new()) {
+                    attribute.set(value);
+                }
+            }
+            stack.extend(entry.children().copied());
+        }
+    }
+
+    type_units
+        .into_iter()
+        .map(|(entry, signature, unit)| TypeUnit {
+            unit: dwarf.units.add(unit),
+            entry,
+            signature,
+            offset: 0,
+            type_offset: 0,
+        })
+        .collect()
+}
+
+// cuts the type units off the end of .debug_info and returns them as
+// .debug_types
+pub fn extract(
+    sections: &mut Sections<EndianVec<RunTimeEndian>>,
+    offsets: &DebugInfoOffsets,
+    encoding: Encoding,
+    type_units: &mut [TypeUnit],
+) -> Result<Vec<u8>, DynErr> {
+    let Some(first) = type_units.first() else {
+        return Ok(Vec::new());
+    };
+    let endian = sections.debug_info.0.endian();
+    let start = offsets.unit(first.unit).0;
+    let info = sections.debug_info.0.take();
+    sections.debug_info.0.write(&info[..start])?;
+
+    let format = encoding.format;
+    let word = format.word_size();
+    let mut debug_types = EndianVec::new(endian);
+    let mut headers = DebugInfo::new(&info, endian).units();
+    while let Some(header) = headers.next()? {
+        let Some(begin) = header.offset().as_debug_info_offset() else {
+            continue;
+        };
+        let Some(type_unit) = type_units
+            .iter_mut()
+            .find(|type_unit| offsets.unit(type_unit.unit) == begin)
+        else {
+            continue;
+        };
+
+        let abbrev = header.debug_abbrev_offset().0;
+        // the root's abbreviation comes first, code 1 for DW_TAG_compile_unit
+        if sections.debug_abbrev.0.slice().get(abbrev..abbrev + 2)
+            != Some(&[1, gimli::DW_TAG_compile_unit.0 as u8])
+        {
+            return Err(format!(
+                "unexpected abbreviations for the type unit at {:#x}",
+                begin.0
+            )
+            .into());
+        }
+
+        // the header grows by the signature and the type's offset, and with it
+        // every reference relative to the unit
+        let grown = 8 + word as u64;
+        let mut references = Vec::new();
+        let debug_abbrev = DebugAbbrev::new(sections.debug_abbrev.0.slice(), endian);
+        let abbreviations = header.abbreviations(&debug_abbrev)?;
+        let mut entries = header.entries_raw(&abbreviations, None)?;
+        while !entries.is_empty() {
+            let Some(abbreviation) = entries.read_abbreviation()? else {
+                continue;
+            };
+            for spec in abbreviation.attributes() {
+                let at = entries.next_offset().0;
+                if let ReadValue::UnitRef(offset) = entries.read_attribute(*spec)?.value() {
+                    references.push((at, offset.0));
+                }
+            }
+        }
+        sections
+            .debug_abbrev
+            .0
+            .write_u8_at(abbrev + 1, gimli::DW_TAG_type_unit.0 as u8)?;
+
+        let unit = &info[begin.0..begin.0 + header.length_including_self()];
+        let offset = debug_types.len();
+        let length = debug_types.write_initial_length(format)?;
+        let length_base = debug_types.len();
+        debug_types.write_u16(encoding.version)?;
+        debug_types.write_udata(abbrev as u64, word)?;
+        debug_types.write_u8(encoding.address_size)?;
+        debug_types.write_u64(type_unit.signature)?;
+        let type_offset =
+            (offsets.entry(type_unit.unit, type_unit.entry).0 - begin.0) as u64 + grown;
+        debug_types.write_udata(type_offset, word)?;
+        debug_types.write(&unit[header.size_of_header()..])?;
+        for (at, target) in references {
+            let at = offset + at + grown as usize;
+            debug_types.write_udata_at(at, target as u64 + grown, word)?;
+        }
+        let size = (debug_types.len() - length_base) as u64;
+        debug_types.write_initial_length_at(length, size, format)?;
+
+        type_unit.offset = offset as u64;
+        type_unit.type_offset = type_offset;
+    }
+    Ok(debug_types.into_vec())
+}
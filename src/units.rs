@@ -0,0 +1,182 @@
+// `--units units.json` splits the output into several compilation units, each
+// with its own language and producer, since debuggers pick name lookup and
+// printing rules by the language of the unit a DIE lives in. units that don't
+// name theirs take `--language`, C unless it says otherwise.
+//
+// [
+//     {"name": "app.cpp", "language": "c++", "producer": "clang version 17"},
+//     {"name": "libc.c", "language": "c", "producer": "GNU C17 11.4.0",
+//      "types": ["FILE", "malloc_chunk"], "variables": ["stdin", "main_arena"],
+//      "functions": ["malloc", "free"]}
+// ]
+//
+// types and variables not listed anywhere go into the first unit, references
+// between units use DW_FORM_ref_addr. subprograms not listed go to the unit
+// named like the file of their first line in `--lines`, else the first one.
+// each unit's DW_AT_ranges covers exactly the code placed in it.
+
+use std::fs;
+use std::path::Path;
+
+use gimli::write::UnitId;
+use gimli::DwLang;
+use serde::Deserialize;
+
+use crate::{BinjaType, DynErr, HashMap};
+
+fn default_producer() -> String {
+    String::from(":3")
+}
+
+fn default_comp_dir() -> String {
+    String::from("llvm-dwarf")
+}
+
+#[derive(Deserialize)]
+pub struct UnitSpec {
+    pub name: String,
+    // filled in from --language when it's left out
+    #[serde(default)]
+    pub language: String,
+    #[serde(default = "default_producer")]
+    pub producer: String,
+    #[serde(default = "default_comp_dir")]
+    pub comp_dir: String,
+    #[serde(default)]
+    pub types: Vec<String>,
+    #[serde(default)]
+    pub variables: Vec<String>,
+    #[serde(default)]
+    pub functions: Vec<String>,
+}
+
+// what every output looked like before units could be chosen, in `language`
+pub fn default(language: &str) -> Result<UnitSpec, DynErr> {
+    self::language(language)?;
+    Ok(UnitSpec {
+        name: String::from("debuginfo.c"),
+        language: language.to_string(),
+        producer: default_producer(),
+        comp_dir: default_comp_dir(),
+        types: Vec::new(),
+        variables: Vec::new(),
+        functions: Vec::new(),
+    })
+}
+
+pub fn load(path: &Path, default: &str) -> Result<Vec<UnitSpec>, DynErr> {
+    let mut specs: Vec<UnitSpec> = match fs::read_to_string(path) {
+        Ok(json) => {
+            serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e))?
+        }
+        Err(e) => return Err(format!("{}: {}", path.display(), e).into()),
+    };
+    if specs.is_empty() {
+        return Err(format!("{}: no units", path.display()).into());
+    }
+    for spec in specs.iter_mut() {
+        if spec.language.is_empty() {
+            spec.language = default.to_string();
+        }
+        language(&spec.language).map_err(|e| format!("unit {}: {}", spec.name, e))?;
+    }
+    Ok(specs)
+}
+
+// the unit each listed type, variable and function goes into
+pub struct Placement<'a> {
+    pub types: HashMap<&'a str, UnitId>,
+    pub variables: HashMap<&'a str, UnitId>,
+    pub functions: HashMap<&'a str, UnitId>,
+}
+
+pub fn place<'a>(
+    specs: &'a [UnitSpec],
+    unit_ids: &[UnitId],
+    mappings: &HashMap<String, BinjaType>,
+) -> Result<Placement<'a>, DynErr> {
+    let mut placement = Placement {
+        types: HashMap::new(),
+        variables: HashMap::new(),
+        functions: HashMap::new(),
+    };
+    for (spec, cu) in specs.iter().zip(unit_ids.iter()) {
+        for name in spec.types.iter() {
+            if !mappings.contains_key(name) {
+                return Err(format!("unit {} lists unknown type `{}`", spec.name, name).into());
+            }
+            if placement.types.insert(name, *cu).is_some() {
+                return Err(format!("type `{}` is listed in several units", name).into());
+            }
+        }
+        for name in spec.variables.iter() {
+            if placement.variables.insert(name, *cu).is_some() {
+                return Err(format!("variable `{}` is listed in several units", name).into());
+            }
+        }
+        for name in spec.functions.iter() {
+            if placement.functions.insert(name, *cu).is_some() {
+                return Err(format!("function `{}` is listed in several units", name).into());
+            }
+        }
+    }
+    Ok(placement)
+}
+
+// (start, end) ranges sorted, with touching ones merged
+pub fn coalesce(mut ranges: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+    ranges.sort();
+    let mut merged: Vec<(u64, u64)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some((_, last)) if start <= *last => *last = (*last).max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+// the ones gdb has expression parsers for, and a few it reads as C
+const LANGUAGES: &[(&str, DwLang)] = &[
+    ("c", gimli::DW_LANG_C),
+    ("c89", gimli::DW_LANG_C89),
+    ("c99", gimli::DW_LANG_C99),
+    ("c11", gimli::DW_LANG_C11),
+    ("c17", gimli::DW_LANG_C17),
+    ("c++", gimli::DW_LANG_C_plus_plus),
+    ("cpp", gimli::DW_LANG_C_plus_plus),
+    ("c++03", gimli::DW_LANG_C_plus_plus_03),
+    ("c++11", gimli::DW_LANG_C_plus_plus_11),
+    ("c++14", gimli::DW_LANG_C_plus_plus_14),
+    ("c++17", gimli::DW_LANG_C_plus_plus_17),
+    ("c++20", gimli::DW_LANG_C_plus_plus_20),
+    ("objc", gimli::DW_LANG_ObjC),
+    ("objc++", gimli::DW_LANG_ObjC_plus_plus),
+    ("rust", gimli::DW_LANG_Rust),
+    ("go", gimli::DW_LANG_Go),
+    ("d", gimli::DW_LANG_D),
+    ("zig", gimli::DW_LANG_Zig),
+    ("swift", gimli::DW_LANG_Swift),
+    ("fortran", gimli::DW_LANG_Fortran95),
+    ("ada", gimli::DW_LANG_Ada95),
+    ("pascal", gimli::DW_LANG_Pascal83),
+    ("modula-2", gimli::DW_LANG_Modula2),
+    ("opencl", gimli::DW_LANG_OpenCL),
+    ("asm", gimli::DW_LANG_Mips_Assembler),
+    ("assembly", gimli::DW_LANG_Mips_Assembler),
+];
+
+pub fn language(name: &str) -> Result<DwLang, DynErr> {
+    match LANGUAGES.iter().find(|(language, _)| *language == name) {
+        Some((_, language)) => Ok(*language),
+        None => {
+            let available: Vec<&str> = LANGUAGES.iter().map(|(name, _)| *name).collect();
+            Err(format!(
+                "unknown language `{}`, available: {}",
+                name,
+                available.join(", ")
+            )
+            .into())
+        }
+    }
+}
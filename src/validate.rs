@@ -0,0 +1,219 @@
+// every run goes over the structs' layouts before anything is emitted and
+// warns about what doesn't add up: members that overlap, ones running past
+// the struct's `size`, and holes bigger than aligning the next member (or
+// the struct's size) could need, which usually mean a member is missing or
+// sits at the wrong offset:
+//
+//   layout: `node`: `key` at 0x8 overlaps `next` at 0x0 by 4 bytes
+//   layout: `node`: 8 byte hole before `data` at 0x18
+//
+// `--strict` makes them errors that stop the run. union members only have
+// to fit, and bitfields are compared bit by bit. alignments are taken as
+// large as the sizes allow, so a hole the ABI needs is never reported, a
+// few too small might not be. declarations and structs without members
+// have nothing to check.
+
+use crate::export_headers::natural;
+use crate::{Array, BinjaType, Class, Field, HashMap, Structure};
+
+pub struct Validator<'a> {
+    types: &'a HashMap<String, BinjaType>,
+    pointer_size: u64,
+}
+
+// `12 bytes` or `3 bits`
+fn amount(bits: u64) -> String {
+    match bits.is_multiple_of(8) {
+        true => format!("{} bytes", bits / 8),
+        false => format!("{} bits", bits),
+    }
+}
+
+fn member(field: &Field) -> String {
+    match field.name.as_str() {
+        "" => format!("the member at {:#x}", field.offset),
+        name => format!("`{}` at {:#x}", name, field.offset),
+    }
+}
+
+impl<'a> Validator<'a> {
+    pub fn new(types: &'a HashMap<String, BinjaType>, pointer_size: u64) -> Validator<'a> {
+        Validator {
+            types,
+            pointer_size,
+        }
+    }
+
+    // with the pointers' sizes filled in, unlike layout::size_of
+    pub fn size(&self, name: &str) -> Option<u64> {
+        Some(self.layout(name, 0)?.0)
+    }
+
+    // (size, alignment), None for dangling names and cycles
+    fn layout(&self, name: &str, depth: usize) -> Option<(u64, u64)> {
+        if name.is_empty() {
+            return Some((0, 1));
+        }
+        if depth > 64 {
+            return None;
+        }
+        Some(match self.types.get(name)? {
+            BinjaType::Structure(layout)
+            | BinjaType::Union(layout)
+            | BinjaType::Class(Class { layout, .. }) => {
+                let mut align = 1;
+                for field in layout.fields.iter() {
+                    align = align.max(self.layout(&field.typename, depth + 1)?.1);
+                }
+                (layout.size, align)
+            }
+            BinjaType::Integer(integer) => (integer.size, natural(integer.size)),
+            BinjaType::Enum(enumeration) => (enumeration.size, natural(enumeration.size)),
+            BinjaType::Pointer(pointer) => {
+                let size = match (pointer.size, &pointer.member_of) {
+                    (0, Some(_))
+                        if matches!(
+                            self.types.get(&pointer.target),
+                            Some(BinjaType::Function(_))
+                        ) =>
+                    {
+                        2 * self.pointer_size
+                    }
+                    (0, _) => self.pointer_size,
+                    (size, _) => size,
+                };
+                (size, natural(size).min(self.pointer_size))
+            }
+            BinjaType::Function(_) => (0, 1),
+            BinjaType::Typedef(typedef) => self.layout(&typedef.target, depth + 1)?,
+            BinjaType::Qualified(qualified) => self.layout(&qualified.target, depth + 1)?,
+            BinjaType::Array(Array {
+                dynamic: Some(dynamic),
+                ..
+            }) => {
+                let size = match dynamic.size {
+                    0 => self.pointer_size,
+                    size => size,
+                };
+                (size, natural(size).min(self.pointer_size))
+            }
+            BinjaType::Array(array) => {
+                let (size, align) = self.layout(&array.target, depth + 1)?;
+                (size * array.elements(), align)
+            }
+            BinjaType::Vector(vector) => {
+                let size = self.layout(&vector.target, depth + 1)?.0 * vector.count;
+                (size, natural(size))
+            }
+        })
+    }
+
+    fn check(&self, issues: &mut Vec<String>, name: &str, layout: &Structure, union: bool) {
+        // (field, first bit, bits, alignment)
+        let mut members: Vec<(&Field, u64, u64, u64)> = (layout.fields.iter())
+            .filter_map(|field| {
+                let (size, align) = self.layout(&field.typename, 0)?;
+                let start = field.offset * 8 + field.bit_offset.unwrap_or(0);
+                Some((field, start, field.bit_size.unwrap_or(size * 8), align))
+            })
+            .collect();
+        members.sort_by_key(|(_, start, _, _)| *start);
+
+        let size = layout.size * 8;
+        for (field, start, bits, _) in members.iter() {
+            if start + bits > size {
+                issues.push(format!(
+                    "`{}`: {} runs {} past its {} bytes",
+                    name,
+                    member(field),
+                    amount(start + bits - size),
+                    layout.size
+                ));
+            }
+        }
+        if union || members.is_empty() {
+            return;
+        }
+
+        // the member reaching furthest so far, and where it ends
+        let mut furthest: Option<(&Field, u64)> = None;
+        let mut align = 1;
+        for (field, start, bits, field_align) in members.iter() {
+            align = align.max(*field_align);
+            let reach = furthest.map_or(0, |(_, end)| end);
+            match furthest {
+                Some((before, end)) if *bits > 0 && end > *start => issues.push(format!(
+                    "`{}`: {} overlaps {} by {}",
+                    name,
+                    member(field),
+                    member(before),
+                    amount((end - start).min(*bits))
+                )),
+                _ => {
+                    let hole = (start / 8).saturating_sub(reach.div_ceil(8));
+                    if hole > 0 && hole >= *field_align {
+                        issues.push(format!(
+                            "`{}`: {} byte hole before {}",
+                            name,
+                            hole,
+                            member(field)
+                        ));
+                    }
+                }
+            }
+            if start + bits > reach {
+                furthest = Some((field, start + bits));
+            }
+        }
+        let reach = furthest.map_or(0, |(_, end)| end).div_ceil(8);
+        let hole = layout.size.saturating_sub(reach);
+        if hole > 0 && hole >= align {
+            issues.push(format!(
+                "`{}`: {} byte hole at the end, from {:#x}",
+                name, hole, reach
+            ));
+        }
+    }
+}
+
+pub fn issues(types: &HashMap<String, BinjaType>, pointer_size: u64) -> Vec<String> {
+    let validator = Validator::new(types, pointer_size);
+    let mut issues = Vec::new();
+    for (name, binja_type) in types.iter() {
+        let (layout, union) = match binja_type {
+            BinjaType::Structure(layout) => (layout.clone(), false),
+            BinjaType::Union(layout) => (layout.clone(), true),
+            // the bases take up room like members, unless they're empty
+            BinjaType::Class(class) => {
+                let mut layout = class.layout.clone();
+                for base in class.bases.iter() {
+                    let empty = match types.get(&base.typename) {
+                        Some(BinjaType::Class(base)) => {
+                            base.layout.fields.is_empty() && base.bases.is_empty()
+                        }
+                        Some(BinjaType::Structure(base)) => base.fields.is_empty(),
+                        _ => false,
+                    };
+                    if !empty {
+                        layout.fields.push(Field {
+                            offset: base.offset,
+                            name: base.typename.clone(),
+                            typename: base.typename.clone(),
+                            endianity: None,
+                            bit_offset: None,
+                            bit_size: None,
+                            access: None,
+                        });
+                    }
+                }
+                (layout, false)
+            }
+            _ => continue,
+        };
+        if layout.declaration {
+            continue;
+        }
+        validator.check(&mut issues, name, &layout, union);
+    }
+    issues
+}
@@ -0,0 +1,217 @@
+// the global variables' DIEs and symbols, the `vtable for` symbols of the
+// classes with a vtable and the constants, whose DIEs have their value
+// instead of a location. a variable goes into the unit listing it, else the
+// first one, and under the namespaces its name is scoped by.
+
+use gimli::write::{Address, AttributeValue, Expression};
+use log::debug;
+
+use crate::{
+    global_scope, global_type, layout, mangle, parse_wide, type_ref, wide_bytes, BinjaType, Class,
+    Constant, ConstantValue, DebugSup, DynErr, Enum, Err, Generator, GlobalVariable, HashMap,
+    Integer, RawSymbol,
+};
+
+impl Generator<'_> {
+    // (start, size) of the variables in each unit, for .debug_aranges
+    pub fn variables(
+        &mut self,
+        global_variables: &HashMap<u64, GlobalVariable>,
+    ) -> Result<Vec<Vec<(u64, u64)>>, DynErr> {
+        let options = self.options;
+        let mut data = vec![Vec::new(); self.unit_ids.len()];
+        for (
+            address,
+            GlobalVariable {
+                name,
+                size,
+                typename,
+                decl,
+                space,
+            },
+        ) in global_variables.clone().into_iter()
+        {
+            debug!("variable `{}` at {:#x}", name, address);
+            if address > options.target.max_address() {
+                return Err(format!(
+                    "variable `{}` at {:#x} doesn't fit a {} byte address",
+                    name, address, options.target.address_size
+                )
+                .into());
+            }
+            let cu = *(self.placement.variables)
+                .get(name.as_str())
+                .unwrap_or(&self.unit_ids[0]);
+            let index = self.index(cu);
+            data[index].push((
+                address,
+                match size {
+                    0 => layout::size_of(self.mappings, &typename).unwrap_or(0),
+                    size => size,
+                },
+            ));
+            let (parent, unqualified) = global_scope(
+                &mut self.dwarf,
+                self.mappings,
+                &mut self.namespaces,
+                cu,
+                &name,
+            );
+            let unit = self.dwarf.units.get_mut(cu);
+            let id = unit.add(parent, gimli::DW_TAG_variable);
+            if let Some(decl) = decl {
+                self.declared[index].push((id, decl));
+            }
+            let unit = unit.get_mut(id);
+            unit.set(
+                gimli::DW_AT_name,
+                AttributeValue::StringRef(self.dwarf.strings.add(unqualified)),
+            );
+            if !typename.is_empty() {
+                unit.set(
+                    gimli::DW_AT_type,
+                    match self.sup {
+                        DebugSup::Reference(supplementary) => AttributeValue::DebugInfoRefSup(
+                            *supplementary.types.get(&typename).unwrap(),
+                        ),
+                        _ => type_ref(&self.dwarf_types, cu, &typename),
+                    },
+                );
+            }
+            unit.set(gimli::DW_AT_external, AttributeValue::Flag(true));
+            if let Some(class) = space.and_then(|space| self.spaces.get(&space)?.class) {
+                unit.set(
+                    gimli::DW_AT_address_class,
+                    AttributeValue::AddressClass(gimli::DwAddr(class)),
+                );
+            }
+            let mut location = Expression::new();
+            location.op_addr(Address::Constant(address));
+            unit.set(gimli::DW_AT_location, AttributeValue::Exprloc(location));
+
+            self.writer.add_symbol(
+                name,
+                RawSymbol {
+                    st_name: 0,
+                    // 0x10 <- global binding
+                    // 0x01 <- object type
+                    st_info: 0x11,
+                    st_other: 0,
+                    st_shndx: 0,
+                    st_size: size,
+                    // assumed to be non rebased offset
+                    st_value: address,
+                },
+            );
+        }
+        Ok(data)
+    }
+
+    // gdb reads the dynamic type of an object off the `vtable for` symbol its
+    // vtable pointer points into
+    pub fn vtables(&mut self) -> Err {
+        for (name, binja_type) in self.mappings.iter() {
+            let BinjaType::Class(Class {
+                vtable: Some(address),
+                methods,
+                ..
+            }) = binja_type
+            else {
+                continue;
+            };
+            let Some(mangled) = mangle(name) else {
+                return Err(format!("class `{}` has a vtable but can't be mangled", name).into());
+            };
+            // the offset to the top and the typeinfo pointer, then the slots
+            let slots = (methods.iter().filter_map(|method| method.vtable_index))
+                .max()
+                .map_or(0, |index| index + 1);
+            self.writer.add_symbol(
+                format!("_ZTV{}", mangled),
+                RawSymbol {
+                    st_name: 0,
+                    // 0x10 <- global binding
+                    // 0x01 <- object type
+                    st_info: 0x11,
+                    st_other: 0,
+                    st_shndx: 0,
+                    st_size: (2 + slots) * self.options.target.pointer_size,
+                    st_value: *address,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    pub fn constants(&mut self, constants: &HashMap<String, Constant>) -> Err {
+        let mappings = self.mappings;
+        for (name, Constant { typename, value }) in constants.iter() {
+            let cu = *(self.placement.variables)
+                .get(name.as_str())
+                .unwrap_or(&self.unit_ids[0]);
+            let constant_type = global_type(self.sup, &self.dwarf_types, cu, typename)
+                .ok_or_else(|| format!("constant `{}` has unknown type `{}`", name, typename))?;
+
+            let (parent, unqualified) =
+                global_scope(&mut self.dwarf, mappings, &mut self.namespaces, cu, name);
+            let unit = self.dwarf.units.get_mut(cu);
+            let id = unit.add(parent, gimli::DW_TAG_variable);
+            let unit = unit.get_mut(id);
+            unit.set(
+                gimli::DW_AT_name,
+                AttributeValue::StringRef(self.dwarf.strings.add(unqualified)),
+            );
+            unit.set(gimli::DW_AT_type, constant_type);
+            unit.set(gimli::DW_AT_external, AttributeValue::Flag(true));
+            // no DW_AT_location, the value is all there is. integers past 64
+            // bits have theirs written as a string, like enumerators
+            let mut integer = mappings.get(typename);
+            for _ in 0..64 {
+                match integer {
+                    Some(BinjaType::Typedef(typedef)) => integer = mappings.get(&typedef.target),
+                    Some(BinjaType::Qualified(qualified)) => {
+                        integer = mappings.get(&qualified.target)
+                    }
+                    _ => break,
+                }
+            }
+            let size = match integer {
+                Some(
+                    BinjaType::Integer(Integer { size, .. }) | BinjaType::Enum(Enum { size, .. }),
+                ) => Some(*size),
+                _ => None,
+            };
+            let wide = match (size, value) {
+                (Some(size), ConstantValue::String(text)) => match parse_wide(text) {
+                    Some(value) => Some((size, value)),
+                    None => {
+                        return Err(format!("constant `{}` isn't a number: {:?}", name, text).into())
+                    }
+                },
+                (Some(size), ConstantValue::Unsigned(value)) => Some((size, *value as i128)),
+                (Some(size), ConstantValue::Signed(value)) => Some((size, *value as i128)),
+                (None, _) => None,
+            };
+            unit.set(
+                gimli::DW_AT_const_value,
+                match (wide, value) {
+                    (Some((size, value)), _) if size > 8 => AttributeValue::Block(wide_bytes(
+                        value,
+                        size,
+                        self.options.target.endianity,
+                    )),
+                    (Some((_, value)), _) if value < 0 => AttributeValue::Sdata(value as i64),
+                    (Some((_, value)), _) => AttributeValue::Udata(value as u64),
+                    (None, ConstantValue::Unsigned(value)) => AttributeValue::Udata(*value),
+                    (None, ConstantValue::Signed(value)) => AttributeValue::Sdata(*value),
+                    (None, ConstantValue::String(value)) => {
+                        let mut bytes = value.as_bytes().to_vec();
+                        bytes.push(0);
+                        AttributeValue::Block(bytes)
+                    }
+                },
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+// conditional definitions: an export may carry a variants.json so one
+// maintained type file serves several handouts (32 and 64 bit builds, before
+// and after a patch), picked with `--define KEY=VALUE`.
+//
+// {
+//     "defaults": {"PATCHED": "0"},
+//     "variants": [
+//         {"when": {"ARCH": "64"}, "types": {"structs": {"chunk": ...}, "pointers": ...}},
+//         {"when": {"ARCH": "32"}, "types": {"structs": {"chunk": ...}, "pointers": ...}},
+//         {"when": {"PATCHED": "1"}, "types": {"variables": {...}}}
+//     ]
+// }
+//
+// every variant whose conditions all hold is merged over the export in file
+// order, winning on name collisions. a key that a variant tests must be
+// defined or have a default, so forgetting `--define ARCH=..` is an error
+// instead of silently generating the unconditional types only.
+
+use serde::Deserialize;
+
+use crate::{DynErr, HashMap, TypeSet};
+
+#[derive(Deserialize)]
+struct Variant {
+    when: HashMap<String, String>,
+    types: TypeSet,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Variants {
+    defaults: HashMap<String, String>,
+    variants: Vec<Variant>,
+}
+
+// `KEY=VALUE`, a bare `KEY` reads as KEY=1
+pub fn parse_define(define: &str) -> (String, String) {
+    match define.split_once('=') {
+        Some((key, value)) => (key.to_string(), value.to_string()),
+        None => (define.to_string(), String::from("1")),
+    }
+}
+
+impl Variants {
+    pub fn select(
+        &self,
+        mut type_set: TypeSet,
+        defines: &HashMap<String, String>,
+    ) -> Result<TypeSet, DynErr> {
+        for variant in self.variants.iter() {
+            let mut selected = true;
+            for (key, expected) in variant.when.iter() {
+                let Some(value) = defines.get(key).or_else(|| self.defaults.get(key)) else {
+                    let values: Vec<&str> = self
+                        .variants
+                        .iter()
+                        .filter_map(|variant| variant.when.get(key).map(String::as_str))
+                        .collect();
+                    return Err(format!(
+                        "variants need `--define {}=...`, one of: {}",
+                        key,
+                        values.join(", ")
+                    )
+                    .into());
+                };
+                selected &= value == expected;
+            }
+            if selected {
+                type_set.extend(variant.types.clone());
+            }
+        }
+        Ok(type_set)
+    }
+}
@@ -0,0 +1,178 @@
+// `--verify` parses the sections just generated back with gimli before they
+// are written out, so corrupt output shows up here instead of as gdb
+// misbehaving. every unit has to parse with its abbreviations, every string
+// has to resolve, every reference has to land on a DIE (a type, for
+// DW_AT_type) and the DIEs gdb looks things up by need their names.
+
+use gimli::read::{AttributeValue, EndianSlice};
+use gimli::write::{EndianVec, Sections, Writer};
+use gimli::{DwTag, RunTimeEndian, SectionId, UnitSectionOffset, UnitType};
+
+use crate::{DynErr, HashMap};
+
+type Reader<'a> = EndianSlice<'a, RunTimeEndian>;
+
+const TYPES: [DwTag; 18] = [
+    gimli::DW_TAG_base_type,
+    gimli::DW_TAG_pointer_type,
+    gimli::DW_TAG_reference_type,
+    gimli::DW_TAG_rvalue_reference_type,
+    gimli::DW_TAG_ptr_to_member_type,
+    gimli::DW_TAG_structure_type,
+    gimli::DW_TAG_class_type,
+    gimli::DW_TAG_union_type,
+    gimli::DW_TAG_enumeration_type,
+    gimli::DW_TAG_typedef,
+    gimli::DW_TAG_array_type,
+    gimli::DW_TAG_string_type,
+    gimli::DW_TAG_subroutine_type,
+    gimli::DW_TAG_const_type,
+    gimli::DW_TAG_volatile_type,
+    gimli::DW_TAG_restrict_type,
+    gimli::DW_TAG_atomic_type,
+    gimli::DW_TAG_unspecified_type,
+];
+
+const NAMED: [DwTag; 5] = [
+    gimli::DW_TAG_base_type,
+    gimli::DW_TAG_typedef,
+    gimli::DW_TAG_enumerator,
+    gimli::DW_TAG_variable,
+    gimli::DW_TAG_subprogram,
+];
+
+pub fn check(
+    sections: &Sections<EndianVec<RunTimeEndian>>,
+    debug_types: &[u8],
+) -> Result<Vec<String>, DynErr> {
+    let endian = sections.debug_info.0.endian();
+    let dwarf = gimli::read::Dwarf::load(|id| -> Result<Reader, gimli::Error> {
+        let data = match id {
+            SectionId::DebugTypes => debug_types,
+            _ => sections.get(id).map(|w| w.slice()).unwrap_or(&[]),
+        };
+        Ok(EndianSlice::new(data, endian))
+    })?;
+
+    let mut headers = Vec::new();
+    let mut units = dwarf.units();
+    while let Some(header) = units.next()? {
+        headers.push(header);
+    }
+    let mut type_units = dwarf.type_units();
+    while let Some(header) = type_units.next()? {
+        headers.push(header);
+    }
+
+    let describe = |offset: UnitSectionOffset| match offset {
+        UnitSectionOffset::DebugInfoOffset(offset) => format!(".debug_info+{:#x}", offset.0),
+        UnitSectionOffset::DebugTypesOffset(offset) => format!(".debug_types+{:#x}", offset.0),
+    };
+    let mut issues = Vec::new();
+
+    // every DIE's tag, and the type units' DIEs by signature
+    let mut tags: HashMap<UnitSectionOffset, DwTag> = HashMap::new();
+    let mut signatures: HashMap<u64, UnitSectionOffset> = HashMap::new();
+    let mut units = Vec::new();
+    for header in headers {
+        let start = header.offset();
+        let unit = match dwarf.unit(header) {
+            Ok(unit) => unit,
+            Err(e) => {
+                issues.push(format!("unit at {}: {}", describe(start), e));
+                continue;
+            }
+        };
+        if let UnitType::Type {
+            type_signature,
+            type_offset,
+        } = unit.header.type_()
+        {
+            _ = signatures.insert(type_signature.0, type_offset.to_unit_section_offset(&unit));
+        }
+        let mut entries = unit.entries();
+        loop {
+            match entries.next_dfs() {
+                Ok(Some((_, entry))) => {
+                    _ = tags.insert(entry.offset().to_unit_section_offset(&unit), entry.tag())
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    issues.push(format!("unit at {}: {}", describe(start), e));
+                    break;
+                }
+            }
+        }
+        units.push(unit);
+    }
+
+    for unit in units.iter() {
+        let mut entries = unit.entries();
+        let mut depth = 0;
+        while let Ok(Some((delta, entry))) = entries.next_dfs() {
+            depth += delta;
+            let at = describe(entry.offset().to_unit_section_offset(unit));
+            let tag = entry.tag();
+
+            let mut attributes = entry.attrs();
+            let mut named = false;
+            while let Some(attribute) = attributes.next()? {
+                let target = match attribute.value() {
+                    AttributeValue::UnitRef(offset) => Some(offset.to_unit_section_offset(unit)),
+                    AttributeValue::DebugInfoRef(offset) => {
+                        Some(UnitSectionOffset::DebugInfoOffset(offset))
+                    }
+                    AttributeValue::DebugTypesRef(signature) => {
+                        match signatures.get(&signature.0) {
+                            Some(offset) => Some(*offset),
+                            None => {
+                                issues.push(format!(
+                                    "{} {} refers to missing type unit {:#x}",
+                                    at,
+                                    attribute.name(),
+                                    signature.0
+                                ));
+                                None
+                            }
+                        }
+                    }
+                    _ => None,
+                };
+                if let Some(target) = target {
+                    match tags.get(&target) {
+                        None => issues.push(format!(
+                            "{} {} refers to {}, which isn't a DIE",
+                            at,
+                            attribute.name(),
+                            describe(target)
+                        )),
+                        Some(found)
+                            if attribute.name() == gimli::DW_AT_type && !TYPES.contains(found) =>
+                        {
+                            issues.push(format!(
+                                "{} {} refers to {} at {}, which isn't a type",
+                                at,
+                                attribute.name(),
+                                found,
+                                describe(target)
+                            ))
+                        }
+                        _ => {}
+                    }
+                }
+                if attribute.name() == gimli::DW_AT_name {
+                    match dwarf.attr_string(unit, attribute.value()) {
+                        Ok(name) if !name.is_empty() => named = true,
+                        Ok(_) => {}
+                        Err(e) => issues.push(format!("{} {}: {}", at, attribute.name(), e)),
+                    }
+                }
+            }
+            // locals and parameters aren't looked up by name
+            if !named && NAMED.contains(&tag) && (depth == 1 || tag == gimli::DW_TAG_enumerator) {
+                issues.push(format!("{} {} has no name", at, tag));
+            }
+        }
+    }
+    Ok(issues)
+}
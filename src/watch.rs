@@ -0,0 +1,238 @@
+// `teemo watch` (or `--watch`): regenerates the output whenever the export, an
+// overlay or any other input file changes, polling their modification times.
+// a `--bndb` database is exported again each time binja saves it, and with
+// `--inject` the binary is written again too, so `file chall.dbg` in gdb
+// picks up the renames.
+//
+// with `--gdb-mi <socket>` a running gdb is told about each new file: the old
+// one is dropped with remove-symbol-file and the new one loaded with
+// add-symbol-file at the inferior's load bias, so retyped structs show up in
+// the session right away. the socket (or tty) has to speak MI, e.g.
+//
+//   (gdb) new-ui mi3 /dev/pts/7
+//   $ teemo watch --gdb-mi /dev/pts/7
+//
+// or a unix socket bridged to one with socat.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use log::{error, info};
+
+use crate::inspect::process_bias;
+use crate::{bndb, collect, generate, inject, DebugSup, DynErr, Err, Options};
+
+const POLL: Duration = Duration::from_millis(500);
+
+const EXPORT: &[&str] = &[
+    "types.json",
+    "structs.json",
+    "unions.json",
+    "integers.json",
+    "pointers.json",
+    "typedefs.json",
+    "functions.json",
+    "enums.json",
+    "arrays.json",
+    "vectors.json",
+    "qualifiers.json",
+    "classes.json",
+    "variables.json",
+    "constants.json",
+    "subprograms.json",
+    "variants.json",
+    "templates.json",
+];
+
+pub fn inputs(options: &Options) -> Vec<PathBuf> {
+    let mut inputs: Vec<PathBuf> = EXPORT
+        .iter()
+        .map(|file| options.types_dir.join(file))
+        .collect();
+    inputs.extend(options.overlays.iter().cloned());
+    inputs.extend(options.gdb_scripts.iter().cloned());
+    inputs.extend(options.units.iter().cloned());
+    inputs.extend(options.trampolines.iter().cloned());
+    inputs.extend(options.spaces.iter().cloned());
+    inputs.extend(options.frames.iter().cloned());
+    inputs.extend(options.lines.iter().cloned());
+    inputs.extend(options.headers.iter().cloned());
+    inputs.extend(options.symbols.iter().cloned());
+    inputs.extend(options.pdb.iter().cloned());
+    inputs.extend(options.bndb.iter().cloned());
+    inputs
+}
+
+fn modified(inputs: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    inputs
+        .iter()
+        .map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+// MI c-string quoting
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+struct Gdb {
+    reader: BufReader<Box<dyn Read>>,
+    writer: Box<dyn Write>,
+}
+
+impl Gdb {
+    fn connect(path: &Path) -> Result<Gdb, DynErr> {
+        let error = |e: std::io::Error| format!("{}: {}", path.display(), e);
+        let is_socket = fs::metadata(path).map_err(error)?.file_type().is_socket();
+        let (reader, writer): (Box<dyn Read>, Box<dyn Write>) = if is_socket {
+            let stream = UnixStream::connect(path).map_err(error)?;
+            (Box::new(stream.try_clone()?), Box::new(stream))
+        } else {
+            let tty: File = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(path)
+                .map_err(error)?;
+            (Box::new(tty.try_clone()?), Box::new(tty))
+        };
+        let mut gdb = Gdb {
+            reader: BufReader::new(reader),
+            writer,
+        };
+        // add-symbol-file asks before loading
+        gdb.command("-gdb-set confirm off")?;
+        Ok(gdb)
+    }
+
+    // sends one command and returns its result record
+    fn command(&mut self, command: &str) -> Result<String, DynErr> {
+        writeln!(self.writer, "{}", command)?;
+        self.writer.flush()?;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err("gdb closed the MI connection".into());
+            }
+            // async and stream records come in between
+            if line.starts_with('^') {
+                break;
+            }
+        }
+        let result = line.trim_end().to_string();
+        if let Some(message) = result.strip_prefix("^error,msg=") {
+            return Err(format!("gdb: {}", message).into());
+        }
+        Ok(result)
+    }
+
+    fn console(&mut self, command: &str) -> Result<String, DynErr> {
+        self.command(&format!("-interpreter-exec console {}", quote(command)))
+    }
+
+    // the pid of the first inferior, if it runs
+    fn pid(&mut self) -> Result<Option<u32>, DynErr> {
+        let groups = self.command("-list-thread-groups")?;
+        Ok(groups
+            .split("pid=\"")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next().and_then(|pid| pid.parse().ok())))
+    }
+}
+
+pub fn run(options: &Options, output: &Path) -> Err {
+    let mut gdb = match &options.gdb_mi {
+        Some(path) => Some(Gdb::connect(path)?),
+        None => None,
+    };
+    let absolute = fs::canonicalize(".")?.join(output);
+    let mut loaded = false;
+
+    let inputs = inputs(options);
+    let mut seen = None;
+    // `run` exported it already
+    let mut exported = options
+        .bndb
+        .as_ref()
+        .map(|bndb| modified(std::slice::from_ref(bndb)));
+    loop {
+        let now = modified(&inputs);
+        if seen.as_ref() == Some(&now) {
+            thread::sleep(POLL);
+            continue;
+        }
+        if let Some(bndb) = &options.bndb {
+            let saved = modified(std::slice::from_ref(bndb));
+            if exported.as_ref() != Some(&saved) {
+                if let Err(e) = bndb::extract(bndb, &options.types_dir) {
+                    error!("watch: {}", e);
+                }
+                exported = Some(saved);
+            }
+        }
+        // what the export just rewrote doesn't count as another change
+        seen = Some(modified(&inputs));
+
+        let mut type_set = match collect(options) {
+            Ok(type_set) => type_set,
+            // a half written file, wait for the next save
+            Err(e) => {
+                error!("watch: {}", e);
+                continue;
+            }
+        };
+        let (types, variables) = type_set.split_types();
+        if let Err(e) = generate(
+            options,
+            &types,
+            &variables,
+            &type_set.constants,
+            &type_set.subprograms,
+            output,
+            DebugSup::None,
+        ) {
+            error!("watch: {}", e);
+            continue;
+        }
+        if let Some(binary) = &options.inject {
+            if let Err(e) = inject::inject(
+                binary,
+                output,
+                &options.dynsym,
+                options.inject_align.unwrap_or(1),
+                options.inject_load,
+            ) {
+                error!("watch: {}", e);
+                continue;
+            }
+        }
+
+        let Some(gdb) = gdb.as_mut() else {
+            continue;
+        };
+        let lowest = variables.keys().next().copied().unwrap_or(0);
+        let bias = match options.base {
+            Some(base) => base,
+            None => match gdb.pid()? {
+                Some(pid) => process_bias(pid, options.binary.as_deref(), lowest)?,
+                // not running yet, the static addresses are all there is
+                None => 0,
+            },
+        };
+        if loaded {
+            gdb.console(&format!("remove-symbol-file {}", absolute.display()))?;
+        }
+        gdb.console(&format!(
+            "add-symbol-file {} -o {:#x}",
+            absolute.display(),
+            bias
+        ))?;
+        loaded = true;
+        info!("gdb: loaded {} at {:#x}", absolute.display(), bias);
+    }
+}
@@ -0,0 +1,480 @@
+// the ELF container around the debug sections: an empty .text, the
+// sections themselves, a .symtab and the two string tables. on its own it
+// has no program headers, nothing gets loaded from it.
+//
+// given the binary, its section headers come first instead of the .text, at
+// their original indices like `objcopy --only-keep-debug` keeps them: the
+// allocated ones as SHT_NOBITS, the rest as unused SHT_NULL entries. symbols
+// then point into the section holding their address, and are SHN_ABS without.
+// its program headers are kept as well, with none of their contents in the
+// file but the table itself, so tools matching a core or a running process
+// to the debug file see the same segments. but for PT_INTERP and PT_NOTE,
+// which are only their contents and would point at nothing.
+//
+// the output only depends on the inputs: sections go out by name, symbols by
+// name and address and the string tables in that order, so the same export
+// always makes the same bytes to diff or hash. the locals come first in the
+// .symtab as the ELF spec wants, sh_info is the first global and sh_link the
+// string table wherever the binary's sections put it.
+//
+// each section starts at a multiple of its sh_addralign: a word for the
+// .symtab and the frame tables, 4 bytes for .gdb_index and the notes and 1 for
+// the rest of the DWARF and the string tables.
+//
+// every offset is worked out before anything is written, and the file then goes
+// out front to back through one buffer, no seeking, which network file systems
+// and pipes are happier with.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use goblin::elf::Elf;
+use goblin::elf64::{header::*, program_header as segment, section_header as section, sym};
+use log::debug;
+
+use crate::coff;
+use crate::compress::{self, Style};
+use crate::target::Target;
+use crate::{Err, HashMap, RawSection, RawSegment, RawSymbol, Section, Segment};
+
+// an ELF string table, every name (or the tail of a longer one, like .text of
+// .rela.text) stored once
+struct StringTable {
+    data: Vec<u8>,
+    offsets: HashMap<Vec<u8>, u32>,
+}
+
+impl StringTable {
+    fn new() -> StringTable {
+        StringTable {
+            data: vec![0],
+            offsets: HashMap::from([(Vec::new(), 0)]),
+        }
+    }
+
+    fn add(&mut self, name: &str) -> u32 {
+        let name = name.as_bytes();
+        if let Some(offset) = self.offsets.get(name) {
+            return *offset;
+        }
+        let offset = self.data.len() as u32;
+        self.data.extend_from_slice(name);
+        self.data.push(0);
+        for start in 0..name.len() {
+            _ = self
+                .offsets
+                .entry(name[start..].to_vec())
+                .or_insert(offset + start as u32);
+        }
+        offset
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+fn symbol_order((name, symbol): &(String, RawSymbol)) -> (bool, &str, u64, u64, u8) {
+    (
+        symbol.st_info >> 4 != sym::STB_LOCAL,
+        name,
+        symbol.st_value,
+        symbol.st_size,
+        symbol.st_info,
+    )
+}
+
+pub struct ElfWriter {
+    target: Target,
+    sections: HashMap<String, Section>,
+    // static functions of different files may share a name
+    symbols: Vec<(String, RawSymbol)>,
+    // the binary's section headers, from index 1
+    mirrored: Vec<(String, RawSection)>,
+    // the binary's program headers
+    segments: Vec<Segment>,
+    compression: Option<Style>,
+    coff: bool,
+}
+
+impl ElfWriter {
+    pub fn new(target: Target) -> ElfWriter {
+        let mut sections = HashMap::new();
+        sections.insert(
+            String::from(".text"),
+            Section {
+                hdr: RawSection {
+                    sh_type: section::SHT_PROGBITS,
+                    sh_flags: (section::SHF_EXECINSTR | section::SHF_ALLOC) as u64,
+                    sh_addralign: 1,
+                    ..Default::default()
+                },
+                raw: Vec::new(),
+                off: 0,
+            },
+        );
+        ElfWriter {
+            target,
+            sections,
+            symbols: Vec::new(),
+            mirrored: Vec::new(),
+            segments: Vec::new(),
+            compression: None,
+            coff: false,
+        }
+    }
+
+    // compresses the .debug_* sections on write
+    pub fn compress(&mut self, style: Style) {
+        self.compression = Some(style);
+    }
+
+    // writes a COFF object with the same sections instead
+    pub fn coff(&mut self) {
+        self.coff = true;
+    }
+
+    // takes over the section headers of `binary`
+    pub fn mirror(&mut self, binary: &Path) -> Err {
+        let data = fs::read(binary).map_err(|e| format!("{}: {}", binary.display(), e))?;
+        let elf = Elf::parse(&data)?;
+        for header in elf.section_headers.iter().skip(1) {
+            let allocated = header.sh_flags & section::SHF_ALLOC as u64 != 0;
+            let hdr = RawSection {
+                sh_type: section::SHT_NOBITS,
+                sh_flags: header.sh_flags,
+                sh_addr: header.sh_addr,
+                sh_size: header.sh_size,
+                sh_addralign: header.sh_addralign,
+                ..Default::default()
+            };
+            self.mirrored.push(if allocated {
+                let name = elf.shdr_strtab.get_at(header.sh_name).unwrap_or("");
+                (name.to_string(), hdr)
+            } else {
+                (String::new(), RawSection::default())
+            });
+        }
+        for header in elf.program_headers.iter() {
+            if matches!(header.p_type, segment::PT_INTERP | segment::PT_NOTE) {
+                continue;
+            }
+            self.segments.push(Segment {
+                hdr: RawSegment {
+                    p_type: header.p_type,
+                    p_flags: header.p_flags,
+                    p_offset: 0,
+                    p_vaddr: header.p_vaddr,
+                    p_paddr: header.p_paddr,
+                    p_filesz: 0,
+                    p_memsz: header.p_memsz,
+                    p_align: header.p_align,
+                },
+                raw: Vec::new(),
+                off: 0,
+            });
+        }
+        // the binary's own .text takes the place of the empty one
+        if !self.mirrored.is_empty() {
+            _ = self.sections.remove(".text");
+        }
+        Ok(())
+    }
+
+    // the section index for a symbol at `address`
+    fn section_of(&self, address: u64) -> u16 {
+        self.mirrored
+            .iter()
+            .position(|(_, hdr)| {
+                hdr.sh_type == section::SHT_NOBITS
+                    && hdr.sh_addr <= address
+                    && address - hdr.sh_addr < hdr.sh_size.max(1)
+            })
+            .map_or(section::SHN_ABS as u16, |index| index as u16 + 1)
+    }
+
+    // most DWARF is read a byte at a time, but the frame tables are made of
+    // address sized words and .gdb_index of 4 byte ones
+    fn alignment(&self, name: &str) -> u64 {
+        match name {
+            ".debug_frame" | ".eh_frame" => self.target.chdr_alignment(),
+            ".gdb_index" => 4,
+            _ => 1,
+        }
+    }
+
+    // a non-allocated section such as .debug_info
+    pub fn add_section(&mut self, name: &str, data: Vec<u8>) {
+        let alignment = self.alignment(name);
+        self.sections.insert(
+            String::from(name),
+            Section {
+                hdr: RawSection {
+                    sh_type: section::SHT_PROGBITS,
+                    sh_addralign: alignment,
+                    ..Default::default()
+                },
+                raw: data,
+                off: 0,
+            },
+        );
+    }
+
+    pub fn add_note(&mut self, name: &str, data: Vec<u8>) {
+        self.sections.insert(
+            String::from(name),
+            Section {
+                hdr: RawSection {
+                    sh_type: section::SHT_NOTE,
+                    sh_addralign: 4,
+                    ..Default::default()
+                },
+                raw: data,
+                off: 0,
+            },
+        );
+    }
+
+    // the sections so far by name
+    pub fn contents(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        (self.sections.iter()).map(|(name, section)| (name.as_str(), section.raw.as_slice()))
+    }
+
+    // `st_name` and `st_shndx` are filled in on write
+    pub fn add_symbol(&mut self, name: String, symbol: RawSymbol) {
+        self.symbols.push((name, symbol));
+    }
+
+    pub fn write_to(mut self, output: &Path) -> Err {
+        // locals have to come first, and the rest of the key leaves nothing to
+        // the order they were added in, even two statics of the same name
+        self.symbols
+            .sort_by(|a, b| symbol_order(a).cmp(&symbol_order(b)));
+
+        if let Some(style) = self.compression {
+            let mut sections = HashMap::new();
+            for (name, mut section) in std::mem::take(&mut self.sections) {
+                if name.starts_with(".debug_") && !section.raw.is_empty() {
+                    let (name, raw) = compress::section(&self.target, style, &name, &section.raw)?;
+                    if style == Style::Zlib {
+                        section.hdr.sh_flags |= section::SHF_COMPRESSED as u64;
+                        section.hdr.sh_addralign = self.target.chdr_alignment();
+                    }
+                    section.raw = raw;
+                    _ = sections.insert(name, section);
+                    continue;
+                }
+                _ = sections.insert(name, section);
+            }
+            self.sections = sections;
+        }
+
+        if self.coff {
+            return coff::write(&self.target, &self.sections, &self.symbols, output);
+        }
+
+        let mut file = BufWriter::new(File::create(output)?);
+
+        let mut ident: [u8; SIZEOF_IDENT] = [0u8; 16];
+        ident[..4].copy_from_slice(&ELFMAG[..4]);
+        ident[EI_ABIVERSION] = 0;
+        ident[EI_CLASS] = self.target.class;
+        ident[EI_DATA] = self.target.ident_data();
+        ident[EI_OSABI] = ELFOSABI_SYSV;
+        ident[EI_VERSION] = 1;
+        let mut header = Header {
+            e_ident: ident,
+            e_type: ET_EXEC,
+            e_machine: self.target.machine,
+            e_version: 1,
+            e_entry: 0,
+            e_phoff: 0,
+            e_shoff: 0,
+            e_flags: self.target.flags,
+            e_ehsize: self.target.sizeof_ehdr() as u16,
+            e_phentsize: self.target.sizeof_phdr() as u16,
+            e_phnum: self.segments.len() as u16,
+            e_shentsize: self.target.sizeof_shdr() as u16,
+            e_shnum: 0,
+            e_shstrndx: 0,
+        };
+
+        // the section headers go out as the null one, the binary's, the two
+        // string tables and then the rest by name, .symtab among them
+        let section_names_index = self.mirrored.len() as u32 + 1;
+        let symbol_names_index = section_names_index + 1;
+
+        // finalize elf file
+        let mut section_names = Section {
+            hdr: RawSection {
+                sh_type: section::SHT_STRTAB,
+                sh_addralign: 1,
+                ..Default::default()
+            },
+            raw: Vec::new(),
+            off: 0,
+        };
+
+        let symbol_table = Section {
+            hdr: RawSection {
+                sh_type: section::SHT_SYMTAB,
+                sh_link: symbol_names_index,
+                // the first global, after the null symbol and the locals
+                sh_info: 1 + self
+                    .symbols
+                    .iter()
+                    .filter(|(_, symbol)| symbol.st_info >> 4 == sym::STB_LOCAL)
+                    .count() as u32,
+                sh_entsize: self.target.sizeof_sym() as u64,
+                sh_addralign: self.target.chdr_alignment(),
+                ..Default::default()
+            },
+            raw: Vec::new(),
+            off: 0,
+        };
+
+        let mut symbol_names = Section {
+            hdr: RawSection {
+                sh_type: section::SHT_STRTAB,
+                sh_addralign: 1,
+                ..Default::default()
+            },
+            raw: Vec::new(),
+            off: 0,
+        };
+
+        self.sections.insert(String::from(".symtab"), symbol_table);
+
+        // account for NULL section
+        header.e_shnum += 1;
+
+        // account for the binary's sections
+        header.e_shnum += self.mirrored.len() as u16;
+
+        // account for section names table
+        header.e_shnum += 1;
+
+        // account for symbol names table
+        header.e_shnum += 1;
+
+        // account for all the dwarf sections
+        header.e_shnum += self.sections.len() as u16;
+
+        // the program headers right after the ELF header, then the sections'
+        if !self.segments.is_empty() {
+            header.e_phoff = self.target.sizeof_ehdr() as u64;
+        }
+        let program_headers_size = self.segments.len() as u64 * self.target.sizeof_phdr() as u64;
+        // the table is in the file, and so is the start of the PT_LOAD it's
+        // mapped with when the binary had it as far in
+        let table = (self.segments.iter())
+            .find(|segment| segment.hdr.p_type == segment::PT_PHDR)
+            .map(|segment| segment.hdr.p_vaddr);
+        let loaded = header.e_phoff + program_headers_size;
+        for segment in self.segments.iter_mut() {
+            match segment.hdr.p_type {
+                segment::PT_PHDR => {
+                    segment.hdr.p_offset = header.e_phoff;
+                    segment.hdr.p_filesz = program_headers_size;
+                }
+                segment::PT_LOAD
+                    if table.and_then(|vaddr| vaddr.checked_sub(segment.hdr.p_vaddr))
+                        == Some(header.e_phoff)
+                        && loaded <= segment.hdr.p_memsz =>
+                {
+                    segment.hdr.p_filesz = loaded;
+                }
+                _ => {}
+            }
+        }
+        header.e_shoff = self.target.sizeof_ehdr() as u64 + program_headers_size;
+
+        // set section names index
+        header.e_shstrndx = section_names_index as u16;
+
+        // lay the file out first, the section headers right after the ELF
+        // and program headers and the contents after them in order, so it's
+        // then written front to back in one pass
+        let section_contents_start =
+            header.e_shoff + header.e_shnum as u64 * self.target.sizeof_shdr() as u64;
+
+        let mut section_name_table = StringTable::new();
+        section_names.hdr.sh_name = section_name_table.add(".shstrtab");
+        symbol_names.hdr.sh_name = section_name_table.add(".strtab");
+        for (name, hdr) in self.mirrored.iter_mut() {
+            if !name.is_empty() {
+                hdr.sh_name = section_name_table.add(name);
+            }
+        }
+        for (name, section) in self.sections.iter_mut() {
+            section.hdr.sh_name = section_name_table.add(name);
+        }
+        section_names.hdr.sh_offset = section_contents_start;
+        section_names.hdr.sh_size = section_name_table.as_bytes().len() as u64;
+
+        let mut symbol_name_table = StringTable::new();
+        for (name, symbol) in self.symbols.iter_mut() {
+            symbol.st_name = symbol_name_table.add(name);
+        }
+        symbol_names.hdr.sh_offset = section_names.hdr.sh_offset + section_names.hdr.sh_size;
+        symbol_names.hdr.sh_size = symbol_name_table.as_bytes().len() as u64;
+
+        let mut symtab = vec![0u8; self.target.sizeof_sym()];
+        for (_, symbol) in self.symbols.iter() {
+            let mut symbol = *symbol;
+            symbol.st_shndx = self.section_of(symbol.st_value);
+            symtab.extend(self.target.symbol(symbol)?);
+        }
+        self.sections.get_mut(".symtab").unwrap().raw = symtab;
+
+        let mut section_contents_offset = symbol_names.hdr.sh_offset + symbol_names.hdr.sh_size;
+        for (_, section) in self.sections.iter_mut() {
+            section_contents_offset =
+                section_contents_offset.next_multiple_of(section.hdr.sh_addralign.max(1));
+            section.hdr.sh_offset = section_contents_offset;
+            section.hdr.sh_size = section.raw.len() as u64;
+            section_contents_offset += section.hdr.sh_size;
+        }
+
+        file.write_all(&self.target.header(header)?)?;
+
+        for segment in self.segments.iter() {
+            file.write_all(&self.target.segment(segment.hdr)?)?;
+        }
+
+        // write NULL section
+        file.write_all(&self.target.section(RawSection {
+            ..Default::default()
+        })?)?;
+
+        for (_, hdr) in self.mirrored.iter() {
+            file.write_all(&self.target.section(*hdr)?)?;
+        }
+
+        // write section names
+        file.write_all(&self.target.section(section_names.hdr)?)?;
+
+        // write symbol names
+        file.write_all(&self.target.section(symbol_names.hdr)?)?;
+
+        // write rest of sections
+        for (name, section) in self.sections.iter() {
+            debug!("section {}, {} bytes", name, section.hdr.sh_size);
+            file.write_all(&self.target.section(section.hdr)?)?;
+        }
+
+        file.write_all(section_name_table.as_bytes())?;
+        file.write_all(symbol_name_table.as_bytes())?;
+        let mut position = symbol_names.hdr.sh_offset + symbol_names.hdr.sh_size;
+        for (_, section) in self.sections.iter() {
+            // zeros up to the alignment
+            file.write_all(&vec![0; (section.hdr.sh_offset - position) as usize])?;
+            file.write_all(section.raw.as_slice())?;
+            position = section.hdr.sh_offset + section.hdr.sh_size;
+        }
+        file.flush()?;
+
+        Ok(())
+    }
+}
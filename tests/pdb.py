@@ -0,0 +1,202 @@
+# writes tests/app.pdb, the program database src/pdb.rs is tested against:
+#
+#   struct _node { struct _node *next; unsigned flags : 3, kind : 5;
+#                  char name[16]; unsigned long long big; };
+#   enum color { RED, BLUE, NEG = -1 };
+#   struct _node *head;                     // .data+0x10
+#   static enum color palette;              // .data+0x18
+#   typedef struct _node node;
+#   int main(int, char **);                 // .text+0x30, 120 bytes
+#   static int helper(int, char **);        // .text+0x100, 16 bytes
+#
+# `llvm-pdbutil dump -types -ids -symbols -globals -section-headers` reads it.
+
+import os
+import struct
+
+
+def u16(v):
+    return struct.pack('<H', v)
+
+
+def u32(v):
+    return struct.pack('<I', v)
+
+
+def cstr(s):
+    return s.encode() + b'\0'
+
+
+# padded with 0xf3 0xf2 0xf1 like the members of a field list
+def pad4(b, fill=True):
+    n = (-len(b)) % 4
+    return b + (bytes(0xf0 + n - i for i in range(n)) if fill else b'\0' * n)
+
+
+# a type or symbol record, aligned to four with the length in front
+def rec(kind, body):
+    body = pad4(u16(0) + u16(kind) + body)[2:]
+    return u16(len(body)) + body
+
+
+def member(t, offset, name):
+    return pad4(u16(0x150d) + u16(3) + u32(t) + u16(offset) + cstr(name))
+
+
+def enumerate_(value, name):
+    if value < 0:
+        value = u16(0x8000) + struct.pack('<b', value)
+    else:
+        value = u16(value)
+    return pad4(u16(0x1502) + u16(3) + value + cstr(name))
+
+
+POINTER64 = 0xc | 8 << 13
+
+types = [
+    # 0x1000 struct _node, forward
+    (0x1505, u16(0) + u16(0x80) + u32(0) * 3 + u16(0) + cstr('_node')),
+    # 0x1001 struct _node *
+    (0x1002, u32(0x1000) + u32(POINTER64)),
+    # 0x1002 unsigned : 3
+    (0x1205, u32(0x75) + bytes([3, 0])),
+    # 0x1003 unsigned : 5 at bit 3
+    (0x1205, u32(0x75) + bytes([5, 3])),
+    # 0x1004 char[16]
+    (0x1503, u32(0x70) + u32(0x23) + u16(16) + cstr('')),
+    # 0x1005
+    (0x1203, member(0x1001, 0, 'next') + member(0x1002, 8, 'flags')
+     + member(0x1003, 8, 'kind') + member(0x1004, 12, 'name')
+     + member(0x77, 32, 'big')),
+    # 0x1006 struct _node
+    (0x1505, u16(5) + u16(0) + u32(0x1005) + u32(0) * 2 + u16(40) + cstr('_node')),
+    # 0x1007
+    (0x1203, enumerate_(0, 'RED') + enumerate_(1, 'BLUE') + enumerate_(-1, 'NEG')),
+    # 0x1008 enum color
+    (0x1507, u16(3) + u16(0) + u32(0x74) + u32(0x1007) + cstr('color')),
+    # 0x1009 (int, char **)
+    (0x1201, u32(2) + u32(0x74) + u32(0x100a)),
+    # 0x100a char **
+    (0x1002, u32(0x670) + u32(POINTER64)),
+    # 0x100b int (int, char **)
+    (0x1008, u32(0x74) + bytes([0, 0]) + u16(2) + u32(0x1009)),
+    # 0x100c const char
+    (0x1001, u32(0x70) + u16(1)),
+]
+
+
+def typestream(records):
+    body = b''.join(rec(kind, record) for kind, record in records)
+    header = u32(20040203) + u32(56) + u32(0x1000) + u32(0x1000 + len(records))
+    # no hash stream
+    header += u32(len(body)) + u16(0xffff) + u16(0xffff) + u32(4) + u32(0x3ffff)
+    return header + u32(0) * 6 + body
+
+
+tpi = typestream(types)
+ipi = typestream([(0x1601, u32(0) + u32(0x100b) + cstr('main'))])
+
+
+def procedure(kind, size, typeindex, offset, name):
+    return rec(kind, u32(0) * 3 + u32(size) + u32(0) * 2 + u32(typeindex)
+               + u32(offset) + u16(1) + b'\0' + cstr(name)) + rec(0x0006, b'')
+
+
+module = u32(4) + procedure(0x1147, 120, 0x1000, 0x30, 'main')
+module += procedure(0x110f, 16, 0x100b, 0x100, 'helper')
+symbols = len(module)
+# no line info, then no global refs
+module += u32(0)
+
+globals_ = [
+    ('head', rec(0x110d, u32(0x1001) + u32(0x10) + u16(2) + cstr('head'))),
+    ('node', rec(0x1108, u32(0x1006) + cstr('node'))),
+    ('palette', rec(0x110c, u32(0x1008) + u32(0x18) + u16(2) + cstr('palette'))),
+    ('_node', rec(0x1108, u32(0x1000) + cstr('_node'))),
+]
+records = b''.join(record for _, record in globals_)
+
+
+# the hash the globals are bucketed by
+def hash_v1(name):
+    name = name.encode()
+    words = len(name) // 4
+    result = 0
+    for i in range(words):
+        result ^= struct.unpack('<I', name[i * 4:i * 4 + 4])[0]
+    rest = name[words * 4:]
+    if len(rest) >= 2:
+        result ^= struct.unpack('<H', rest[:2])[0]
+        rest = rest[2:]
+    if rest:
+        result ^= rest[0]
+    result |= 0x20202020
+    result ^= result >> 11
+    result ^= result >> 16
+    return result % 4096
+
+
+def gsi():
+    offsets, at = [], 0
+    for name, record in globals_:
+        offsets.append((hash_v1(name), at))
+        at += len(record)
+    offsets.sort()
+    hashes = b''.join(u32(offset + 1) + u32(1) for _, offset in offsets)
+    bitmap = [0] * 129
+    buckets = b''
+    for i, (bucket, _) in enumerate(offsets):
+        if not bitmap[bucket // 32] & 1 << bucket % 32:
+            bitmap[bucket // 32] |= 1 << bucket % 32
+            buckets += u32(i * 12)
+    buckets = b''.join(u32(word) for word in bitmap) + buckets
+    return u32(0xffffffff) + u32(0xeffe0000 + 19990810) + u32(len(hashes)) \
+        + u32(len(buckets)) + hashes + buckets
+
+
+def section(name, address):
+    return name.ljust(8, b'\0') + u32(0x1000) + u32(address) + b'\0' * 24
+
+
+sections = section(b'.text', 0x1000) + section(b'.data', 0x3000)
+
+modinfo = u32(0) + b'\0' * 28 + u16(0) + u16(6) + u32(symbols) + u32(0) * 2
+modinfo = pad4(modinfo + u16(0) + u16(0) + u32(0) * 3 + cstr('a.obj') + cstr('a.obj'), False)
+fileinfo = u16(1) + u16(0) + u16(0) + u16(0)
+# the EC substream is an empty string table
+ec = u32(0xeffeeffe) + u32(1) + u32(1) + b'\0' + u32(1) + u32(0) + u32(0)
+# the section headers in stream 8
+optional = b''.join(u16(8 if i == 5 else 0xffff) for i in range(11))
+dbi = struct.pack('<iII', -1, 19990903, 1)
+dbi += u16(9) + u16(0) + u16(0xffff) + u16(0) + u16(7) + u16(0)
+dbi += struct.pack('<iiiiiIii', len(modinfo), 0, 0, len(fileinfo), 0, 0, len(optional), len(ec))
+dbi += u16(0) + u16(0x8664) + u32(0)
+dbi += modinfo + fileinfo + ec + optional
+
+# VC70 with no named streams, then VC140
+info = u32(20000404) + u32(0) + u32(1) + bytes(16)
+info += u32(0) + u32(0) + u32(1) + u32(0) + u32(0) + u32(20140508)
+
+streams = [b'', info, tpi, dbi, ipi, b'', module, records, sections, gsi()]
+size = 512
+# the superblock and the free block maps
+blocks = [b'', b'', b'']
+lists = []
+for stream in streams:
+    lists.append([])
+    for i in range(0, len(stream), size):
+        lists[-1].append(len(blocks))
+        blocks.append(stream[i:i + size])
+directory = u32(len(streams)) + b''.join(u32(len(stream)) for stream in streams)
+directory += b''.join(u32(block) for blocks_ in lists for block in blocks_)
+directory_blocks = []
+for i in range(0, len(directory), size):
+    directory_blocks.append(len(blocks))
+    blocks.append(directory[i:i + size])
+map_block = len(blocks)
+blocks.append(b''.join(u32(block) for block in directory_blocks))
+blocks[0] = b'Microsoft C/C++ MSF 7.00\r\n\x1aDS\0\0\0' + u32(size) + u32(1) \
+    + u32(len(blocks)) + u32(len(directory)) + u32(0) + u32(map_block)
+
+with open(os.path.join(os.path.dirname(__file__), 'app.pdb'), 'wb') as pdb:
+    pdb.write(b''.join(block.ljust(size, b'\0') for block in blocks))
@@ -0,0 +1,49 @@
+// generating the same export from two directories has to give the same bytes
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const TYPES: &str = r#"{
+    "types": {
+        "int": {"kind": "integer", "size": 4, "signed": true},
+        "int ()": {"kind": "function", "parameters": [], "returntype": "int"},
+        "node": {"kind": "struct", "size": 16, "anon": false, "fields": [
+            {"offset": 0, "name": "next", "typename": "node*"},
+            {"offset": 8, "name": "key", "typename": "int"}
+        ]},
+        "node*": {"kind": "pointer", "size": 8, "target": "node"}
+    },
+    "variables": {"16384": {"name": "head", "size": 8, "typename": "node*"}},
+    "subprograms": {"4096": {"name": "main", "size": 16, "typename": "int ()"}}
+}"#;
+
+fn generate(dir: &Path) -> (Vec<u8>, Vec<u8>) {
+    _ = fs::remove_dir_all(dir);
+    fs::create_dir_all(dir.join("sources")).unwrap();
+    fs::write(dir.join("types.json"), TYPES).unwrap();
+    fs::write(
+        dir.join("sources/main.c"),
+        "int main() {\n    return 0;\n}\n",
+    )
+    .unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_dwarf"))
+        .current_dir(dir)
+        .args(["--split-dwarf", "--sources", "sources"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    (
+        fs::read(dir.join("test.o")).unwrap(),
+        fs::read(dir.join("test.o.dwo")).unwrap(),
+    )
+}
+
+#[test]
+fn same_bytes_from_another_directory() {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("reproducible");
+    let first = generate(&root.join("a"));
+    let second = generate(&root.join("some/other/directory"));
+    assert!(first.0 == second.0, "the outputs differ");
+    assert!(first.1 == second.1, "the .dwo files differ");
+}
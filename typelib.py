@@ -0,0 +1,132 @@
+# builds a binary ninja type library (.bntl) out of an export directory, the
+# reverse of extract.py. any source teemo reads can be turned into one:
+#
+#   teemo dump out                 # the export (and preset, overlays)
+#   teemo dump out chall.debug     # the DWARF in an existing file
+#   python typelib.py out chall.bntl x86_64
+#
+# talks to binja over rpyc like extract.py, since type libraries can only be
+# written through its api.
+from collections import ChainMap
+import binaryninja as binaryninja
+import rpyc
+import json
+import sys
+c = rpyc.connect("0.0.0.0", 18812)
+
+bn: binaryninja = c.root.binaryninja
+
+if len(sys.argv) < 3:
+    exit(f"usage: {sys.argv[0]} <export dir> <output.bntl> [arch] [name]")
+directory = sys.argv[1]
+output = sys.argv[2]
+arch = bn.Architecture[sys.argv[3] if len(sys.argv) > 3 else "x86_64"]
+name = sys.argv[4] if len(sys.argv) > 4 else output.removesuffix(".bntl")
+
+def load(kind: str):
+    try:
+        return json.load(open(f"{directory}/{kind}.json"))
+    except FileNotFoundError:
+        return {}
+
+structs = load("structs")
+unions = load("unions")
+enums = load("enums")
+integers = load("integers")
+typedefs = load("typedefs")
+pointers = load("pointers")
+functions = load("functions")
+arrays = load("arrays")
+qualifiers = load("qualifiers")
+variables = load("variables")
+types = ChainMap(structs, unions, enums, integers, typedefs, pointers, functions, arrays, qualifiers)
+
+# fields come as [offset, name, typename] from extract.py and as objects from
+# `teemo dump`
+def field(entry):
+    if isinstance(entry, dict):
+        return entry["offset"], entry["name"], entry["typename"]
+    return entry[0], entry[1], entry[2]
+
+def parameter(entry):
+    if isinstance(entry, dict):
+        return entry["name"], entry["typename"]
+    return entry[0], entry[1]
+
+def enum_field(entry):
+    if isinstance(entry, dict):
+        return entry["name"], entry["value"]
+    return entry[0], entry[1]
+
+# named types are referenced instead of inlined, the library holds them once
+def reference(key: str):
+    if key == "":
+        return bn.Type.void()
+    if key in structs and not structs[key]["anon"]:
+        return bn.Type.named_type_reference(bn.NamedTypeReferenceClass.StructNamedTypeClass, key)
+    if key in unions and not unions[key]["anon"]:
+        return bn.Type.named_type_reference(bn.NamedTypeReferenceClass.UnionNamedTypeClass, key)
+    if key in enums:
+        return bn.Type.named_type_reference(bn.NamedTypeReferenceClass.EnumNamedTypeClass, key)
+    if key in typedefs:
+        return bn.Type.named_type_reference(bn.NamedTypeReferenceClass.TypedefNamedTypeClass, key)
+    return build(key)
+
+def build(key: str):
+    if key in integers:
+        match integers[key].get("encoding"):
+            case "float":
+                return bn.Type.float(integers[key]["size"])
+            case "bool":
+                return bn.Type.bool()
+            case "char" if integers[key]["size"] == 1:
+                return bn.Type.char()
+        return bn.Type.int(integers[key]["size"], integers[key]["signed"])
+    if key in structs or key in unions:
+        kind = structs.get(key) or unions[key]
+        builder = bn.StructureBuilder.create()
+        if key in unions:
+            builder.type = bn.StructureVariant.UnionStructureType
+        for offset, field_name, typename in map(field, kind["fields"]):
+            builder.insert(offset, reference(typename), field_name)
+        builder.width = kind["size"]
+        return bn.Type.structure_type(builder)
+    if key in pointers:
+        size = pointers[key]["size"] or arch.address_size
+        return bn.Type.pointer(arch, reference(pointers[key]["target"]), width=size)
+    if key in typedefs:
+        return reference(typedefs[key]["target"])
+    if key in functions:
+        return bn.Type.function(
+            reference(functions[key]["returntype"]),
+            [(reference(typename), parameter_name) for parameter_name, typename in map(parameter, functions[key]["parameters"])],
+        )
+    if key in enums:
+        builder = bn.EnumerationBuilder.create([], enums[key]["size"], arch, enums[key]["signed"])
+        for member_name, value in map(enum_field, enums[key]["fields"]):
+            builder.append(member_name, value)
+        return bn.Type.enumeration_type(arch, builder, enums[key]["size"], enums[key]["signed"])
+    if key in arrays:
+        return bn.Type.array(reference(arrays[key]["target"]), arrays[key]["count"])
+    if key in qualifiers:
+        # binja has no restrict
+        builder = reference(qualifiers[key]["target"]).mutable_copy()
+        builder.const = qualifiers[key].get("const", False) or builder.const
+        builder.volatile = qualifiers[key].get("volatile", False) or builder.volatile
+        return builder.immutable_copy()
+    exit(f"unknown type: {key}")
+
+library = bn.TypeLibrary.new(arch, name)
+for key in types:
+    # anonymous aggregates only exist inline
+    if (key in structs or key in unions) and types[key].get("anon"):
+        continue
+    library.add_named_type(key, build(key))
+
+for address, variable in variables.items():
+    if variable["typename"] != "":
+        library.add_named_object(variable["name"], reference(variable["typename"]))
+
+library.finalize()
+library.write_to_file(output)
+print(f"{output}: {len(library.named_types)} types, {len(library.named_objects)} objects")